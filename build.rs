@@ -0,0 +1,42 @@
+//! Pins the SandoooV3 creation code in `contracts/bytecode/SandoooV3.hex`.
+//!
+//! The bot and its tests embed that file, so a change to `contracts/src` shows up as a diff of the
+//! committed bytecode. With `forge` installed the contracts are rebuilt and the file is rewritten
+//! from the fresh artifact; without it the committed file is used as is.
+use std::path::Path;
+use std::process::Command;
+
+const PINNED: &str = "contracts/bytecode/SandoooV3.hex";
+const ARTIFACT: &str = "contracts/out/SandoooV3.sol/SandoooV3.json";
+
+fn main() {
+    println!("cargo:rerun-if-changed=contracts/src");
+    println!("cargo:rerun-if-changed=contracts/foundry.toml");
+
+    let built = Command::new("forge")
+        .arg("build")
+        .current_dir("contracts")
+        .output()
+        .map_or(false, |output| output.status.success());
+    if !built {
+        if !Path::new(PINNED).exists() {
+            panic!("{} is missing, install forge to build it", PINNED);
+        }
+        println!("cargo:warning=forge not found, using the committed {}", PINNED);
+        return;
+    }
+
+    let artifact = std::fs::read_to_string(ARTIFACT)
+        .unwrap_or_else(|e| panic!("{} not readable after forge build: {}", ARTIFACT, e));
+    let json: serde_json::Value = serde_json::from_str(&artifact).unwrap();
+    let bytecode = json["bytecode"]["object"]
+        .as_str()
+        .unwrap_or_else(|| panic!("{} has no bytecode", ARTIFACT));
+
+    let pinned = std::fs::read_to_string(PINNED).unwrap_or_default();
+    if pinned.trim() != bytecode {
+        std::fs::create_dir_all("contracts/bytecode").unwrap();
+        std::fs::write(PINNED, format!("{}\n", bytecode)).unwrap();
+        println!("cargo:warning=SandoooV3 bytecode changed, commit {}", PINNED);
+    }
+}
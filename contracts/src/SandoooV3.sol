@@ -4,10 +4,17 @@ pragma solidity ^0.8.20;
 import "./interfaces/IPool.sol";
 import "./interfaces/IFlashLoanReceiver.sol";
 import "./interfaces/IERC20.sol";
+import "./interfaces/IUniswapV3Pool.sol";
+import "./interfaces/IUniswapV3SwapCallback.sol";
 
 /// @title SandoooV3 (refactored)
-/// @notice Executes single-asset sandwich attacks with Aave V3 flash-loans
-contract SandoooV3 is IFlashLoanReceiver {
+/// @notice Executes single-asset sandwich attacks on Uniswap V2 / V3 pools from inventory or with
+///         Aave V3 flash-loans
+/// @dev    A sandwich needs the victim between its two legs, so it runs as two `executeLeg`
+///         transactions funded by the contract's inventory. `executeSandwichWithFlashloan` runs
+///         every trade inside one transaction and only suits atomic (backrun-only) trades, as
+///         does `executeRoute`, which chains hops and checks the profit of a cycle.
+contract SandoooV3 is IFlashLoanReceiver, IUniswapV3SwapCallback {
     /*───────────────────  Constants / immutables  ───────────────────*/
 
     /// @notice Contract owner (can trigger flash-loan executions & recover funds)
@@ -20,9 +27,17 @@ contract SandoooV3 is IFlashLoanReceiver {
     bytes4 private constant TOKEN_APPROVE_ID  = 0x095ea7b3;      // approve(address,uint256)
     bytes4 private constant V2_SWAP_ID        = 0x022c0d9f;      // swap(uint,uint,address,bytes)
 
+    uint8   private constant DEX_UNISWAP_V2 = 0;
+    uint8   private constant DEX_UNISWAP_V3 = 1;
+    uint256 private constant TRADE_SIZE     = 129;                // bytes per trade entry
+
+    uint160 private constant MIN_SQRT_RATIO = 4295128739;
+    uint160 private constant MAX_SQRT_RATIO = 1461446703485210103287273052203988822378723970342;
+
     /*────────────────────────  State  ───────────────────────────────*/
 
     uint256 private _unlocked = 1;                        // re-entrancy mutex
+    address private _expectedPool;                        // V3 pool allowed to call back
 
     /*────────────────────────  Errors  ──────────────────────────────*/
 
@@ -33,6 +48,8 @@ contract SandoooV3 is IFlashLoanReceiver {
     error InvalidInitiator();
     error InsufficientProfit();
     error EthTransferFailed();
+    error UnknownDex();
+    error InsufficientOutput();
 
     /*─────────────────────  Constructor  ────────────────────────────*/
 
@@ -57,10 +74,36 @@ contract SandoooV3 is IFlashLoanReceiver {
 
     /*─────────────────────  External API  ───────────────────────────*/
 
-    /// @notice Initiates an Aave V3 flash-loan and executes the sandwich bundle
+    /// @notice Executes one leg (frontrun or backrun) of a sandwich from the contract's inventory
+    /// @param legData  Encoded leg: 8-byte block number + N×(129-byte trade)
+    function executeLeg(bytes calldata legData) external onlyOwner nonReentrant {
+        _checkData(legData);
+        _executeSandwich(address(0), legData);
+    }
+
+    /// @notice Executes a multi-hop route (e.g. a backrun arbitrage cycle) from the contract's
+    ///         inventory, reverting unless it ends with `minProfit` more of the first hop's tokenIn
+    /// @param minProfit  Minimum gain in the first hop's tokenIn
+    /// @param routeData  Encoded route: 8-byte block number + N×(129-byte trade); a hop with
+    ///                   amountIn = 0 spends the whole output of the previous hop
+    function executeRoute(uint256 minProfit, bytes calldata routeData) external onlyOwner nonReentrant {
+        _checkData(routeData);
+
+        // tokenIn of the first hop: 8-byte block # + dexKind + zeroForOne + pair
+        address startToken;
+        assembly { startToken := shr(96, calldataload(add(routeData.offset, 30))) }
+
+        uint256 balanceBefore = IERC20(startToken).balanceOf(address(this));
+        _executeRoute(routeData);
+        if (IERC20(startToken).balanceOf(address(this)) < balanceBefore + minProfit) {
+            revert InsufficientProfit();
+        }
+    }
+
+    /// @notice Initiates an Aave V3 flash-loan and executes all trades atomically
     /// @param asset         Token to borrow (e.g. WETH)
     /// @param amount        Amount to borrow
-    /// @param sandwichData  Encoded bundle: 8-byte block number + N×(105-byte trade)
+    /// @param sandwichData  Encoded bundle: 8-byte block number + N×(129-byte trade)
     function executeSandwichWithFlashloan(
         address asset,
         uint256 amount,
@@ -68,15 +111,7 @@ contract SandoooV3 is IFlashLoanReceiver {
     ) external onlyOwner nonReentrant {
         /*── pre-checks ─────────────────────────────────────────────*/
 
-        // 8-byte header + N×105 bytes body
-        if (sandwichData.length <= 8 ||
-            (sandwichData.length - 8) % 105 != 0
-        ) revert InvalidData();
-
-        // block-number pinning (first 8 bytes)
-        uint64 blk;
-        assembly { blk := shr(192, calldataload(sandwichData.offset)) }
-        if (blk != block.number) revert WrongBlock();
+        _checkData(sandwichData);
 
         /*── request flash-loan ─────────────────────────────────────*/
 
@@ -96,13 +131,15 @@ contract SandoooV3 is IFlashLoanReceiver {
 
     /*─────────────────  Aave flash-loan callback  ───────────────────*/
 
+    /// @dev Not `nonReentrant`: Aave calls it while `executeSandwichWithFlashloan` holds the lock.
+    ///      Only the pool may call it, for a loan this contract initiated.
     function executeOperation(
         address asset,
         uint256 amount,
         uint256 premium,
         address initiator,
         bytes calldata params
-    ) external override nonReentrant returns (bool) {
+    ) external override returns (bool) {
         if (msg.sender != AAVE_POOL)         revert Unauthorized();
         if (initiator != address(this))      revert InvalidInitiator();
 
@@ -143,42 +180,112 @@ contract SandoooV3 is IFlashLoanReceiver {
         revert("MULTI_ASSET_NOT_SUPPORTED");
     }
 
+    /*────────────────────  Internal: data checks  ──────────────────*/
+
+    function _checkData(bytes calldata data) internal view {
+        // 8-byte header + N×129 bytes body
+        if (data.length <= 8 ||
+            (data.length - 8) % TRADE_SIZE != 0
+        ) revert InvalidData();
+
+        // block-number pinning (first 8 bytes)
+        uint64 blk;
+        assembly { blk := shr(192, calldataload(data.offset)) }
+        if (blk != block.number) revert WrongBlock();
+    }
+
     /*────────────────────  Internal: sandwich loop  ────────────────*/
 
+    /// @dev Trade entry (129 bytes):
+    ///      dexKind (1) | zeroForOne (1) | pair (20) | tokenIn (20) | amountIn (32) |
+    ///      amountOut (32) | fee (3) | sqrtPriceLimitX96 (20)
+    ///      V2 entries take amountOut as the exact output and ignore fee / sqrtPriceLimitX96.
+    ///      V3 entries take amountOut as the minimum output; a zero price limit means no limit.
+    struct Trade {
+        uint8   dexKind;
+        bool    zeroForOne;
+        address pair;
+        address tokenIn;
+        uint256 amountIn;
+        uint256 amountOut;
+        uint24  fee;
+        uint160 sqrtPriceLimitX96;
+    }
+
     function _executeSandwich(address /*asset*/, bytes calldata data) internal {
         uint256 offset = 8;                                  // skip block #
         uint256 end    = data.length;
 
         while (offset < end) {
-            bool zeroForOne  = uint8(data[offset]) == 1;
-            offset += 1;
-
-            address pair;
-            address tokenIn;
-            uint256 amountIn;
-            uint256 amountOut;
-
-            assembly {
-                // pair (20 bytes)
-                pair := shr(96, calldataload(add(data.offset, offset)))
-                offset := add(offset, 20)
-
-                // tokenIn (20 bytes)
-                tokenIn := shr(96, calldataload(add(data.offset, offset)))
-                offset := add(offset, 20)
-
-                // amountIn (32 bytes)
-                amountIn := calldataload(add(data.offset, offset))
-                offset   := add(offset, 32)
-
-                // amountOut (32 bytes)
-                amountOut := calldataload(add(data.offset, offset))
-                offset     := add(offset, 32)
-            }
+            _executeTrade(_readTrade(data, offset));
+            offset += TRADE_SIZE;
+        }
+    }
+
+    /// @dev Same entries as `_executeSandwich`, but a hop with amountIn = 0 pays in the whole
+    ///      output of the previous hop
+    function _executeRoute(bytes calldata data) internal {
+        uint256 offset = 8;                                  // skip block #
+        uint256 end    = data.length;
+        uint256 carried;                                     // output of the previous hop
+
+        while (offset < end) {
+            Trade memory trade = _readTrade(data, offset);
+            if (trade.amountIn == 0) trade.amountIn = carried;
+            carried = _executeTrade(trade);
+            offset += TRADE_SIZE;
+        }
+    }
+
+    function _readTrade(bytes calldata data, uint256 offset) internal pure returns (Trade memory trade) {
+        trade.dexKind    = uint8(data[offset]);
+        trade.zeroForOne = uint8(data[offset + 1]) == 1;
+        offset += 2;
 
-            _safeTransfer(tokenIn, pair, amountIn);          // pay in
+        address pair;
+        address tokenIn;
+        uint256 amountIn;
+        uint256 amountOut;
+        uint24  fee;
+        uint160 sqrtPriceLimitX96;
 
-            _executeSwap(pair, zeroForOne, amountOut);       // pull out
+        assembly {
+            let ptr := add(data.offset, offset)
+
+            pair              := shr(96, calldataload(ptr))               // 20 bytes
+            tokenIn           := shr(96, calldataload(add(ptr, 20)))      // 20 bytes
+            amountIn          := calldataload(add(ptr, 40))               // 32 bytes
+            amountOut         := calldataload(add(ptr, 72))               // 32 bytes
+            fee               := shr(232, calldataload(add(ptr, 104)))    // 3 bytes
+            sqrtPriceLimitX96 := shr(96, calldataload(add(ptr, 107)))     // 20 bytes
+        }
+
+        trade.pair              = pair;
+        trade.tokenIn           = tokenIn;
+        trade.amountIn          = amountIn;
+        trade.amountOut         = amountOut;
+        trade.fee               = fee;
+        trade.sqrtPriceLimitX96 = sqrtPriceLimitX96;
+    }
+
+    /// @dev Returns the amount received
+    function _executeTrade(Trade memory trade) internal returns (uint256) {
+        if (trade.dexKind == DEX_UNISWAP_V2) {
+            _safeTransfer(trade.tokenIn, trade.pair, trade.amountIn);      // pay in
+            _executeSwap(trade.pair, trade.zeroForOne, trade.amountOut);   // pull out
+            return trade.amountOut;
+        } else if (trade.dexKind == DEX_UNISWAP_V3) {
+            return _executeV3Swap(
+                trade.pair,
+                trade.zeroForOne,
+                trade.tokenIn,
+                trade.amountIn,
+                trade.amountOut,
+                trade.fee,
+                trade.sqrtPriceLimitX96
+            );
+        } else {
+            revert UnknownDex();
         }
     }
 
@@ -203,8 +310,8 @@ contract SandoooV3 is IFlashLoanReceiver {
                 mstore(add(ptr, 0x24), amountOut)
             }
 
-            // to
-            mstore(add(ptr, 0x44), shl(96, address()))      // address(this)
+            // to (right-aligned, like any ABI-encoded address)
+            mstore(add(ptr, 0x44), address())               // address(this)
 
             // offset to bytes data (0xa0 = 160)
             mstore(add(ptr, 0x64), 0xa0)
@@ -218,6 +325,54 @@ contract SandoooV3 is IFlashLoanReceiver {
         }
     }
 
+    /// @dev Exact-input Uniswap V3 swap, paid in `uniswapV3SwapCallback`
+    function _executeV3Swap(
+        address pool,
+        bool zeroForOne,
+        address tokenIn,
+        uint256 amountIn,
+        uint256 minAmountOut,
+        uint24 fee,
+        uint160 sqrtPriceLimitX96
+    ) internal returns (uint256 amountOut) {
+        // guards against entries pointing at the wrong pool / fee tier
+        if (IUniswapV3Pool(pool).fee() != fee) revert InvalidData();
+
+        if (sqrtPriceLimitX96 == 0) {
+            sqrtPriceLimitX96 = zeroForOne ? MIN_SQRT_RATIO + 1 : MAX_SQRT_RATIO - 1;
+        }
+
+        _expectedPool = pool;
+        (int256 amount0, int256 amount1) = IUniswapV3Pool(pool).swap(
+            address(this),
+            zeroForOne,
+            int256(amountIn),
+            sqrtPriceLimitX96,
+            abi.encode(tokenIn)
+        );
+        _expectedPool = address(0);
+
+        amountOut = uint256(-(zeroForOne ? amount1 : amount0));
+        if (amountOut < minAmountOut) revert InsufficientOutput();
+    }
+
+    /*──────────────────  Uniswap V3 swap callback  ─────────────────*/
+
+    /// @notice Pays the pool for a swap started by `_executeV3Swap`
+    /// @dev    Only the pool currently being swapped against may call back
+    function uniswapV3SwapCallback(
+        int256 amount0Delta,
+        int256 amount1Delta,
+        bytes calldata data
+    ) external override {
+        address pool = _expectedPool;
+        if (pool == address(0) || msg.sender != pool) revert Unauthorized();
+
+        address tokenIn = abi.decode(data, (address));
+        uint256 amountToPay = amount0Delta > 0 ? uint256(amount0Delta) : uint256(amount1Delta);
+        _safeTransfer(tokenIn, pool, amountToPay);
+    }
+
     /*────────────────  Internal: safe ERC-20 helpers  ──────────────*/
 
     function _safeTransfer(address token, address to, uint256 amount) private {
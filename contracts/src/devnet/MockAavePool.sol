@@ -0,0 +1,43 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.20;
+
+import "../interfaces/IERC20.sol";
+import "../interfaces/IFlashLoanReceiver.sol";
+
+/// @title MockAavePool
+/// @notice Aave V3 `flashLoanSimple` for the local devnet, lending its own balance at the 0.09%
+///         premium `FLASHLOAN_FEE_BASIS_POINTS` assumes
+contract MockAavePool {
+    uint256 public constant FLASHLOAN_PREMIUM_TOTAL = 9;
+
+    /// @dev Same signature as Aave V3, so `parse_flashloan_premium` reads the premium from it
+    event FlashLoan(
+        address indexed target,
+        address initiator,
+        address indexed asset,
+        uint256 amount,
+        uint8 interestRateMode,
+        uint256 premium,
+        uint16 indexed referralCode
+    );
+
+    function flashLoanSimple(
+        address receiverAddress,
+        address asset,
+        uint256 amount,
+        bytes calldata params,
+        uint16 referralCode
+    ) external {
+        uint256 premium = (amount * FLASHLOAN_PREMIUM_TOTAL) / 10000;
+        require(IERC20(asset).transfer(receiverAddress, amount), "LOAN_TRANSFER_FAILED");
+        require(
+            IFlashLoanReceiver(receiverAddress).executeOperation(asset, amount, premium, msg.sender, params),
+            "INVALID_FLASHLOAN_EXECUTOR_RETURN"
+        );
+        require(
+            IERC20(asset).transferFrom(receiverAddress, address(this), amount + premium),
+            "REPAY_FAILED"
+        );
+        emit FlashLoan(receiverAddress, msg.sender, asset, amount, 0, premium, referralCode);
+    }
+}
@@ -0,0 +1,29 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.20;
+
+/**
+ * @title IUniswapV3Pool
+ * @notice Minimal Uniswap V3 pool interface used by SandoooV3.
+ */
+interface IUniswapV3Pool {
+    /**
+     * @notice Swap token0 for token1, or token1 for token0
+     * @param recipient The address to receive the output of the swap
+     * @param zeroForOne The direction of the swap, true for token0 to token1, false for token1 to token0
+     * @param amountSpecified The amount of the swap, positive for exact input
+     * @param sqrtPriceLimitX96 The price limit the swap may not cross
+     * @param data Any data to be passed through to the callback
+     * @return amount0 The delta of the balance of token0 of the pool, exact when negative, minimum when positive
+     * @return amount1 The delta of the balance of token1 of the pool, exact when negative, minimum when positive
+     */
+    function swap(
+        address recipient,
+        bool zeroForOne,
+        int256 amountSpecified,
+        uint160 sqrtPriceLimitX96,
+        bytes calldata data
+    ) external returns (int256 amount0, int256 amount1);
+
+    /// @notice The pool's fee in hundredths of a bip, i.e. 1e-6
+    function fee() external view returns (uint24);
+}
@@ -0,0 +1,21 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.20;
+
+/**
+ * @title IUniswapV3SwapCallback
+ * @notice Any contract that calls IUniswapV3Pool#swap must implement this interface
+ */
+interface IUniswapV3SwapCallback {
+    /**
+     * @notice Called to `msg.sender` after executing a swap via IUniswapV3Pool#swap
+     * @dev The caller must pay the pool the tokens owed for the swap
+     * @param amount0Delta The amount of token0 that was sent (negative) or must be received (positive) by the pool
+     * @param amount1Delta The amount of token1 that was sent (negative) or must be received (positive) by the pool
+     * @param data Any data passed through by the caller via the IUniswapV3Pool#swap call
+     */
+    function uniswapV3SwapCallback(
+        int256 amount0Delta,
+        int256 amount1Delta,
+        bytes calldata data
+    ) external;
+}
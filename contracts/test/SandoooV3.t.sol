@@ -7,6 +7,7 @@ import "forge-std/console.sol";
 import "../src/interfaces/IPool.sol";
 import "../src/interfaces/IFlashLoanReceiver.sol";
 import "../src/interfaces/IERC20.sol";
+import "../src/interfaces/IUniswapV3SwapCallback.sol";
 import "../src/SandoooV3.sol";
 
 /// @title MockToken
@@ -112,6 +113,49 @@ contract SimpleMockPair {
     receive() external payable {}
 }
 
+/// @title MockV3Pool
+/// @notice Mocks a Uniswap V3 pool: sends the output first, then collects the input via the callback
+contract MockV3Pool {
+    address public token0;
+    address public token1;
+    uint24 public fee;
+    uint256 public amountOut;
+    
+    constructor(address _token0, address _token1, uint24 _fee) {
+        token0 = _token0;
+        token1 = _token1;
+        fee = _fee;
+    }
+    
+    function setAmountOut(uint256 _amountOut) external {
+        amountOut = _amountOut;
+    }
+    
+    function swap(
+        address recipient,
+        bool zeroForOne,
+        int256 amountSpecified,
+        uint160 /* sqrtPriceLimitX96 */,
+        bytes calldata data
+    ) external returns (int256 amount0, int256 amount1) {
+        address tokenIn = zeroForOne ? token0 : token1;
+        address tokenOut = zeroForOne ? token1 : token0;
+        
+        MockToken(tokenOut).mint(recipient, amountOut);
+        
+        (amount0, amount1) = zeroForOne
+            ? (amountSpecified, -int256(amountOut))
+            : (-int256(amountOut), amountSpecified);
+        
+        uint256 balanceBefore = MockToken(tokenIn).balanceOf(address(this));
+        IUniswapV3SwapCallback(msg.sender).uniswapV3SwapCallback(amount0, amount1, data);
+        require(
+            MockToken(tokenIn).balanceOf(address(this)) >= balanceBefore + uint256(amountSpecified),
+            "IIA"
+        );
+    }
+}
+
 /// @title MockAavePool
 /// @notice Mocks the Aave V3 Pool for flashloan testing
 contract MockAavePool is IPool {
@@ -176,6 +220,7 @@ contract SandoooV3Test is Test {
     MockToken weth;
     MockToken usdt;
     SimpleMockPair wethUsdtPair;
+    MockV3Pool wethUsdtV3Pool;
     
     address owner;
     address public constant AAVE_POOL_ADDRESS = 0x87870Bca3F3fD6335C3F4ce8392D69350B4fA4E2;
@@ -195,6 +240,9 @@ contract SandoooV3Test is Test {
         // Deploy simple mock Uniswap V2 pair
         wethUsdtPair = new SimpleMockPair(address(weth), address(usdt));
         
+        // Deploy mock Uniswap V3 pool (0.05% fee tier)
+        wethUsdtV3Pool = new MockV3Pool(address(weth), address(usdt), 500);
+        
         // Deploy mock Aave V3 Pool
         mockPool = new MockAavePool();
         
@@ -227,11 +275,36 @@ contract SandoooV3Test is Test {
     ) internal view returns (bytes memory) {
         return abi.encodePacked(
             uint64(block.number), // blockNumber (8 bytes)
+            uint8(0), // dexKind: Uniswap V2 (1 byte)
             uint8(zeroForOne ? 1 : 0), // zeroForOne flag (1 byte)
             pair, // pair address (20 bytes)
             tokenIn, // token in (20 bytes)
             amountIn, // amount in (32 bytes)
-            amountOut // minimum amount out (32 bytes)
+            amountOut, // amount out (32 bytes)
+            uint24(0), // fee, unused for V2 (3 bytes)
+            uint160(0) // sqrtPriceLimitX96, unused for V2 (20 bytes)
+        );
+    }
+    
+    /// @notice Helper function to create a Uniswap V3 trade entry
+    function createV3SandwichData(
+        bool zeroForOne,
+        address pool,
+        address tokenIn,
+        uint256 amountIn,
+        uint256 minAmountOut,
+        uint24 fee
+    ) internal view returns (bytes memory) {
+        return abi.encodePacked(
+            uint64(block.number), // blockNumber (8 bytes)
+            uint8(1), // dexKind: Uniswap V3 (1 byte)
+            uint8(zeroForOne ? 1 : 0), // zeroForOne flag (1 byte)
+            pool, // pool address (20 bytes)
+            tokenIn, // token in (20 bytes)
+            amountIn, // amount in (32 bytes)
+            minAmountOut, // minimum amount out (32 bytes)
+            fee, // fee tier (3 bytes)
+            uint160(0) // sqrtPriceLimitX96, 0 = no limit (20 bytes)
         );
     }
     
@@ -277,6 +350,36 @@ contract SandoooV3Test is Test {
         assertGt(ownerBalance, 0, "Owner should have received tokens");
     }
     
+    /// @notice Test the whole flashloan path: the pool calls back into the locked entry point
+    function testExecuteSandwichWithFlashloan() public {
+        console.log("=== Test Execute Sandwich With Flashloan ===");
+        
+        uint256 flashloanAmount = 0.5 ether;
+        uint256 flashloanFee = flashloanAmount * 9 / 10000; // 0.09% fee
+        uint256 ownerWethBefore = weth.balanceOf(owner);
+        
+        bytes memory sandwichData = createSandwichData(
+            true,
+            address(wethUsdtPair),
+            address(weth),
+            0.3 ether,
+            500 * 10**6
+        );
+        
+        vm.startPrank(owner);
+        bot.executeSandwichWithFlashloan(address(weth), flashloanAmount, sandwichData);
+        vm.stopPrank();
+        
+        // the inventory pays the trade and the premium, the rest is swept to the owner
+        assertEq(
+            weth.balanceOf(owner),
+            ownerWethBefore + 2 ether - 0.3 ether - flashloanFee,
+            "Owner should receive the swept balance"
+        );
+        assertEq(weth.balanceOf(address(bot)), 0, "Contract should have 0 WETH");
+        assertEq(usdt.balanceOf(address(bot)), 500 * 10**6, "Bot should receive the output");
+    }
+    
     /// @notice Test transaction atomicity with successful execution
     function testAtomicitySuccess() public {
         console.log("=== Test Transaction Atomicity - Success ===");
@@ -505,4 +608,218 @@ contract SandoooV3Test is Test {
         assertEq(ownerWethAfter, ownerWethBefore + 1 ether, "WETH recovery failed");
         assertEq(weth.balanceOf(address(bot)), 0, "Contract should have 0 WETH");
     }
+    
+    /// @notice Test a frontrun leg paid from the contract's inventory
+    function testExecuteLeg() public {
+        console.log("=== Test Execute Leg ===");
+        
+        uint256 wethBefore = weth.balanceOf(address(bot));
+        
+        bytes memory legData = createSandwichData(
+            true,
+            address(wethUsdtPair),
+            address(weth),
+            0.5 ether,
+            800 * 10**6
+        );
+        
+        vm.startPrank(owner);
+        bot.executeLeg(legData);
+        vm.stopPrank();
+        
+        assertEq(weth.balanceOf(address(bot)), wethBefore - 0.5 ether, "Inventory should pay the leg");
+        assertEq(usdt.balanceOf(address(bot)), 800 * 10**6, "Bot should receive the output");
+    }
+    
+    /// @notice Test that legs are pinned to the target block
+    function testExecuteLegWrongBlock() public {
+        console.log("=== Test Execute Leg - Wrong Block ===");
+        
+        bytes memory legData = createSandwichData(
+            true,
+            address(wethUsdtPair),
+            address(weth),
+            0.1 ether,
+            100 * 10**6
+        );
+        
+        vm.roll(block.number + 1);
+        
+        vm.startPrank(owner);
+        vm.expectRevert(SandoooV3.WrongBlock.selector);
+        bot.executeLeg(legData);
+        vm.stopPrank();
+    }
+    
+    /// @notice Test that only the owner can execute legs
+    function testExecuteLegNotOwner() public {
+        console.log("=== Test Execute Leg - Not Owner ===");
+        
+        bytes memory legData = createSandwichData(
+            true,
+            address(wethUsdtPair),
+            address(weth),
+            0.1 ether,
+            100 * 10**6
+        );
+        
+        vm.startPrank(address(0x1234));
+        vm.expectRevert(SandoooV3.NotOwner.selector);
+        bot.executeLeg(legData);
+        vm.stopPrank();
+    }
+    
+    /// @notice Test a Uniswap V3 leg paid through the swap callback
+    function testExecuteV3Leg() public {
+        console.log("=== Test Execute V3 Leg ===");
+        
+        uint256 wethBefore = weth.balanceOf(address(bot));
+        wethUsdtV3Pool.setAmountOut(800 * 10**6);
+        
+        bytes memory legData = createV3SandwichData(
+            true,
+            address(wethUsdtV3Pool),
+            address(weth),
+            0.5 ether,
+            790 * 10**6,
+            500
+        );
+        
+        vm.startPrank(owner);
+        bot.executeLeg(legData);
+        vm.stopPrank();
+        
+        assertEq(weth.balanceOf(address(bot)), wethBefore - 0.5 ether, "Callback should pay the pool");
+        assertEq(weth.balanceOf(address(wethUsdtV3Pool)), 0.5 ether, "Pool should receive the input");
+        assertEq(usdt.balanceOf(address(bot)), 800 * 10**6, "Bot should receive the output");
+    }
+    
+    /// @notice Test that V3 legs revert below the minimum output
+    function testExecuteV3LegInsufficientOutput() public {
+        console.log("=== Test Execute V3 Leg - Insufficient Output ===");
+        
+        wethUsdtV3Pool.setAmountOut(700 * 10**6);
+        
+        bytes memory legData = createV3SandwichData(
+            true,
+            address(wethUsdtV3Pool),
+            address(weth),
+            0.5 ether,
+            790 * 10**6,
+            500
+        );
+        
+        vm.startPrank(owner);
+        vm.expectRevert(SandoooV3.InsufficientOutput.selector);
+        bot.executeLeg(legData);
+        vm.stopPrank();
+    }
+    
+    /// @notice Test that V3 legs reject a pool with a different fee tier
+    function testExecuteV3LegWrongFee() public {
+        console.log("=== Test Execute V3 Leg - Wrong Fee ===");
+        
+        bytes memory legData = createV3SandwichData(
+            true,
+            address(wethUsdtV3Pool),
+            address(weth),
+            0.5 ether,
+            0,
+            3000
+        );
+        
+        vm.startPrank(owner);
+        vm.expectRevert(SandoooV3.InvalidData.selector);
+        bot.executeLeg(legData);
+        vm.stopPrank();
+    }
+    
+    /// @notice Test that only the pool being swapped against can call back
+    function testUnauthorizedV3Callback() public {
+        console.log("=== Test Unauthorized V3 Callback ===");
+        
+        vm.expectRevert(SandoooV3.Unauthorized.selector);
+        bot.uniswapV3SwapCallback(1 ether, 0, abi.encode(address(weth)));
+        
+        vm.startPrank(address(wethUsdtV3Pool));
+        vm.expectRevert(SandoooV3.Unauthorized.selector);
+        bot.uniswapV3SwapCallback(1 ether, 0, abi.encode(address(weth)));
+        vm.stopPrank();
+    }
+    
+    /// @notice Helper building a V3 WETH→USDT hop chained into a V2 USDT→WETH hop
+    function createCycleRoute(uint256 wethIn, uint256 wethOut) internal view returns (bytes memory) {
+        return abi.encodePacked(
+            createV3SandwichData(
+                true,
+                address(wethUsdtV3Pool),
+                address(weth),
+                wethIn,
+                0,
+                500
+            ),
+            uint8(0), // dexKind: Uniswap V2
+            uint8(0), // oneForZero: USDT in
+            address(wethUsdtPair),
+            address(usdt),
+            uint256(0), // amountIn 0: spend the previous hop's output
+            wethOut,
+            uint24(0),
+            uint160(0)
+        );
+    }
+    
+    /// @notice Test a profitable two-pool cycle where the second hop spends the first hop's output
+    function testExecuteRoute() public {
+        console.log("=== Test Execute Route ===");
+        
+        uint256 wethBefore = weth.balanceOf(address(bot));
+        wethUsdtV3Pool.setAmountOut(800 * 10**6);
+        
+        vm.startPrank(owner);
+        bot.executeRoute(0.1 ether, createCycleRoute(0.5 ether, 0.6 ether));
+        vm.stopPrank();
+        
+        assertEq(weth.balanceOf(address(bot)), wethBefore + 0.1 ether, "Cycle should return the profit");
+        assertEq(usdt.balanceOf(address(bot)), 0, "Chained hop should spend the whole output");
+        assertEq(usdt.balanceOf(address(wethUsdtPair)), 800 * 10**6, "Pair should receive the output");
+    }
+    
+    /// @notice Test that routes revert below the minimum profit
+    function testExecuteRouteInsufficientProfit() public {
+        console.log("=== Test Execute Route - Insufficient Profit ===");
+        
+        wethUsdtV3Pool.setAmountOut(800 * 10**6);
+        bytes memory routeData = createCycleRoute(0.5 ether, 0.6 ether);
+        
+        vm.startPrank(owner);
+        vm.expectRevert(SandoooV3.InsufficientProfit.selector);
+        bot.executeRoute(0.2 ether, routeData);
+        vm.stopPrank();
+    }
+    
+    /// @notice Test that routes are pinned to the target block
+    function testExecuteRouteWrongBlock() public {
+        console.log("=== Test Execute Route - Wrong Block ===");
+        
+        bytes memory routeData = createCycleRoute(0.5 ether, 0.6 ether);
+        vm.roll(block.number + 1);
+        
+        vm.startPrank(owner);
+        vm.expectRevert(SandoooV3.WrongBlock.selector);
+        bot.executeRoute(0, routeData);
+        vm.stopPrank();
+    }
+    
+    /// @notice Test that only the owner can execute routes
+    function testExecuteRouteNotOwner() public {
+        console.log("=== Test Execute Route - Not Owner ===");
+        
+        bytes memory routeData = createCycleRoute(0.5 ether, 0.6 ether);
+        
+        vm.startPrank(address(0x1234));
+        vm.expectRevert(SandoooV3.NotOwner.selector);
+        bot.executeRoute(0, routeData);
+        vm.stopPrank();
+    }
 }
@@ -73,7 +73,7 @@ describe("SandoooV3", function() {
     });
     
     it("Should revert if sandwich data length is invalid", async function() {
-      // Create data with 8 bytes header + invalid trade length (not a multiple of 105)
+      // Create data with 8 bytes header + invalid trade length (not a multiple of 129)
       const blockNumber = ethers.BigNumber.from(String(await ethers.provider.getBlockNumber()));
       const blockHeader = ethers.utils.hexZeroPad(blockNumber.toHexString(), 8);
       const invalidData = ethers.utils.hexConcat([blockHeader, "0x1234"]); // 8 + 2 bytes
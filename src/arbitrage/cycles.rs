@@ -0,0 +1,320 @@
+/// Arbitrage cycle search and quoting over the known pools.
+///
+/// A `Cycle` starts and ends in the same token (WETH) and crosses 2 or 3 pools. `PoolGraph`
+/// indexes the pools by token so the cycles through a pool a pending swap moves can be listed
+/// without scanning every pool. Cycles are quoted with the same constant-product math as the
+/// sandwich simulation: exact for Uniswap V2, an estimate from virtual reserves for Uniswap V3.
+use ethers::types::{H160, U256};
+use std::collections::{HashMap, HashSet};
+
+use crate::common::pools::{DexVariant, Pool};
+use crate::common::sando_calldata::{SandoPayload, SandoTrade};
+use crate::sandwich::simulation::{get_v2_amount_out, get_v3_amount_out, min_amount_out};
+
+/// Steps of the ternary search for the best input amount.
+const OPTIMIZE_STEPS: usize = 96;
+
+/// One swap of a cycle.
+#[derive(Debug, Clone, Copy)]
+pub struct Hop {
+    /// The pool swapped on.
+    pub pool: Pool,
+    /// Token paid into the pool.
+    pub token_in: H160,
+    /// Token received from the pool.
+    pub token_out: H160,
+}
+
+impl Hop {
+    /// Creates the hop selling `token_in` on `pool`.
+    pub fn new(pool: &Pool, token_in: H160) -> Self {
+        let token_out = if pool.token0 == token_in {
+            pool.token1
+        } else {
+            pool.token0
+        };
+        Self {
+            pool: *pool,
+            token_in,
+            token_out,
+        }
+    }
+
+    /// True if token0 is paid in.
+    pub fn zero_for_one(&self) -> bool {
+        self.pool.token0 == self.token_in
+    }
+
+    /// Output of the hop for `amount_in`, given the pool's (reserve0, reserve1).
+    pub fn amount_out(&self, amount_in: U256, reserves: (U256, U256)) -> U256 {
+        let (reserve_in, reserve_out) = if self.zero_for_one() {
+            reserves
+        } else {
+            (reserves.1, reserves.0)
+        };
+        match self.pool.version {
+            DexVariant::UniswapV2 => get_v2_amount_out(amount_in, reserve_in, reserve_out),
+            DexVariant::UniswapV3 => {
+                get_v3_amount_out(amount_in, reserve_in, reserve_out, self.pool.fee)
+            }
+        }
+    }
+}
+
+/// A route that starts and ends in the same token.
+#[derive(Debug, Clone)]
+pub struct Cycle {
+    /// The swaps, in order.
+    pub hops: Vec<Hop>,
+}
+
+impl Cycle {
+    /// The token the cycle starts and ends in.
+    pub fn start_token(&self) -> H160 {
+        self.hops[0].token_in
+    }
+
+    /// Addresses of the pools crossed, in order.
+    pub fn pools(&self) -> Vec<H160> {
+        self.hops.iter().map(|hop| hop.pool.address).collect()
+    }
+
+    /// Identifies the cycle by its pools and starting direction.
+    pub fn id(&self) -> String {
+        let pools: Vec<String> = self.pools().iter().map(|p| format!("{:?}", p)).collect();
+        format!("{}:{}", pools.join("-"), self.hops[0].zero_for_one() as u8)
+    }
+
+    /// Output of every hop for `amount_in`.
+    ///
+    /// # Parameters
+    /// * `amount_in`: U256 - Amount of the start token paid into the first pool.
+    /// * `reserves`: &HashMap<H160, (U256, U256)> - (reserve0, reserve1) of each pool.
+    ///
+    /// # Returns
+    /// * `Option<Vec<U256>>` - The outputs, or None if reserves of a pool are missing.
+    pub fn amounts_out(
+        &self,
+        amount_in: U256,
+        reserves: &HashMap<H160, (U256, U256)>,
+    ) -> Option<Vec<U256>> {
+        let mut amounts = Vec::with_capacity(self.hops.len());
+        let mut amount = amount_in;
+        for hop in &self.hops {
+            amount = hop.amount_out(amount, *reserves.get(&hop.pool.address)?);
+            amounts.push(amount);
+        }
+        Some(amounts)
+    }
+
+    /// Amount of the start token the cycle returns for `amount_in`.
+    pub fn quote(&self, amount_in: U256, reserves: &HashMap<H160, (U256, U256)>) -> U256 {
+        self.amounts_out(amount_in, reserves)
+            .and_then(|amounts| amounts.last().copied())
+            .unwrap_or_default()
+    }
+
+    /// Finds the input with the largest profit, searching up to `max_amount_in`.
+    ///
+    /// The output of a chain of constant-product swaps is concave in its input, so a ternary
+    /// search converges on the best amount.
+    ///
+    /// # Parameters
+    /// * `reserves`: &HashMap<H160, (U256, U256)> - (reserve0, reserve1) of each pool.
+    /// * `max_amount_in`: U256 - Largest input to consider, e.g. the bot's balance.
+    ///
+    /// # Returns
+    /// * `Option<(U256, U256)>` - (amount_in, profit) of the best input, None if none is profitable.
+    pub fn optimize(
+        &self,
+        reserves: &HashMap<H160, (U256, U256)>,
+        max_amount_in: U256,
+    ) -> Option<(U256, U256)> {
+        let (mut low, mut high) = (U256::zero(), max_amount_in);
+        for _ in 0..OPTIMIZE_STEPS {
+            if high - low < U256::from(3) {
+                break;
+            }
+            let third = (high - low) / 3;
+            let (mid_low, mid_high) = (low + third, high - third);
+            // profit(mid_low) < profit(mid_high), without going negative
+            if self.quote(mid_low, reserves) + mid_high < self.quote(mid_high, reserves) + mid_low {
+                low = mid_low;
+            } else {
+                high = mid_high;
+            }
+        }
+
+        let amount_in = (low + high) / 2;
+        let amount_out = self.quote(amount_in, reserves);
+        if amount_in.is_zero() || amount_out <= amount_in {
+            return None;
+        }
+        Some((amount_in, amount_out - amount_in))
+    }
+
+    /// Builds the `executeRoute` payload for `amount_in`.
+    ///
+    /// Only the first hop carries an input amount, later hops spend the whole output of the one
+    /// before. V3 outputs are estimates, so every hop after a V3 hop is quoted on its minimum
+    /// output: a V2 hop then asks for no more than it is sure to get.
+    ///
+    /// # Parameters
+    /// * `block_number`: u64 - Block the route is pinned to.
+    /// * `amount_in`: U256 - Amount of the start token paid into the first pool.
+    /// * `reserves`: &HashMap<H160, (U256, U256)> - (reserve0, reserve1) of each pool.
+    ///
+    /// # Returns
+    /// * `Option<(SandoPayload, U256)>` - The payload and the amount of the start token it is
+    ///   sure to return, or None if reserves of a pool are missing.
+    pub fn payload(
+        &self,
+        block_number: u64,
+        amount_in: U256,
+        reserves: &HashMap<H160, (U256, U256)>,
+    ) -> Option<(SandoPayload, U256)> {
+        let mut payload = SandoPayload::new(block_number);
+        let mut amount = amount_in;
+        for (index, hop) in self.hops.iter().enumerate() {
+            let amount_out = min_amount_out(
+                hop.pool.version,
+                hop.amount_out(amount, *reserves.get(&hop.pool.address)?),
+            );
+            let hop_amount_in = if index == 0 { amount } else { U256::zero() };
+            let trade = match hop.pool.version {
+                DexVariant::UniswapV2 => SandoTrade::v2(
+                    hop.zero_for_one(),
+                    hop.pool.address,
+                    hop.token_in,
+                    hop_amount_in,
+                    amount_out,
+                ),
+                DexVariant::UniswapV3 => SandoTrade::v3(
+                    hop.zero_for_one(),
+                    hop.pool.address,
+                    hop.token_in,
+                    hop_amount_in,
+                    amount_out,
+                    hop.pool.fee,
+                ),
+            };
+            payload.trades.push(trade);
+            amount = amount_out;
+        }
+        Some((payload, amount))
+    }
+}
+
+/// Pools indexed by the tokens they trade.
+#[derive(Debug, Clone, Default)]
+pub struct PoolGraph {
+    pools_by_token: HashMap<H160, Vec<Pool>>,
+}
+
+impl PoolGraph {
+    /// Indexes the pools of `pools_map`.
+    pub fn new(pools_map: &HashMap<H160, Pool>) -> Self {
+        let mut pools_by_token: HashMap<H160, Vec<Pool>> = HashMap::new();
+        for pool in pools_map.values() {
+            pools_by_token.entry(pool.token0).or_default().push(*pool);
+            pools_by_token.entry(pool.token1).or_default().push(*pool);
+        }
+        Self { pools_by_token }
+    }
+
+    /// Number of tokens indexed.
+    pub fn len(&self) -> usize {
+        self.pools_by_token.len()
+    }
+
+    /// True if no pools are indexed.
+    pub fn is_empty(&self) -> bool {
+        self.pools_by_token.is_empty()
+    }
+
+    /// Pools trading `token`.
+    pub fn pools_of(&self, token: H160) -> &[Pool] {
+        self.pools_by_token
+            .get(&token)
+            .map(|pools| pools.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Pools trading `token_a` against `token_b`, other than `except`.
+    fn pools_between(&self, token_a: H160, token_b: H160, except: &[H160]) -> Vec<&Pool> {
+        self.pools_of(token_a)
+            .iter()
+            .filter(|p| p.trades(token_a, token_b) && !except.contains(&p.address))
+            .collect()
+    }
+
+    /// Lists the 2- and 3-pool cycles from `start` that cross `pool`, in both directions.
+    ///
+    /// # Parameters
+    /// * `pool`: &Pool - The pool a pending swap moves.
+    /// * `start`: H160 - The token every cycle starts and ends in.
+    ///
+    /// # Returns
+    /// * `Vec<Cycle>` - The cycles, without duplicates.
+    pub fn cycles_through(&self, pool: &Pool, start: H160) -> Vec<Cycle> {
+        let mut routes: Vec<Vec<Hop>> = Vec::new();
+
+        if pool.token0 == start || pool.token1 == start {
+            let token = if pool.token0 == start {
+                pool.token1
+            } else {
+                pool.token0
+            };
+
+            // start -> token on one pool, back on another pool of the same tokens
+            for other in self.pools_between(token, start, &[pool.address]) {
+                routes.push(vec![Hop::new(pool, start), Hop::new(other, token)]);
+                routes.push(vec![Hop::new(other, start), Hop::new(pool, token)]);
+            }
+
+            // start -> token -> middle -> start, with `pool` as the first or last hop
+            for middle_pool in self.pools_of(token) {
+                if middle_pool.address == pool.address || middle_pool.trades(token, start) {
+                    continue;
+                }
+                let middle = Hop::new(middle_pool, token).token_out;
+                for closing in self.pools_between(middle, start, &[pool.address]) {
+                    routes.push(vec![
+                        Hop::new(pool, start),
+                        Hop::new(middle_pool, token),
+                        Hop::new(closing, middle),
+                    ]);
+                    routes.push(vec![
+                        Hop::new(closing, start),
+                        Hop::new(middle_pool, middle),
+                        Hop::new(pool, token),
+                    ]);
+                }
+            }
+        } else {
+            // start -> token0 -> token1 -> start, with `pool` in the middle
+            let (token0, token1) = (pool.token0, pool.token1);
+            for opening in self.pools_between(start, token0, &[pool.address]) {
+                for closing in self.pools_between(token1, start, &[pool.address]) {
+                    routes.push(vec![
+                        Hop::new(opening, start),
+                        Hop::new(pool, token0),
+                        Hop::new(closing, token1),
+                    ]);
+                    routes.push(vec![
+                        Hop::new(closing, start),
+                        Hop::new(pool, token1),
+                        Hop::new(opening, token0),
+                    ]);
+                }
+            }
+        }
+
+        let mut seen = HashSet::new();
+        routes
+            .into_iter()
+            .map(|hops| Cycle { hops })
+            .filter(|cycle| seen.insert(cycle.id()))
+            .collect()
+    }
+}
@@ -0,0 +1,7 @@
+/// Backrun-only cross-DEX arbitrage for the Sandooo project.
+///
+/// This module contains the cycle search and quoting over the known pools (`cycles`) and the
+/// strategy that simulates and submits the backruns (`strategy`).
+///
+pub mod cycles;
+pub mod strategy;
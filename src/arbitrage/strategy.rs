@@ -0,0 +1,498 @@
+/// Backrun-only cross-DEX arbitrage.
+///
+/// When a pending swap moves a known pool, `ArbitrageStrategy` lists the 2- and 3-pool WETH
+/// cycles through that pool, quotes them on the reserves left by the victim, simulates the best
+/// ones as `SandoooV3.executeRoute` calls right after the victim and submits the most profitable
+/// as a `[victim, backrun]` bundle.
+///
+/// Every pending transaction is handled on its own by a `WorkerPool` worker; the workers share
+/// the pool graph and send their bundles straight to the engine.
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use bounded_vec_deque::BoundedVecDeque;
+use ethers::signers::{LocalWallet, Signer};
+use ethers::{
+    providers::Middleware,
+    types::{transaction::eip2930::AccessList, Bytes, H160, U256, U64},
+};
+use log::{debug, info, warn};
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use crate::arbitrage::cycles::{Cycle, PoolGraph};
+use crate::common::constants::{Env, WETH};
+use crate::common::engine::{
+    Action, ActionSender, BundleResult, BundleSubmission, EngineContext, PendingTxJob, Strategy,
+};
+use crate::common::evm::{EvmSimulator, Tx, VictimTx};
+use crate::common::execution_v3::{encode_route_call, ExecutorV3Extension};
+use crate::common::pools::{DexVariant, Pool};
+use crate::common::sando_calldata::TradeLayout;
+use crate::common::streams::NewPendingTx;
+use crate::common::utils::get_token_balance;
+use crate::common::worker_pool::{WorkerPool, DEFAULT_QUEUE_CAPACITY, DEFAULT_WORKERS};
+use crate::sandwich::simulation::{deploy_sando_v3, main_currency_balance_slot};
+
+/// Most cycles simulated per pending swap, best quotes first.
+const MAX_SIMULATED_CYCLES: usize = 3;
+
+/// Gas limit of a simulated route.
+const ROUTE_GAS_LIMIT: u64 = 1_000_000;
+
+/// WETH the bot is funded with in debug mode, where it is deployed in the simulation.
+const DEBUG_INVENTORY: u128 = 100_000_000_000_000_000_000; // 100 WETH
+
+/// A route simulated right after the victim.
+#[derive(Debug, Clone)]
+pub struct SimulatedBackrun {
+    /// The cycle traded.
+    pub cycle: Cycle,
+    /// WETH paid into the first pool.
+    pub amount_in: U256,
+    /// The `executeRoute` call.
+    pub calldata: Bytes,
+    /// Access list of the call.
+    pub access_list: AccessList,
+    /// Gas used by the call.
+    pub gas_used: u64,
+    /// WETH gained by the bot, before gas.
+    pub revenue: U256,
+}
+
+impl SimulatedBackrun {
+    /// Revenue left after paying the base fee, zero if the gas costs more.
+    pub fn profit(&self, base_fee: U256) -> U256 {
+        self.revenue
+            .saturating_sub(U256::from(self.gas_used) * base_fee)
+    }
+}
+
+/// Simulates the victim, then the most promising WETH cycles through the pools it moved.
+///
+/// The simulator must be fresh, forked at the parent of the target block. Cycles are quoted on
+/// the reserves after the victim, the best `MAX_SIMULATED_CYCLES` are simulated one at a time
+/// from the same post-victim state and the one with the largest profit after gas is kept.
+///
+/// # Parameters
+/// * `simulator`: &mut EvmSimulator<M> - The simulator.
+/// * `graph`: &PoolGraph - The known pools.
+/// * `touched_pools`: &[Pool] - The pools the victim swaps on.
+/// * `victim_tx`: VictimTx - The victim transaction.
+/// * `bot_address`: Option<H160> - The deployed bot, or None to deploy and fund one.
+/// * `max_amount_in`: U256 - The most WETH a route may spend.
+/// * `base_fee`: U256 - Base fee of the target block.
+///
+/// # Returns
+/// * `Result<Option<SimulatedBackrun>>` - The best route, None if no route makes a profit.
+pub fn simulate_backrun<M: Middleware + 'static>(
+    simulator: &mut EvmSimulator<M>,
+    graph: &PoolGraph,
+    touched_pools: &[Pool],
+    victim_tx: VictimTx,
+    bot_address: Option<H160>,
+    max_amount_in: U256,
+    base_fee: U256,
+) -> Result<Option<SimulatedBackrun>> {
+    let weth = H160::from_str(WETH).unwrap();
+
+    let bot_address = match bot_address {
+        Some(bot_address) => bot_address,
+        None => {
+            // set ETH balance so that it's enough to cover gas fees
+            let initial_eth_balance = U256::from(100) * U256::from(10).pow(U256::from(18));
+            simulator.set_eth_balance(simulator.owner, initial_eth_balance);
+
+            let bot_address = deploy_sando_v3(simulator)?;
+            let balance_slot = main_currency_balance_slot(simulator, weth)?;
+            simulator.set_token_balance_at(bot_address, &balance_slot, max_amount_in.into())?;
+            bot_address
+        }
+    };
+
+    if let Err(e) = simulator.call(Tx::from(victim_tx)) {
+        debug!("victim tx reverts: {}", e);
+        return Ok(None);
+    }
+
+    let mut seen = HashSet::new();
+    let cycles: Vec<Cycle> = touched_pools
+        .iter()
+        .flat_map(|pool| graph.cycles_through(pool, weth))
+        .filter(|cycle| seen.insert(cycle.id()))
+        .collect();
+
+    // reserves after the victim
+    let mut reserves = HashMap::new();
+    for cycle in &cycles {
+        for hop in &cycle.hops {
+            let pool = hop.pool;
+            if reserves.contains_key(&pool.address) {
+                continue;
+            }
+            let pool_reserves = match pool.version {
+                DexVariant::UniswapV2 => simulator.get_pair_reserves(pool.address),
+                DexVariant::UniswapV3 => simulator.get_v3_pool_reserves(pool.address),
+            };
+            match pool_reserves {
+                Ok(pool_reserves) => {
+                    reserves.insert(pool.address, pool_reserves);
+                }
+                Err(e) => debug!("reserves of {:?} unavailable: {}", pool.address, e),
+            }
+        }
+    }
+
+    let mut quotes: Vec<(Cycle, U256, U256)> = cycles
+        .into_iter()
+        .filter_map(|cycle| {
+            let (amount_in, profit) = cycle.optimize(&reserves, max_amount_in)?;
+            Some((cycle, amount_in, profit))
+        })
+        .collect();
+    quotes.sort_by(|a, b| b.2.cmp(&a.2));
+    quotes.truncate(MAX_SIMULATED_CYCLES);
+    if quotes.is_empty() {
+        return Ok(None);
+    }
+
+    simulator.set_base_fee(base_fee);
+    let block_number = simulator.get_block_number().as_u64();
+    let post_victim = simulator.clone_db();
+
+    let mut best: Option<SimulatedBackrun> = None;
+    for (cycle, amount_in, _) in quotes {
+        simulator.insert_db(post_victim.clone());
+
+        let (payload, min_amount_out) = match cycle.payload(block_number, amount_in, &reserves) {
+            Some(payload) => payload,
+            None => continue,
+        };
+        let route_data = payload.encode(TradeLayout::SandoooV3)?;
+        let min_profit = min_amount_out.saturating_sub(amount_in);
+        let calldata = encode_route_call(&simulator.abi, min_profit, route_data)?;
+
+        let tx = Tx {
+            caller: simulator.owner,
+            transact_to: bot_address,
+            data: calldata.0.clone(),
+            value: U256::zero(),
+            gas_price: base_fee,
+            gas_limit: ROUTE_GAS_LIMIT,
+        };
+        let access_list = simulator.get_access_list(tx.clone()).unwrap_or_default();
+        simulator.set_access_list(access_list.clone());
+
+        let balance_before = simulator.get_token_balance(weth, bot_address)?;
+        let gas_used = match simulator.call(tx) {
+            Ok(result) => result.gas_used,
+            Err(e) => {
+                debug!("route {} reverts: {}", cycle.id(), e);
+                continue;
+            }
+        };
+        let balance_after = simulator.get_token_balance(weth, bot_address)?;
+
+        let backrun = SimulatedBackrun {
+            cycle,
+            amount_in,
+            calldata,
+            access_list,
+            gas_used,
+            revenue: balance_after.saturating_sub(balance_before),
+        };
+        let profit = backrun.profit(base_fee);
+        if profit.is_zero() {
+            continue;
+        }
+        if best.as_ref().map_or(true, |b| profit > b.profit(base_fee)) {
+            best = Some(backrun);
+        }
+    }
+    Ok(best)
+}
+
+/// Backruns pending swaps with WETH cycles across the pools of the same tokens.
+pub struct ArbitrageStrategy {
+    owner: H160,
+    bot_address: H160,
+    bribe_pct: U256,
+    debug: bool,
+    workers: usize,
+    queue_capacity: usize,
+    pool: Option<WorkerPool<PendingTxJob>>,
+}
+
+impl ArbitrageStrategy {
+    /// Creates a strategy for the bot and owner configured in the environment.
+    ///
+    /// # Returns
+    /// * `ArbitrageStrategy` - The strategy, bribing 99% of the profit.
+    pub fn new() -> Self {
+        let env = Env::new();
+        let bot_address = H160::from_str(&env.bot_address).unwrap();
+        let owner = env
+            .private_key
+            .parse::<LocalWallet>()
+            .unwrap()
+            .with_chain_id(1u64)
+            .address();
+        Self {
+            owner,
+            bot_address,
+            bribe_pct: U256::from(9900), // 99%
+            debug: env.debug,
+            workers: DEFAULT_WORKERS,
+            queue_capacity: DEFAULT_QUEUE_CAPACITY,
+            pool: None,
+        }
+    }
+
+    /// Sets the share of the profit paid as bribe, in basis points.
+    pub fn with_bribe_pct(mut self, bribe_pct: U256) -> Self {
+        self.bribe_pct = bribe_pct;
+        self
+    }
+
+    /// Sets the number of workers and the pending transactions queued for them.
+    pub fn with_workers(mut self, workers: usize, queue_capacity: usize) -> Self {
+        self.workers = workers;
+        self.queue_capacity = queue_capacity;
+        self
+    }
+}
+
+impl Default for ArbitrageStrategy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What the workers share: the configuration, the pool graph and the submitted bundle ids.
+struct Backrunner {
+    owner: H160,
+    bot_address: H160,
+    bribe_pct: U256,
+    debug: bool,
+    ctx: EngineContext,
+    graph: Arc<PoolGraph>,
+    simulated_bundle_ids: Mutex<BoundedVecDeque<String>>,
+    actions: ActionSender,
+}
+
+impl Backrunner {
+    /// Looks for a backrun of a pending transaction and sends its bundle to the engine.
+    async fn process(&self, job: PendingTxJob) -> Result<()> {
+        let ctx = &self.ctx;
+        let PendingTxJob {
+            new_block,
+            pending_tx,
+        } = &job;
+        let tx_hash = pending_tx.tx.hash;
+
+        let swap_info = ctx
+            .swaps
+            .get_or_extract(&ctx.provider, new_block, pending_tx, &ctx.pools_map)
+            .await?;
+        let touched_pools: Vec<Pool> = swap_info
+            .iter()
+            .filter_map(|info| ctx.pools_map.get(&info.target_pair).copied())
+            .collect();
+        if touched_pools.is_empty() {
+            return Ok(());
+        }
+
+        let victim_gas_price = match pending_tx.tx.transaction_type {
+            Some(tx_type) if tx_type == U64::from(2) => {
+                pending_tx.tx.max_fee_per_gas.unwrap_or_default()
+            }
+            _ => pending_tx.tx.gas_price.unwrap_or_default(),
+        };
+        let victim_tx = VictimTx {
+            tx_hash,
+            from: pending_tx.tx.from,
+            to: pending_tx.tx.to.unwrap_or_default(),
+            data: pending_tx.tx.input.0.clone().into(),
+            value: pending_tx.tx.value,
+            gas_price: victim_gas_price,
+            gas_limit: Some(pending_tx.tx.gas.as_u64()),
+        };
+
+        // the route trades from the contract's WETH inventory
+        let (owner, bot_address, max_amount_in) = if self.debug {
+            (None, None, U256::from(DEBUG_INVENTORY))
+        } else {
+            let weth = H160::from_str(WETH).unwrap();
+            let balance = get_token_balance(ctx.provider.clone(), self.bot_address, weth).await?;
+            (Some(self.owner), Some(self.bot_address), balance)
+        };
+        if max_amount_in.is_zero() {
+            return Ok(());
+        }
+
+        let base_fee = new_block.next_base_fee;
+        // revm blocks on its state reads, so the simulation runs off the async workers
+        let (provider, graph) = (ctx.provider.clone(), self.graph.clone());
+        let next_block = new_block.next_block.clone();
+        let backrun = tokio::task::spawn_blocking(move || {
+            let mut simulator = EvmSimulator::new(provider, owner, &next_block);
+            simulate_backrun(
+                &mut simulator,
+                &graph,
+                &touched_pools,
+                victim_tx,
+                bot_address,
+                max_amount_in,
+                base_fee,
+            )
+        })
+        .await??;
+        let backrun = match backrun {
+            Some(backrun) => backrun,
+            None => return Ok(()),
+        };
+
+        let bundle_id = format!("arb-{:?}-{}", tx_hash, backrun.cycle.id());
+        {
+            let mut simulated_bundle_ids = self.simulated_bundle_ids.lock().unwrap();
+            if simulated_bundle_ids.contains(&bundle_id) {
+                return Ok(());
+            }
+            simulated_bundle_ids.push_back(bundle_id.clone());
+        }
+
+        // the bribe is paid through the priority fee, out of the profit after the base fee
+        let profit = backrun.profit(base_fee);
+        let bribe_amount = profit * self.bribe_pct / U256::from(10000);
+        let realistic_gas_limit = (backrun.gas_used * 105) / 100;
+        let max_priority_fee_per_gas = bribe_amount / U256::from(realistic_gas_limit);
+        let max_fee_per_gas = base_fee + max_priority_fee_per_gas;
+        let gas_limit = (backrun.gas_used * 13) / 10;
+
+        let target_block = new_block.next_block.number;
+        info!(
+            "🔁 Arbitrage backrun: {} -> block {:?}",
+            bundle_id, target_block
+        );
+        info!(
+            "> Amount in: {:?} / Revenue: {:?} / Profit: {:?} / Bribe: {:?}",
+            backrun.amount_in, backrun.revenue, profit, bribe_amount
+        );
+
+        let bundle = match ctx
+            .executor
+            .create_v3_backrun_bundle(
+                vec![pending_tx.tx.clone()],
+                target_block,
+                backrun.calldata,
+                backrun.access_list,
+                gas_limit,
+                max_priority_fee_per_gas,
+                max_fee_per_gas,
+            )
+            .await
+        {
+            Ok(bundle) => bundle,
+            Err(e) => {
+                warn!("Executor.create_v3_backrun_bundle error: {e:?}");
+                return Ok(());
+            }
+        };
+
+        let message = format!(
+            "[{:?}] Arbitrage / Hops: {} / Gas: {:?} / Bribe: {:?}",
+            bundle_id,
+            backrun.cycle.hops.len(),
+            backrun.gas_used,
+            bribe_amount,
+        );
+        self.actions.send(vec![
+            Action::Alert(message),
+            Action::SubmitBundle(BundleSubmission {
+                id: bundle_id,
+                bundle,
+                next_block: new_block.next_block.clone(),
+            }),
+        ]);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Strategy for ArbitrageStrategy {
+    fn name(&self) -> &str {
+        "arbitrage"
+    }
+
+    async fn start(&mut self, ctx: &EngineContext, actions: ActionSender) -> Result<()> {
+        let graph = PoolGraph::new(&ctx.pools_map);
+        info!("Arbitrage pool graph: {} tokens", graph.len());
+
+        let backrunner = Arc::new(Backrunner {
+            owner: self.owner,
+            bot_address: self.bot_address,
+            bribe_pct: self.bribe_pct,
+            debug: self.debug,
+            ctx: ctx.clone(),
+            graph: Arc::new(graph),
+            simulated_bundle_ids: Mutex::new(BoundedVecDeque::new(30)),
+            actions,
+        });
+        let pool = WorkerPool::spawn(
+            self.name(),
+            self.workers,
+            self.queue_capacity,
+            ctx.metrics.clone(),
+            move |job: PendingTxJob| {
+                let backrunner = backrunner.clone();
+                async move { backrunner.process(job).await }
+            },
+        );
+        self.pool = Some(pool);
+        Ok(())
+    }
+
+    async fn on_block(&mut self, _ctx: &EngineContext) -> Result<Vec<Action>> {
+        Ok(Vec::new())
+    }
+
+    async fn on_pending_tx(
+        &mut self,
+        ctx: &EngineContext,
+        pending_tx: &NewPendingTx,
+    ) -> Result<Vec<Action>> {
+        let pool = self
+            .pool
+            .as_ref()
+            .ok_or_else(|| anyhow!("{} not started", self.name()))?;
+        pool.submit(PendingTxJob {
+            new_block: ctx.new_block.clone(),
+            pending_tx: pending_tx.clone(),
+        });
+        Ok(Vec::new())
+    }
+
+    async fn on_bundle_result(
+        &mut self,
+        _ctx: &EngineContext,
+        result: &BundleResult,
+    ) -> Result<Vec<Action>> {
+        match &result.responses {
+            Ok(responses) => {
+                let message = format!(
+                    "[{:?}] Bundle sent to {} builders ({})",
+                    result.target_block,
+                    responses.len(),
+                    result.id
+                );
+                Ok(vec![Action::Alert(message)])
+            }
+            Err(e) => {
+                warn!("send_sando_bundle_request error ({}): {e:?}", result.id);
+                Ok(Vec::new())
+            }
+        }
+    }
+}
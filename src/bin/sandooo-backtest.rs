@@ -0,0 +1,110 @@
+//! sandooo-backtest - replays a range of mined blocks through the sandwich pipeline
+//!
+//! Usage:
+//!   sandooo-backtest <from_block> <to_block> [--out backtest.json] [--fixtures DIR]
+//!                    [--events LOG] [--bribe-pct BPS]
+//!
+//! Victims are taken from the mined blocks, or from a mempool log recorded with RECORD_EVENTS
+//! (`--events`). Both contracts are backtested side by side and the reports written as JSON.
+//! `--fixtures DIR` reads blocks recorded with `sandooo-inspect --record` when available.
+//! WSS_URL must point to a node with archive state for the range.
+use anyhow::{anyhow, Result};
+use dotenv::dotenv;
+use ethers::providers::{Provider, Ws};
+use ethers::types::{H160, U256};
+use log::info;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use sandooo::common::constants::Env;
+use sandooo::common::engine::{POOLS_CHUNK, POOLS_START_BLOCK};
+use sandooo::common::pools::{load_all_pools, Pool};
+use sandooo::common::recorder::ReplaySource;
+use sandooo::sandwich::backtest::{
+    pending_by_block, write_reports, Backtester, StrategyVersion, VictimSource,
+};
+use sandooo::sandwich::simulation::SandoContract;
+
+struct Args {
+    from_block: u64,
+    to_block: u64,
+    out: PathBuf,
+    fixtures: Option<PathBuf>,
+    events: Option<PathBuf>,
+    bribe_pct: u64,
+}
+
+fn parse_args() -> Result<Args> {
+    let usage = "usage: sandooo-backtest <from_block> <to_block> [--out FILE] [--fixtures DIR] \
+                 [--events LOG] [--bribe-pct BPS]";
+    let mut args = std::env::args().skip(1);
+    let from_block = args.next().ok_or_else(|| anyhow!(usage))?.parse()?;
+    let to_block = args.next().ok_or_else(|| anyhow!(usage))?.parse()?;
+    let mut parsed = Args {
+        from_block,
+        to_block,
+        out: PathBuf::from("backtest.json"),
+        fixtures: None,
+        events: None,
+        bribe_pct: 9900,
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--out" => parsed.out = args.next().ok_or_else(|| anyhow!(usage))?.into(),
+            "--fixtures" => {
+                parsed.fixtures = Some(args.next().ok_or_else(|| anyhow!(usage))?.into())
+            }
+            "--events" => parsed.events = Some(args.next().ok_or_else(|| anyhow!(usage))?.into()),
+            "--bribe-pct" => {
+                parsed.bribe_pct = args.next().ok_or_else(|| anyhow!(usage))?.parse()?
+            }
+            _ => return Err(anyhow!(usage)),
+        }
+    }
+    if parsed.to_block < parsed.from_block {
+        return Err(anyhow!("to_block is before from_block"));
+    }
+    Ok(parsed)
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenv().ok();
+    fern::Dispatch::new()
+        .level(log::LevelFilter::Info)
+        .chain(std::io::stdout())
+        .apply()?;
+
+    let args = parse_args()?;
+    let env = Env::new();
+    let ws = Ws::connect(&env.wss_url).await?;
+    let provider = Arc::new(Provider::new(ws));
+
+    let (pools, _) = load_all_pools(env.wss_url.clone(), POOLS_START_BLOCK, POOLS_CHUNK).await?;
+    let pools_map: HashMap<H160, Pool> = pools.into_iter().map(|p| (p.address, p)).collect();
+
+    let source = match &args.events {
+        Some(path) => VictimSource::Recorded(pending_by_block(ReplaySource::load(path)?.events())),
+        None => VictimSource::MinedBlocks,
+    };
+    let version = env!("CARGO_PKG_VERSION");
+    let bribe_pct = U256::from(args.bribe_pct);
+    let versions = vec![
+        StrategyVersion::new(&format!("{}-sandooo", version), SandoContract::Sandooo, bribe_pct),
+        StrategyVersion::new(&format!("{}-v3", version), SandoContract::SandoooV3, bribe_pct),
+    ];
+
+    let mut backtester = Backtester::new(provider, pools_map, source, versions);
+    if let Some(dir) = &args.fixtures {
+        backtester = backtester.with_fixtures(dir);
+    }
+    let reports = backtester.run(args.from_block, args.to_block).await;
+
+    for report in &reports {
+        info!("{}", report.summary());
+    }
+    write_reports(&args.out, &reports)?;
+    info!("Wrote {} reports to {:?}", reports.len(), args.out);
+    Ok(())
+}
@@ -0,0 +1,542 @@
+//! sandooo-devnet - runs the sandwich pipeline end to end against a local anvil
+//!
+//! Usage:
+//!   sandooo-devnet [--anvil PATH] [--port PORT] [--victims N] [--timeout SECS]
+//!
+//! Starts anvil with no fork, deploys the devnet contracts from `contracts/src/devnet` (build them
+//! first with `forge build` in `contracts/`), seeds WETH/token pairs, deploys `Sandooo` and the
+//! pinned `SANDOOO_V3_BYTECODE` (with a mock Aave pool) and funds their inventory. Each contract then gets its own
+//! round of victim swaps, sent into anvil's mempool and passed through the `Engine` and a
+//! `SandwichStrategy` in the matching mode (`extract_swap_info` -> `appetizer` -> `main_dish` or
+//! `main_dish_v3`), whose bundles go to a `MockBuilder` mining them on anvil.
+//!
+//! A last round simulates a trade through `executeSandwichWithFlashloan` against the mock Aave
+//! pool and sends it, checking that it lands with the simulated premium.
+//!
+//! WETH and the Aave pool are placed at their mainnet addresses, since the quote assets and the
+//! flashloan premium are keyed on them.
+use anyhow::{anyhow, Result};
+use ethers::abi::{encode, Token};
+use ethers::providers::{Middleware, Provider, Ws};
+use ethers::signers::{LocalWallet, Signer};
+use ethers::types::{
+    BlockNumber, Bytes, TransactionReceipt, TransactionRequest, H160, H256, U256, U64,
+};
+use ethers::utils::{id, keccak256};
+use log::{error, info, warn};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::process::{Child, Command};
+use tokio::sync::broadcast;
+
+use sandooo::common::bytecode::{forge_artifact, SANDOOO_V3_BYTECODE};
+use sandooo::common::constants::{AAVE_V3_POOL, WETH};
+use sandooo::common::engine::{Engine, EngineContext};
+use sandooo::common::evm::VictimTx;
+use sandooo::common::mock_builder::MockBuilder;
+use sandooo::common::pools::{DexVariant, Pool};
+use sandooo::common::streams::{Event, NewBlock};
+use sandooo::sandwich::simulation::{
+    get_v2_amount_out, BatchSandwich, Sandwich, SwapDirection, SwapInfo,
+};
+use sandooo::sandwich::strategy::{SandwichMode, SandwichStrategy};
+
+/// Anvil's default dev accounts 0, 1 and 2: bot owner, relay identity and victim.
+const OWNER_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+const IDENTITY_KEY: &str = "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+const VICTIM_KEY: &str = "0x5de4111afa1a4b94908f83103eb1f1706367c2e68ca870fc3fb9a804cdab365a";
+
+/// WETH9 keeps name, symbol and decimals in its first slots.
+const WETH_METADATA_SLOTS: u64 = 3;
+
+struct Args {
+    anvil: String,
+    port: u16,
+    victims: usize,
+    timeout: u64,
+}
+
+fn parse_args() -> Result<Args> {
+    let usage = "usage: sandooo-devnet [--anvil PATH] [--port PORT] [--victims N] [--timeout SECS]";
+    let mut args = std::env::args().skip(1);
+    let mut parsed = Args {
+        anvil: "anvil".to_string(),
+        port: 8545,
+        victims: 3,
+        timeout: 30,
+    };
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or_else(|| anyhow!(usage))?;
+        match arg.as_str() {
+            "--anvil" => parsed.anvil = value,
+            "--port" => parsed.port = value.parse()?,
+            "--victims" => parsed.victims = value.parse()?,
+            "--timeout" => parsed.timeout = value.parse()?,
+            _ => return Err(anyhow!(usage)),
+        }
+    }
+    Ok(parsed)
+}
+
+/// Starts anvil as mainnet (chain id 1, which the executor signs for) and connects to it.
+async fn start_anvil(args: &Args) -> Result<(Child, Arc<Provider<Ws>>)> {
+    let child = Command::new(&args.anvil)
+        .args(["--port", &args.port.to_string()])
+        .args(["--chain-id", "1", "--order", "fifo", "--silent"])
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| anyhow!("Could not start {}: {:?}", args.anvil, e))?;
+
+    let url = format!("ws://127.0.0.1:{}", args.port);
+    for _ in 0..50 {
+        if let Ok(ws) = Ws::connect(&url).await {
+            return Ok((child, Arc::new(Provider::new(ws))));
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+    Err(anyhow!("anvil did not come up on {}", url))
+}
+
+fn calldata(signature: &str, args: &[Token]) -> Bytes {
+    let mut data = id(signature).to_vec();
+    data.extend(encode(args));
+    data.into()
+}
+
+/// Sends a transaction from an unlocked dev account and waits for it to succeed.
+async fn send(
+    provider: &Arc<Provider<Ws>>,
+    from: H160,
+    to: Option<H160>,
+    data: Bytes,
+    value: U256,
+) -> Result<TransactionReceipt> {
+    let mut tx = TransactionRequest::new().from(from).data(data).value(value);
+    if let Some(to) = to {
+        tx = tx.to(to);
+    }
+    let receipt = provider
+        .send_transaction(tx, None)
+        .await?
+        .await?
+        .ok_or_else(|| anyhow!("Transaction dropped"))?;
+    if receipt.status != Some(U64::from(1)) {
+        return Err(anyhow!("Transaction {:?} reverted", receipt.transaction_hash));
+    }
+    Ok(receipt)
+}
+
+async fn deploy(
+    provider: &Arc<Provider<Ws>>,
+    from: H160,
+    init_code: Bytes,
+    constructor_args: &[Token],
+) -> Result<H160> {
+    let mut data = init_code.to_vec();
+    data.extend(encode(constructor_args));
+    let receipt = send(provider, from, None, data.into(), U256::zero()).await?;
+    receipt
+        .contract_address
+        .ok_or_else(|| anyhow!("No contract address"))
+}
+
+/// Moves a deployed contract (code and the given storage slots) to a fixed address.
+async fn place_at(
+    provider: &Arc<Provider<Ws>>,
+    deployed: H160,
+    target: H160,
+    slots: u64,
+) -> Result<()> {
+    let code = provider.get_code(deployed, None).await?;
+    provider
+        .request::<_, ()>("anvil_setCode", (target, code))
+        .await?;
+    for slot in 0..slots {
+        let slot = H256::from_low_u64_be(slot);
+        let value = provider.get_storage_at(deployed, slot, None).await?;
+        provider
+            .request::<_, ()>("anvil_setStorageAt", (target, slot, value))
+            .await?;
+    }
+    Ok(())
+}
+
+async fn token_balance(provider: &Arc<Provider<Ws>>, token: H160, owner: H160) -> Result<U256> {
+    let tx = TransactionRequest::new()
+        .to(token)
+        .data(calldata("balanceOf(address)", &[Token::Address(owner)]));
+    let output = provider.call(&tx.into(), None).await?;
+    Ok(U256::from_big_endian(&output))
+}
+
+/// Returns the (WETH, token) reserves of a devnet pair.
+async fn weth_reserves(
+    provider: &Arc<Provider<Ws>>,
+    pool: &Pool,
+    weth: H160,
+) -> Result<(U256, U256)> {
+    let tx = TransactionRequest::new()
+        .to(pool.address)
+        .data(calldata("getReserves()", &[]));
+    let output = provider.call(&tx.into(), None).await?;
+    let reserve0 = U256::from_big_endian(&output[0..32]);
+    let reserve1 = U256::from_big_endian(&output[32..64]);
+    if pool.token0 == weth {
+        Ok((reserve0, reserve1))
+    } else {
+        Ok((reserve1, reserve0))
+    }
+}
+
+/// Addresses of the deployed scenario.
+struct Devnet {
+    victim: H160,
+    weth: H160,
+    router: H160,
+    /// `Sandooo`, run by `SandwichMode::Traditional`.
+    sandooo: H160,
+    sandooo_v3: H160,
+    pools: Vec<Pool>,
+}
+
+/// Deploys the contracts and seeds liquidity and inventory.
+async fn deploy_devnet(provider: &Arc<Provider<Ws>>) -> Result<Devnet> {
+    let owner = OWNER_KEY.parse::<LocalWallet>()?.address();
+    let victim = VICTIM_KEY.parse::<LocalWallet>()?.address();
+    let ether = U256::exp10(18);
+
+    let weth = H160::from_str(WETH).unwrap();
+    let devnet_weth_code = forge_artifact("DevnetWETH.sol", "DevnetWETH")?;
+    let devnet_weth = deploy(provider, owner, devnet_weth_code, &[]).await?;
+    place_at(provider, devnet_weth, weth, WETH_METADATA_SLOTS).await?;
+
+    let aave_pool = H160::from_str(AAVE_V3_POOL).unwrap();
+    let mock_pool_code = forge_artifact("MockAavePool.sol", "MockAavePool")?;
+    let mock_pool = deploy(provider, owner, mock_pool_code, &[]).await?;
+    place_at(provider, mock_pool, aave_pool, 0).await?;
+
+    let factory_code = forge_artifact("DevnetUniswapV2.sol", "DevnetFactory")?;
+    let factory = deploy(provider, owner, factory_code, &[]).await?;
+    let router = deploy(
+        provider,
+        owner,
+        forge_artifact("DevnetUniswapV2.sol", "DevnetRouter")?,
+        &[Token::Address(factory), Token::Address(weth)],
+    )
+    .await?;
+
+    send(provider, owner, Some(weth), calldata("deposit()", &[]), ether * 1000).await?;
+
+    let mut pools = Vec::new();
+    for (i, symbol) in ["DEVA", "DEVB"].iter().enumerate() {
+        let token = deploy(
+            provider,
+            owner,
+            forge_artifact("DevnetERC20.sol", "DevnetERC20")?,
+            &[
+                Token::String(format!("Devnet {}", symbol)),
+                Token::String(symbol.to_string()),
+                Token::Uint(U256::from(18)),
+            ],
+        )
+        .await?;
+        let receipt = send(
+            provider,
+            owner,
+            Some(factory),
+            calldata("createPair(address,address)", &[Token::Address(weth), Token::Address(token)]),
+            U256::zero(),
+        )
+        .await?;
+        let pair = H160::from_slice(&receipt.logs[0].data[12..32]);
+
+        // 100 WETH against 1M tokens
+        let transfer = "transfer(address,uint256)";
+        let seed_weth = calldata(transfer, &[Token::Address(pair), Token::Uint(ether * 100)]);
+        send(provider, owner, Some(weth), seed_weth, U256::zero()).await?;
+        let mint = calldata(
+            "mint(address,uint256)",
+            &[Token::Address(pair), Token::Uint(ether * 1_000_000)],
+        );
+        send(provider, owner, Some(token), mint, U256::zero()).await?;
+        send(provider, owner, Some(pair), calldata("sync()", &[]), U256::zero()).await?;
+
+        let (token0, token1) = if weth < token { (weth, token) } else { (token, weth) };
+        pools.push(Pool {
+            id: i as i64,
+            address: pair,
+            version: DexVariant::UniswapV2,
+            token0,
+            token1,
+            fee: 300,
+            block_number: receipt.block_number.unwrap_or_default().as_u64(),
+            timestamp: 0,
+        });
+        info!("{} pair {:?} (token {:?})", symbol, pair, token);
+    }
+
+    // flashloan liquidity, and the inventory the executeLeg legs trade from
+    let transfer = "transfer(address,uint256)";
+    let fund_pool = calldata(transfer, &[Token::Address(aave_pool), Token::Uint(ether * 100)]);
+    send(provider, owner, Some(weth), fund_pool, U256::zero()).await?;
+    let sandooo = deploy(provider, owner, forge_artifact("Sandooo.sol", "Sandooo")?, &[]).await?;
+    let sandooo_v3 = deploy(
+        provider,
+        owner,
+        SANDOOO_V3_BYTECODE.clone(),
+        &[Token::Address(aave_pool)],
+    )
+    .await?;
+    for (name, bot_address) in [("Sandooo", sandooo), ("SandoooV3", sandooo_v3)] {
+        let fund_bot = calldata(transfer, &[Token::Address(bot_address), Token::Uint(ether * 50)]);
+        send(provider, owner, Some(weth), fund_bot, U256::zero()).await?;
+        info!("{} {:?} funded with 50 WETH", name, bot_address);
+    }
+
+    Ok(Devnet {
+        victim,
+        weth,
+        router,
+        sandooo,
+        sandooo_v3,
+        pools,
+    })
+}
+
+/// Whether every transaction of a mined bundle succeeded.
+async fn bundle_landed(provider: &Arc<Provider<Ws>>, txs: &[Bytes]) -> bool {
+    for tx in txs {
+        let tx_hash = H256::from(keccak256(tx));
+        match provider.get_transaction_receipt(tx_hash).await {
+            Ok(Some(receipt)) if receipt.status == Some(U64::from(1)) => {}
+            _ => return false,
+        }
+    }
+    true
+}
+
+/// Sends `args.victims` swaps and returns how many of them landed sandwiched in a bundle.
+async fn sandwich_victims(
+    provider: &Arc<Provider<Ws>>,
+    devnet: &Devnet,
+    builder: &MockBuilder,
+    args: &Args,
+) -> Result<usize> {
+    let mut sandwiched = 0;
+    for i in 0..args.victims {
+        let pool = &devnet.pools[i % devnet.pools.len()];
+        let token = if pool.token0 == devnet.weth {
+            pool.token1
+        } else {
+            pool.token0
+        };
+        let (reserve_weth, reserve_token) = weth_reserves(provider, pool, devnet.weth).await?;
+
+        // a 5 ETH buy with 3% slippage leaves room for a frontrun
+        let amount_in = U256::exp10(18) * 5;
+        let min_out = get_v2_amount_out(amount_in, reserve_weth, reserve_token) * 97 / 100;
+        let swap = calldata(
+            "swapExactETHForTokens(uint256,address[],address,uint256)",
+            &[
+                Token::Uint(min_out),
+                Token::Array(vec![Token::Address(devnet.weth), Token::Address(token)]),
+                Token::Address(devnet.victim),
+                Token::Uint(U256::MAX),
+            ],
+        );
+        let tx = TransactionRequest::new()
+            .from(devnet.victim)
+            .to(devnet.router)
+            .data(swap)
+            .value(amount_in);
+        let victim_hash = *provider.send_transaction(tx, None).await?;
+        info!("Victim {} sent: {:?}", i, victim_hash);
+
+        let mut landed = false;
+        for _ in 0..args.timeout {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            if provider.get_transaction_receipt(victim_hash).await?.is_some() {
+                break;
+            }
+        }
+        for bundle in builder.bundles() {
+            let victim_raw = bundle
+                .txs
+                .iter()
+                .any(|tx| H256::from(keccak256(tx)) == victim_hash);
+            if victim_raw && bundle.included && bundle_landed(provider, &bundle.txs).await {
+                landed = true;
+            }
+        }
+        if provider.get_transaction_receipt(victim_hash).await?.is_none() {
+            // no bundle for this victim, mine it on its own
+            provider.request::<_, serde_json::Value>("evm_mine", ()).await?;
+        }
+        if landed {
+            sandwiched += 1;
+            info!("Victim {} sandwiched", i);
+        } else {
+            warn!("Victim {} not sandwiched", i);
+        }
+    }
+    Ok(sandwiched)
+}
+
+/// Simulates a 1 WETH trade through `SandoooV3.executeSandwichWithFlashloan` against the mock
+/// Aave pool, then sends the simulated call and checks it lands with the simulated premium.
+///
+/// Both trades run back to back with no victim between them, so they lose their fees; the bot's
+/// inventory covers the loss and the rest of it is swept to the owner.
+async fn flashloan_round(provider: &Arc<Provider<Ws>>, devnet: &Devnet) -> Result<()> {
+    provider.request::<_, ()>("evm_setAutomine", [true]).await?;
+    let owner = OWNER_KEY.parse::<LocalWallet>()?.address();
+    let aave_pool = H160::from_str(AAVE_V3_POOL).unwrap();
+
+    let pool = &devnet.pools[0];
+    let token0_is_main = pool.token0 == devnet.weth;
+    let swap_info = SwapInfo {
+        tx_hash: H256::zero(),
+        target_pair: pool.address,
+        main_currency: devnet.weth,
+        target_token: if token0_is_main { pool.token1 } else { pool.token0 },
+        version: DexVariant::UniswapV2,
+        token0_is_main,
+        fee: pool.fee,
+        direction: SwapDirection::Buy,
+    };
+    let mut batch = BatchSandwich::new(devnet.weth);
+    batch.swap_info_vec.push(swap_info.clone());
+    batch.sandwiches.push(Sandwich {
+        amount_in: U256::exp10(18),
+        swap_info,
+        victim_tx: VictimTx::default(),
+        optimized_sandwich: None,
+    });
+
+    let block = provider
+        .get_block(BlockNumber::Latest)
+        .await?
+        .ok_or_else(|| anyhow!("No latest block"))?;
+    let new_block = NewBlock::from_block(&block).ok_or_else(|| anyhow!("Pending block"))?;
+    let simulated = batch
+        .simulate_flashloan(
+            provider.clone(),
+            Some(owner),
+            &new_block.next_block,
+            new_block.next_base_fee,
+            None,
+            Some(devnet.sandooo_v3),
+        )
+        .await?;
+
+    // automine puts it in the block the calldata is pinned to
+    let receipt = send(
+        provider,
+        owner,
+        Some(devnet.sandooo_v3),
+        simulated.front_calldata.clone(),
+        U256::zero(),
+    )
+    .await?;
+    let event_id = H256::from(keccak256(
+        "FlashLoan(address,address,address,uint256,uint8,uint256,uint16)",
+    ));
+    let premium = receipt
+        .logs
+        .iter()
+        .find(|log| log.address == aave_pool && log.topics.first() == Some(&event_id))
+        .map(|log| U256::from_big_endian(&log.data[96..128]))
+        .ok_or_else(|| anyhow!("No FlashLoan event in {:?}", receipt.transaction_hash))?;
+
+    info!(
+        "Flashloan: premium {} (simulated {}) / gas {} (simulated {})",
+        premium,
+        simulated.flashloan_premium,
+        receipt.gas_used.unwrap_or_default(),
+        simulated.front_gas_used
+    );
+    if premium != simulated.flashloan_premium {
+        return Err(anyhow!("Flashloan premium differs from the simulation"));
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    fern::Dispatch::new()
+        .level(log::LevelFilter::Info)
+        .chain(std::io::stdout())
+        .apply()?;
+
+    let args = parse_args()?;
+    let (_anvil, provider) = start_anvil(&args).await?;
+    let devnet = deploy_devnet(&provider).await?;
+
+    let builder = MockBuilder::new(provider.clone());
+    let builder_url = builder.spawn().await?;
+    info!("Mock builder listening on {}", builder_url);
+
+    // the executor and strategy read their config from the environment
+    std::env::set_var("WSS_URL", format!("ws://127.0.0.1:{}", args.port));
+    std::env::set_var("HTTPS_URL", format!("http://127.0.0.1:{}", args.port));
+    std::env::set_var("PRIVATE_KEY", OWNER_KEY);
+    std::env::set_var("IDENTITY_KEY", IDENTITY_KEY);
+    std::env::set_var("BUILDER_URLS", format!("devnet={}", builder_url));
+    std::env::set_var("USE_ALERT", "false");
+    std::env::set_var("DEBUG", "false");
+
+    // from here on victims wait in the mempool until a bundle (or the scenario) mines them
+    provider.request::<_, ()>("evm_setAutomine", [false]).await?;
+
+    let pools_map: HashMap<H160, Pool> =
+        devnet.pools.iter().map(|p| (p.address, p.clone())).collect();
+    let rounds = [
+        (SandwichMode::Traditional, devnet.sandooo),
+        (SandwichMode::SandoooV3, devnet.sandooo_v3),
+    ];
+    let mut failed = Vec::new();
+    for (mode, bot_address) in rounds {
+        // the strategy reads its contract when it is created
+        std::env::set_var("BOT_ADDRESS", format!("{:?}", bot_address));
+
+        let (event_sender, _) = broadcast::channel::<Event>(512);
+        let context =
+            EngineContext::new(provider.clone(), pools_map.clone(), HashMap::new()).await?;
+        // blocks are only mined on demand, so don't wait for a cut-off deep into the slot
+        let strategy = SandwichStrategy::new(mode).with_cutoffs(vec![Duration::from_secs(1)]);
+        let engine = Engine::new(context).with_strategy(strategy);
+        engine.spawn_streams(&event_sender);
+        let engine = tokio::spawn(async move {
+            if let Err(e) = engine.run(event_sender).await {
+                error!("Devnet engine stopped: {e:?}");
+            }
+        });
+        // let the subscriptions start before the first victim
+        tokio::time::sleep(Duration::from_secs(1)).await;
+
+        let bundles_before = builder.bundles().len();
+        let inventory_before = token_balance(&provider, devnet.weth, bot_address).await?;
+        let sandwiched = sandwich_victims(&provider, &devnet, &builder, &args).await?;
+        let inventory_after = token_balance(&provider, devnet.weth, bot_address).await?;
+        engine.abort();
+
+        info!(
+            "{:?}: {}/{} victims sandwiched / {} bundles received / WETH inventory {} -> {}",
+            mode,
+            sandwiched,
+            args.victims,
+            builder.bundles().len() - bundles_before,
+            inventory_before,
+            inventory_after
+        );
+        if sandwiched == 0 {
+            failed.push(mode);
+        }
+    }
+    if !failed.is_empty() {
+        return Err(anyhow!("No victim was sandwiched in {:?}", failed));
+    }
+    flashloan_round(&provider, &devnet).await
+}
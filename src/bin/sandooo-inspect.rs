@@ -0,0 +1,99 @@
+//! sandooo-inspect - finds the sandwiches of a range of mined blocks
+//!
+//! Usage:
+//!   sandooo-inspect <from_block> <to_block> [--out sandwiches.csv] [--record DIR] [--no-replay]
+//!
+//! Writes one CSV row per sandwich (searcher, victim, pool, profit, coinbase payment).
+//! `--record DIR` also saves every block with its receipts as a JSON fixture, and `--no-replay`
+//! skips replaying blocks in revm (coinbase payments are then priority fees only).
+use anyhow::{anyhow, Result};
+use dotenv::dotenv;
+use ethers::providers::{Provider, Ws};
+use ethers::types::H160;
+use log::{info, warn};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use sandooo::common::constants::Env;
+use sandooo::common::engine::{POOLS_CHUNK, POOLS_START_BLOCK};
+use sandooo::common::pools::{load_all_pools, Pool};
+use sandooo::sandwich::inspect::{
+    inspect_block, replay_coinbase_transfers, write_csv, BlockFixture,
+};
+
+struct Args {
+    from_block: u64,
+    to_block: u64,
+    out: PathBuf,
+    record: Option<PathBuf>,
+    replay: bool,
+}
+
+fn parse_args() -> Result<Args> {
+    let usage = "usage: sandooo-inspect <from_block> <to_block> [--out FILE] [--record DIR] [--no-replay]";
+    let mut args = std::env::args().skip(1);
+    let from_block = args.next().ok_or_else(|| anyhow!(usage))?.parse()?;
+    let to_block = args.next().ok_or_else(|| anyhow!(usage))?.parse()?;
+    let mut parsed = Args {
+        from_block,
+        to_block,
+        out: PathBuf::from("sandwiches.csv"),
+        record: None,
+        replay: true,
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--out" => parsed.out = args.next().ok_or_else(|| anyhow!(usage))?.into(),
+            "--record" => parsed.record = Some(args.next().ok_or_else(|| anyhow!(usage))?.into()),
+            "--no-replay" => parsed.replay = false,
+            _ => return Err(anyhow!(usage)),
+        }
+    }
+    if parsed.to_block < parsed.from_block {
+        return Err(anyhow!("to_block is before from_block"));
+    }
+    Ok(parsed)
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenv().ok();
+    fern::Dispatch::new()
+        .level(log::LevelFilter::Info)
+        .chain(std::io::stdout())
+        .apply()?;
+
+    let args = parse_args()?;
+    let env = Env::new();
+    let ws = Ws::connect(&env.wss_url).await?;
+    let provider = Arc::new(Provider::new(ws));
+
+    let (pools, _) = load_all_pools(env.wss_url.clone(), POOLS_START_BLOCK, POOLS_CHUNK).await?;
+    let pools_map: HashMap<H160, Pool> = pools.into_iter().map(|p| (p.address, p)).collect();
+
+    if let Some(dir) = &args.record {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let mut sandwiches = Vec::new();
+    for number in args.from_block..=args.to_block {
+        let fixture = BlockFixture::fetch(&provider, number).await?;
+        if let Some(dir) = &args.record {
+            fixture.save(dir.join(format!("{}.json", number)))?;
+        }
+
+        let mut found = inspect_block(&fixture.block, &fixture.receipts, &pools_map);
+        if args.replay && !found.is_empty() {
+            if let Err(e) = replay_coinbase_transfers(provider.clone(), &fixture.block, &mut found) {
+                warn!("Block {} replay failed, using priority fees only: {e:?}", number);
+            }
+        }
+        info!("Block {}: {} sandwiches", number, found.len());
+        sandwiches.extend(found);
+    }
+
+    write_csv(&args.out, &sandwiches)?;
+    info!("Wrote {} sandwiches to {:?}", sandwiches.len(), args.out);
+    Ok(())
+}
@@ -15,7 +15,7 @@ use ethers::prelude::BaseContract;
 pub struct Abi {
     /// Factory contract ABI (UniswapV2-like), with `getPair(address,address)`
     pub factory: BaseContract,
-    /// Pair contract ABI, with `token0()`, `token1()`, and `getReserves()`
+    /// Pair contract ABI, with `token0()`, `token1()`, `getReserves()` and `swap(...)`
     pub pair: BaseContract,
     /// ERC-20 token contract ABI, with standard token functions
     pub token: BaseContract,
@@ -23,6 +23,8 @@ pub struct Abi {
     pub sando_bot: BaseContract,
     /// SandoooV3 contract ABI with Aave V3 flashloan integration
     pub sando_v3: BaseContract,
+    /// Chainlink aggregator ABI, with `latestRoundData()`
+    pub price_feed: BaseContract,
 }
 
 impl Abi {
@@ -44,6 +46,7 @@ impl Abi {
                 "function token0() external view returns (address)",
                 "function token1() external view returns (address)",
                 "function getReserves() external view returns (uint112,uint112,uint32)",
+                "function swap(uint256,uint256,address,bytes) external",
             ])
             .unwrap(),
         );
@@ -69,7 +72,9 @@ impl Abi {
 
         let sando_v3 = BaseContract::from(
             parse_abi(&[
+                "function executeLeg(bytes) external",
                 "function executeSandwichWithFlashloan(address,uint256,bytes) external",
+                "function executeRoute(uint256,bytes) external",
                 "function executeOperation(address,uint256,uint256,address,bytes) external returns (bool)",
                 "function recoverToken(address,uint256) external",
                 "function recoverETH() external",
@@ -77,12 +82,20 @@ impl Abi {
             .unwrap(),
         );
 
+        let price_feed = BaseContract::from(
+            parse_abi(&[
+                "function latestRoundData() external view returns (uint80,int256,uint256,uint256,uint80)",
+            ])
+            .unwrap(),
+        );
+
         Self {
             factory,
             pair,
             token,
             sando_bot,
             sando_v3,
+            price_feed,
         }
     }
 }
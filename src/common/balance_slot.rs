@@ -0,0 +1,225 @@
+/// ERC-20 balance storage slot discovery.
+///
+/// Traces every SLOAD executed by `balanceOf` (including the ones made through delegatecalls into
+/// proxy implementations), matches the loaded keys against Solidity and Vyper mapping layouts and
+/// verifies each candidate by writing a marker value and reading `balanceOf` back.
+use anyhow::Result;
+use ethers::prelude::abi;
+use ethers::providers::Middleware;
+use ethers::types::{H160, U256};
+use log::debug;
+use revm::interpreter::{opcode, InstructionResult, Interpreter};
+use revm::primitives::{keccak256, TransactTo, B160, U256 as rU256};
+use revm::{Database, EVMData, Inspector};
+use serde::{Deserialize, Serialize};
+
+use crate::common::evm::EvmSimulator;
+use crate::common::token_registry::get_token_registry;
+use crate::common::utils::create_new_wallet;
+
+/// Highest mapping slot index we try to match traced storage keys against.
+const MAX_BALANCE_SLOT: i32 = 200;
+
+/// How a `mapping(address => uint256)` derives the storage key of an entry.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MappingLayout {
+    /// Solidity: `keccak256(abi.encode(key, slot))`
+    #[default]
+    Solidity,
+    /// Vyper: `keccak256(abi.encode(slot, key))`
+    Vyper,
+}
+
+impl MappingLayout {
+    /// Computes the storage key of `owner` in a mapping stored at `slot`.
+    ///
+    /// # Parameters
+    /// * `owner`: H160 - The mapping key.
+    /// * `slot`: i32 - The slot of the mapping.
+    ///
+    /// # Returns
+    /// * `rU256` - The storage key of the entry.
+    pub fn storage_key(&self, owner: H160, slot: i32) -> rU256 {
+        let owner = abi::Token::Address(owner);
+        let slot = abi::Token::Uint(U256::from(slot));
+        let encoded = match self {
+            MappingLayout::Solidity => abi::encode(&[owner, slot]),
+            MappingLayout::Vyper => abi::encode(&[slot, owner]),
+        };
+        U256::from(keccak256(&encoded)).into()
+    }
+}
+
+/// A verified location of the balances mapping of an ERC-20 token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BalanceSlot {
+    /// Slot of the balances mapping.
+    pub slot: i32,
+    /// Key derivation used by the mapping.
+    pub layout: MappingLayout,
+    /// Contract whose storage holds the mapping (the proxy itself for delegatecall proxies).
+    pub storage_address: H160,
+}
+
+impl BalanceSlot {
+    /// Creates a Solidity-layout balance slot stored in the token contract itself.
+    ///
+    /// # Parameters
+    /// * `token`: H160 - The token contract address.
+    /// * `slot`: i32 - Slot of the balances mapping.
+    pub fn solidity(token: H160, slot: i32) -> Self {
+        Self {
+            slot,
+            layout: MappingLayout::Solidity,
+            storage_address: token,
+        }
+    }
+
+    /// Computes the storage key holding the balance of `owner`.
+    pub fn storage_key(&self, owner: H160) -> rU256 {
+        self.layout.storage_key(owner, self.slot)
+    }
+}
+
+/// Inspector recording the (storage address, key) pair of every SLOAD.
+#[derive(Debug, Default)]
+pub struct SloadTracer {
+    /// Loaded storage keys in execution order.
+    pub loads: Vec<(B160, rU256)>,
+}
+
+impl<DB: Database> Inspector<DB> for SloadTracer {
+    fn step(&mut self, interp: &mut Interpreter, _data: &mut EVMData<'_, DB>) -> InstructionResult {
+        if interp.current_opcode() == opcode::SLOAD {
+            if let Ok(key) = interp.stack().peek(0) {
+                // contract.address is the storage context, so delegatecalls resolve to the proxy
+                let address = interp.contract().address;
+                if !self.loads.contains(&(address, key)) {
+                    self.loads.push((address, key));
+                }
+            }
+        }
+        InstructionResult::Continue
+    }
+}
+
+impl<M: Middleware + 'static> EvmSimulator<M> {
+    /// Finds and verifies the balances mapping of an ERC-20 token.
+    ///
+    /// # Parameters
+    /// * `token_address`: H160 - The token contract address.
+    ///
+    /// # Returns
+    /// * `Result<Option<BalanceSlot>>` - The verified slot, or None if no candidate passed.
+    pub fn find_balance_slot(&mut self, token_address: H160) -> Result<Option<BalanceSlot>> {
+        // a fresh owner makes sure the traced keys can't collide with cached ones
+        let probe = create_new_wallet().1;
+        let calldata = self.abi.token.encode("balanceOf", probe)?;
+        self.evm.env.tx.caller = self.owner.into();
+        self.evm.env.tx.transact_to = TransactTo::Call(token_address.into());
+        self.evm.env.tx.data = calldata.0;
+        self.evm.env.tx.value = rU256::ZERO;
+        self.evm.env.tx.gas_limit = 5000000;
+
+        let mut tracer = SloadTracer::default();
+        if self.evm.inspect_ref(&mut tracer).is_err() {
+            return Ok(None);
+        }
+
+        for (address, key) in tracer.loads {
+            let storage_address: H160 = address.into();
+            for slot in 0..MAX_BALANCE_SLOT {
+                for layout in [MappingLayout::Solidity, MappingLayout::Vyper] {
+                    if layout.storage_key(probe, slot) != key {
+                        continue;
+                    }
+                    let candidate = BalanceSlot {
+                        slot,
+                        layout,
+                        storage_address,
+                    };
+                    if self.verify_balance_slot(token_address, &candidate)? {
+                        return Ok(Some(candidate));
+                    }
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Writes a marker balance through `slot` and checks that `balanceOf` reports it.
+    ///
+    /// The original storage value is restored afterwards.
+    ///
+    /// # Parameters
+    /// * `token_address`: H160 - The token contract address.
+    /// * `slot`: &BalanceSlot - The candidate balance slot.
+    ///
+    /// # Returns
+    /// * `Result<bool>` - True if the slot controls `balanceOf`.
+    pub fn verify_balance_slot(&mut self, token_address: H160, slot: &BalanceSlot) -> Result<bool> {
+        let probe = create_new_wallet().1;
+        let key = slot.storage_key(probe);
+        let original = self
+            .evm
+            .db
+            .as_mut()
+            .unwrap()
+            .storage(slot.storage_address.into(), key)
+            .unwrap_or_default();
+
+        let marker = U256::from(0x5a4d0000_u64) + U256::from(slot.slot as u64);
+        self.insert_account_storage(slot.storage_address, key, marker.into())?;
+        let verified = match self.get_token_balance(token_address, probe) {
+            Ok(balance) => balance == marker,
+            Err(_) => false,
+        };
+        self.insert_account_storage(slot.storage_address, key, original)?;
+
+        Ok(verified)
+    }
+
+    /// Sets the token balance of an address through a discovered balance slot.
+    ///
+    /// # Parameters
+    /// * `to`: H160 - The owner address.
+    /// * `slot`: &BalanceSlot - The balance slot of the token.
+    /// * `amount`: rU256 - The new token balance.
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if successful, error otherwise.
+    pub fn set_token_balance_at(&mut self, to: H160, slot: &BalanceSlot, amount: rU256) -> Result<()> {
+        self.insert_account_storage(slot.storage_address, slot.storage_key(to), amount)
+    }
+}
+
+/// Resolves the balance slot of a token from the registry, discovering and caching it if unknown.
+///
+/// # Parameters
+/// * `simulator`: &mut EvmSimulator<M> - Simulator used for discovery.
+/// * `token`: H160 - The token contract address.
+///
+/// # Returns
+/// * `Result<Option<BalanceSlot>>` - The balance slot, or None if it couldn't be found.
+pub fn resolve_balance_slot<M: Middleware + 'static>(
+    simulator: &mut EvmSimulator<M>,
+    token: H160,
+) -> Result<Option<BalanceSlot>> {
+    let registry = get_token_registry();
+    if let Some(slot) = registry.get_token(token).and_then(|t| t.verified_balance_slot(token)) {
+        return Ok(Some(slot));
+    }
+
+    let slot = simulator.find_balance_slot(token)?;
+    match slot {
+        Some(slot) => {
+            debug!("Discovered balance slot of {:?}: {:?}", token, slot);
+            if registry.update_balance_slot(token, &slot).is_ok() {
+                registry.save_to_cache_debounced();
+            }
+        }
+        None => debug!("Could not find the balance slot of {:?}", token),
+    }
+    Ok(slot)
+}
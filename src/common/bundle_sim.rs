@@ -0,0 +1,225 @@
+/// Local `eth_callBundle` equivalent.
+///
+/// Decodes signed RLP transactions, recovers their senders and executes them in order on the
+/// simulator's next-block env. Nonces, balances and fees are checked and charged by revm exactly
+/// as a block builder would, so a `SandoBundle` can be verified as signed before it is broadcast.
+use anyhow::{anyhow, Result};
+use ethers::providers::Middleware;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Bytes, NameOrAddress, H160, H256, U256};
+use ethers::utils::keccak256;
+use ethers_flashbots::{BundleRequest, BundleTransaction};
+use revm::primitives::{CreateScheme, ExecutionResult, Log, Output, TransactTo};
+
+use crate::common::evm::EvmSimulator;
+use crate::common::utils::access_list_to_revm;
+
+/// Result of one transaction of a locally simulated bundle.
+#[derive(Debug, Clone)]
+pub struct TxSimResult {
+    /// Hash of the signed transaction.
+    pub tx_hash: H256,
+    /// Recovered sender.
+    pub from: H160,
+    /// Recipient (None for contract creations).
+    pub to: Option<H160>,
+    /// False if the transaction reverted or halted.
+    pub success: bool,
+    /// Gas used after refunds.
+    pub gas_used: u64,
+    /// Price paid per gas (base fee + effective priority fee).
+    pub gas_price: U256,
+    /// Priority fees paid to the coinbase.
+    pub gas_fees: U256,
+    /// ETH transferred to the coinbase directly (e.g. bribes).
+    pub eth_sent_to_coinbase: U256,
+    /// Total coinbase balance increase caused by the transaction.
+    pub coinbase_diff: U256,
+    /// Emitted logs (empty if the transaction failed).
+    pub logs: Vec<Log>,
+    /// Return data, or revert data if the transaction reverted.
+    pub output: Bytes,
+    /// Halt reason if the transaction halted.
+    pub error: Option<String>,
+}
+
+/// Result of a locally simulated bundle.
+#[derive(Debug, Clone, Default)]
+pub struct BundleSimResult {
+    /// Per-transaction results in bundle order.
+    pub results: Vec<TxSimResult>,
+    /// Gas used by the whole bundle.
+    pub total_gas_used: u64,
+    /// Priority fees paid to the coinbase.
+    pub gas_fees: U256,
+    /// ETH transferred to the coinbase directly.
+    pub eth_sent_to_coinbase: U256,
+    /// Total coinbase payment of the bundle.
+    pub coinbase_diff: U256,
+    /// Coinbase payment per unit of gas, the value builders sort bundles by.
+    pub bundle_gas_price: U256,
+}
+
+impl BundleSimResult {
+    /// Returns the first transaction that reverted or halted, if any.
+    pub fn first_failure(&self) -> Option<&TxSimResult> {
+        self.results.iter().find(|result| !result.success)
+    }
+}
+
+/// Returns the signed RLP bytes of every transaction in a bundle request.
+///
+/// # Parameters
+/// * `bundle`: &BundleRequest - The bundle to read.
+///
+/// # Returns
+/// * `Vec<Bytes>` - Signed transactions in bundle order.
+pub fn bundle_transactions(bundle: &BundleRequest) -> Vec<Bytes> {
+    bundle
+        .transactions()
+        .iter()
+        .map(|tx| match tx {
+            BundleTransaction::Signed(tx) => tx.rlp(),
+            BundleTransaction::Raw(raw) => raw.clone(),
+        })
+        .collect()
+}
+
+impl<M: Middleware + 'static> EvmSimulator<M> {
+    /// Simulates a bundle of signed transactions on top of the current state.
+    ///
+    /// Transactions run in order on the simulator's `NextBlockEnv`. The simulator state is left
+    /// untouched. Invalid transactions (bad
+    /// encoding, wrong nonce, insufficient funds for gas, fee cap below the base fee) fail the
+    /// whole simulation, like they would fail the bundle on a builder; reverts are reported per
+    /// transaction.
+    ///
+    /// # Parameters
+    /// * `signed_txs`: &[Bytes] - Signed RLP transactions in bundle order.
+    ///
+    /// # Returns
+    /// * `Result<BundleSimResult>` - Per-transaction and aggregate results.
+    pub fn simulate_bundle(&mut self, signed_txs: &[Bytes]) -> Result<BundleSimResult> {
+        let db = self.clone_db();
+        let tx_env = self.evm.env.tx.clone();
+        let disable_base_fee = self.evm.env.cfg.disable_base_fee;
+
+        // builders reject transactions paying less than the base fee
+        self.evm.env.cfg.disable_base_fee = false;
+        let result = self._simulate_bundle(signed_txs);
+
+        *self.evm.db.as_mut().unwrap() = db;
+        self.evm.env.tx = tx_env;
+        self.evm.env.cfg.disable_base_fee = disable_base_fee;
+        result
+    }
+
+    fn _simulate_bundle(&mut self, signed_txs: &[Bytes]) -> Result<BundleSimResult> {
+        let coinbase = self.get_coinbase();
+        let base_fee = self.get_base_fee();
+
+        let mut bundle = BundleSimResult::default();
+
+        for (i, raw) in signed_txs.iter().enumerate() {
+            let tx_hash = H256::from(keccak256(raw));
+            let (tx, signature) = TypedTransaction::decode_signed(&rlp::Rlp::new(raw))
+                .map_err(|e| anyhow!("Bundle tx {} ({:?}) decode error: {:?}", i, tx_hash, e))?;
+            let from = signature
+                .recover(tx.sighash())
+                .map_err(|e| anyhow!("Bundle tx {} ({:?}) bad signature: {:?}", i, tx_hash, e))?;
+
+            let to = match tx.to() {
+                Some(NameOrAddress::Address(to)) => Some(*to),
+                Some(NameOrAddress::Name(name)) => {
+                    return Err(anyhow!("Bundle tx {} has an unresolved ENS recipient {}", i, name))
+                }
+                None => None,
+            };
+            // gas_price() is the fee cap for EIP-1559 transactions
+            let max_fee = tx.gas_price().unwrap_or_default();
+            let priority_fee = match &tx {
+                TypedTransaction::Eip1559(inner) => inner.max_priority_fee_per_gas,
+                _ => None,
+            };
+            let gas_price = match priority_fee {
+                Some(priority_fee) => std::cmp::min(max_fee, base_fee + priority_fee),
+                None => max_fee,
+            };
+
+            let env = &mut self.evm.env.tx;
+            env.caller = from.into();
+            env.transact_to = match to {
+                Some(to) => TransactTo::Call(to.into()),
+                None => TransactTo::Create(CreateScheme::Create),
+            };
+            env.data = tx.data().cloned().unwrap_or_default().0;
+            env.value = tx.value().cloned().unwrap_or_default().into();
+            env.gas_limit = tx.gas().cloned().unwrap_or_default().as_u64();
+            env.gas_price = max_fee.into();
+            env.gas_priority_fee = priority_fee.map(|fee| fee.into());
+            env.nonce = tx.nonce().map(|nonce| nonce.as_u64());
+            env.chain_id = tx.chain_id().map(|chain_id| chain_id.as_u64());
+            env.access_list = access_list_to_revm(tx.access_list().cloned().unwrap_or_default());
+
+            let coinbase_before = self.coinbase_balance(coinbase)?;
+            let result = self
+                .evm
+                .transact_commit()
+                .map_err(|e| anyhow!("Bundle tx {} ({:?}) invalid: {:?}", i, tx_hash, e))?;
+            let coinbase_diff = self
+                .coinbase_balance(coinbase)?
+                .saturating_sub(coinbase_before);
+
+            let gas_used = result.gas_used();
+            let gas_fees = U256::from(gas_used) * gas_price.saturating_sub(base_fee);
+            let (success, logs, output, error) = match result {
+                ExecutionResult::Success { logs, output, .. } => {
+                    let output = match output {
+                        Output::Call(o) => o,
+                        Output::Create(o, _) => o,
+                    };
+                    (true, logs, output, None)
+                }
+                ExecutionResult::Revert { output, .. } => (false, Vec::new(), output, None),
+                ExecutionResult::Halt { reason, .. } => (
+                    false,
+                    Vec::new(),
+                    Default::default(),
+                    Some(format!("{:?}", reason)),
+                ),
+            };
+
+            bundle.total_gas_used += gas_used;
+            bundle.gas_fees += gas_fees;
+            bundle.eth_sent_to_coinbase += coinbase_diff.saturating_sub(gas_fees);
+            bundle.coinbase_diff += coinbase_diff;
+            bundle.results.push(TxSimResult {
+                tx_hash,
+                from,
+                to,
+                success,
+                gas_used,
+                gas_price,
+                gas_fees,
+                eth_sent_to_coinbase: coinbase_diff.saturating_sub(gas_fees),
+                coinbase_diff,
+                logs,
+                output: output.into(),
+                error,
+            });
+        }
+
+        if bundle.total_gas_used > 0 {
+            bundle.bundle_gas_price = bundle.coinbase_diff / U256::from(bundle.total_gas_used);
+        }
+
+        Ok(bundle)
+    }
+
+    fn coinbase_balance(&mut self, coinbase: H160) -> Result<U256> {
+        Ok(self
+            .basic(coinbase)?
+            .map(|info| info.balance.into())
+            .unwrap_or_default())
+    }
+}
@@ -1,7 +1,10 @@
 /// Contains static bytecode blobs for use in contract deployment and simulation.
 ///
 /// Provides lazy-initialized bytecode for special contracts used by the Sandooo project.
+use anyhow::{anyhow, Result};
 use ethers::{prelude::Lazy, types::Bytes};
+use std::fs::File;
+use std::path::PathBuf;
 
 /// Bytecode for the request contract, used for on-chain data retrieval or simulation.
 pub static REQUEST_BYTECODE: Lazy<Bytes> = Lazy::new(|| {
@@ -13,7 +16,36 @@ pub static SANDOOO_BYTECODE: Lazy<Bytes> = Lazy::new(|| {
     "0x6080604052600436106100745760003560e01c80635c68e2601161004e5780635c68e260146101605780638da5cb5b14610180578063920f5c84146101b4578063b29a8140146101d45761007b565b80630614117a146100c857806308a01675146100df5780631b11d0ff146101305761007b565b3661007b57005b60405162461bcd60e51b815260206004820152601860248201527f4449524543545f43414c4c535f4e4f545f414c4c4f574544000000000000000060448201526064015b60405180910390fd5b3480156100d457600080fd5b506100dd6101f4565b005b3480156100eb57600080fd5b506101137f00000000000000000000000087870bca3f3fd6335c3f4ce8392d69350b4fa4e281565b6040516001600160a01b0390911681526020015b60405180910390f35b34801561013c57600080fd5b5061015061014b366004610950565b6102d4565b6040519015158152602001610127565b34801561016c57600080fd5b506100dd61017b3660046109c8565b610498565b34801561018c57600080fd5b506101137f00000000000000000000000020d691780780b43b6639e623a409dcce58befc4981565b3480156101c057600080fd5b506101506101cf366004610a67565b6106bc565b3480156101e057600080fd5b506100dd6101ef366004610b41565b610707565b336001600160a01b037f00000000000000000000000020d691780780b43b6639e623a409dcce58befc49161461023d576040516330cd747160e01b815260040160405180910390fd5b60007f00000000000000000000000020d691780780b43b6639e623a409dcce58befc496001600160a01b03164760405160006040518083038185875af1925050503d80600081146102aa576040519150601f19603f3d011682016040523d82523d6000602084013e6102af565b606091505b50509050806102d157604051630db2c7f160e31b815260040160405180910390fd5b50565b600080546001146103105760405162461bcd60e51b81526020600482015260066024820152651313d0d2d15160d21b60448201526064016100bf565b60008055336001600160a01b037f00000000000000000000000087870bca3f3fd6335c3f4ce8392d69350b4fa4e2161461035c576040516282b42960e81b815260040160405180910390fd5b6001600160a01b0384163014610385576040516317fb43e560e31b815260040160405180910390fd5b60006103918688610b81565b905061039e88858561077f565b6040516370a0823160e01b81523060048201526000906001600160a01b038a16906370a0823190602401602060405180830381865afa1580156103e5573d6000803e3d6000fd5b505050506040513d601f19601f820116820180604052508101906104099190610b9a565b90508181101561042c57604051632723fc7560e11b815260040160405180910390fd5b610458897f00000000000000000000000087870bca3f3fd6335c3f4ce8392d69350b4fa4e26000610801565b610483897f00000000000000000000000087870bca3f3fd6335c3f4ce8392d69350b4fa4e284610801565b60019250505060016000559695505050505050565b336001600160a01b037f00000000000000000000000020d691780780b43b6639e623a409dcce58befc4916146104e1576040516330cd747160e01b815260040160405180910390fd5b60005460011461051c5760405162461bcd60e51b81526020600482015260066024820152651313d0d2d15160d21b60448201526064016100bf565b6000805560088111158061054557506069610538600883610bb3565b6105429190610bc6565b15155b1561056357604051635cb045db60e01b815260040160405180910390fd5b813560c01c43811461058857604051637890ce5960e11b815260040160405180910390fd5b6040516310ac2ddf60e21b81526001600160a01b037f00000000000000000000000087870bca3f3fd6335c3f4ce8392d69350b4fa4e216906342b0b77c906105df9030908990899089908990600090600401610be8565b600060405180830381600087803b1580156105f957600080fd5b505af115801561060d573d6000803e3d6000fd5b50506040516370a0823160e01b8152306004820152600092506001600160a01b03881691506370a0823190602401602060405180830381865afa158015610658573d6000803e3d6000fd5b505050506040513d601f19601f8201168201806040525081019061067c9190610b9a565b905080156106af576106af867f00000000000000000000000020d691780780b43b6639e623a409dcce58befc4983610858565b5050600160005550505050565b60405162461bcd60e51b815260206004820152601960248201527f4d554c54495f41535345545f4e4f545f535550504f525445440000000000000060448201526000906064016100bf565b336001600160a01b037f00000000000000000000000020d691780780b43b6639e623a409dcce58befc491614610750576040516330cd747160e01b815260040160405180910390fd5b61077b827f00000000000000000000000020d691780780b43b6639e623a409dcce58befc4983610858565b5050565b6008815b808210156107fa57600084848481811061079f5761079f610c47565b600192013560f81c821492506107b791905084610b81565b60688101935085018035606090811c91601481013590911c90602881013590604801356107e5838584610858565b6107f0848683610885565b5050505050610783565b5050505050565b60405163095ea7b360e01b81528260048201528160248201526000806044836000885af161082e57600080fd5b3d8015610842576020811461084757600080fd5b6107fa565b60206000833e81516107fa57600080fd5b60405163a9059cbb60e01b81528260048201528160248201526000806044836000885af161082e57600080fd5b60405163022c0d9f60e01b81528280156108ab57600060048301528260248301526108b9565b826004830152600060248301525b503060601b604482015260a060648201526000608482015260008060a4836000885af16108e557600080fd5b50505050565b80356001600160a01b038116811461090257600080fd5b919050565b60008083601f84011261091957600080fd5b50813567ffffffffffffffff81111561093157600080fd5b60208301915083602082850101111561094957600080fd5b9250929050565b60008060008060008060a0878903121561096957600080fd5b610972876108eb565b9550602087013594506040870135935061098e606088016108eb565b9250608087013567ffffffffffffffff8111156109aa57600080fd5b6109b689828a01610907565b979a9699509497509295939492505050565b600080600080606085870312156109de57600080fd5b6109e7856108eb565b935060208501359250604085013567ffffffffffffffff811115610a0a57600080fd5b610a1687828801610907565b95989497509550505050565b60008083601f840112610a3457600080fd5b50813567ffffffffffffffff811115610a4c57600080fd5b6020830191508360208260051b850101111561094957600080fd5b600080600080600080600080600060a08a8c031215610a8557600080fd5b893567ffffffffffffffff80821115610a9d57600080fd5b610aa98d838e01610a22565b909b50995060208c0135915080821115610ac257600080fd5b610ace8d838e01610a22565b909950975060408c0135915080821115610ae757600080fd5b610af38d838e01610a22565b9097509550859150610b0760608d016108eb565b945060808c0135915080821115610b1d57600080fd5b50610b2a8c828d01610907565b915080935050809150509295985092959850929598565b60008060408385031215610b5457600080fd5b610b5d836108eb565b946020939093013593505050565b634e487b7160e01b600052601160045260246000fd5b80820180821115610b9457610b94610b6b565b92915050565b600060208284031215610bac57600080fd5b5051919050565b81810381811115610b9457610b94610b6b565b600082610be357634e487b7160e01b600052601260045260246000fd5b500690565b6001600160a01b038781168252861660208201526040810185905260a06060820181905281018390526000838560c0840137600060c0858401015260c0601f19601f860116830101905061ffff83166080830152979650505050505050565b634e487b7160e01b600052603260045260246000fdfea26469706673582212201455a40d28232957c3d9041c034148d138a4470f776e3c313d08bb5bcef8376a64736f6c63430008140033".parse().unwrap()
 });
 
-/// Bytecode for the SandoooV3 bot contract, which uses Aave V3 flashloans.
+/// Creation code of the SandoooV3 bot contract, which uses Aave V3 flashloans.
+///
+/// Embedded from `contracts/bytecode/SandoooV3.hex`, which `build.rs` rewrites from the forge
+/// artifact when `contracts/src` changes, so a contract change shows up as a diff of that file.
 pub static SANDOOO_V3_BYTECODE: Lazy<Bytes> = Lazy::new(|| {
-    "0x60c0604052600160005534801561001557600080fd5b50604051610d76380380610d7683398101604081905261003491610049565b336080526001600160a01b031660a052610079565b60006020828403121561005b57600080fd5b81516001600160a01b038116811461007257600080fd5b9392505050565b60805160a051610c936100e36000396000818160f10152818161031f015281816104320152818161045e015261059f015260008181610192015281816101ff01528181610241015281816104a30152818161068a0152818161071201526107560152610c936000f3fe6080604052600436106100745760003560e01c80635c68e2601161004e5780635c68e260146101605780638da5cb5b14610180578063920f5c84146101b4578063b29a8140146101d45761007b565b80630614117a146100c857806308a01675146100df5780631b11d0ff146101305761007b565b3661007b57005b60405162461bcd60e51b815260206004820152601860248201527f4449524543545f43414c4c535f4e4f545f414c4c4f574544000000000000000060448201526064015b60405180910390fd5b3480156100d457600080fd5b506100dd6101f4565b005b3480156100eb57600080fd5b506101137f000000000000000000000000000000000000000000000000000000000000000081565b6040516001600160a01b0390911681526020015b60405180910390f35b34801561013c57600080fd5b5061015061014b366004610950565b6102d4565b6040519015158152602001610127565b34801561016c57600080fd5b506100dd61017b3660046109c8565b610498565b34801561018c57600080fd5b506101137f000000000000000000000000000000000000000000000000000000000000000081565b3480156101c057600080fd5b506101506101cf366004610a67565b6106bc565b3480156101e057600080fd5b506100dd6101ef366004610b41565b610707565b336001600160a01b037f0000000000000000000000000000000000000000000000000000000000000000161461023d576040516330cd747160e01b815260040160405180910390fd5b60007f00000000000000000000000000000000000000000000000000000000000000006001600160a01b03164760405160006040518083038185875af1925050503d80600081146102aa576040519150601f19603f3d011682016040523d82523d6000602084013e6102af565b606091505b50509050806102d157604051630db2c7f160e31b815260040160405180910390fd5b50565b600080546001146103105760405162461bcd60e51b81526020600482015260066024820152651313d0d2d15160d21b60448201526064016100bf565b60008055336001600160a01b037f0000000000000000000000000000000000000000000000000000000000000000161461035c576040516282b42960e81b815260040160405180910390fd5b6001600160a01b0384163014610385576040516317fb43e560e31b815260040160405180910390fd5b60006103918688610b81565b905061039e88858561077f565b6040516370a0823160e01b81523060048201526000906001600160a01b038a16906370a0823190602401602060405180830381865afa1580156103e5573d6000803e3d6000fd5b505050506040513d601f19601f820116820180604052508101906104099190610b9a565b90508181101561042c57604051632723fc7560e11b815260040160405180910390fd5b610458897f00000000000000000000000000000000000000000000000000000000000000006000610801565b610483897f000000000000000000000000000000000000000000000000000000000000000084610801565b60019250505060016000559695505050505050565b336001600160a01b037f000000000000000000000000000000000000000000000000000000000000000016146104e1576040516330cd747160e01b815260040160405180910390fd5b60005460011461051c5760405162461bcd60e51b81526020600482015260066024820152651313d0d2d15160d21b60448201526064016100bf565b6000805560088111158061054557506069610538600883610bb3565b6105429190610bc6565b15155b1561056357604051635cb045db60e01b815260040160405180910390fd5b813560c01c43811461058857604051637890ce5960e11b815260040160405180910390fd5b6040516310ac2ddf60e21b81526001600160a01b037f000000000000000000000000000000000000000000000000000000000000000016906342b0b77c906105df9030908990899089908990600090600401610be8565b600060405180830381600087803b1580156105f957600080fd5b505af115801561060d573d6000803e3d6000fd5b50506040516370a0823160e01b8152306004820152600092506001600160a01b03881691506370a0823190602401602060405180830381865afa158015610658573d6000803e3d6000fd5b505050506040513d601f19601f8201168201806040525081019061067c9190610b9a565b905080156106af576106af867f000000000000000000000000000000000000000000000000000000000000000083610858565b5050600160005550505050565b60405162461bcd60e51b815260206004820152601960248201527f4d554c54495f41535345545f4e4f545f535550504f525445440000000000000060448201526000906064016100bf565b336001600160a01b037f00000000000000000000000000000000000000000000000000000000000000001614610750576040516330cd747160e01b815260040160405180910390fd5b61077b827f000000000000000000000000000000000000000000000000000000000000000083610858565b5050565b6008815b808210156107fa57600084848481811061079f5761079f610c47565b600192013560f81c821492506107b791905084610b81565b60688101935085018035606090811c91601481013590911c90602881013590604801356107e5838584610858565b6107f0848683610885565b5050505050610783565b5050505050565b60405163095ea7b360e01b81528260048201528160248201526000806044836000885af161082e57600080fd5b3d8015610842576020811461084757600080fd5b6107fa565b60206000833e81516107fa57600080fd5b60405163a9059cbb60e01b81528260048201528160248201526000806044836000885af161082e57600080fd5b60405163022c0d9f60e01b81528280156108ab57600060048301528260248301526108b9565b826004830152600060248301525b503060601b604482015260a060648201526000608482015260008060a4836000885af16108e557600080fd5b50505050565b80356001600160a01b038116811461090257600080fd5b919050565b60008083601f84011261091957600080fd5b50813567ffffffffffffffff81111561093157600080fd5b60208301915083602082850101111561094957600080fd5b9250929050565b60008060008060008060a0878903121561096957600080fd5b610972876108eb565b9550602087013594506040870135935061098e606088016108eb565b9250608087013567ffffffffffffffff8111156109aa57600080fd5b6109b689828a01610907565b979a9699509497509295939492505050565b600080600080606085870312156109de57600080fd5b6109e7856108eb565b935060208501359250604085013567ffffffffffffffff811115610a0a57600080fd5b610a1687828801610907565b95989497509550505050565b60008083601f840112610a3457600080fd5b50813567ffffffffffffffff811115610a4c57600080fd5b6020830191508360208260051b850101111561094957600080fd5b600080600080600080600080600060a08a8c031215610a8557600080fd5b893567ffffffffffffffff80821115610a9d57600080fd5b610aa98d838e01610a22565b909b50995060208c0135915080821115610ac257600080fd5b610ace8d838e01610a22565b909950975060408c0135915080821115610ae757600080fd5b610af38d838e01610a22565b9097509550859150610b0760608d016108eb565b945060808c0135915080821115610b1d57600080fd5b50610b2a8c828d01610907565b915080935050809150509295985092959850929598565b60008060408385031215610b5457600080fd5b610b5d836108eb565b946020939093013593505050565b634e487b7160e01b600052601160045260246000fd5b80820180821115610b9457610b94610b6b565b92915050565b600060208284031215610bac57600080fd5b5051919050565b81810381811115610b9457610b94610b6b565b600082610be357634e487b7160e01b600052601260045260246000fd5b500690565b6001600160a01b038781168252861660208201526040810185905260a06060820181905281018390526000838560c0840137600060c0858401015260c0601f19601f860116830101905061ffff83166080830152979650505050505050565b634e487b7160e01b600052603260045260246000fdfea26469706673582212201455a40d28232957c3d9041c034148d138a4470f776e3c313d08bb5bcef8376a64736f6c63430008140033".parse().unwrap()
+    include_str!("../../contracts/bytecode/SandoooV3.hex")
+        .trim()
+        .parse()
+        .unwrap()
 });
+
+/// Reads the creation code of a contract from the forge artifacts in `contracts/out`.
+///
+/// # Parameters
+/// * `file`: &str - The Solidity source file name, e.g. `SandoooV3.sol`
+/// * `contract`: &str - The contract name
+///
+/// # Returns
+/// * `Result<Bytes>` - The creation code, without constructor arguments
+pub fn forge_artifact(file: &str, contract: &str) -> Result<Bytes> {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("contracts/out")
+        .join(file)
+        .join(format!("{}.json", contract));
+    let json: serde_json::Value = serde_json::from_reader(
+        File::open(&path)
+            .map_err(|_| anyhow!("{:?} not found, run `forge build` in contracts/", path))?,
+    )?;
+    let object = json["bytecode"]["object"]
+        .as_str()
+        .ok_or_else(|| anyhow!("{:?} has no bytecode", path))?;
+    Ok(hex::decode(object.trim_start_matches("0x"))?.into())
+}
@@ -1,4 +1,5 @@
 use ethers::types::Transaction;
+use crate::common::competitors::get_competitor_db;
 use crate::common::routers::{is_known_router, is_known_swap_selector};
 use crate::common::contract_detector::{is_pool_contract, is_router_like};
 use ethers::providers::Middleware;
@@ -9,12 +10,17 @@ pub enum TxKind {
     Erc20Approve,
     Erc20Transfer,
     Swap,
+    /// Sent to a contract seen sandwiching on chain
+    Competitor,
     Other,
 }
 
 /// Classifies transactions from the mempool to filter non-swaps.
 pub async fn classify_transaction<M: Middleware>(provider: &M, tx: &Transaction) -> TxKind {
-    // 1. Address-based detection for known routers
+    // 1. Known searchers (by contract, or by sender and selector) and routers
+    if get_competitor_db().is_competitor(tx) {
+        return TxKind::Competitor;
+    }
     if let Some(to) = tx.to {
         if is_known_router(&to) {
             return TxKind::Swap;
@@ -0,0 +1,358 @@
+/// Competing searcher detection.
+///
+/// Learns sandwich contracts from mined blocks (a frontrun and backrun through the same contract
+/// swapping in opposite directions on a pool around a victim swap) and keeps them in a cached
+/// database, so pending transactions from competitors can be recognised in the mempool.
+use anyhow::{anyhow, Result};
+use ethers::providers::Middleware;
+use ethers::types::{Block, Transaction, TransactionReceipt, H160, H256, U256};
+use lazy_static::lazy_static;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use crate::common::constants::{BASIS_POINTS_DIVISOR, COMPETITOR_GAS_PEG_BPS, MAX_SANDWICH_SPAN};
+use crate::common::routers::is_known_router;
+use crate::common::trace::{TracedLog, TracedLogKind};
+
+/// A sandwich found in a mined block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetectedSandwich {
+    /// Contract both legs were sent to.
+    pub contract: H160,
+    /// Sender of the frontrun.
+    pub searcher: H160,
+    /// Pool that was sandwiched.
+    pub pool: H160,
+    pub frontrun: H256,
+    pub victim: H256,
+    pub backrun: H256,
+    /// Selectors of the frontrun and backrun calls.
+    pub selectors: Vec<[u8; 4]>,
+}
+
+/// A contract seen sandwiching on chain.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearcherContract {
+    pub address: H160,
+    pub first_seen_block: u64,
+    pub last_seen_block: u64,
+    /// Number of sandwiches seen through this contract
+    pub sandwiches: u64,
+    /// EOAs that sent the legs
+    pub searchers: Vec<H160>,
+    /// Selectors used by the legs (0x-prefixed hex)
+    pub selectors: Vec<String>,
+}
+
+/// Returns the swap direction (`token0_in`) of every pool a transaction swapped on.
+fn swaps_of(receipt: &TransactionReceipt) -> Vec<(H160, bool)> {
+    receipt
+        .logs
+        .iter()
+        .filter_map(|log| {
+            let kind = TracedLogKind::from_topic(log.topics.first()?)?;
+            let log = TracedLog {
+                kind,
+                address: log.address,
+                topics: log.topics.clone(),
+                data: log.data.clone(),
+            };
+            log.token0_in().map(|token0_in| (log.address, token0_in))
+        })
+        .collect()
+}
+
+fn selector(tx: &Transaction) -> Option<[u8; 4]> {
+    tx.input.get(..4).map(|s| [s[0], s[1], s[2], s[3]])
+}
+
+/// Finds sandwiches in a mined block.
+///
+/// A sandwich is a frontrun and a backrun sent to the same non-router contract, swapping in
+/// opposite directions on a pool, with a transaction from someone else in between swapping on
+/// that pool in the frontrun's direction. Legs must be at most `MAX_SANDWICH_SPAN` apart.
+///
+/// # Parameters
+/// * `txs`: &[Transaction] - Block transactions in block order.
+/// * `receipts`: &[TransactionReceipt] - Their receipts.
+///
+/// # Returns
+/// * `Vec<DetectedSandwich>` - Sandwiches in block order of their frontrun.
+pub fn detect_sandwiches(
+    txs: &[Transaction],
+    receipts: &[TransactionReceipt],
+) -> Vec<DetectedSandwich> {
+    let receipts: HashMap<H256, &TransactionReceipt> =
+        receipts.iter().map(|r| (r.transaction_hash, r)).collect();
+    let swaps: Vec<Vec<(H160, bool)>> = txs
+        .iter()
+        .map(|tx| receipts.get(&tx.hash).map(|r| swaps_of(r)).unwrap_or_default())
+        .collect();
+
+    let mut sandwiches = Vec::new();
+    for (i, front) in txs.iter().enumerate() {
+        let contract = match front.to {
+            Some(to) if !is_known_router(&to) => to,
+            _ => continue,
+        };
+        let end = std::cmp::min(txs.len(), i + MAX_SANDWICH_SPAN + 1);
+        for j in i + 2..end {
+            let back = &txs[j];
+            if back.to != Some(contract) {
+                continue;
+            }
+            let pool = swaps[i].iter().find_map(|(pool, front_dir)| {
+                swaps[j]
+                    .iter()
+                    .any(|(p, back_dir)| p == pool && back_dir != front_dir)
+                    .then_some((*pool, *front_dir))
+            });
+            let (pool, direction) = match pool {
+                Some(pool) => pool,
+                None => continue,
+            };
+            let victim = (i + 1..j).find(|k| {
+                txs[*k].to != Some(contract)
+                    && swaps[*k].iter().any(|(p, dir)| *p == pool && *dir == direction)
+            });
+            if let Some(k) = victim {
+                sandwiches.push(DetectedSandwich {
+                    contract,
+                    searcher: front.from,
+                    pool,
+                    frontrun: front.hash,
+                    victim: txs[k].hash,
+                    backrun: back.hash,
+                    selectors: [selector(front), selector(back)].into_iter().flatten().collect(),
+                });
+                break;
+            }
+        }
+    }
+    sandwiches
+}
+
+/// Returns true if `gas_price` sits just above `victim_gas_price`, the way sandwich frontruns
+/// outbid their victim in the public mempool.
+///
+/// # Parameters
+/// * `gas_price`: U256 - Max fee (or gas price) of the suspect transaction.
+/// * `victim_gas_price`: U256 - Max fee (or gas price) of the victim.
+pub fn is_pegged_above(gas_price: U256, victim_gas_price: U256) -> bool {
+    let margin = victim_gas_price * U256::from(COMPETITOR_GAS_PEG_BPS)
+        / U256::from(BASIS_POINTS_DIVISOR);
+    gas_price > victim_gas_price && gas_price - victim_gas_price <= std::cmp::max(margin, U256::one())
+}
+
+/// Database of known searcher contracts.
+#[derive(Debug)]
+pub struct CompetitorDb {
+    contracts: RwLock<HashMap<H160, SearcherContract>>,
+    /// Path to JSON cache file
+    cache_path: String,
+}
+
+impl CompetitorDb {
+    /// Creates the database and loads it from the cache file.
+    pub fn new() -> Self {
+        Self::with_cache_path("cache/competitors.json")
+    }
+
+    /// Creates the database on another cache file and loads it.
+    ///
+    /// # Parameters
+    /// * `cache_path`: &str - Path to the JSON cache file.
+    pub fn with_cache_path(cache_path: &str) -> Self {
+        let db = Self {
+            contracts: RwLock::new(HashMap::new()),
+            cache_path: cache_path.to_string(),
+        };
+        db.load_from_cache();
+        db
+    }
+
+    fn load_from_cache(&self) {
+        let path = Path::new(&self.cache_path);
+        if !path.exists() {
+            return;
+        }
+        match File::open(path) {
+            Ok(mut file) => {
+                let mut contents = String::new();
+                if file.read_to_string(&mut contents).is_ok() {
+                    if let Ok(cache_data) = serde_json::from_str::<Vec<SearcherContract>>(&contents)
+                    {
+                        let mut contracts = self.contracts.write().unwrap();
+                        for contract in cache_data {
+                            contracts.insert(contract.address, contract);
+                        }
+                        debug!("Loaded {} searcher contracts from cache", contracts.len());
+                    }
+                }
+            }
+            Err(_) => info!("Failed to load competitor cache"),
+        }
+    }
+
+    /// Saves the database to the cache file.
+    pub fn save_to_cache(&self) {
+        let contracts = self.contracts.read().unwrap();
+        let contract_vec: Vec<SearcherContract> = contracts.values().cloned().collect();
+
+        if let Some(parent) = Path::new(&self.cache_path).parent() {
+            std::fs::create_dir_all(parent).ok();
+        }
+        if let Ok(mut file) = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&self.cache_path)
+        {
+            if let Ok(json) = serde_json::to_string_pretty(&contract_vec) {
+                let _ = file.write_all(json.as_bytes());
+            }
+        }
+    }
+
+    /// Returns true if `address` was seen sandwiching.
+    pub fn is_searcher(&self, address: &H160) -> bool {
+        self.contracts.read().unwrap().contains_key(address)
+    }
+
+    /// Gets a known searcher contract.
+    pub fn get(&self, address: &H160) -> Option<SearcherContract> {
+        self.contracts.read().unwrap().get(address).cloned()
+    }
+
+    /// Returns true if a transaction goes to a known searcher contract, or comes from a known
+    /// searcher calling a selector its contracts use.
+    ///
+    /// The second case catches searchers who moved to a freshly deployed contract we haven't seen
+    /// sandwiching yet. Known routers are never competitors.
+    ///
+    /// # Parameters
+    /// * `tx`: &Transaction - The pending transaction.
+    pub fn is_competitor(&self, tx: &Transaction) -> bool {
+        let to = match tx.to {
+            Some(to) => to,
+            None => return false,
+        };
+        if self.is_searcher(&to) {
+            return true;
+        }
+        let selector = match selector(tx) {
+            Some(selector) if !is_known_router(&to) => format!("0x{}", hex::encode(selector)),
+            _ => return false,
+        };
+        self.contracts
+            .read()
+            .unwrap()
+            .values()
+            .any(|contract| {
+                contract.searchers.contains(&tx.from) && contract.selectors.contains(&selector)
+            })
+    }
+
+    pub fn len(&self) -> usize {
+        self.contracts.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Records a detected sandwich.
+    ///
+    /// # Parameters
+    /// * `sandwich`: &DetectedSandwich - The sandwich.
+    /// * `block_number`: u64 - Block it was mined in.
+    ///
+    /// # Returns
+    /// * `bool` - True if the contract wasn't known before.
+    pub fn record(&self, sandwich: &DetectedSandwich, block_number: u64) -> bool {
+        let mut contracts = self.contracts.write().unwrap();
+        let is_new = !contracts.contains_key(&sandwich.contract);
+        let contract = contracts
+            .entry(sandwich.contract)
+            .or_insert_with(|| SearcherContract {
+                address: sandwich.contract,
+                first_seen_block: block_number,
+                last_seen_block: block_number,
+                sandwiches: 0,
+                searchers: Vec::new(),
+                selectors: Vec::new(),
+            });
+        contract.last_seen_block = std::cmp::max(contract.last_seen_block, block_number);
+        contract.sandwiches += 1;
+        if !contract.searchers.contains(&sandwich.searcher) {
+            contract.searchers.push(sandwich.searcher);
+        }
+        for selector in &sandwich.selectors {
+            let selector = format!("0x{}", hex::encode(selector));
+            if !contract.selectors.contains(&selector) {
+                contract.selectors.push(selector);
+            }
+        }
+        is_new
+    }
+
+    /// Detects the sandwiches of a mined block and records their contracts.
+    ///
+    /// # Parameters
+    /// * `provider`: &Arc<M> - Provider used to fetch the block receipts.
+    /// * `block`: &Block<Transaction> - The mined block with its transactions.
+    ///
+    /// # Returns
+    /// * `Result<Vec<DetectedSandwich>>` - The sandwiches found in the block.
+    pub async fn learn_from_block<M: Middleware + 'static>(
+        &self,
+        provider: &Arc<M>,
+        block: &Block<Transaction>,
+    ) -> Result<Vec<DetectedSandwich>> {
+        let block_number = block
+            .number
+            .ok_or_else(|| anyhow!("Block has no number"))?;
+        let receipts = provider
+            .get_block_receipts(block_number)
+            .await
+            .map_err(|e| anyhow!("Failed to fetch receipts of {}: {:?}", block_number, e))?;
+
+        let sandwiches = detect_sandwiches(&block.transactions, &receipts);
+        let mut new_contracts = 0;
+        for sandwich in &sandwiches {
+            if self.record(sandwich, block_number.as_u64()) {
+                new_contracts += 1;
+            }
+        }
+        if new_contracts > 0 {
+            info!(
+                "Learned {} new searcher contracts in block {} ({} known)",
+                new_contracts,
+                block_number,
+                self.len()
+            );
+            self.save_to_cache();
+        }
+        Ok(sandwiches)
+    }
+}
+
+impl Default for CompetitorDb {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+lazy_static! {
+    static ref COMPETITOR_DB: Arc<CompetitorDb> = Arc::new(CompetitorDb::new());
+}
+
+/// Gets the competitor database instance.
+pub fn get_competitor_db() -> Arc<CompetitorDb> {
+    COMPETITOR_DB.clone()
+}
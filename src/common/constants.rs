@@ -1,6 +1,9 @@
 /// Defines global constants and environment configuration for the Sandooo project.
 ///
 /// Contains static addresses, environment variable parsing, and token metadata.
+use ethers::types::H160;
+use std::str::FromStr;
+
 pub static PROJECT_NAME: &str = "sandooo";
 
 /// Retrieves the value of an environment variable by key, or returns an empty string if not found.
@@ -68,6 +71,31 @@ impl Env {
 /// Static address for the Flashbots Builder coinbase.
 pub static COINBASE: &str = "0xDAFEA492D9c6733ae3d56b7Ed1ADB60692c98Bc5"; // Flashbots Builder
 
+/// Seconds between consecutive mainnet blocks.
+pub static BLOCK_TIME: u64 = 12;
+
+/// Fee recipients (block coinbase) of the builders we send bundles to.
+pub static BUILDER_FEE_RECIPIENTS: &[(&str, &str)] = &[
+    ("flashbots", "0xDAFEA492D9c6733ae3d56b7Ed1ADB60692c98Bc5"),
+    ("beaverbuild", "0x95222290DD7278Aa3Ddd389Cc1E1d165CC4BAfe5"),
+    ("titanbuilder", "0x4838B106FCe9647Bdf1E7877BF73cE8B0BAD5f97"),
+    ("rsync", "0x1f9090aaE28b8a3dCeaDf281B0F12828e676c326"),
+];
+
+/// Returns the fee recipient of a builder, if known.
+///
+/// # Parameters
+/// * `builder`: &str - The builder name, as used in `Executor::builder_urls`.
+///
+/// # Returns
+/// * `Option<H160>` - The builder's coinbase address.
+pub fn builder_fee_recipient(builder: &str) -> Option<H160> {
+    BUILDER_FEE_RECIPIENTS
+        .iter()
+        .find(|(name, _)| *name == builder)
+        .map(|(_, address)| H160::from_str(address).unwrap())
+}
+
 /// Static addresses for contracts and major tokens on Ethereum mainnet.
 pub static WETH: &str = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2";
 pub static USDT: &str = "0xdAC17F958D2ee523a2206206994597C13D831ec7";
@@ -84,52 +112,46 @@ pub static AAVE_V3_POOL: &str = "0x87870Bca3F3fD6335C3F4ce8392D69350B4fA4E2";
 /// Note: This is a placeholder and should be replaced with the actual deployed address
 pub static SANDOOO_V3_ADDRESS: &str = "0x5770764057f164492BfcE78b4E9c9e49Ee735504";
 
+/// Size in bytes of a Sandooo trade entry (zeroForOne, pair, tokenIn, amountIn, amountOut).
+pub static SANDOOO_TRADE_SIZE: usize = 105;
+
+/// Size in bytes of a SandoooV3 trade entry (Sandooo entry + dexKind, fee, sqrtPriceLimitX96).
+pub static SANDOOO_V3_TRADE_SIZE: usize = 129;
+
+/// Slippage (in basis points) taken off the estimated output to get the minimum of a V3 trade.
+pub static V3_MIN_OUT_SLIPPAGE_BPS: u64 = 10;
+
 /// Flashloan fee percentage in basis points (0.09% for Aave V3)
 pub static FLASHLOAN_FEE_BASIS_POINTS: u64 = 9;
 pub static BASIS_POINTS_DIVISOR: u64 = 10000;
 
-/*
-Can figure out the balance slot of ERC-20 tokens using the:
-EvmSimulator::get_balance_slot method
-
-However, note that this does not work for all tokens.
-Especially tokens that are using proxy patterns.
-*/
-/// Storage slot for WETH balance (for direct storage access).
-pub static WETH_BALANCE_SLOT: i32 = 3;
-/// Storage slot for USDT balance (for direct storage access).
-pub static USDT_BALANCE_SLOT: i32 = 2;
-/// Storage slot for USDC balance (for direct storage access).
-pub static USDC_BALANCE_SLOT: i32 = 9;
-/// Storage slot for WBTC balance (for direct storage access).
-pub static WBTC_BALANCE_SLOT: i32 = 0;
-/// Storage slot for DAI balance (for direct storage access).
-pub static DAI_BALANCE_SLOT: i32 = 2;
-/// Storage slot for LINK balance (for direct storage access).
-pub static LINK_BALANCE_SLOT: i32 = 1;
-/// Storage slot for MKR balance (for direct storage access).
-pub static MKR_BALANCE_SLOT: i32 = 1;
-
-/// Number of decimals for WETH.
-pub static WETH_DECIMALS: u8 = 18;
-/// Number of decimals for USDT.
-pub static USDT_DECIMALS: u8 = 6;
-/// Number of decimals for USDC.
-pub static USDC_DECIMALS: u8 = 6;
-/// Number of decimals for WBTC.
-pub static WBTC_DECIMALS: u8 = 8;
-/// Number of decimals for DAI.
-pub static DAI_DECIMALS: u8 = 18;
-/// Number of decimals for LINK.
-pub static LINK_DECIMALS: u8 = 18;
-/// Number of decimals for MKR.
-pub static MKR_DECIMALS: u8 = 18;
-
-/// Chainlink price feed addresses for major tokens
-pub static CHAINLINK_ETH_USD: &str = "0x5f4eC3Df9cbd43714FE2740f5E3616155c5b8419";
-pub static CHAINLINK_BTC_USD: &str = "0xF4030086522a5bEEa4988F8cA5B36dbC97BeE88c";
-pub static CHAINLINK_USDT_USD: &str = "0x3E7d1eAB13ad0104d2750B8863b489D65364e32D";
-pub static CHAINLINK_USDC_USD: &str = "0x8fFfFfd4AfB6115b954Bd326cbe7B4BA576818f6";
-pub static CHAINLINK_DAI_USD: &str = "0xAed0c38402a5d19df6E4c03F4E2DceD6e29c1ee9";
-pub static CHAINLINK_LINK_USD: &str = "0x2c1d072e956AFFC0D435Cb7AC38EF18d24d9127c";
-pub static CHAINLINK_MKR_USD: &str = "0xec1D1B3b0443256cc3860e24a46F108e699484Aa";
+/// Maximum buy or sell tax (in basis points) we accept on a sandwich target token.
+pub static MAX_TOKEN_TAX_BPS: u32 = 1000;
+
+/// Number of blocks (~1 hour) before a token rejected by its safety report is analyzed again.
+pub static TOKEN_SAFETY_RECHECK_BLOCKS: u64 = 300;
+
+/// Maximum deviation (in basis points) between a pool mid-price and Chainlink before we distrust the pool.
+pub static MAX_PRICE_DEVIATION_BPS: u64 = 500;
+
+/// Maximum number of other pending swaps on our pools simulated as landing before us.
+pub static MAX_CONTENDING_SWAPS: usize = 3;
+
+/// Chance (in basis points) that a pending swap paying at least our priority fee lands before us.
+pub static CONTENTION_AHEAD_BPS: u64 = 7500;
+
+/// Chance (in basis points) that a pending swap paying less than our priority fee lands before us.
+pub static CONTENTION_BEHIND_BPS: u64 = 1000;
+
+/// Chance (in basis points) that a known competitor paying at least our priority fee lands before us.
+pub static COMPETITOR_AHEAD_BPS: u64 = 9500;
+
+/// Chance (in basis points) that a known competitor paying less than our priority fee lands before us
+/// (competitors usually bid through private bundles, so their public fee understates them).
+pub static COMPETITOR_BEHIND_BPS: u64 = 5000;
+
+/// Max premium (in basis points) over a victim's gas price for a tx to count as pegged to it.
+pub static COMPETITOR_GAS_PEG_BPS: u64 = 100;
+
+/// Max distance (in transactions) between the frontrun and backrun of a detected sandwich.
+pub static MAX_SANDWICH_SPAN: usize = 8;
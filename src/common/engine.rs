@@ -0,0 +1,471 @@
+/// Event engine dispatching blocks and pending transactions to pluggable strategies.
+///
+/// The `Engine` owns what every strategy shares: the event streams, the pool and token maps, the
+/// latest block, the `Executor`, the `Metrics` and the `Alert` handler. Strategies implement
+/// `Strategy` and answer each event with `Action`s; the engine submits their bundles, sends their
+/// alerts and reports each submission back through `Strategy::on_bundle_result`. Submissions run
+/// on their own tasks, so the loop keeps handling events while builders answer.
+///
+/// Handlers run one at a time on the engine loop, so they should return quickly. Heavy work
+/// (simulations) goes to background tasks, such as a `WorkerPool`, started in `Strategy::start`
+/// with a clone of the context; those tasks hand their actions back through an `ActionSender`.
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use ethers::{
+    providers::{Middleware, Provider, Ws},
+    types::{BlockNumber, H160, H256, U64},
+};
+use log::{error, info, warn};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::{error::RecvError, Sender};
+use tokio::sync::mpsc;
+
+use crate::common::alert::Alert;
+use crate::common::competitors::get_competitor_db;
+use crate::common::constants::Env;
+use crate::common::evm::NextBlockEnv;
+use crate::common::execution::{Executor, SandoBundle, SendBundleResponse};
+use crate::common::metrics::Metrics;
+use crate::common::pools::{load_all_pools, Pool};
+use crate::common::streams::{
+    poll_new_blocks, stream_new_blocks, stream_pending_txs, Event, NewBlock, NewPendingTx,
+};
+use crate::common::tokens::{load_all_tokens, Token};
+use crate::sandwich::main_dish::send_sando_bundle_request;
+use crate::sandwich::swap_cache::SwapCache;
+
+/// Block the pool cache starts from when loading mainnet pools.
+pub const POOLS_START_BLOCK: u64 = 22413000;
+/// Block range of each pool log query.
+pub const POOLS_CHUNK: u64 = 50000;
+/// Interval of the fallback block poller.
+const BLOCK_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A bundle a strategy wants sent to the builders.
+#[derive(Debug, Clone)]
+pub struct BundleSubmission {
+    /// Strategy-defined id, echoed in the `BundleResult`.
+    pub id: String,
+    /// The bundle to sign and send.
+    pub bundle: SandoBundle,
+    /// The block the bundle targets.
+    pub next_block: NextBlockEnv,
+}
+
+/// Something a strategy asks the engine to do.
+#[derive(Debug, Clone)]
+pub enum Action {
+    /// Verify the bundle locally and broadcast it to the builders.
+    SubmitBundle(BundleSubmission),
+    /// Send a message through the alert handler.
+    Alert(String),
+}
+
+/// Outcome of a `SubmitBundle` action.
+#[derive(Debug)]
+pub struct BundleResult {
+    /// The id of the submission.
+    pub id: String,
+    /// The block the bundle targeted.
+    pub target_block: U64,
+    /// Builder responses by builder name, or why the bundle was not sent.
+    pub responses: Result<HashMap<String, SendBundleResponse>>,
+}
+
+/// Shared state handed to strategies with every event.
+///
+/// Cloning is cheap: the provider, executor, pools, tokens, metrics and swap cache are behind
+/// `Arc`s and shared by every clone.
+#[derive(Clone)]
+pub struct EngineContext {
+    /// The Ethereum provider.
+    pub provider: Arc<Provider<Ws>>,
+    /// Executor building and signing bundles.
+    pub executor: Arc<Executor>,
+    /// Known pools by address.
+    pub pools_map: Arc<HashMap<H160, Pool>>,
+    /// Known tokens by address.
+    pub tokens_map: Arc<HashMap<H160, Token>>,
+    /// Pipeline counters.
+    pub metrics: Arc<Metrics>,
+    /// Swaps of pending transactions, extracted once for every strategy.
+    pub swaps: Arc<SwapCache>,
+    /// The latest block.
+    pub new_block: NewBlock,
+    /// Hashes of the transactions mined in `new_block`, empty until the first block event.
+    pub block_txs: Vec<H256>,
+}
+
+impl EngineContext {
+    /// Creates a context at the latest block.
+    ///
+    /// # Parameters
+    /// * `provider`: Arc<Provider<Ws>> - The Ethereum provider.
+    /// * `pools_map`: HashMap<H160, Pool> - Known pools.
+    /// * `tokens_map`: HashMap<H160, Token> - Known tokens.
+    ///
+    /// # Returns
+    /// * `Result<EngineContext>` - The context, or an error if the latest block can't be read.
+    pub async fn new(
+        provider: Arc<Provider<Ws>>,
+        pools_map: HashMap<H160, Pool>,
+        tokens_map: HashMap<H160, Token>,
+    ) -> Result<Self> {
+        let block = provider
+            .get_block(BlockNumber::Latest)
+            .await?
+            .ok_or_else(|| anyhow!("No latest block"))?;
+        let new_block = NewBlock::from_block(&block).ok_or_else(|| anyhow!("Pending block"))?;
+        let executor = Arc::new(Executor::new(provider.clone()));
+        Ok(Self {
+            provider,
+            executor,
+            pools_map: Arc::new(pools_map),
+            tokens_map: Arc::new(tokens_map),
+            metrics: Arc::new(Metrics::new()),
+            swaps: Arc::new(SwapCache::new()),
+            new_block,
+            block_txs: Vec::new(),
+        })
+    }
+
+    /// Loads the mainnet pools and tokens and creates a context at the latest block.
+    ///
+    /// Pools whose tokens could not be loaded are dropped.
+    ///
+    /// # Parameters
+    /// * `provider`: Arc<Provider<Ws>> - The Ethereum provider.
+    ///
+    /// # Returns
+    /// * `Result<EngineContext>` - The context.
+    pub async fn load(provider: Arc<Provider<Ws>>) -> Result<Self> {
+        let env = Env::new();
+        let (pools, prev_pool_id) =
+            load_all_pools(env.wss_url.clone(), POOLS_START_BLOCK, POOLS_CHUNK).await?;
+        info!("Pools loaded: {}", pools.len());
+
+        let block_number = provider.get_block_number().await?;
+        let tokens_map = load_all_tokens(&provider, block_number, &pools, prev_pool_id).await?;
+        info!("Tokens map count: {:?}", tokens_map.len());
+
+        let pools_map: HashMap<H160, Pool> = pools
+            .into_iter()
+            .filter(|p| tokens_map.contains_key(&p.token0) && tokens_map.contains_key(&p.token1))
+            .map(|p| (p.address, p))
+            .collect();
+        info!("Filtered pools by tokens count: {:?}", pools_map.len());
+
+        Self::new(provider, pools_map, tokens_map).await
+    }
+}
+
+/// What background tasks hand back to the engine loop, with the index of their strategy.
+#[derive(Debug)]
+enum EngineMessage {
+    /// Actions from a strategy's background task.
+    Actions(usize, Vec<Action>),
+    /// A submission finished.
+    BundleResult(usize, BundleResult),
+}
+
+/// Hands actions from a strategy's background tasks to the engine.
+#[derive(Debug, Clone)]
+pub struct ActionSender {
+    index: usize,
+    sender: mpsc::UnboundedSender<EngineMessage>,
+}
+
+impl ActionSender {
+    /// Queues actions for the engine, which executes them as if a handler returned them.
+    ///
+    /// # Returns
+    /// * `bool` - False if the engine stopped.
+    pub fn send(&self, actions: Vec<Action>) -> bool {
+        if actions.is_empty() {
+            return true;
+        }
+        self.sender
+            .send(EngineMessage::Actions(self.index, actions))
+            .is_ok()
+    }
+}
+
+/// A pending transaction handed to a worker, with the block it was seen at.
+///
+/// Workers run behind the engine loop, so the job carries its own block instead of reading the
+/// context's, which may have moved on by the time the job runs.
+#[derive(Debug, Clone)]
+pub struct PendingTxJob {
+    /// The latest block when the transaction was received.
+    pub new_block: NewBlock,
+    /// The pending transaction.
+    pub pending_tx: NewPendingTx,
+}
+
+/// A trading strategy driven by the engine.
+///
+/// Handlers read shared state from the `EngineContext` and return the actions to take. Errors
+/// are logged by the engine and do not stop it.
+#[async_trait]
+pub trait Strategy: Send {
+    /// Name used in logs.
+    fn name(&self) -> &str;
+
+    /// Called once before the first event, to start background tasks.
+    ///
+    /// # Parameters
+    /// * `ctx`: &EngineContext - The shared state; clone it to move it into a task.
+    /// * `actions`: ActionSender - Where those tasks send their actions.
+    async fn start(&mut self, _ctx: &EngineContext, _actions: ActionSender) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called once `ctx.new_block` and `ctx.block_txs` hold a new block.
+    async fn on_block(&mut self, ctx: &EngineContext) -> Result<Vec<Action>>;
+
+    /// Called for every pending transaction.
+    async fn on_pending_tx(
+        &mut self,
+        ctx: &EngineContext,
+        pending_tx: &NewPendingTx,
+    ) -> Result<Vec<Action>>;
+
+    /// Called after a bundle this strategy asked for was submitted, or failed to be.
+    async fn on_bundle_result(
+        &mut self,
+        _ctx: &EngineContext,
+        _result: &BundleResult,
+    ) -> Result<Vec<Action>> {
+        Ok(Vec::new())
+    }
+}
+
+/// Dispatches events to the registered strategies and executes their actions.
+pub struct Engine {
+    context: EngineContext,
+    alert: Alert,
+    strategies: Vec<Box<dyn Strategy>>,
+    action_sender: mpsc::UnboundedSender<EngineMessage>,
+    action_receiver: mpsc::UnboundedReceiver<EngineMessage>,
+}
+
+impl Engine {
+    /// Creates an engine with no strategies.
+    pub fn new(context: EngineContext) -> Self {
+        let (action_sender, action_receiver) = mpsc::unbounded_channel();
+        Self {
+            context,
+            alert: Alert::new(),
+            strategies: Vec::new(),
+            action_sender,
+            action_receiver,
+        }
+    }
+
+    /// Registers a strategy. Strategies see each event in registration order.
+    pub fn with_strategy<S: Strategy + 'static>(mut self, strategy: S) -> Self {
+        self.strategies.push(Box::new(strategy));
+        self
+    }
+
+    /// The shared state strategies run against.
+    pub fn context(&self) -> &EngineContext {
+        &self.context
+    }
+
+    /// Starts the block and pending transaction streams into `event_sender`, and a block poller
+    /// in case the block subscription stalls.
+    pub fn spawn_streams(&self, event_sender: &Sender<Event>) {
+        let (provider, sender) = (self.context.provider.clone(), event_sender.clone());
+        tokio::spawn(async move {
+            if let Err(e) = stream_pending_txs(provider, sender).await {
+                error!("Error in pending tx stream: {:?}", e);
+            }
+        });
+        let (provider, sender) = (self.context.provider.clone(), event_sender.clone());
+        tokio::spawn(async move {
+            if let Err(e) = stream_new_blocks(provider, sender).await {
+                error!("Error in new blocks stream: {:?}", e);
+            }
+        });
+        let (provider, sender) = (self.context.provider.clone(), event_sender.clone());
+        tokio::spawn(poll_new_blocks(provider, sender, BLOCK_POLL_INTERVAL));
+    }
+
+    /// Updates the shared state with an event and dispatches it to every strategy.
+    ///
+    /// # Parameters
+    /// * `event`: Event - The block or pending transaction.
+    pub async fn handle_event(&mut self, event: Event) {
+        match event {
+            Event::Block(block) => {
+                // the block stream and the poller both deliver each block
+                if block.block_number <= self.context.new_block.block_number {
+                    return;
+                }
+                info!("[Block #{:?}]", block.block_number);
+                info!("Metrics: {}", self.context.metrics.snapshot());
+                self.context.new_block = block;
+                self.context.block_txs = self.sync_block().await;
+
+                for index in 0..self.strategies.len() {
+                    let strategy = &mut self.strategies[index];
+                    let actions = strategy.on_block(&self.context).await;
+                    self.execute(index, actions).await;
+                }
+            }
+            Event::PendingTransaction(pending_tx) => {
+                for index in 0..self.strategies.len() {
+                    let strategy = &mut self.strategies[index];
+                    let actions = strategy.on_pending_tx(&self.context, &pending_tx).await;
+                    self.execute(index, actions).await;
+                }
+            }
+        }
+    }
+
+    /// Reads the transactions of the new block.
+    ///
+    /// Searchers are learnt from the block's sandwiches on a background task, so strategies
+    /// don't wait for its receipts.
+    async fn sync_block(&self) -> Vec<H256> {
+        let block_number = self.context.new_block.block_number;
+        let block = match self.context.provider.get_block_with_txs(block_number).await {
+            Ok(Some(block)) => block,
+            Ok(None) => return Vec::new(),
+            Err(e) => {
+                error!("get_block_with_txs error: {e:?}");
+                return Vec::new();
+            }
+        };
+        let block_txs = block.transactions.iter().map(|tx| tx.hash).collect();
+
+        let provider = self.context.provider.clone();
+        tokio::spawn(async move {
+            match get_competitor_db().learn_from_block(&provider, &block).await {
+                Ok(sandwiches) if !sandwiches.is_empty() => {
+                    info!("Sandwiches in block: {}", sandwiches.len())
+                }
+                Err(e) => error!("learn_from_block error: {e:?}"),
+                _ => {}
+            }
+        });
+        block_txs
+    }
+
+    /// Executes the actions of a strategy.
+    ///
+    /// Bundles are sent on their own tasks, which report back through the action channel.
+    async fn execute(&mut self, index: usize, actions: Result<Vec<Action>>) {
+        let actions = match actions {
+            Ok(actions) => actions,
+            Err(e) => {
+                error!("{} error: {e:?}", self.strategies[index].name());
+                return;
+            }
+        };
+
+        for action in actions {
+            match action {
+                Action::Alert(message) => {
+                    if let Err(e) = self.alert.send(&message).await {
+                        warn!("Telegram error: {e:?}");
+                    }
+                }
+                Action::SubmitBundle(submission) => {
+                    self.context.metrics.bundle_submitted();
+                    let executor = self.context.executor.clone();
+                    let sender = self.action_sender.clone();
+                    tokio::spawn(async move {
+                        let result = BundleResult {
+                            id: submission.id,
+                            target_block: submission.next_block.number,
+                            responses: send_sando_bundle_request(
+                                &executor,
+                                submission.bundle,
+                                &submission.next_block,
+                            )
+                            .await,
+                        };
+                        sender.send(EngineMessage::BundleResult(index, result)).ok();
+                    });
+                }
+            }
+        }
+    }
+
+    /// Reports a finished submission to its strategy and executes the actions it returns.
+    async fn handle_bundle_result(&mut self, index: usize, result: BundleResult) {
+        let strategy = &mut self.strategies[index];
+        let actions = strategy.on_bundle_result(&self.context, &result).await;
+        self.execute(index, actions).await;
+    }
+
+    /// Starts every strategy's background tasks.
+    ///
+    /// # Returns
+    /// * `Result<()>` - An error if a strategy failed to start.
+    pub async fn start(&mut self) -> Result<()> {
+        for index in 0..self.strategies.len() {
+            let actions = ActionSender {
+                index,
+                sender: self.action_sender.clone(),
+            };
+            let strategy = &mut self.strategies[index];
+            strategy
+                .start(&self.context, actions)
+                .await
+                .map_err(|e| anyhow!("{} failed to start: {e:?}", strategy.name()))?;
+        }
+        Ok(())
+    }
+
+    /// Starts the strategies and runs them on the events of `event_sender` until the channel
+    /// closes, executing the actions their background tasks send in between.
+    ///
+    /// # Parameters
+    /// * `event_sender`: Sender<Event> - Channel of block and transaction events.
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok once every sender is dropped.
+    pub async fn run(mut self, event_sender: Sender<Event>) -> Result<()> {
+        let names: Vec<&str> = self.strategies.iter().map(|s| s.name()).collect();
+        info!("Starting engine with strategies: {:?}", names);
+        self.start().await?;
+
+        let mut event_receiver = event_sender.subscribe();
+        drop(event_sender);
+        loop {
+            tokio::select! {
+                // background actions and bundle results first, they are few and time-sensitive
+                biased;
+                Some(message) = self.action_receiver.recv() => match message {
+                    EngineMessage::Actions(index, actions) => {
+                        self.execute(index, Ok(actions)).await
+                    }
+                    EngineMessage::BundleResult(index, result) => {
+                        self.handle_bundle_result(index, result).await
+                    }
+                },
+                event = event_receiver.recv() => match event {
+                    Ok(event) => {
+                        self.context.metrics.event_received();
+                        self.handle_event(event).await;
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        // the channel overwrote events we did not read in time
+                        self.context.metrics.events_lagged(skipped);
+                        warn!(
+                            "Engine lagged, {} events skipped ({} so far)",
+                            skipped,
+                            self.context.metrics.snapshot().events_lagged
+                        );
+                    }
+                    Err(RecvError::Closed) => return Ok(()),
+                },
+            }
+        }
+    }
+}
@@ -4,7 +4,7 @@
 use anyhow::{anyhow, Result};
 use ethers::prelude::abi;
 use ethers::providers::Middleware;
-use ethers::types::{transaction::eip2930::AccessList, H160, H256, U256, U64};
+use ethers::types::{transaction::eip2930::AccessList, Block, H160, H256, U256, U64};
 use foundry_evm_mini::evm::executor::fork::{BlockchainDb, BlockchainDbMeta, SharedBackend};
 use foundry_evm_mini::evm::executor::inspector::{get_precompiles_for, AccessListTracer};
 use revm::primitives::bytes::Bytes as rBytes;
@@ -12,15 +12,18 @@ use revm::primitives::{Bytes, Log, B160};
 use revm::{
     db::{CacheDB, Database},
     primitives::{
-        keccak256, AccountInfo, Bytecode, ExecutionResult, Output, TransactTo, B256, U256 as rU256,
+        keccak256, AccountInfo, Bytecode, CreateScheme, ExecutionResult, Output, TransactTo, B256,
+        U256 as rU256,
     },
     EVM,
 };
+use serde::{Deserialize, Serialize};
 use std::{collections::BTreeSet, default::Default, str::FromStr, sync::Arc};
 
 use crate::common::abi::Abi;
-use crate::common::constants::COINBASE;
-use crate::common::utils::{access_list_to_revm, create_new_wallet};
+use crate::common::constants::{BLOCK_TIME, COINBASE};
+use crate::common::utils::{access_list_to_revm, calculate_next_block_base_fee, create_new_wallet};
+use crate::common::sim_error::{SimError, TxFailure};
 use ethers::abi::parse_abi;
 use ethers::prelude::BaseContract;
 
@@ -97,6 +100,121 @@ pub struct TxResult {
     pub gas_refunded: u64,
 }
 
+/// Block environment of the block a simulation targets, predicted from its parent header.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NextBlockEnv {
+    /// The block number (parent + 1).
+    pub number: U64,
+    /// The block timestamp (parent + 12 seconds).
+    pub timestamp: U256,
+    /// The predicted base fee per gas.
+    pub base_fee: U256,
+    /// The block gas limit.
+    pub gas_limit: U256,
+    /// The prevrandao value (the parent's, the next one isn't known in advance).
+    pub prevrandao: H256,
+    /// The fee recipient of the builder we target.
+    pub coinbase: H160,
+    /// The chain id.
+    pub chain_id: u64,
+}
+
+impl NextBlockEnv {
+    /// Predicts the next block environment from its parent header.
+    ///
+    /// The coinbase defaults to the Flashbots builder; use `with_coinbase` to target another builder.
+    ///
+    /// # Parameters
+    /// * `parent`: &Block<T> - The parent block header.
+    ///
+    /// # Returns
+    /// * `Result<NextBlockEnv>` - The next block env, or an error for pending blocks.
+    pub fn from_parent<T>(parent: &Block<T>) -> Result<Self> {
+        let number = parent
+            .number
+            .ok_or_else(|| anyhow!("Parent block has no number"))?;
+        let base_fee = parent.base_fee_per_gas.unwrap_or_default();
+        Ok(Self {
+            number: number + 1,
+            timestamp: parent.timestamp + U256::from(BLOCK_TIME),
+            base_fee: calculate_next_block_base_fee(parent.gas_used, parent.gas_limit, base_fee),
+            gas_limit: parent.gas_limit,
+            prevrandao: parent.mix_hash.unwrap_or_default(),
+            coinbase: H160::from_str(COINBASE).unwrap(),
+            chain_id: 1,
+        })
+    }
+
+    /// Fetches the parent header and predicts the block after it.
+    ///
+    /// # Parameters
+    /// * `provider`: &Arc<M> - The Ethereum provider.
+    /// * `parent_number`: U64 - The parent block number.
+    ///
+    /// # Returns
+    /// * `Result<NextBlockEnv>` - The next block env.
+    pub async fn fetch<M: Middleware + 'static>(
+        provider: &Arc<M>,
+        parent_number: U64,
+    ) -> Result<Self> {
+        let parent = provider
+            .get_block(parent_number)
+            .await
+            .map_err(|e| anyhow!("Failed to fetch block {}: {:?}", parent_number, e))?
+            .ok_or_else(|| anyhow!("Block {} not found", parent_number))?;
+        Self::from_parent(&parent)
+    }
+
+    /// Returns the environment of an already mined block, to replay its transactions.
+    ///
+    /// # Parameters
+    /// * `block`: &Block<T> - The mined block header.
+    ///
+    /// # Returns
+    /// * `Result<NextBlockEnv>` - The block env, or an error for pending blocks.
+    pub fn of_block<T>(block: &Block<T>) -> Result<Self> {
+        Ok(Self {
+            number: block.number.ok_or_else(|| anyhow!("Block has no number"))?,
+            timestamp: block.timestamp,
+            base_fee: block.base_fee_per_gas.unwrap_or_default(),
+            gas_limit: block.gas_limit,
+            prevrandao: block.mix_hash.unwrap_or_default(),
+            coinbase: block.author.unwrap_or_default(),
+            chain_id: 1,
+        })
+    }
+
+    /// Sets the coinbase to the fee recipient of the targeted builder.
+    pub fn with_coinbase(mut self, coinbase: H160) -> Self {
+        self.coinbase = coinbase;
+        self
+    }
+
+    /// Returns the parent block number, the state simulations fork from.
+    pub fn parent_number(&self) -> U64 {
+        self.number - 1
+    }
+
+    /// Writes the block and chain fields into a revm environment.
+    ///
+    /// The base fee check is disabled so that zero gas price helper calls keep working; the
+    /// base fee is still charged to (and readable by) transactions paying a gas price.
+    ///
+    /// # Parameters
+    /// * `env`: &mut revm::primitives::Env - The environment to update.
+    pub fn apply(&self, env: &mut revm::primitives::Env) {
+        env.block.number = rU256::from(self.number.as_u64());
+        env.block.timestamp = self.timestamp.into();
+        env.block.basefee = self.base_fee.into();
+        env.block.gas_limit = self.gas_limit.into();
+        env.block.prevrandao = Some(B256::from(self.prevrandao.0));
+        env.block.difficulty = rU256::ZERO;
+        env.block.coinbase = self.coinbase.into();
+        env.cfg.chain_id = self.chain_id;
+        env.cfg.disable_base_fee = true;
+    }
+}
+
 /// EVM simulator for forking, state manipulation, and contract interaction.
 #[derive(Clone)]
 pub struct EvmSimulator<M> {
@@ -106,23 +224,24 @@ pub struct EvmSimulator<M> {
     pub owner: H160,
     /// The EVM instance.
     pub evm: EVM<CacheDB<SharedBackend>>,
-    /// The current block number.
+    /// The block the state is forked at (the parent of the simulated block).
     pub block_number: U64,
     /// The ABI instance.
     pub abi: Abi,
 }
 
 impl<M: Middleware + 'static> EvmSimulator<M> {
-    /// Creates a new `EvmSimulator` with a fresh database forked at a specific block.
+    /// Creates a new `EvmSimulator` forked at the parent of `next_block`.
     ///
     /// # Parameters
     /// * `provider`: Arc<M> - The Ethereum provider.
     /// * `owner`: Option<H160> - The owner address (random if None).
-    /// * `block_number`: U64 - The block number to fork from.
+    /// * `next_block`: &NextBlockEnv - The block to simulate in.
     ///
     /// # Returns
     /// * `EvmSimulator<M>` - Initialized simulator.
-    pub fn new(provider: Arc<M>, owner: Option<H160>, block_number: U64) -> Self {
+    pub fn new(provider: Arc<M>, owner: Option<H160>, next_block: &NextBlockEnv) -> Self {
+        let block_number = next_block.parent_number();
         let shared_backend = SharedBackend::spawn_backend_thread(
             provider.clone(),
             BlockchainDb::new(
@@ -136,7 +255,7 @@ impl<M: Middleware + 'static> EvmSimulator<M> {
             Some(block_number.into()),
         );
         let db = CacheDB::new(shared_backend);
-        EvmSimulator::new_with_db(provider, owner, block_number, db)
+        EvmSimulator::new_with_db(provider, owner, next_block, db)
     }
 
     /// Creates a new `EvmSimulator` using an existing state database.
@@ -144,15 +263,15 @@ impl<M: Middleware + 'static> EvmSimulator<M> {
     /// # Parameters
     /// * `provider`: Arc<M> - The Ethereum provider.
     /// * `owner`: Option<H160> - The owner address (random if None).
-    /// * `block_number`: U64 - The block number to fork from.
-    /// * `db`: CacheDB<SharedBackend> - The EVM state database.
+    /// * `next_block`: &NextBlockEnv - The block to simulate in.
+    /// * `db`: CacheDB<SharedBackend> - The EVM state database, forked at the parent block.
     ///
     /// # Returns
     /// * `EvmSimulator<M>` - Initialized simulator.
     pub fn new_with_db(
         provider: Arc<M>,
         owner: Option<H160>,
-        block_number: U64,
+        next_block: &NextBlockEnv,
         db: CacheDB<SharedBackend>,
     ) -> Self {
         let owner = match owner {
@@ -163,14 +282,13 @@ impl<M: Middleware + 'static> EvmSimulator<M> {
         let mut evm = EVM::new();
         evm.database(db);
 
-        evm.env.block.number = rU256::from(block_number.as_u64() + 1);
-        evm.env.block.coinbase = H160::from_str(COINBASE).unwrap().into();
+        next_block.apply(&mut evm.env);
 
         Self {
             provider,
             owner,
             evm,
-            block_number,
+            block_number: next_block.parent_number(),
             abi: Abi::new(),
         }
     }
@@ -183,13 +301,14 @@ impl<M: Middleware + 'static> EvmSimulator<M> {
         self.evm.db.as_mut().unwrap().clone()
     }
 
-    /// Replaces the current EVM state with a provided database.
+    /// Replaces the current EVM state with a provided database, keeping the block env.
     ///
     /// # Parameters
     /// * `db`: CacheDB<SharedBackend> - The new EVM state database.
     pub fn insert_db(&mut self, db: CacheDB<SharedBackend>) {
         let mut evm = EVM::new();
         evm.database(db);
+        evm.env = self.evm.env.clone();
 
         self.evm = evm;
     }
@@ -278,15 +397,15 @@ impl<M: Middleware + 'static> EvmSimulator<M> {
         let result;
 
         if commit {
-            result = match self.evm.transact_commit() {
-                Ok(result) => result,
-                Err(e) => return Err(anyhow!("EVM call failed: {:?}", e)),
-            };
+            result = self.evm.transact_commit().map_err(|e| TxFailure {
+                error: SimError::Invalid(format!("{:?}", e)),
+                gas_used: 0,
+            })?;
         } else {
-            let ref_tx = self
-                .evm
-                .transact_ref()
-                .map_err(|e| anyhow!("EVM staticcall failed: {:?}", e))?;
+            let ref_tx = self.evm.transact_ref().map_err(|e| TxFailure {
+                error: SimError::Invalid(format!("{:?}", e)),
+                gas_used: 0,
+            })?;
             result = ref_tx.result;
         }
 
@@ -312,13 +431,19 @@ impl<M: Middleware + 'static> EvmSimulator<M> {
                 },
             },
             ExecutionResult::Revert { gas_used, output } => {
-                return Err(anyhow!(
-                    "EVM REVERT: {:?} / Gas used: {:?}",
-                    output,
-                    gas_used
-                ))
+                return Err(TxFailure {
+                    error: SimError::from_revert(&output),
+                    gas_used,
+                }
+                .into())
+            }
+            ExecutionResult::Halt { reason, gas_used } => {
+                return Err(TxFailure {
+                    error: SimError::Halt(format!("{:?}", reason)),
+                    gas_used,
+                }
+                .into())
             }
-            ExecutionResult::Halt { reason, .. } => return Err(anyhow!("EVM HALT: {:?}", reason)),
         };
 
         Ok(output)
@@ -379,6 +504,43 @@ impl<M: Middleware + 'static> EvmSimulator<M> {
         self.insert_account_info(target, contract_info);
     }
 
+    /// Deploys a contract by executing its creation code in a CREATE transaction.
+    ///
+    /// Unlike `deploy`, this runs the constructor, so immutables (e.g. `owner`) are set.
+    ///
+    /// # Parameters
+    /// * `caller`: H160 - The deployer address.
+    /// * `init_code`: rBytes - The creation code with ABI-encoded constructor arguments appended.
+    ///
+    /// # Returns
+    /// * `Result<H160>` - The address of the deployed contract.
+    pub fn create(&mut self, caller: H160, init_code: rBytes) -> Result<H160> {
+        self.evm.env.tx.caller = caller.into();
+        self.evm.env.tx.transact_to = TransactTo::Create(CreateScheme::Create);
+        self.evm.env.tx.data = init_code;
+        self.evm.env.tx.value = rU256::ZERO;
+        self.evm.env.tx.gas_price = self.evm.env.block.basefee;
+        self.evm.env.tx.gas_limit = 10000000;
+
+        let result = match self.evm.transact_commit() {
+            Ok(result) => result,
+            Err(e) => return Err(anyhow!("EVM create failed: {:?}", e)),
+        };
+
+        match result {
+            ExecutionResult::Success {
+                output: Output::Create(_, Some(address)),
+                ..
+            } => Ok(address.into()),
+            ExecutionResult::Revert { gas_used, output } => Err(TxFailure {
+                error: SimError::from_revert(&output),
+                gas_used,
+            }
+            .into()),
+            other => Err(anyhow!("Contract creation failed: {:?}", other)),
+        }
+    }
+
     /// Returns the ETH balance of an address in the EVM state.
     ///
     /// # Parameters
@@ -519,37 +681,18 @@ impl<M: Middleware + 'static> EvmSimulator<M> {
 
     /// Attempts to find the storage slot for the balance of a given ERC-20 token contract.
     ///
+    /// Only returns the mapping slot; use `find_balance_slot` to also get the layout and the
+    /// contract holding the storage.
+    ///
     /// # Parameters
     /// * `token_address`: H160 - The token contract address.
     ///
     /// # Returns
     /// * `Result<i32>` - The balance slot if found, or -1 if not found.
     pub fn get_balance_slot(&mut self, token_address: H160) -> Result<i32> {
-        let calldata = self.abi.token.encode("balanceOf", token_address)?;
-        self.evm.env.tx.caller = self.owner.into();
-        self.evm.env.tx.transact_to = TransactTo::Call(token_address.into());
-        self.evm.env.tx.data = calldata.0;
-        let result = match self.evm.transact_ref() {
-            Ok(result) => result,
-            Err(e) => return Err(anyhow!("EVM ref call failed: {e:?}")),
-        };
-        let token_b160: B160 = token_address.into();
-        let token_acc = result.state.get(&token_b160).unwrap();
-        let token_touched_storage = token_acc.storage.clone();
-        for i in 0..30 {
-            let slot = keccak256(&abi::encode(&[
-                abi::Token::Address(token_address.into()),
-                abi::Token::Uint(U256::from(i)),
-            ]));
-            let slot: rU256 = U256::from(slot).into();
-            match token_touched_storage.get(&slot) {
-                Some(_) => {
-                    return Ok(i);
-                }
-                None => {}
-            }
-        }
-
-        Ok(-1)
+        Ok(self
+            .find_balance_slot(token_address)?
+            .map(|slot| slot.slot)
+            .unwrap_or(-1))
     }
 }
@@ -7,6 +7,7 @@ use ethers::providers::{Middleware, Provider};
 use ethers::signers::{LocalWallet, Signer};
 use ethers::types::transaction::{eip2718::TypedTransaction, eip2930::AccessList};
 use ethers_flashbots::*;
+use log::warn;
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::str::FromStr;
@@ -14,17 +15,57 @@ use std::sync::Arc;
 use url::Url;
 
 use crate::common::abi::Abi;
-use crate::common::constants::Env;
+use crate::common::constants::{get_env, Env};
+
+/// Parses a `name=url,name=url` list of builder endpoints.
+///
+/// # Parameters
+/// * `spec`: &str - The comma separated list.
+///
+/// # Returns
+/// * `Result<HashMap<String, Url>>` - Builder URLs by name, or an error for malformed entries.
+pub fn parse_builder_urls(spec: &str) -> Result<HashMap<String, Url>> {
+    let mut builder_urls = HashMap::new();
+    for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (name, url) = entry
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("Builder entry {:?} is not name=url", entry))?;
+        builder_urls.insert(name.trim().to_string(), Url::parse(url.trim())?);
+    }
+    Ok(builder_urls)
+}
+
+/// Returns the public builder endpoints bundles are broadcast to.
+///
+/// The endpoints here will gracefully fail if they don't work.
+pub fn default_builder_urls() -> HashMap<String, Url> {
+    [
+        ("flashbots", "https://relay.flashbots.net"),
+        ("beaverbuild", "https://rpc.beaverbuild.org"),
+        ("rsync", "https://rsync-builder.xyz"),
+        ("titanbuilder", "https://rpc.titanbuilder.xyz"),
+        ("builder0x69", "https://builder0x69.io"),
+        ("f1b", "https://rpc.f1b.io"),
+        ("lokibuilder", "https://rpc.lokibuilder.xyz"),
+        ("eden", "https://api.edennetwork.io/v1/rpc"),
+        ("penguinbuild", "https://rpc.penguinbuild.org"),
+        ("gambit", "https://builder.gmbit.co/rpc"),
+        ("idcmev", "https://rpc.idcmev.xyz"),
+    ]
+    .into_iter()
+    .map(|(name, url)| (name.to_string(), Url::parse(url).unwrap()))
+    .collect()
+}
 
 /// Represents a bundle of transactions for a sandwich attack.
 #[derive(Debug, Clone)]
 pub struct SandoBundle {
-    /// The frontrun transaction.
-    pub frontrun_tx: TypedTransaction,
+    /// The frontrun transaction (None for backrun-only bundles).
+    pub frontrun_tx: Option<TypedTransaction>,
     /// The victim transactions.
     pub victim_txs: Vec<Transaction>,
-    /// The backrun transaction.
-    pub backrun_tx: TypedTransaction,
+    /// The backrun transaction (None for bundles whose trades all run in the frontrun).
+    pub backrun_tx: Option<TypedTransaction>,
 }
 
 /// Response from a builder after sending a bundle.
@@ -35,6 +76,31 @@ pub struct SendBundleResponse {
     pub bundle_hash: BundleHash,
 }
 
+/// Builds a bundle request from signed transactions, as they were signed and verified.
+///
+/// # Parameters
+/// * `signed_txs`: &[Bytes] - Signed RLP transactions in bundle order.
+/// * `block_number`: U64 - The block number.
+/// * `retries`: usize - The number of retries.
+///
+/// # Returns
+/// * `BundleRequest` - The bundle request, targeting `block_number + retries`.
+pub fn signed_bundle_request(
+    signed_txs: &[Bytes],
+    block_number: U64,
+    retries: usize,
+) -> BundleRequest {
+    let mut bundle = BundleRequest::new()
+        .set_block(block_number + U64::from(retries))
+        .set_simulation_block(block_number)
+        .set_simulation_timestamp(0);
+
+    for signed_tx in signed_txs {
+        bundle = bundle.push_transaction(signed_tx.clone());
+    }
+    bundle
+}
+
 /// Sends a bundle to a builder relay and returns the builder name and response.
 ///
 /// # Parameters
@@ -106,49 +172,17 @@ impl Executor {
             owner.clone(),
         );
 
-        // The endpoints here will gracefully fail if it doesn't work
-        let mut builder_urls = HashMap::new();
-        builder_urls.insert(
-            "flashbots".to_string(),
-            Url::parse("https://relay.flashbots.net").unwrap(),
-        );
-        builder_urls.insert(
-            "beaverbuild".to_string(),
-            Url::parse("https://rpc.beaverbuild.org").unwrap(),
-        );
-        builder_urls.insert(
-            "rsync".to_string(),
-            Url::parse("https://rsync-builder.xyz").unwrap(),
-        );
-        builder_urls.insert(
-            "titanbuilder".to_string(),
-            Url::parse("https://rpc.titanbuilder.xyz").unwrap(),
-        );
-        builder_urls.insert(
-            "builder0x69".to_string(),
-            Url::parse("https://builder0x69.io").unwrap(),
-        );
-        builder_urls.insert("f1b".to_string(), Url::parse("https://rpc.f1b.io").unwrap());
-        builder_urls.insert(
-            "lokibuilder".to_string(),
-            Url::parse("https://rpc.lokibuilder.xyz").unwrap(),
-        );
-        builder_urls.insert(
-            "eden".to_string(),
-            Url::parse("https://api.edennetwork.io/v1/rpc").unwrap(),
-        );
-        builder_urls.insert(
-            "penguinbuild".to_string(),
-            Url::parse("https://rpc.penguinbuild.org").unwrap(),
-        );
-        builder_urls.insert(
-            "gambit".to_string(),
-            Url::parse("https://builder.gmbit.co/rpc").unwrap(),
-        );
-        builder_urls.insert(
-            "idcmev".to_string(),
-            Url::parse("https://rpc.idcmev.xyz").unwrap(),
-        );
+        // BUILDER_URLS replaces the default builders, e.g. to target a local mock builder
+        let builder_urls = match get_env("BUILDER_URLS").as_str() {
+            "" => default_builder_urls(),
+            spec => match parse_builder_urls(spec) {
+                Ok(builder_urls) => builder_urls,
+                Err(e) => {
+                    warn!("Invalid BUILDER_URLS, using the default builders: {e:?}");
+                    default_builder_urls()
+                }
+            },
+        };
 
         Self {
             provider,
@@ -322,9 +356,9 @@ impl Executor {
             access_list: back_access_list,
         });
         Ok(SandoBundle {
-            frontrun_tx,
+            frontrun_tx: Some(frontrun_tx),
             victim_txs,
-            backrun_tx,
+            backrun_tx: Some(backrun_tx),
         })
     }
 
@@ -352,6 +386,30 @@ impl Executor {
         Ok(bundle)
     }
 
+    /// Signs a sandwich bundle into the raw transactions sent to builders.
+    ///
+    /// # Parameters
+    /// * `sando_bundle`: &SandoBundle - The sandwich bundle to sign.
+    ///
+    /// # Returns
+    /// * `Result<Vec<Bytes>>` - Signed RLP transactions in bundle order.
+    pub async fn sign_sando_bundle(&self, sando_bundle: &SandoBundle) -> Result<Vec<Bytes>> {
+        let mut signed_txs = Vec::new();
+        if let Some(frontrun_tx) = &sando_bundle.frontrun_tx {
+            let frontrun_signature = self.client.signer().sign_transaction(frontrun_tx).await?;
+            signed_txs.push(frontrun_tx.rlp_signed(&frontrun_signature));
+        }
+        for victim_tx in &sando_bundle.victim_txs {
+            signed_txs.push(victim_tx.rlp());
+        }
+        if let Some(backrun_tx) = &sando_bundle.backrun_tx {
+            let backrun_signature = self.client.signer().sign_transaction(backrun_tx).await?;
+            signed_txs.push(backrun_tx.rlp_signed(&backrun_signature));
+        }
+
+        Ok(signed_txs)
+    }
+
     /// Converts a sandwich bundle to a bundle request.
     ///
     /// # Parameters
@@ -367,36 +425,13 @@ impl Executor {
         block_number: U64,
         retries: usize,
     ) -> Result<BundleRequest> {
-        let frontrun_signature = self
-            .client
-            .signer()
-            .sign_transaction(&sando_bundle.frontrun_tx)
-            .await?;
-        let signed_frontrun_tx = sando_bundle.frontrun_tx.rlp_signed(&frontrun_signature);
-
-        let backrun_signature = self
-            .client
-            .signer()
-            .sign_transaction(&sando_bundle.backrun_tx)
-            .await?;
-        let signed_backrun_tx = sando_bundle.backrun_tx.rlp_signed(&backrun_signature);
-
-        let mut bundle = BundleRequest::new()
-            .set_block(block_number + U64::from(retries))
-            .set_simulation_block(block_number)
-            .set_simulation_timestamp(0);
-
-        bundle = bundle.push_transaction(signed_frontrun_tx);
-        for victim_tx in &sando_bundle.victim_txs {
-            let signed_victim_tx = victim_tx.rlp();
-            bundle = bundle.push_transaction(signed_victim_tx);
-        }
-        bundle = bundle.push_transaction(signed_backrun_tx);
-
-        Ok(bundle)
+        let signed_txs = self.sign_sando_bundle(&sando_bundle).await?;
+        Ok(signed_bundle_request(&signed_txs, block_number, retries))
     }
 
-    /// Simulates a bundle.
+    /// Simulates a bundle on the Flashbots relay.
+    ///
+    /// Use `EvmSimulator::simulate_bundle` to verify bundles locally without the round trip.
     ///
     /// # Parameters
     /// * `bundle`: &BundleRequest - The bundle to simulate.
@@ -421,9 +456,25 @@ impl Executor {
     pub async fn broadcast_bundle(
         &self,
         bundle: BundleRequest,
+    ) -> Result<HashMap<String, SendBundleResponse>> {
+        self.broadcast_bundle_to(bundle, &self.builder_urls).await
+    }
+
+    /// Broadcasts a bundle to the given builders.
+    ///
+    /// # Parameters
+    /// * `bundle`: BundleRequest - The bundle to broadcast.
+    /// * `builder_urls`: &HashMap<String, Url> - The builders to send it to.
+    ///
+    /// # Returns
+    /// * `Result<HashMap<String, SendBundleResponse>>` - A map of builder names to responses.
+    pub async fn broadcast_bundle_to(
+        &self,
+        bundle: BundleRequest,
+        builder_urls: &HashMap<String, Url>,
     ) -> Result<HashMap<String, SendBundleResponse>> {
         let mut requests = Vec::new();
-        for (builder, url) in &self.builder_urls {
+        for (builder, url) in builder_urls {
             requests.push(tokio::task::spawn(send_bundle(
                 builder.clone(),
                 url.clone(),
@@ -1,25 +1,24 @@
 /// Execution logic specifically for SandoooV3 contract with Aave V3 flashloans integration.
 ///
-/// This module extends the execution functionality to support atomic flashloan-based sandwich attacks.
-use anyhow::Result;
+/// A sandwich runs as two `executeLeg` transactions around the victim, funded by the contract's
+/// inventory. The flashloan entry point stays available for atomic trades, and `executeRoute`
+/// runs backrun-only multi-hop routes after the victim.
+use anyhow::{anyhow, Result};
 use ethers::prelude::*;
 use ethers::types::transaction::{eip2718::TypedTransaction, eip2930::AccessList};
 use std::future::Future;
 
+use crate::common::abi::Abi;
 use crate::common::constants::*;
 use crate::common::execution::{Executor, SandoBundle};
+use crate::common::sando_calldata::{SandoPayload, TradeLayout};
 
 /// Extension trait for Executor to add Aave V3 flashloan functionality
 pub trait ExecutorV3Extension {
     /// Creates a flashloan-based sandwich transaction using the SandoooV3 contract
     ///
     /// # Parameters
-    /// * `asset`: H160 - The asset address to borrow (typically WETH)
-    /// * `amount`: U256 - The amount to borrow
-    /// * `front_calldata`: Bytes - The encoded frontrun transaction data
-    /// * `victim_txs`: Vec<Transaction> - The victim transactions
-    /// * `back_calldata`: Bytes - The encoded backrun transaction data
-    /// * `block_number`: U64 - The target block number
+    /// * `calldata`: Bytes - The `executeSandwichWithFlashloan` call from `BatchSandwich::simulate_flashloan`
     /// * `gas_limit`: u64 - The gas limit for the transaction
     /// * `max_priority_fee_per_gas`: U256 - The maximum priority fee per gas
     /// * `max_fee_per_gas`: U256 - The maximum fee per gas
@@ -28,45 +27,68 @@ pub trait ExecutorV3Extension {
     /// * `Result<TypedTransaction>` - The typed transaction
     fn create_flashloan_sandwich_tx<'a>(
         &'a self,
-        asset: H160,
-        amount: U256,
-        front_calldata: Bytes,
-        _victim_txs: &Vec<Transaction>,
-        back_calldata: Bytes,
-        block_number: U64,
+        calldata: Bytes,
         gas_limit: u64,
         max_priority_fee_per_gas: U256,
         max_fee_per_gas: U256,
     ) -> impl Future<Output = Result<TypedTransaction>> + Send + 'a;
 
-    /// Creates a sandwich bundle using the flashloan method
+    /// Creates a sandwich bundle executing both legs through `SandoooV3.executeLeg`
+    ///
+    /// The frontrun and backrun are separate transactions funded by the contract's inventory,
+    /// so the victim transactions sit between them.
     ///
     /// # Parameters
-    /// * `asset`: H160 - The asset address to borrow (typically WETH)
-    /// * `amount`: U256 - The amount to borrow
     /// * `victim_txs`: Vec<Transaction> - The victim transactions
-    /// * `front_calldata`: Bytes - The frontrun transaction calldata
-    /// * `back_calldata`: Bytes - The backrun transaction calldata
+    /// * `target_block`: U64 - The block the bundle is submitted for
+    /// * `front_calldata`: Bytes - The frontrun `executeLeg` call from `BatchSandwich::simulate_with`
+    /// * `back_calldata`: Bytes - The backrun `executeLeg` call from `BatchSandwich::simulate_with`
     /// * `front_access_list`: AccessList - The frontrun transaction access list
     /// * `back_access_list`: AccessList - The backrun transaction access list
-    /// * `gas_limit`: u64 - The gas limit for the transaction
+    /// * `front_gas_limit`: u64 - The frontrun transaction gas limit
+    /// * `back_gas_limit`: u64 - The backrun transaction gas limit
     /// * `base_fee`: U256 - The base fee
     /// * `max_priority_fee_per_gas`: U256 - The maximum priority fee per gas
     /// * `max_fee_per_gas`: U256 - The maximum fee per gas
     ///
     /// # Returns
     /// * `Result<SandoBundle>` - The sandwich bundle
-    fn create_flashloan_sando_bundle<'a>(
+    fn create_v3_sando_bundle<'a>(
         &'a self,
-        asset: H160,
-        amount: U256,
         victim_txs: Vec<Transaction>,
+        target_block: U64,
         front_calldata: Bytes,
         back_calldata: Bytes,
-        _front_access_list: AccessList,
-        _back_access_list: AccessList,
+        front_access_list: AccessList,
+        back_access_list: AccessList,
+        front_gas_limit: u64,
+        back_gas_limit: u64,
+        base_fee: U256,
+        max_priority_fee_per_gas: U256,
+        max_fee_per_gas: U256,
+    ) -> impl Future<Output = Result<SandoBundle>> + Send + 'a;
+
+    /// Creates a backrun-only bundle: the victim transactions followed by one
+    /// `SandoooV3.executeRoute` transaction
+    ///
+    /// # Parameters
+    /// * `victim_txs`: Vec<Transaction> - The victim transactions
+    /// * `target_block`: U64 - The block the bundle is submitted for
+    /// * `route_calldata`: Bytes - The `executeRoute` call from `encode_route_call`
+    /// * `access_list`: AccessList - The backrun transaction access list
+    /// * `gas_limit`: u64 - The backrun transaction gas limit
+    /// * `max_priority_fee_per_gas`: U256 - The maximum priority fee per gas
+    /// * `max_fee_per_gas`: U256 - The maximum fee per gas
+    ///
+    /// # Returns
+    /// * `Result<SandoBundle>` - The bundle, without a frontrun
+    fn create_v3_backrun_bundle<'a>(
+        &'a self,
+        victim_txs: Vec<Transaction>,
+        target_block: U64,
+        route_calldata: Bytes,
+        access_list: AccessList,
         gas_limit: u64,
-        _base_fee: U256,
         max_priority_fee_per_gas: U256,
         max_fee_per_gas: U256,
     ) -> impl Future<Output = Result<SandoBundle>> + Send + 'a;
@@ -84,12 +106,7 @@ pub trait ExecutorV3Extension {
 impl ExecutorV3Extension for Executor {
     fn create_flashloan_sandwich_tx<'a>(
         &'a self,
-        asset: H160,
-        amount: U256,
-        front_calldata: Bytes,
-        _victim_txs: &Vec<Transaction>,
-        back_calldata: Bytes,
-        block_number: U64,
+        calldata: Bytes,
         gas_limit: u64,
         max_priority_fee_per_gas: U256,
         max_fee_per_gas: U256,
@@ -98,61 +115,7 @@ impl ExecutorV3Extension for Executor {
             // Get common fields for transaction construction
             let (owner, nonce, _) = self._common_fields().await?;
 
-            // The format for the refactored SandoooV3 contract:
-            // - 8 bytes of block number (uint64)
-            // - Multiple 105-byte trade entries, each containing:
-            //   - 1 byte for zeroForOne flag
-            //   - 20 bytes for pair address
-            //   - 20 bytes for tokenIn address
-            //   - 32 bytes for amountIn
-            //   - 32 bytes for amountOut
-
-            // Start with block number (uint64)
-            let mut sandwich_data = Vec::new();
-
-            // Add block number (uint64) - packed as 8 bytes
-            let block_number_u64 = block_number.as_u64();
-            let block_number_bytes = block_number_u64.to_be_bytes();
-            sandwich_data.extend_from_slice(&block_number_bytes);
-
-            // Process the front-run calldata
-            // Extract the actual trade data skipping the function selector (first 4 bytes)
-            if front_calldata.len() > 4 {
-                let front_data = &front_calldata[4..];
-                sandwich_data.extend_from_slice(front_data);
-            }
-
-            // Process the back-run calldata
-            // Extract the actual trade data skipping the function selector (first 4 bytes)
-            if back_calldata.len() > 4 {
-                let back_data = &back_calldata[4..];
-                sandwich_data.extend_from_slice(back_data);
-            }
-
-            // Verify that the data follows the required format after block number:
-            // total length = 8 + N * 105 bytes
-            if (sandwich_data.len() <= 8) || ((sandwich_data.len() - 8) % 105 != 0) {
-                return Err(anyhow::anyhow!(
-                    "Invalid sandwich data format. Expected 8 bytes + N×105 bytes."
-                ));
-            }
-
-            // Find the function in the ABI
-            let function = self
-                .abi
-                .sando_v3
-                .abi()
-                .functions()
-                .find(|f| f.name == "executeSandwichWithFlashloan")
-                .expect("Function not found in ABI");
-
-            // Create the function call to executeSandwichWithFlashloan
-            let calldata = function.encode_input(&[
-                ethers::abi::Token::Address(asset),
-                ethers::abi::Token::Uint(amount),
-                ethers::abi::Token::Bytes(sandwich_data.into()),
-            ])?;
-
+            // The calldata comes straight from the simulation, so we send exactly what we simulated
             // Create the typed transaction
             let tx = Eip1559TransactionRequest::new()
                 .from(owner)
@@ -171,48 +134,89 @@ impl ExecutorV3Extension for Executor {
         }
     }
 
-    fn create_flashloan_sando_bundle<'a>(
+    fn create_v3_sando_bundle<'a>(
         &'a self,
-        asset: H160,
-        amount: U256,
         victim_txs: Vec<Transaction>,
+        target_block: U64,
         front_calldata: Bytes,
         back_calldata: Bytes,
-        _front_access_list: AccessList,
-        _back_access_list: AccessList,
-        gas_limit: u64,
-        _base_fee: U256,
+        front_access_list: AccessList,
+        back_access_list: AccessList,
+        front_gas_limit: u64,
+        back_gas_limit: u64,
+        base_fee: U256,
         max_priority_fee_per_gas: U256,
         max_fee_per_gas: U256,
     ) -> impl Future<Output = Result<SandoBundle>> + Send + 'a {
         async move {
-            // For V3 with flashloans, we have a single transaction that contains both front and back
-            let flashloan_tx = self
-                .create_flashloan_sandwich_tx(
-                    asset,
-                    amount,
-                    front_calldata,
-                    &victim_txs,
-                    back_calldata,
-                    U64::from(block_number().await?), // Use actual block number for strict validation
-                    gas_limit,
-                    max_priority_fee_per_gas,
-                    max_fee_per_gas,
-                )
-                .await?;
-
-            // In the flashloan model, we have a single frontrun tx (the flashloan call)
-            // The victim txs stay the same
-            // And there's no separate backrun tx (it's part of the flashloan callback)
-            let sando_bundle = SandoBundle {
-                frontrun_tx: flashloan_tx,
+            // the contract reverts with WrongBlock unless both legs are pinned to the target block
+            for calldata in [&front_calldata, &back_calldata] {
+                let leg_block = leg_block_number(&self.abi, calldata)?;
+                if leg_block != target_block.as_u64() {
+                    return Err(anyhow!(
+                        "Leg is pinned to block {} but the bundle targets {}",
+                        leg_block,
+                        target_block
+                    ));
+                }
+            }
+
+            self.create_sando_bundle(
                 victim_txs,
-                backrun_tx: TypedTransaction::Eip1559(
-                    Eip1559TransactionRequest::new(), // Empty placeholder since we don't need a separate backrun tx
-                ),
-            };
+                front_calldata,
+                back_calldata,
+                front_access_list,
+                back_access_list,
+                front_gas_limit,
+                back_gas_limit,
+                base_fee,
+                max_priority_fee_per_gas,
+                max_fee_per_gas,
+            )
+            .await
+        }
+    }
 
-            Ok(sando_bundle)
+    fn create_v3_backrun_bundle<'a>(
+        &'a self,
+        victim_txs: Vec<Transaction>,
+        target_block: U64,
+        route_calldata: Bytes,
+        access_list: AccessList,
+        gas_limit: u64,
+        max_priority_fee_per_gas: U256,
+        max_fee_per_gas: U256,
+    ) -> impl Future<Output = Result<SandoBundle>> + Send + 'a {
+        async move {
+            // the contract reverts with WrongBlock unless the route is pinned to the target block
+            let route_block = route_block_number(&self.abi, &route_calldata)?;
+            if route_block != target_block.as_u64() {
+                return Err(anyhow!(
+                    "Route is pinned to block {} but the bundle targets {}",
+                    route_block,
+                    target_block
+                ));
+            }
+
+            let (owner, nonce, chain_id) = self._common_fields().await?;
+            let backrun_tx = TypedTransaction::Eip1559(Eip1559TransactionRequest {
+                to: Some(NameOrAddress::Address(self.bot_address)),
+                from: Some(owner),
+                data: Some(route_calldata),
+                value: Some(U256::zero()),
+                chain_id: Some(chain_id),
+                max_priority_fee_per_gas: Some(max_priority_fee_per_gas),
+                max_fee_per_gas: Some(max_fee_per_gas),
+                gas: Some(U256::from(gas_limit)),
+                nonce: Some(nonce),
+                access_list,
+            });
+
+            Ok(SandoBundle {
+                frontrun_tx: None,
+                victim_txs,
+                backrun_tx: Some(backrun_tx),
+            })
         }
     }
 
@@ -228,9 +232,102 @@ impl ExecutorV3Extension for Executor {
     }
 }
 
-// Helper function to get the current block number
-async fn block_number() -> Result<u64> {
-    // In a real implementation, this would query the blockchain
-    // Since this is just a test function, we'll return a placeholder value
-    Ok(1) // Replace with actual provider.get_block_number().await?.as_u64() in production
+/// Encodes a call to `SandoooV3.executeLeg`.
+///
+/// # Parameters
+/// * `abi`: &Abi - The ABI holder with the SandoooV3 ABI
+/// * `leg_data`: Bytes - 8-byte block number + N×129-byte trades
+///
+/// # Returns
+/// * `Result<Bytes>` - The calldata
+pub fn encode_leg_call(abi: &Abi, leg_data: Bytes) -> Result<Bytes> {
+    check_sandwich_data(&leg_data)?;
+    let calldata = abi.sando_v3.encode("executeLeg", leg_data)?;
+    Ok(calldata)
+}
+
+/// Returns the block number an `executeLeg` call is pinned to.
+///
+/// # Parameters
+/// * `abi`: &Abi - The ABI holder with the SandoooV3 ABI
+/// * `calldata`: &Bytes - The `executeLeg` call
+///
+/// # Returns
+/// * `Result<u64>` - The block number in the 8-byte header of the leg data
+pub fn leg_block_number(abi: &Abi, calldata: &Bytes) -> Result<u64> {
+    let leg_data: Bytes = abi.sando_v3.decode("executeLeg", calldata)?;
+    let payload = SandoPayload::decode(&leg_data, TradeLayout::SandoooV3)?;
+    Ok(payload.block_number)
+}
+
+/// Encodes a call to `SandoooV3.executeSandwichWithFlashloan`.
+///
+/// All trades run inside one transaction, so this only suits atomic trades without a victim
+/// in between. Sandwiches go through `encode_leg_call` instead.
+///
+/// # Parameters
+/// * `abi`: &Abi - The ABI holder with the SandoooV3 ABI
+/// * `asset`: H160 - The asset to borrow
+/// * `amount`: U256 - The amount to borrow
+/// * `sandwich_data`: Bytes - 8-byte block number + N×129-byte trades
+///
+/// # Returns
+/// * `Result<Bytes>` - The calldata
+pub fn encode_flashloan_call(
+    abi: &Abi,
+    asset: H160,
+    amount: U256,
+    sandwich_data: Bytes,
+) -> Result<Bytes> {
+    check_sandwich_data(&sandwich_data)?;
+
+    let calldata = abi.sando_v3.encode(
+        "executeSandwichWithFlashloan",
+        (asset, amount, sandwich_data),
+    )?;
+    Ok(calldata)
+}
+
+/// Encodes a call to `SandoooV3.executeRoute`.
+///
+/// Hops run in order from the contract's inventory, and a hop with a zero `amount_in` spends the
+/// whole output of the previous hop. The call reverts unless the contract ends with `min_profit`
+/// more of the first hop's input token.
+///
+/// # Parameters
+/// * `abi`: &Abi - The ABI holder with the SandoooV3 ABI
+/// * `min_profit`: U256 - Minimum gain in the first hop's input token
+/// * `route_data`: Bytes - 8-byte block number + N×129-byte hops
+///
+/// # Returns
+/// * `Result<Bytes>` - The calldata
+pub fn encode_route_call(abi: &Abi, min_profit: U256, route_data: Bytes) -> Result<Bytes> {
+    check_sandwich_data(&route_data)?;
+    let calldata = abi
+        .sando_v3
+        .encode("executeRoute", (min_profit, route_data))?;
+    Ok(calldata)
+}
+
+/// Returns the block number an `executeRoute` call is pinned to.
+///
+/// # Parameters
+/// * `abi`: &Abi - The ABI holder with the SandoooV3 ABI
+/// * `calldata`: &Bytes - The `executeRoute` call
+///
+/// # Returns
+/// * `Result<u64>` - The block number in the 8-byte header of the route data
+pub fn route_block_number(abi: &Abi, calldata: &Bytes) -> Result<u64> {
+    let (_, route_data): (U256, Bytes) = abi.sando_v3.decode("executeRoute", calldata)?;
+    let payload = SandoPayload::decode(&route_data, TradeLayout::SandoooV3)?;
+    Ok(payload.block_number)
+}
+
+/// Checks the packed data layout, otherwise the contract reverts with InvalidData.
+fn check_sandwich_data(data: &Bytes) -> Result<()> {
+    let payload = SandoPayload::decode(data, TradeLayout::SandoooV3)?;
+    if payload.trades.is_empty() {
+        return Err(anyhow!("Sandwich data has no trades"));
+    }
+    Ok(())
 }
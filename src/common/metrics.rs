@@ -0,0 +1,146 @@
+/// Counters of the engine's event and pending transaction pipeline.
+///
+/// `Metrics` is shared by the engine, the worker pools and the strategies through the
+/// `EngineContext`; every counter is an atomic, so it can be bumped from any task. The engine
+/// logs a `MetricsSnapshot` with every block.
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Pipeline counters, all monotonic except `in_flight`.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    events_received: AtomicU64,
+    events_lagged: AtomicU64,
+    jobs_queued: AtomicU64,
+    jobs_dropped: AtomicU64,
+    jobs_processed: AtomicU64,
+    job_errors: AtomicU64,
+    job_time_us: AtomicU64,
+    in_flight: AtomicU64,
+    opportunities: AtomicU64,
+    bundles_submitted: AtomicU64,
+}
+
+/// Point-in-time copy of the counters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    /// Events read from the broadcast channel.
+    pub events_received: u64,
+    /// Events the engine fell too far behind to read.
+    pub events_lagged: u64,
+    /// Pending transactions handed to a worker pool.
+    pub jobs_queued: u64,
+    /// Pending transactions dropped because a worker pool queue was full.
+    pub jobs_dropped: u64,
+    /// Pending transactions a worker finished.
+    pub jobs_processed: u64,
+    /// Pending transactions whose processing failed.
+    pub job_errors: u64,
+    /// Total processing time of the finished jobs, in microseconds.
+    pub job_time_us: u64,
+    /// Jobs being processed right now.
+    pub in_flight: u64,
+    /// Simulated opportunities handed to a coordinator.
+    pub opportunities: u64,
+    /// Bundles sent to the builders.
+    pub bundles_submitted: u64,
+}
+
+impl MetricsSnapshot {
+    /// Mean processing time of a job, zero before the first one finishes.
+    pub fn mean_job_time(&self) -> Duration {
+        match self.jobs_processed {
+            0 => Duration::ZERO,
+            processed => Duration::from_micros(self.job_time_us / processed),
+        }
+    }
+}
+
+impl fmt::Display for MetricsSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "events {} (lagged {}) / jobs {} queued, {} dropped, {} done, {} failed, {} in flight, {:?} mean / opportunities {} / bundles {}",
+            self.events_received,
+            self.events_lagged,
+            self.jobs_queued,
+            self.jobs_dropped,
+            self.jobs_processed,
+            self.job_errors,
+            self.in_flight,
+            self.mean_job_time(),
+            self.opportunities,
+            self.bundles_submitted,
+        )
+    }
+}
+
+impl Metrics {
+    /// Creates zeroed counters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts an event read from the broadcast channel.
+    pub fn event_received(&self) {
+        self.events_received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts events skipped because the receiver lagged.
+    pub fn events_lagged(&self, skipped: u64) {
+        self.events_lagged.fetch_add(skipped, Ordering::Relaxed);
+    }
+
+    /// Counts a job accepted by a worker pool.
+    pub fn job_queued(&self) {
+        self.jobs_queued.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts a job rejected by a full worker pool.
+    pub fn job_dropped(&self) {
+        self.jobs_dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Marks a job as started.
+    pub fn job_started(&self) {
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Marks a job as finished after `elapsed`.
+    pub fn job_finished(&self, elapsed: Duration, failed: bool) {
+        self.in_flight.fetch_sub(1, Ordering::Relaxed);
+        self.jobs_processed.fetch_add(1, Ordering::Relaxed);
+        self.job_time_us
+            .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+        if failed {
+            self.job_errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Counts a simulated opportunity handed to a coordinator.
+    pub fn opportunity(&self) {
+        self.opportunities.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts a bundle sent to the builders.
+    pub fn bundle_submitted(&self) {
+        self.bundles_submitted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Reads every counter.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            events_received: self.events_received.load(Ordering::Relaxed),
+            events_lagged: self.events_lagged.load(Ordering::Relaxed),
+            jobs_queued: self.jobs_queued.load(Ordering::Relaxed),
+            jobs_dropped: self.jobs_dropped.load(Ordering::Relaxed),
+            jobs_processed: self.jobs_processed.load(Ordering::Relaxed),
+            job_errors: self.job_errors.load(Ordering::Relaxed),
+            job_time_us: self.job_time_us.load(Ordering::Relaxed),
+            in_flight: self.in_flight.load(Ordering::Relaxed),
+            opportunities: self.opportunities.load(Ordering::Relaxed),
+            bundles_submitted: self.bundles_submitted.load(Ordering::Relaxed),
+        }
+    }
+}
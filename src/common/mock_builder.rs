@@ -0,0 +1,220 @@
+/// A local stand-in for a block builder, for devnet runs.
+///
+/// `MockBuilder` serves the `eth_sendBundle` JSON-RPC method over plain HTTP, so the `Executor`
+/// can target it through `BUILDER_URLS`. Each bundle is included in the next block of an anvil
+/// node: the bundle transactions are dropped from anvil's mempool, re-sent in bundle order and a
+/// block is mined with `evm_mine`. Anvil should run with `--order fifo` and automine off, so the
+/// block keeps the bundle order; pending transactions outside the bundle land before it.
+use anyhow::{anyhow, Result};
+use ethers::providers::Middleware;
+use ethers::types::{Bytes, H256, U64};
+use ethers::utils::keccak256;
+use log::{info, warn};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use url::Url;
+
+/// A bundle received by the mock builder.
+#[derive(Debug, Clone)]
+pub struct ReceivedBundle {
+    /// Signed transactions in bundle order.
+    pub txs: Vec<Bytes>,
+    /// The block the bundle targeted.
+    pub block_number: U64,
+    /// Hash returned to the sender.
+    pub bundle_hash: H256,
+    /// Whether the bundle was mined.
+    pub included: bool,
+}
+
+/// Returns the hash a builder reports for a bundle: the keccak of its transaction hashes.
+pub fn bundle_hash(txs: &[Bytes]) -> H256 {
+    let mut hashes = Vec::with_capacity(txs.len() * 32);
+    for tx in txs {
+        hashes.extend_from_slice(&keccak256(tx));
+    }
+    H256::from(keccak256(hashes))
+}
+
+/// Mock builder mining bundles on an anvil node.
+pub struct MockBuilder<M> {
+    provider: Arc<M>,
+    bundles: Arc<Mutex<Vec<ReceivedBundle>>>,
+}
+
+impl<M> Clone for MockBuilder<M> {
+    fn clone(&self) -> Self {
+        Self {
+            provider: self.provider.clone(),
+            bundles: self.bundles.clone(),
+        }
+    }
+}
+
+impl<M: Middleware + 'static> MockBuilder<M> {
+    /// Creates a mock builder mining through `provider`, which must be an anvil node.
+    pub fn new(provider: Arc<M>) -> Self {
+        Self {
+            provider,
+            bundles: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Bundles received so far, in arrival order.
+    pub fn bundles(&self) -> Vec<ReceivedBundle> {
+        self.bundles.lock().unwrap().clone()
+    }
+
+    /// Mines the bundle transactions, in order, in one block.
+    ///
+    /// # Parameters
+    /// * `txs`: &[Bytes] - Signed transactions in bundle order.
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok once the block is mined, or the first anvil RPC error.
+    pub async fn include_bundle(&self, txs: &[Bytes]) -> Result<()> {
+        // victims are already pending, pull them so they are re-queued behind the frontrun
+        for tx in txs {
+            let tx_hash = H256::from(keccak256(tx));
+            self.provider
+                .request::<_, Value>("anvil_dropTransaction", [tx_hash])
+                .await
+                .map_err(|e| anyhow!("anvil_dropTransaction {:?}: {:?}", tx_hash, e))?;
+        }
+        for tx in txs {
+            self.provider
+                .request::<_, H256>("eth_sendRawTransaction", [tx])
+                .await
+                .map_err(|e| anyhow!("eth_sendRawTransaction: {:?}", e))?;
+        }
+        self.provider
+            .request::<_, Value>("evm_mine", ())
+            .await
+            .map_err(|e| anyhow!("evm_mine: {:?}", e))?;
+        Ok(())
+    }
+
+    /// Handles one JSON-RPC request and returns the response object.
+    pub async fn handle_request(&self, request: &Value) -> Value {
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+        let method = request.get("method").and_then(Value::as_str).unwrap_or_default();
+        if method != "eth_sendBundle" {
+            return json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": -32601, "message": format!("method {} not supported", method) },
+            });
+        }
+
+        let bundle = &request["params"][0];
+        let txs: Vec<Bytes> = match serde_json::from_value(bundle["txs"].clone()) {
+            Ok(txs) => txs,
+            Err(e) => {
+                return json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": { "code": -32602, "message": format!("invalid txs: {}", e) },
+                })
+            }
+        };
+        let block_number: U64 = serde_json::from_value(bundle["blockNumber"].clone())
+            .unwrap_or_default();
+        let bundle_hash = bundle_hash(&txs);
+
+        let included = match self.include_bundle(&txs).await {
+            Ok(_) => {
+                info!("Mock builder mined bundle {:?} ({} txs)", bundle_hash, txs.len());
+                true
+            }
+            Err(e) => {
+                warn!("Mock builder could not mine bundle {:?}: {:?}", bundle_hash, e);
+                false
+            }
+        };
+        self.bundles.lock().unwrap().push(ReceivedBundle {
+            txs,
+            block_number,
+            bundle_hash,
+            included,
+        });
+
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": { "bundleHash": bundle_hash },
+        })
+    }
+
+    async fn handle_connection(&self, stream: TcpStream) -> Result<()> {
+        let mut reader = BufReader::new(stream);
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).await? == 0 {
+                return Ok(());
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse()?;
+                }
+            }
+        }
+        let mut body = vec![0u8; content_length];
+        reader.read_exact(&mut body).await?;
+
+        let response = match serde_json::from_slice::<Value>(&body) {
+            Ok(request) => self.handle_request(&request).await,
+            Err(e) => json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": { "code": -32700, "message": format!("parse error: {}", e) },
+            }),
+        };
+        let body = response.to_string();
+        let head = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+             Connection: close\r\n\r\n",
+            body.len()
+        );
+        let mut stream = reader.into_inner();
+        stream.write_all(head.as_bytes()).await?;
+        stream.write_all(body.as_bytes()).await?;
+        stream.flush().await?;
+        Ok(())
+    }
+
+    /// Serves bundles on `listener` until the task is dropped.
+    pub async fn serve(self, listener: TcpListener) -> Result<()> {
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let builder = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = builder.handle_connection(stream).await {
+                    warn!("Mock builder connection error: {:?}", e);
+                }
+            });
+        }
+    }
+
+    /// Binds a local port and serves bundles in the background.
+    ///
+    /// # Returns
+    /// * `Result<Url>` - The endpoint to put in `BUILDER_URLS`.
+    pub async fn spawn(&self) -> Result<Url> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = Url::parse(&format!("http://{}", listener.local_addr()?))?;
+        let builder = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = builder.serve(listener).await {
+                warn!("Mock builder stopped: {:?}", e);
+            }
+        });
+        Ok(url)
+    }
+}
@@ -0,0 +1,179 @@
+/// A scripted `Middleware` for provider-agnostic tests.
+///
+/// `MockMiddleware` answers the state reads the classifier, `extract_swap_info` and the forked
+/// EVM make (`get_code`, `get_balance`, `get_transaction_count`, `get_storage_at`) from canned
+/// accounts, and `get_transaction_receipt` and `debug_trace_call` from canned receipts and
+/// traces. Accounts that were not scripted read as empty, like a fresh address; any other call
+/// goes to an inner `MockProvider` with no responses and fails.
+///
+/// Since the EVM runs the scripted code, a `Swap` log can be produced by giving a pool address
+/// code that emits it, and `trace_swap_info` picks it up as it would on a node.
+use async_trait::async_trait;
+use ethers::providers::{Middleware, MockProvider, Provider, ProviderError};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{
+    BlockId, Bytes, GethDebugTracingCallOptions, GethTrace, NameOrAddress, TransactionReceipt,
+    TxHash, H160, H256, U256,
+};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::common::snapshot::SnapshotAccount;
+
+/// Middleware answering from scripted state.
+#[derive(Debug, Clone)]
+pub struct MockMiddleware {
+    inner: Provider<MockProvider>,
+    accounts: BTreeMap<H160, SnapshotAccount>,
+    receipts: HashMap<H256, TransactionReceipt>,
+    traces: HashMap<H160, GethTrace>,
+}
+
+impl Default for MockMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockMiddleware {
+    /// Creates a middleware with no scripted state.
+    pub fn new() -> Self {
+        let (inner, _) = Provider::mocked();
+        Self {
+            inner,
+            accounts: BTreeMap::new(),
+            receipts: HashMap::new(),
+            traces: HashMap::new(),
+        }
+    }
+
+    /// Scripts the code at an address.
+    pub fn with_code(mut self, address: H160, code: Bytes) -> Self {
+        self.accounts.entry(address).or_default().code = code;
+        self
+    }
+
+    /// Scripts the ETH balance of an address.
+    pub fn with_balance(mut self, address: H160, balance: U256) -> Self {
+        self.accounts.entry(address).or_default().balance = balance;
+        self
+    }
+
+    /// Scripts a storage slot of an address.
+    pub fn with_storage(mut self, address: H160, slot: U256, value: U256) -> Self {
+        self.accounts
+            .entry(address)
+            .or_default()
+            .storage
+            .insert(slot, value);
+        self
+    }
+
+    /// Scripts a whole account, e.g. from a `StateSnapshot`.
+    pub fn with_account(mut self, address: H160, account: SnapshotAccount) -> Self {
+        self.accounts.insert(address, account);
+        self
+    }
+
+    /// Scripts the receipt of a mined transaction.
+    pub fn with_receipt(mut self, receipt: TransactionReceipt) -> Self {
+        self.receipts.insert(receipt.transaction_hash, receipt);
+        self
+    }
+
+    /// Scripts the `debug_trace_call` result of calls to an address.
+    pub fn with_trace(mut self, to: H160, trace: GethTrace) -> Self {
+        self.traces.insert(to, trace);
+        self
+    }
+
+    fn account(&self, at: NameOrAddress) -> Result<Option<&SnapshotAccount>, ProviderError> {
+        match at {
+            NameOrAddress::Address(address) => Ok(self.accounts.get(&address)),
+            NameOrAddress::Name(name) => Err(ProviderError::CustomError(format!(
+                "MockMiddleware cannot resolve ENS name {}",
+                name
+            ))),
+        }
+    }
+}
+
+#[async_trait]
+impl Middleware for MockMiddleware {
+    type Error = ProviderError;
+    type Provider = MockProvider;
+    type Inner = Provider<MockProvider>;
+
+    fn inner(&self) -> &Self::Inner {
+        &self.inner
+    }
+
+    async fn get_code<T: Into<NameOrAddress> + Send + Sync>(
+        &self,
+        at: T,
+        _block: Option<BlockId>,
+    ) -> Result<Bytes, Self::Error> {
+        Ok(self
+            .account(at.into())?
+            .map(|account| account.code.clone())
+            .unwrap_or_default())
+    }
+
+    async fn get_balance<T: Into<NameOrAddress> + Send + Sync>(
+        &self,
+        from: T,
+        _block: Option<BlockId>,
+    ) -> Result<U256, Self::Error> {
+        Ok(self
+            .account(from.into())?
+            .map(|account| account.balance)
+            .unwrap_or_default())
+    }
+
+    async fn get_transaction_count<T: Into<NameOrAddress> + Send + Sync>(
+        &self,
+        from: T,
+        _block: Option<BlockId>,
+    ) -> Result<U256, Self::Error> {
+        Ok(self
+            .account(from.into())?
+            .map(|account| U256::from(account.nonce))
+            .unwrap_or_default())
+    }
+
+    async fn get_storage_at<T: Into<NameOrAddress> + Send + Sync>(
+        &self,
+        from: T,
+        location: H256,
+        _block: Option<BlockId>,
+    ) -> Result<H256, Self::Error> {
+        let slot = U256::from_big_endian(location.as_bytes());
+        let value = self
+            .account(from.into())?
+            .and_then(|account| account.storage.get(&slot).copied())
+            .unwrap_or_default();
+        let mut word = [0u8; 32];
+        value.to_big_endian(&mut word);
+        Ok(H256::from(word))
+    }
+
+    async fn get_transaction_receipt<T: Send + Sync + Into<TxHash>>(
+        &self,
+        transaction_hash: T,
+    ) -> Result<Option<TransactionReceipt>, Self::Error> {
+        Ok(self.receipts.get(&transaction_hash.into()).cloned())
+    }
+
+    async fn debug_trace_call<T: Into<TypedTransaction> + Send + Sync>(
+        &self,
+        req: T,
+        _block: Option<BlockId>,
+        _trace_options: GethDebugTracingCallOptions,
+    ) -> Result<GethTrace, Self::Error> {
+        let req = req.into();
+        req.to_addr()
+            .and_then(|to| self.traces.get(to).cloned())
+            .ok_or_else(|| {
+                ProviderError::CustomError(format!("No trace scripted for {:?}", req.to_addr()))
+            })
+    }
+}
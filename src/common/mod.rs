@@ -4,16 +4,33 @@
 /// used across the project.
 pub mod abi;
 pub mod alert;
+pub mod balance_slot;
+pub mod bundle_sim;
 pub mod bytecode;
 pub mod routers;
 pub mod classifier;
+pub mod competitors;
 pub mod constants;
+pub mod engine;
 pub mod evm;
 pub mod execution;
 pub mod execution_v3;
+pub mod mock_builder;
+pub mod metrics;
+pub mod mock_middleware;
 pub mod pools;
+pub mod quote_assets;
+pub mod recorder;
+pub mod sando_calldata;
+pub mod sim_error;
+pub mod slot_clock;
+pub mod snapshot;
 pub mod streams;
+pub mod trace;
 pub mod token_registry;
+pub mod token_safety;
 pub mod tokens;
 pub mod utils;
+pub mod valuation;
+pub mod worker_pool;
 pub mod contract_detector;
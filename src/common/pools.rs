@@ -22,7 +22,7 @@ use std::{
 };
 
 /// Supported DEX variants.
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 pub enum DexVariant {
     /// Uniswap V2-like DEX.
     UniswapV2, // 2
@@ -0,0 +1,279 @@
+/// Quote assets ("main currencies") the bot sandwiches against.
+///
+/// A quote asset is the token we hold and trade a target token against (WETH, stablecoins...).
+/// `config/quote_assets.json` is the only place they are defined: it is built into the binary,
+/// and `QUOTE_ASSETS_PATH` can point to another file, so supporting a new quote asset is a config
+/// change. Amounts are written as decimal strings of whole tokens and parsed exactly.
+use anyhow::{anyhow, Result};
+use ethers::types::{H160, U256};
+use lazy_static::lazy_static;
+use log::{info, warn};
+use serde::Deserialize;
+use std::str::FromStr;
+
+use crate::common::balance_slot::{BalanceSlot, MappingLayout};
+use crate::common::constants::WETH;
+
+/// The quote asset config built into the binary.
+pub static DEFAULT_QUOTE_ASSETS: &str = include_str!("../../config/quote_assets.json");
+
+/// A quote asset as written in the config: the fields of `QuoteAsset`, with the amounts as
+/// decimal strings of whole tokens.
+#[derive(Debug, Deserialize)]
+struct QuoteAssetConfig {
+    address: H160,
+    name: String,
+    symbol: String,
+    decimals: u8,
+    balance_slot: i32,
+    #[serde(default)]
+    balance_slot_layout: MappingLayout,
+    #[serde(default)]
+    price_feed: Option<H160>,
+    #[serde(default)]
+    weth_pools: Vec<H160>,
+    weight: u8,
+    probe_amount: String,
+    max_amount_in: String,
+    tolerance: String,
+}
+
+/// A token the bot holds inventory in and sandwiches target tokens against.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "QuoteAssetConfig")]
+pub struct QuoteAsset {
+    /// Token address
+    pub address: H160,
+    /// Token name
+    pub name: String,
+    /// Token symbol
+    pub symbol: String,
+    /// Number of decimal places
+    pub decimals: u8,
+    /// Storage slot of the balance mapping (a hint until discovery confirms it)
+    pub balance_slot: i32,
+    /// Key derivation of the balance mapping
+    pub balance_slot_layout: MappingLayout,
+    /// Chainlink USD price feed (if available)
+    pub price_feed: Option<H160>,
+    /// Pools pairing the asset with WETH, used to value balances in ETH (empty for WETH)
+    pub weth_pools: Vec<H160>,
+    /// Priority when both tokens of a pair are quote assets (higher = more preferred)
+    pub weight: u8,
+    /// Amounts in base units, see the accessors
+    probe_amount_in: U256,
+    max_amount_in: U256,
+    tolerance: U256,
+}
+
+impl TryFrom<QuoteAssetConfig> for QuoteAsset {
+    type Error = anyhow::Error;
+
+    fn try_from(config: QuoteAssetConfig) -> Result<Self> {
+        let units = |amount: &str| {
+            parse_amount(amount, config.decimals).map_err(|e| anyhow!("{}: {}", config.symbol, e))
+        };
+        Ok(Self {
+            probe_amount_in: units(&config.probe_amount)?,
+            max_amount_in: units(&config.max_amount_in)?,
+            tolerance: units(&config.tolerance)?,
+            address: config.address,
+            name: config.name,
+            symbol: config.symbol,
+            decimals: config.decimals,
+            balance_slot: config.balance_slot,
+            balance_slot_layout: config.balance_slot_layout,
+            price_feed: config.price_feed,
+            weth_pools: config.weth_pools,
+            weight: config.weight,
+        })
+    }
+}
+
+impl QuoteAsset {
+    /// Converts an amount in whole tokens into base units.
+    ///
+    /// # Parameters
+    /// * `amount`: &str - Decimal amount in whole tokens, e.g. "1.5".
+    ///
+    /// # Returns
+    /// * `Result<U256>` - Amount in base units.
+    pub fn to_units(&self, amount: &str) -> Result<U256> {
+        parse_amount(amount, self.decimals)
+    }
+
+    /// Returns the amount used for the first probing simulation, in base units.
+    pub fn probe_amount_in(&self) -> U256 {
+        self.probe_amount_in
+    }
+
+    /// Returns the optimizer ceiling, in base units.
+    pub fn max_amount_in(&self) -> U256 {
+        self.max_amount_in
+    }
+
+    /// Returns the optimizer tolerance, in base units.
+    pub fn tolerance(&self) -> U256 {
+        self.tolerance
+    }
+
+    /// Returns the configured balance slot of the asset.
+    pub fn balance_slot(&self) -> BalanceSlot {
+        BalanceSlot {
+            slot: self.balance_slot,
+            layout: self.balance_slot_layout,
+            storage_address: self.address,
+        }
+    }
+
+    /// Returns true if the asset is WETH.
+    pub fn is_weth(&self) -> bool {
+        self.address == H160::from_str(WETH).unwrap()
+    }
+}
+
+/// Parses a decimal amount of whole tokens into base units, without rounding.
+///
+/// # Parameters
+/// * `amount`: &str - Decimal amount, e.g. "0.0005".
+/// * `decimals`: u8 - Decimals of the token.
+///
+/// # Returns
+/// * `Result<U256>` - Amount in base units, an error if it isn't a plain decimal number or has
+///   more fractional digits than the token.
+pub fn parse_amount(amount: &str, decimals: u8) -> Result<U256> {
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    let is_digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+    if (whole.is_empty() && fraction.is_empty()) || !is_digits(whole) || !is_digits(fraction) {
+        return Err(anyhow!("Invalid amount {:?}", amount));
+    }
+    if fraction.len() > decimals as usize {
+        return Err(anyhow!(
+            "Amount {:?} has more than {} decimals",
+            amount,
+            decimals
+        ));
+    }
+    let digits = format!("{}{:0<width$}", whole, fraction, width = decimals as usize);
+    U256::from_dec_str(&digits).map_err(|e| anyhow!("Invalid amount {:?}: {:?}", amount, e))
+}
+
+/// The set of configured quote assets.
+#[derive(Debug, Clone)]
+pub struct QuoteAssets {
+    assets: Vec<QuoteAsset>,
+}
+
+impl QuoteAssets {
+    /// Loads the quote assets from the file at `QUOTE_ASSETS_PATH` (env) if set.
+    ///
+    /// Falls back to the built-in config when the variable is unset or the file is missing or
+    /// invalid.
+    ///
+    /// # Returns
+    /// * `Self` - The configured quote assets.
+    pub fn load() -> Self {
+        let path = std::env::var("QUOTE_ASSETS_PATH").unwrap_or_default();
+        if path.is_empty() {
+            return Self::defaults();
+        }
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => match Self::from_json(&contents) {
+                Ok(assets) => {
+                    info!("Loaded {} quote assets from {}", assets.all().len(), path);
+                    assets
+                }
+                Err(e) => {
+                    warn!("Failed to load {}: {e:?}, using defaults", path);
+                    Self::defaults()
+                }
+            },
+            Err(e) => {
+                warn!("Failed to read {}: {e:?}, using defaults", path);
+                Self::defaults()
+            }
+        }
+    }
+
+    /// Parses a quote asset config.
+    ///
+    /// # Parameters
+    /// * `json`: &str - A JSON list of quote assets, as in `config/quote_assets.json`.
+    ///
+    /// # Returns
+    /// * `Result<Self>` - The quote assets, an error if the list is invalid or empty.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let assets: Vec<QuoteAsset> = serde_json::from_str(json)?;
+        if assets.is_empty() {
+            return Err(anyhow!("No quote assets"));
+        }
+        Ok(Self { assets })
+    }
+
+    /// Returns the quote assets of the built-in config.
+    pub fn defaults() -> Self {
+        Self::from_json(DEFAULT_QUOTE_ASSETS).expect("config/quote_assets.json is valid")
+    }
+
+    /// Returns all configured quote assets.
+    pub fn all(&self) -> &[QuoteAsset] {
+        &self.assets
+    }
+
+    /// Returns the addresses of all configured quote assets.
+    pub fn addresses(&self) -> Vec<H160> {
+        self.assets.iter().map(|a| a.address).collect()
+    }
+
+    /// Returns the quote asset with the given address.
+    pub fn get(&self, address: H160) -> Option<&QuoteAsset> {
+        self.assets.iter().find(|a| a.address == address)
+    }
+
+    /// Returns true if the token is a configured quote asset.
+    pub fn is_quote_asset(&self, address: H160) -> bool {
+        self.get(address).is_some()
+    }
+
+    /// Returns the symbol of a quote asset, or "Unknown".
+    pub fn symbol(&self, address: H160) -> String {
+        match self.get(address) {
+            Some(asset) => asset.symbol.clone(),
+            None => "Unknown".to_string(),
+        }
+    }
+
+    /// Determines which token of a pair is the quote asset and which is the target.
+    ///
+    /// When both tokens are quote assets, the one with the higher weight is the quote asset.
+    ///
+    /// # Parameters
+    /// * `token0`: H160 - First token address.
+    /// * `token1`: H160 - Second token address.
+    ///
+    /// # Returns
+    /// * `Option<(H160, H160)>` - (quote asset, target) or None if neither is a quote asset.
+    pub fn main_and_target(&self, token0: H160, token1: H160) -> Option<(H160, H160)> {
+        match (self.get(token0), self.get(token1)) {
+            (Some(a0), Some(a1)) => {
+                if a0.weight > a1.weight {
+                    Some((token0, token1))
+                } else {
+                    Some((token1, token0))
+                }
+            }
+            (Some(_), None) => Some((token0, token1)),
+            (None, Some(_)) => Some((token1, token0)),
+            (None, None) => None,
+        }
+    }
+}
+
+lazy_static! {
+    static ref QUOTE_ASSETS: QuoteAssets = QuoteAssets::load();
+}
+
+/// Gets the configured quote assets.
+pub fn get_quote_assets() -> &'static QuoteAssets {
+    &QUOTE_ASSETS
+}
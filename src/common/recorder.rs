@@ -0,0 +1,213 @@
+/// Recording and deterministic replay of the `Event` stream.
+///
+/// `record_events` appends every block and pending transaction event to a JSON lines log, each
+/// stamped with its arrival time; block events carry the header fields the strategy needs
+/// (number, base fee and the predicted next block env). `ReplaySource` feeds such a log back
+/// into the broadcast channel with the original spacing, optionally accelerated, so a session
+/// can be replayed against an archive node or a snapshot DB.
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast::{error::RecvError, Receiver, Sender};
+
+use crate::common::streams::Event;
+
+/// An event with the time it was received at.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    /// Arrival time in milliseconds since the Unix epoch.
+    pub received_at_ms: u64,
+    pub event: Event,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Appends events to a JSON lines log.
+pub struct EventRecorder {
+    writer: BufWriter<File>,
+    count: usize,
+}
+
+impl EventRecorder {
+    /// Opens (or creates) a log for appending.
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            writer: BufWriter::new(file),
+            count: 0,
+        })
+    }
+
+    /// Records an event received now.
+    pub fn record(&mut self, event: &Event) -> Result<()> {
+        self.record_at(event, now_ms())
+    }
+
+    /// Records an event with an explicit arrival time.
+    ///
+    /// The log is flushed on every block, so a crash loses at most the current block's
+    /// pending transactions.
+    pub fn record_at(&mut self, event: &Event, received_at_ms: u64) -> Result<()> {
+        let line = serde_json::to_string(&RecordedEvent {
+            received_at_ms,
+            event: event.clone(),
+        })?;
+        writeln!(self.writer, "{}", line)?;
+        self.count += 1;
+        if matches!(event, Event::Block(_)) {
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Number of events recorded by this recorder.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Records every event of the channel until it closes.
+///
+/// # Parameters
+/// * `receiver`: Receiver<Event> - A receiver subscribed to the event channel.
+/// * `path`: P - The log file to append to.
+///
+/// # Returns
+/// * `Result<usize>` - The number of recorded events once the channel is closed.
+pub async fn record_events<P: AsRef<Path>>(
+    mut receiver: Receiver<Event>,
+    path: P,
+) -> Result<usize> {
+    let mut recorder = EventRecorder::new(&path)?;
+    info!("Recording events to {:?}", path.as_ref());
+    loop {
+        match receiver.recv().await {
+            Ok(event) => recorder.record(&event)?,
+            Err(RecvError::Lagged(skipped)) => {
+                warn!("Event recorder lagged, {} events not recorded", skipped);
+            }
+            Err(RecvError::Closed) => break,
+        }
+    }
+    recorder.flush()?;
+    Ok(recorder.count())
+}
+
+/// Replays a recorded event log into the event channel.
+#[derive(Debug, Clone)]
+pub struct ReplaySource {
+    events: Vec<RecordedEvent>,
+    speed: f64,
+    min_receivers: usize,
+}
+
+impl ReplaySource {
+    /// Wraps already loaded events, at original speed.
+    pub fn new(events: Vec<RecordedEvent>) -> Self {
+        Self {
+            events,
+            speed: 1.0,
+            min_receivers: 1,
+        }
+    }
+
+    /// Loads a log written by `EventRecorder`.
+    ///
+    /// # Parameters
+    /// * `path`: P - The log file.
+    ///
+    /// # Returns
+    /// * `Result<ReplaySource>` - The replay source, or an error naming the first bad line.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut events = Vec::new();
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let event = serde_json::from_str(&line)
+                .map_err(|e| anyhow!("Invalid event on line {}: {:?}", i + 1, e))?;
+            events.push(event);
+        }
+        Ok(Self::new(events))
+    }
+
+    /// Sets the replay speed: 2.0 replays twice as fast, 0 sends events without waiting.
+    pub fn with_speed(mut self, speed: f64) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Waits for this many subscribers before sending the first event.
+    ///
+    /// The strategy only subscribes once its pools are loaded, and events sent before that
+    /// would be dropped.
+    pub fn with_min_receivers(mut self, min_receivers: usize) -> Self {
+        self.min_receivers = min_receivers;
+        self
+    }
+
+    pub fn events(&self) -> &[RecordedEvent] {
+        &self.events
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    fn delay(&self, previous_ms: u64, current_ms: u64) -> Option<Duration> {
+        if self.speed <= 0.0 || current_ms <= previous_ms {
+            return None;
+        }
+        let millis = (current_ms - previous_ms) as f64 / self.speed;
+        Some(Duration::from_micros((millis * 1000.0) as u64))
+    }
+
+    /// Sends the recorded events into the channel, spaced as they were received.
+    ///
+    /// # Parameters
+    /// * `sender`: Sender<Event> - The event channel.
+    ///
+    /// # Returns
+    /// * `Result<usize>` - The number of events sent, or an error if every receiver went away.
+    pub async fn run(self, sender: Sender<Event>) -> Result<usize> {
+        while sender.receiver_count() < self.min_receivers {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        info!("Replaying {} events at speed {}", self.events.len(), self.speed);
+
+        let mut previous_ms = self.events.first().map(|e| e.received_at_ms).unwrap_or_default();
+        let mut sent = 0;
+        for recorded in self.events.iter() {
+            if let Some(delay) = self.delay(previous_ms, recorded.received_at_ms) {
+                tokio::time::sleep(delay).await;
+            }
+            previous_ms = recorded.received_at_ms;
+            sender
+                .send(recorded.event.clone())
+                .map_err(|_| anyhow!("No receivers left after {} replayed events", sent))?;
+            sent += 1;
+        }
+        info!("Replay finished, {} events sent", sent);
+        Ok(sent)
+    }
+}
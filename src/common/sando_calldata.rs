@@ -0,0 +1,260 @@
+/// Typed codec for the packed calldata of the Sandooo contracts.
+///
+/// A payload is an 8-byte block number followed by fixed-size trade entries:
+///
+/// * `Sandooo` (105 bytes): zeroForOne (1) | pair (20) | tokenIn (20) | amountIn (32) | amountOut (32)
+/// * `SandoooV3` (129 bytes): dexKind (1) | zeroForOne (1) | pair (20) | tokenIn (20) |
+///   amountIn (32) | amountOut (32) | fee (3) | sqrtPriceLimitX96 (20)
+///
+/// The payload has no function selector: `Sandooo` takes it as raw calldata, `SandoooV3` as the
+/// `bytes` argument of `executeLeg` / `executeSandwichWithFlashloan`.
+use anyhow::{anyhow, Result};
+use ethers::types::{Bytes, H160, U256};
+
+use crate::common::constants::{SANDOOO_TRADE_SIZE, SANDOOO_V3_TRADE_SIZE};
+use crate::common::pools::DexVariant;
+
+/// Size of the block number header.
+pub const HEADER_SIZE: usize = 8;
+
+/// Largest fee tier that fits the 3-byte fee field.
+const MAX_FEE: u32 = (1 << 24) - 1;
+
+/// Trade entry layout of a Sandooo contract.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TradeLayout {
+    /// `Sandooo`: Uniswap V2 trades only
+    Sandooo,
+    /// `SandoooV3`: versioned Uniswap V2 / V3 trades
+    SandoooV3,
+}
+
+impl TradeLayout {
+    /// Returns the size in bytes of one trade entry.
+    pub fn trade_size(&self) -> usize {
+        match self {
+            TradeLayout::Sandooo => SANDOOO_TRADE_SIZE,
+            TradeLayout::SandoooV3 => SANDOOO_V3_TRADE_SIZE,
+        }
+    }
+}
+
+/// A single swap executed by the contract.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SandoTrade {
+    /// DEX the pool belongs to
+    pub dex: DexVariant,
+    /// True if token0 is paid in
+    pub zero_for_one: bool,
+    /// Pool address
+    pub pair: H160,
+    /// Token paid into the pool
+    pub token_in: H160,
+    /// Amount paid into the pool
+    pub amount_in: U256,
+    /// Exact output for V2, minimum output for V3
+    pub amount_out: U256,
+    /// V3 fee tier (0 for V2)
+    pub fee: u32,
+    /// V3 price limit (0 = no limit)
+    pub sqrt_price_limit_x96: U256,
+}
+
+impl SandoTrade {
+    /// Creates a Uniswap V2 trade.
+    pub fn v2(zero_for_one: bool, pair: H160, token_in: H160, amount_in: U256, amount_out: U256) -> Self {
+        Self {
+            dex: DexVariant::UniswapV2,
+            zero_for_one,
+            pair,
+            token_in,
+            amount_in,
+            amount_out,
+            fee: 0,
+            sqrt_price_limit_x96: U256::zero(),
+        }
+    }
+
+    /// Creates a Uniswap V3 trade without a price limit.
+    pub fn v3(
+        zero_for_one: bool,
+        pair: H160,
+        token_in: H160,
+        amount_in: U256,
+        min_amount_out: U256,
+        fee: u32,
+    ) -> Self {
+        Self {
+            dex: DexVariant::UniswapV3,
+            zero_for_one,
+            pair,
+            token_in,
+            amount_in,
+            amount_out: min_amount_out,
+            fee,
+            sqrt_price_limit_x96: U256::zero(),
+        }
+    }
+
+    fn encode_into(&self, layout: TradeLayout, out: &mut Vec<u8>) -> Result<()> {
+        if layout == TradeLayout::SandoooV3 {
+            let dex_kind = match self.dex {
+                DexVariant::UniswapV2 => 0u8,
+                DexVariant::UniswapV3 => 1u8,
+            };
+            out.push(dex_kind);
+        } else if self.dex != DexVariant::UniswapV2 {
+            return Err(anyhow!(
+                "Sandooo only swaps on Uniswap V2 pairs, got {:?} pool {:?}",
+                self.dex,
+                self.pair
+            ));
+        }
+
+        out.push(self.zero_for_one as u8);
+        out.extend_from_slice(self.pair.as_bytes());
+        out.extend_from_slice(self.token_in.as_bytes());
+        out.extend_from_slice(&to_word(self.amount_in));
+        out.extend_from_slice(&to_word(self.amount_out));
+
+        if layout == TradeLayout::SandoooV3 {
+            if self.fee > MAX_FEE {
+                return Err(anyhow!("Fee {} does not fit in uint24", self.fee));
+            }
+            if self.sqrt_price_limit_x96.bits() > 160 {
+                return Err(anyhow!("sqrtPriceLimitX96 does not fit in uint160"));
+            }
+            out.extend_from_slice(&self.fee.to_be_bytes()[1..]);
+            out.extend_from_slice(&to_word(self.sqrt_price_limit_x96)[12..]);
+        }
+        Ok(())
+    }
+
+    fn decode(layout: TradeLayout, entry: &[u8]) -> Result<Self> {
+        let (dex, entry) = match layout {
+            TradeLayout::Sandooo => (DexVariant::UniswapV2, entry),
+            TradeLayout::SandoooV3 => {
+                let dex = match entry[0] {
+                    0 => DexVariant::UniswapV2,
+                    1 => DexVariant::UniswapV3,
+                    kind => return Err(anyhow!("Unknown dex kind {}", kind)),
+                };
+                (dex, &entry[1..])
+            }
+        };
+
+        // the contracts treat any value other than 1 as a oneForZero swap
+        let zero_for_one = entry[0] == 1;
+        let pair = H160::from_slice(&entry[1..21]);
+        let token_in = H160::from_slice(&entry[21..41]);
+        let amount_in = U256::from_big_endian(&entry[41..73]);
+        let amount_out = U256::from_big_endian(&entry[73..105]);
+
+        let (fee, sqrt_price_limit_x96) = match layout {
+            TradeLayout::Sandooo => (0, U256::zero()),
+            TradeLayout::SandoooV3 => {
+                let mut fee = [0u8; 4];
+                fee[1..].copy_from_slice(&entry[105..108]);
+                (
+                    u32::from_be_bytes(fee),
+                    U256::from_big_endian(&entry[108..128]),
+                )
+            }
+        };
+
+        Ok(Self {
+            dex,
+            zero_for_one,
+            pair,
+            token_in,
+            amount_in,
+            amount_out,
+            fee,
+            sqrt_price_limit_x96,
+        })
+    }
+}
+
+/// Block-pinned list of trades sent to the contract in one call.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SandoPayload {
+    /// Block the contract must execute in
+    pub block_number: u64,
+    /// Trades executed in order
+    pub trades: Vec<SandoTrade>,
+}
+
+impl SandoPayload {
+    /// Creates an empty payload pinned to `block_number`.
+    pub fn new(block_number: u64) -> Self {
+        Self {
+            block_number,
+            trades: Vec::new(),
+        }
+    }
+
+    /// Encodes the payload in the given layout.
+    pub fn encode(&self, layout: TradeLayout) -> Result<Bytes> {
+        encode(self, layout)
+    }
+
+    /// Decodes a payload in the given layout.
+    pub fn decode(data: &[u8], layout: TradeLayout) -> Result<Self> {
+        decode(data, layout)
+    }
+}
+
+/// Encodes a payload: 8-byte block number + one entry per trade.
+///
+/// # Parameters
+/// * `payload`: &SandoPayload - The payload to encode.
+/// * `layout`: TradeLayout - Entry layout of the target contract.
+///
+/// # Returns
+/// * `Result<Bytes>` - The packed payload.
+pub fn encode(payload: &SandoPayload, layout: TradeLayout) -> Result<Bytes> {
+    let mut out = Vec::with_capacity(HEADER_SIZE + payload.trades.len() * layout.trade_size());
+    out.extend_from_slice(&payload.block_number.to_be_bytes());
+    for trade in &payload.trades {
+        trade.encode_into(layout, &mut out)?;
+    }
+    Ok(Bytes::from(out))
+}
+
+/// Decodes a payload, validating its length the same way the contracts do.
+///
+/// # Parameters
+/// * `data`: &[u8] - The packed payload (no function selector).
+/// * `layout`: TradeLayout - Entry layout of the target contract.
+///
+/// # Returns
+/// * `Result<SandoPayload>` - The decoded payload.
+pub fn decode(data: &[u8], layout: TradeLayout) -> Result<SandoPayload> {
+    let trade_size = layout.trade_size();
+    if data.len() < HEADER_SIZE || (data.len() - HEADER_SIZE) % trade_size != 0 {
+        return Err(anyhow!(
+            "Invalid payload length {}. Expected 8 bytes + N×{} bytes.",
+            data.len(),
+            trade_size
+        ));
+    }
+
+    let mut header = [0u8; HEADER_SIZE];
+    header.copy_from_slice(&data[..HEADER_SIZE]);
+
+    let trades = data[HEADER_SIZE..]
+        .chunks(trade_size)
+        .map(|entry| SandoTrade::decode(layout, entry))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(SandoPayload {
+        block_number: u64::from_be_bytes(header),
+        trades,
+    })
+}
+
+fn to_word(value: U256) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    value.to_big_endian(&mut word);
+    word
+}
@@ -0,0 +1,207 @@
+/// Typed simulation errors.
+///
+/// Decodes revert data into the custom errors of `SandoooV3.sol`, the `NOT_OWNER` string of
+/// `Sandooo.sol`, standard `Error(string)` / `Panic(uint256)` reasons and halt reasons, and tags
+/// failures with the sandwich leg that produced them, so a victim reverting from slippage can be
+/// told apart from one of our own legs reverting.
+use ethers::abi::{decode, ParamType};
+use ethers::types::{Bytes, H256, U256};
+use ethers::utils::id;
+use std::fmt;
+
+/// `Error(string)`
+const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+/// `Panic(uint256)`
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// Why a simulated transaction failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SimError {
+    /// Caller is not the bot owner (`NotOwner()` or `"NOT_OWNER"`).
+    NotOwner,
+    /// Malformed trade payload or V3 fee mismatch.
+    InvalidData,
+    /// Payload block number is not the current block.
+    WrongBlock,
+    /// Callback from an unexpected pool or flashloan from an unexpected lender.
+    Unauthorized,
+    /// Flashloan initiated by someone else.
+    InvalidInitiator,
+    /// Not enough balance to repay the flashloan.
+    InsufficientProfit,
+    /// Sweeping ETH to the owner failed.
+    EthTransferFailed,
+    /// Unknown dex kind in the trade payload.
+    UnknownDex,
+    /// V3 swap returned less than the minimum output.
+    InsufficientOutput,
+    /// `Error(string)`, e.g. `"UniswapV2Router: INSUFFICIENT_OUTPUT_AMOUNT"`.
+    Revert(String),
+    /// `Panic(uint256)` with its panic code.
+    Panic(U256),
+    /// Revert without data, e.g. a failed token transfer inside the bot.
+    EmptyRevert,
+    /// Revert data that matches none of the above.
+    UnknownRevert(Bytes),
+    /// Execution halted (out of gas, invalid opcode, ...).
+    Halt(String),
+    /// The transaction could not be executed at all (bad nonce, not enough ETH for gas, ...).
+    Invalid(String),
+}
+
+impl SimError {
+    /// Decodes revert data.
+    ///
+    /// # Parameters
+    /// * `output`: &[u8] - Revert data returned by the EVM.
+    ///
+    /// # Returns
+    /// * `SimError` - The decoded revert reason.
+    pub fn from_revert(output: &[u8]) -> Self {
+        if output.is_empty() {
+            return SimError::EmptyRevert;
+        }
+        if output.len() < 4 {
+            return SimError::UnknownRevert(Bytes::from(output.to_vec()));
+        }
+
+        let (selector, args) = (&output[..4], &output[4..]);
+        if selector == ERROR_SELECTOR {
+            if let Ok(tokens) = decode(&[ParamType::String], args) {
+                if let Some(reason) = tokens.into_iter().next().and_then(|t| t.into_string()) {
+                    return match reason.as_str() {
+                        "NOT_OWNER" => SimError::NotOwner,
+                        _ => SimError::Revert(reason),
+                    };
+                }
+            }
+        } else if selector == PANIC_SELECTOR && args.len() >= 32 {
+            return SimError::Panic(U256::from_big_endian(&args[..32]));
+        } else if let Some(error) = custom_error(selector) {
+            return error;
+        }
+
+        SimError::UnknownRevert(Bytes::from(output.to_vec()))
+    }
+
+    /// Returns true if a `SandoooV3` custom error or owner check caused the failure.
+    pub fn is_contract_error(&self) -> bool {
+        !matches!(
+            self,
+            SimError::Revert(_)
+                | SimError::Panic(_)
+                | SimError::EmptyRevert
+                | SimError::UnknownRevert(_)
+                | SimError::Halt(_)
+                | SimError::Invalid(_)
+        )
+    }
+}
+
+fn custom_error(selector: &[u8]) -> Option<SimError> {
+    let errors = [
+        ("NotOwner()", SimError::NotOwner),
+        ("InvalidData()", SimError::InvalidData),
+        ("WrongBlock()", SimError::WrongBlock),
+        ("Unauthorized()", SimError::Unauthorized),
+        ("InvalidInitiator()", SimError::InvalidInitiator),
+        ("InsufficientProfit()", SimError::InsufficientProfit),
+        ("EthTransferFailed()", SimError::EthTransferFailed),
+        ("UnknownDex()", SimError::UnknownDex),
+        ("InsufficientOutput()", SimError::InsufficientOutput),
+    ];
+    errors
+        .into_iter()
+        .find(|(signature, _)| id(signature) == selector)
+        .map(|(_, error)| error)
+}
+
+impl fmt::Display for SimError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimError::Revert(reason) => write!(f, "Error({:?})", reason),
+            SimError::Panic(code) => write!(f, "Panic({:#x})", code),
+            SimError::EmptyRevert => write!(f, "empty revert"),
+            SimError::UnknownRevert(output) => write!(f, "unknown revert {}", output),
+            SimError::Halt(reason) => write!(f, "halt {}", reason),
+            SimError::Invalid(reason) => write!(f, "invalid tx {}", reason),
+            custom => write!(f, "{:?}()", custom),
+        }
+    }
+}
+
+/// A failed EVM call: the decoded reason and the gas it burned.
+///
+/// Returned (wrapped in `anyhow::Error`) by `EvmSimulator::call` and `staticcall`; recover it with
+/// `e.downcast_ref::<TxFailure>()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxFailure {
+    pub error: SimError,
+    pub gas_used: u64,
+}
+
+impl fmt::Display for TxFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.error {
+            SimError::Halt(_) => write!(f, "EVM HALT: {} / Gas used: {}", self.error, self.gas_used),
+            SimError::Invalid(_) => write!(f, "EVM call failed: {}", self.error),
+            _ => write!(f, "EVM REVERT: {} / Gas used: {}", self.error, self.gas_used),
+        }
+    }
+}
+
+impl std::error::Error for TxFailure {}
+
+/// Transaction of a sandwich simulation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimLeg {
+    Frontrun,
+    Victim,
+    Backrun,
+}
+
+/// A failed transaction of a sandwich simulation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LegError {
+    /// Which transaction failed.
+    pub leg: SimLeg,
+    /// Hash of the victim tx, for `SimLeg::Victim`.
+    pub tx_hash: Option<H256>,
+    /// Why it failed.
+    pub error: SimError,
+    /// Gas burned by the failed transaction.
+    pub gas_used: u64,
+}
+
+impl LegError {
+    /// Builds a leg error from the error returned by `EvmSimulator::call`.
+    ///
+    /// # Parameters
+    /// * `leg`: SimLeg - The leg that was executed.
+    /// * `tx_hash`: Option<H256> - The victim tx hash, if any.
+    /// * `e`: &anyhow::Error - The call error.
+    ///
+    /// # Returns
+    /// * `LegError` - The typed failure (`SimError::Invalid` for untyped errors).
+    pub fn from_call_error(leg: SimLeg, tx_hash: Option<H256>, e: &anyhow::Error) -> Self {
+        let (error, gas_used) = match e.downcast_ref::<TxFailure>() {
+            Some(failure) => (failure.error.clone(), failure.gas_used),
+            None => (SimError::Invalid(e.to_string()), 0),
+        };
+        Self {
+            leg,
+            tx_hash,
+            error,
+            gas_used,
+        }
+    }
+}
+
+impl fmt::Display for LegError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.tx_hash {
+            Some(tx_hash) => write!(f, "{:?} {:?} failed: {}", self.leg, tx_hash, self.error),
+            None => write!(f, "{:?} failed: {}", self.leg, self.error),
+        }
+    }
+}
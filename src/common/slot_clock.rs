@@ -0,0 +1,125 @@
+/// Slot timing derived from block timestamps.
+///
+/// A bundle targets the block after the latest one, which is proposed one slot (12 seconds)
+/// after its parent. `SlotClock` places configurable cut-offs inside that slot, as offsets from
+/// the parent's timestamp: opportunities are collected until a cut-off, then submitted. Times are
+/// unix milliseconds, so the clock can be driven by a test as well as by the system time.
+use ethers::types::U64;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::common::evm::NextBlockEnv;
+
+/// Duration of a slot on mainnet.
+pub const SLOT_DURATION: Duration = Duration::from_secs(12);
+
+/// Default cut-offs: the main submission, then a late one for opportunities found after it.
+pub const DEFAULT_CUTOFFS: [Duration; 2] = [Duration::from_secs(9), Duration::from_secs(11)];
+
+/// A cut-off of the current slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cutoff {
+    /// Position of the cut-off in the slot, from 0.
+    pub index: usize,
+    /// When it falls, in unix milliseconds.
+    pub at_ms: u64,
+}
+
+/// Tracks the slot of the block bundles target and its cut-offs.
+#[derive(Debug, Clone)]
+pub struct SlotClock {
+    slot_duration: Duration,
+    cutoffs: Vec<Duration>,
+    target_block: U64,
+    slot_start_ms: u64,
+    next_cutoff: usize,
+}
+
+impl SlotClock {
+    /// Creates a clock with the given cut-offs.
+    ///
+    /// # Parameters
+    /// * `cutoffs`: Vec<Duration> - Offsets from the start of the slot; they are sorted and those
+    ///   past the end of the slot are dropped.
+    ///
+    /// # Returns
+    /// * `SlotClock` - The clock, without a slot until the first `on_block`.
+    pub fn new(cutoffs: Vec<Duration>) -> Self {
+        Self::with_slot_duration(cutoffs, SLOT_DURATION)
+    }
+
+    /// Creates a clock for slots of `slot_duration`.
+    pub fn with_slot_duration(mut cutoffs: Vec<Duration>, slot_duration: Duration) -> Self {
+        cutoffs.retain(|cutoff| *cutoff < slot_duration);
+        cutoffs.sort();
+        cutoffs.dedup();
+        Self {
+            slot_duration,
+            cutoffs,
+            target_block: U64::zero(),
+            slot_start_ms: 0,
+            // no cut-off falls before the first slot is known
+            next_cutoff: usize::MAX,
+        }
+    }
+
+    /// Starts the slot of `next_block`, which begins one slot before its timestamp.
+    pub fn on_block(&mut self, next_block: &NextBlockEnv) {
+        let slot_end_ms = next_block.timestamp.low_u64() * 1000;
+        self.target_block = next_block.number;
+        self.slot_start_ms = slot_end_ms.saturating_sub(self.slot_duration.as_millis() as u64);
+        self.next_cutoff = 0;
+    }
+
+    /// The block bundles of the current slot target.
+    pub fn target_block(&self) -> U64 {
+        self.target_block
+    }
+
+    /// Start of the current slot, in unix milliseconds.
+    pub fn slot_start_ms(&self) -> u64 {
+        self.slot_start_ms
+    }
+
+    /// The next cut-off of the slot, None once all have passed or before the first block.
+    pub fn next_cutoff(&self) -> Option<Cutoff> {
+        let offset = self.cutoffs.get(self.next_cutoff)?;
+        Some(Cutoff {
+            index: self.next_cutoff,
+            at_ms: self.slot_start_ms + offset.as_millis() as u64,
+        })
+    }
+
+    /// Marks the next cut-off as passed.
+    ///
+    /// # Returns
+    /// * `Option<Cutoff>` - The cut-off passed, None if there was none left.
+    pub fn pass_cutoff(&mut self) -> Option<Cutoff> {
+        let cutoff = self.next_cutoff()?;
+        self.next_cutoff += 1;
+        Some(cutoff)
+    }
+
+    /// True once every cut-off of the slot has passed: opportunities found now are late.
+    pub fn is_late(&self) -> bool {
+        self.next_cutoff != usize::MAX && self.next_cutoff >= self.cutoffs.len()
+    }
+
+    /// Time left until `cutoff` at `now_ms`, zero if it has passed.
+    pub fn until(cutoff: &Cutoff, now_ms: u64) -> Duration {
+        Duration::from_millis(cutoff.at_ms.saturating_sub(now_ms))
+    }
+}
+
+impl Default for SlotClock {
+    fn default() -> Self {
+        Self::new(DEFAULT_CUTOFFS.to_vec())
+    }
+}
+
+/// The system time in unix milliseconds.
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
@@ -0,0 +1,225 @@
+/// Offline state snapshots for deterministic simulations.
+///
+/// A simulator created with `EvmSimulator::new_recording` keeps a handle on the fork cache of
+/// its `SharedBackend`, which holds the pristine parent block state of every account and storage
+/// slot the simulation read. `SnapshotRecorder::snapshot` dumps that state to a JSON
+/// `StateSnapshot`, and `EvmSimulator::from_snapshot` preloads it into a backend whose provider
+/// is a `MockProvider`, so the same simulation runs without a network.
+///
+/// Replays must touch the same addresses as the recording, so the simulator owner is part of the
+/// snapshot; contracts deployed at random addresses (the `Sandooo` bot) don't replay, while
+/// `SandoooV3`, created from the owner, does.
+use anyhow::{anyhow, Result};
+use ethers::providers::{Middleware, MockProvider, Provider};
+use ethers::types::{Bytes, H160, H256, U256};
+use foundry_evm_mini::evm::executor::fork::{BlockchainDb, BlockchainDbMeta, SharedBackend};
+use revm::db::CacheDB;
+use revm::primitives::{keccak256, AccountInfo, Bytecode, B160, B256, KECCAK_EMPTY, U256 as rU256};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::common::evm::{EvmSimulator, NextBlockEnv};
+
+/// State of one account at the snapshot block.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotAccount {
+    pub balance: U256,
+    pub nonce: u64,
+    pub code: Bytes,
+    pub storage: BTreeMap<U256, U256>,
+}
+
+/// Accounts and storage read by a simulation, at the parent of `block`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    /// The block the simulation targeted.
+    pub block: NextBlockEnv,
+    /// Owner of the recording simulator.
+    pub owner: H160,
+    pub accounts: BTreeMap<H160, SnapshotAccount>,
+    pub block_hashes: BTreeMap<u64, H256>,
+}
+
+impl StateSnapshot {
+    /// Creates an empty snapshot for simulations in `block` by `owner`.
+    pub fn new(block: NextBlockEnv, owner: H160) -> Self {
+        Self {
+            block,
+            owner,
+            accounts: BTreeMap::new(),
+            block_hashes: BTreeMap::new(),
+        }
+    }
+
+    /// Loads a snapshot from a JSON file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(path)?;
+        Ok(serde_json::from_reader(BufReader::new(file))?)
+    }
+
+    /// Saves the snapshot to a JSON file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let file = File::create(path)?;
+        serde_json::to_writer_pretty(BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    /// Returns a fork cache holding the snapshot state.
+    fn to_blockchain_db(&self) -> BlockchainDb {
+        let db = empty_blockchain_db();
+        {
+            let mut accounts = db.accounts().write();
+            let mut storage = db.storage().write();
+            for (address, account) in &self.accounts {
+                let code = account.code.to_vec();
+                let (code_hash, bytecode) = if code.is_empty() {
+                    (KECCAK_EMPTY, Bytecode::new())
+                } else {
+                    (keccak256(&code), Bytecode::new_raw(code.into()))
+                };
+                let address: B160 = (*address).into();
+                accounts.insert(
+                    address,
+                    AccountInfo::new(account.balance.into(), account.nonce, code_hash, bytecode),
+                );
+                let slots = storage.entry(address).or_default();
+                for (slot, value) in &account.storage {
+                    slots.insert((*slot).into(), (*value).into());
+                }
+            }
+        }
+        {
+            let mut block_hashes = db.block_hashes().write();
+            for (number, hash) in &self.block_hashes {
+                block_hashes.insert(rU256::from(*number), B256::from(hash.0));
+            }
+        }
+        db
+    }
+}
+
+fn empty_blockchain_db() -> BlockchainDb {
+    BlockchainDb::new(
+        BlockchainDbMeta {
+            cfg_env: Default::default(),
+            block_env: Default::default(),
+            hosts: BTreeSet::from(["".to_string()]),
+        },
+        None,
+    )
+}
+
+/// Handle on the fork cache of a recording simulator.
+#[derive(Clone)]
+pub struct SnapshotRecorder {
+    db: BlockchainDb,
+    block: NextBlockEnv,
+    owner: H160,
+}
+
+impl SnapshotRecorder {
+    /// Dumps the state fetched from the provider so far.
+    ///
+    /// Only what was read is captured, so a snapshot replays the simulations that were run
+    /// while recording; anything else fails with a missing state error.
+    pub fn snapshot(&self) -> StateSnapshot {
+        let mut snapshot = StateSnapshot::new(self.block.clone(), self.owner);
+        for (address, info) in self.db.accounts().read().iter() {
+            let code = info
+                .code
+                .as_ref()
+                .map(|code| Bytes::from(code.original_bytes().to_vec()))
+                .unwrap_or_default();
+            snapshot.accounts.insert(
+                (*address).into(),
+                SnapshotAccount {
+                    balance: info.balance.into(),
+                    nonce: info.nonce,
+                    code,
+                    storage: BTreeMap::new(),
+                },
+            );
+        }
+        for (address, slots) in self.db.storage().read().iter() {
+            let account = snapshot.accounts.entry((*address).into()).or_default();
+            for (slot, value) in slots.iter() {
+                account.storage.insert((*slot).into(), (*value).into());
+            }
+        }
+        for (number, hash) in self.db.block_hashes().read().iter() {
+            let number: U256 = (*number).into();
+            snapshot
+                .block_hashes
+                .insert(number.as_u64(), H256::from(hash.0));
+        }
+        snapshot
+    }
+}
+
+impl<M: Middleware + 'static> EvmSimulator<M> {
+    /// Like `new`, but also returns a recorder to snapshot the state the simulator fetches.
+    ///
+    /// # Parameters
+    /// * `provider`: Arc<M> - The Ethereum provider.
+    /// * `owner`: Option<H160> - The owner address (random if None).
+    /// * `next_block`: &NextBlockEnv - The block to simulate in.
+    ///
+    /// # Returns
+    /// * `(EvmSimulator<M>, SnapshotRecorder)` - The simulator and its recorder.
+    pub fn new_recording(
+        provider: Arc<M>,
+        owner: Option<H160>,
+        next_block: &NextBlockEnv,
+    ) -> (Self, SnapshotRecorder) {
+        let db = empty_blockchain_db();
+        let shared_backend = SharedBackend::spawn_backend_thread(
+            provider.clone(),
+            db.clone(),
+            Some(next_block.parent_number().into()),
+        );
+        let simulator =
+            EvmSimulator::new_with_db(provider, owner, next_block, CacheDB::new(shared_backend));
+        let recorder = SnapshotRecorder {
+            db,
+            block: next_block.clone(),
+            owner: simulator.owner,
+        };
+        (simulator, recorder)
+    }
+}
+
+impl EvmSimulator<Provider<MockProvider>> {
+    /// Creates a simulator on snapshot state, without a network.
+    ///
+    /// The backend's provider is a `MockProvider` with no responses, so reading state that is
+    /// not in the snapshot fails instead of being fetched.
+    ///
+    /// # Parameters
+    /// * `snapshot`: &StateSnapshot - The recorded state.
+    ///
+    /// # Returns
+    /// * `Result<EvmSimulator<Provider<MockProvider>>>` - Simulator in the snapshot's block,
+    ///   owned by the recording owner.
+    pub fn from_snapshot(snapshot: &StateSnapshot) -> Result<Self> {
+        if snapshot.block.number.is_zero() {
+            return Err(anyhow!("Snapshot has no block"));
+        }
+        let (provider, _) = Provider::mocked();
+        let provider = Arc::new(provider);
+        let shared_backend = SharedBackend::spawn_backend_thread(
+            provider.clone(),
+            snapshot.to_blockchain_db(),
+            Some(snapshot.block.parent_number().into()),
+        );
+        Ok(EvmSimulator::new_with_db(
+            provider,
+            Some(snapshot.owner),
+            &snapshot.block,
+            CacheDB::new(shared_backend),
+        ))
+    }
+}
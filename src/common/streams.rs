@@ -4,15 +4,19 @@
 use anyhow::Result;
 use ethers::{
     providers::{Middleware, Provider, Ws},
-    types::{Transaction, U256, U64},
+    types::{Block, Transaction, U256, U64},
 };
 use futures::StreamExt;
-use log::info;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::broadcast::Sender;
 
+use crate::common::evm::NextBlockEnv;
+
 /// Represents a new block event.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NewBlock {
     /// The block number.
     pub block_number: U64,
@@ -20,10 +24,31 @@ pub struct NewBlock {
     pub base_fee: U256,
     /// The estimated next block base fee.
     pub next_base_fee: U256,
+    /// The predicted env of the next block, which bundles target.
+    pub next_block: NextBlockEnv,
+}
+
+impl NewBlock {
+    /// Creates a new block event from a block header.
+    ///
+    /// # Parameters
+    /// * `block`: &Block<T> - The new block.
+    ///
+    /// # Returns
+    /// * `Option<NewBlock>` - The event, or None for pending blocks.
+    pub fn from_block<T>(block: &Block<T>) -> Option<Self> {
+        let next_block = NextBlockEnv::from_parent(block).ok()?;
+        Some(Self {
+            block_number: block.number?,
+            base_fee: block.base_fee_per_gas.unwrap_or_default(),
+            next_base_fee: next_block.base_fee,
+            next_block,
+        })
+    }
 }
 
 /// Represents a new pending transaction event.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct NewPendingTx {
     /// The transaction.
     pub tx: Transaction,
@@ -32,7 +57,7 @@ pub struct NewPendingTx {
 }
 
 /// Events that can be streamed.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Event {
     /// New block event.
     Block(NewBlock),
@@ -73,19 +98,8 @@ pub async fn stream_new_blocks(
         );
 
         // Only process blocks that have a number
-        if let Some(number) = block.number {
-            let next_base_fee = crate::common::utils::calculate_next_block_base_fee(
-                block.gas_used,
-                block.gas_limit,
-                block.base_fee_per_gas.unwrap_or_default(),
-            );
-
-            let new_block = NewBlock {
-                block_number: number,
-                base_fee: block.base_fee_per_gas.unwrap_or_default(),
-                next_base_fee,
-            };
-
+        if let Some(new_block) = NewBlock::from_block(&block) {
+            let number = new_block.block_number;
             if tx.send(Event::Block(new_block)).is_err() {
                 eprintln!("DEBUG: Failed to send block event for #{}", number);
                 info!("Failed to send block event");
@@ -99,6 +113,44 @@ pub async fn stream_new_blocks(
     Ok(())
 }
 
+/// Polls the block number and sends each new block to the event channel.
+///
+/// A fallback for when the block subscription stalls; blocks it also delivered are sent again.
+///
+/// # Arguments
+/// * `provider` - An Ethereum provider.
+/// * `tx` - A channel to send events.
+/// * `interval` - Time between two polls.
+pub async fn poll_new_blocks(provider: Arc<Provider<Ws>>, tx: Sender<Event>, interval: Duration) {
+    let mut last_block_number = U64::zero();
+    loop {
+        match provider.get_block_number().await {
+            Ok(block_number) => {
+                if block_number > last_block_number && !last_block_number.is_zero() {
+                    debug!("Polled new block #{}", block_number);
+
+                    if let Some(new_block) = provider
+                        .get_block(block_number)
+                        .await
+                        .ok()
+                        .flatten()
+                        .and_then(|block| NewBlock::from_block(&block))
+                    {
+                        if tx.send(Event::Block(new_block)).is_err() {
+                            warn!("Failed to send polled block #{}", block_number);
+                        }
+                    }
+                }
+                last_block_number = block_number;
+            }
+            Err(e) => {
+                warn!("Block polling failed: {:?}", e);
+            }
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
 /// Stream pending transactions from the Ethereum network.
 ///
 /// Listens for new pending transactions from the provider and sends them to the provided channel.
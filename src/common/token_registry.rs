@@ -15,10 +15,16 @@ use std::fs::{File, OpenOptions};
 use std::io::{Read, Write};
 use std::path::Path;
 use std::str::FromStr;
-use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 
-use crate::common::constants::*;
-use crate::common::utils::MainCurrency;
+use crate::common::balance_slot::{BalanceSlot, MappingLayout};
+use crate::common::quote_assets::get_quote_assets;
+use crate::common::token_safety::TokenSafetyReport;
+
+/// Least time between two cache writes requested from the hot path.
+const CACHE_SAVE_INTERVAL: Duration = Duration::from_secs(30);
 
 /// Represents detailed token metadata for use in the bot.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -31,8 +37,17 @@ pub struct TokenMetadata {
     pub symbol: String,
     /// Number of decimal places
     pub decimals: u8,
-    /// Storage slot for balance mapping (for EVM simulation)
+    /// Storage slot for balance mapping (for EVM simulation, -1 if unknown)
     pub balance_slot: i32,
+    /// Key derivation of the balance mapping
+    #[serde(default)]
+    pub balance_slot_layout: MappingLayout,
+    /// Contract holding the balance mapping when it isn't the token itself
+    #[serde(default)]
+    pub balance_storage: Option<H160>,
+    /// Whether the balance slot was verified by writing and reading back a balance
+    #[serde(default)]
+    pub balance_slot_verified: bool,
     /// Chainlink price feed address (if available)
     pub price_feed: Option<String>,
     /// Whether this is a main currency the bot supports for sandwiching
@@ -41,6 +56,26 @@ pub struct TokenMetadata {
     pub weight: u8,
     /// Cached last known price in USD (updated periodically)
     pub last_price_usd: Option<f64>,
+    /// Cached result of the buy/transfer/sell round trip simulation (if analyzed)
+    #[serde(default)]
+    pub safety: Option<TokenSafetyReport>,
+}
+
+impl TokenMetadata {
+    /// Returns the verified balance slot of the token, if known.
+    ///
+    /// # Parameters
+    /// * `token`: H160 - The token address (used when the mapping lives in the token itself).
+    pub fn verified_balance_slot(&self, token: H160) -> Option<BalanceSlot> {
+        if !self.balance_slot_verified || self.balance_slot < 0 {
+            return None;
+        }
+        Some(BalanceSlot {
+            slot: self.balance_slot,
+            layout: self.balance_slot_layout,
+            storage_address: self.balance_storage.unwrap_or(token),
+        })
+    }
 }
 
 /// The token registry providing access to token metadata.
@@ -50,6 +85,10 @@ pub struct TokenRegistry {
     tokens: RwLock<HashMap<H160, TokenMetadata>>,
     /// Path to JSON cache file
     cache_path: String,
+    /// When the cache file was last written
+    last_saved: Mutex<Option<Instant>>,
+    /// Whether a debounced save was skipped since the cache file was last written
+    dirty: AtomicBool,
 }
 
 impl TokenRegistry {
@@ -58,9 +97,23 @@ impl TokenRegistry {
     /// # Returns
     /// * `Self` - New TokenRegistry instance
     pub fn new() -> Self {
-        let cache_path = "cache/token_registry.json".to_string();
-        let tokens = RwLock::new(HashMap::new());
-        let registry = Self { tokens, cache_path };
+        Self::with_cache_path("cache/token_registry.json")
+    }
+
+    /// Creates a token registry backed by the cache file at `cache_path`.
+    ///
+    /// # Parameters
+    /// * `cache_path`: &str - The JSON cache file, created if it doesn't exist.
+    ///
+    /// # Returns
+    /// * `Self` - New TokenRegistry instance
+    pub fn with_cache_path(cache_path: &str) -> Self {
+        let registry = Self {
+            tokens: RwLock::new(HashMap::new()),
+            cache_path: cache_path.to_string(),
+            last_saved: Mutex::new(None),
+            dirty: AtomicBool::new(false),
+        };
 
         // Initialize with built-in tokens
         registry.initialize_default_tokens();
@@ -68,89 +121,29 @@ impl TokenRegistry {
         registry
     }
 
-    /// Initializes the registry with the default supported tokens.
+    /// Initializes the registry with the configured quote assets.
     fn initialize_default_tokens(&self) {
-        let default_tokens = [
-            self.create_token_metadata(
-                WETH,
-                "Wrapped Ether",
-                "WETH",
-                18,
-                WETH_BALANCE_SLOT,
-                Some(CHAINLINK_ETH_USD),
-                true,
-                7,
-            ),
-            self.create_token_metadata(
-                USDT,
-                "Tether USD",
-                "USDT",
-                6,
-                USDT_BALANCE_SLOT,
-                Some(CHAINLINK_USDT_USD),
-                true,
-                5,
-            ),
-            self.create_token_metadata(
-                USDC,
-                "USD Coin",
-                "USDC",
-                6,
-                USDC_BALANCE_SLOT,
-                Some(CHAINLINK_USDC_USD),
-                true,
-                4,
-            ),
-            self.create_token_metadata(
-                WBTC,
-                "Wrapped Bitcoin",
-                "WBTC",
-                8,
-                WBTC_BALANCE_SLOT,
-                Some(CHAINLINK_BTC_USD),
-                true,
-                6,
-            ),
-            self.create_token_metadata(
-                DAI,
-                "Dai Stablecoin",
-                "DAI",
-                18,
-                DAI_BALANCE_SLOT,
-                Some(CHAINLINK_DAI_USD),
-                true,
-                3,
-            ),
-            self.create_token_metadata(
-                LINK,
-                "ChainLink Token",
-                "LINK",
-                18,
-                LINK_BALANCE_SLOT,
-                Some(CHAINLINK_LINK_USD),
-                true,
-                2,
-            ),
-            self.create_token_metadata(
-                MKR,
-                "Maker",
-                "MKR",
-                18,
-                MKR_BALANCE_SLOT,
-                Some(CHAINLINK_MKR_USD),
-                true,
-                1,
-            ),
-        ];
-
         let mut tokens = self.tokens.write().unwrap();
-        for token in default_tokens.iter() {
-            let address = H160::from_str(&format!("0x{}", token.address)).unwrap();
-            tokens.insert(address, token.clone());
+        for asset in get_quote_assets().all() {
+            let mut token = self.create_token_metadata(
+                &format!("{:x}", asset.address),
+                &asset.name,
+                &asset.symbol,
+                asset.decimals,
+                asset.balance_slot,
+                asset.price_feed.map(|feed| format!("{:?}", feed)).as_deref(),
+                true,
+                asset.weight,
+            );
+            token.balance_slot_layout = asset.balance_slot_layout;
+            tokens.insert(asset.address, token);
         }
     }
 
     /// Creates TokenMetadata for a token.
+    ///
+    /// A configured `balance_slot` is only a hint: it counts as verified once
+    /// `update_balance_slot` stores a slot confirmed by `find_balance_slot`.
     fn create_token_metadata(
         &self,
         address: &str,
@@ -171,10 +164,14 @@ impl TokenRegistry {
             symbol: symbol.to_string(),
             decimals,
             balance_slot,
+            balance_slot_layout: MappingLayout::Solidity,
+            balance_storage: None,
+            balance_slot_verified: false,
             price_feed: price_feed.map(|s| s.to_string()),
             is_main_currency,
             weight,
             last_price_usd: None,
+            safety: None,
         }
     }
 
@@ -212,6 +209,9 @@ impl TokenRegistry {
 
     /// Saves the current token registry to the cache file.
     pub fn save_to_cache(&self) {
+        *self.last_saved.lock().unwrap() = Some(Instant::now());
+        self.dirty.store(false, Ordering::Relaxed);
+
         let tokens = self.tokens.read().unwrap();
         let token_vec: Vec<TokenMetadata> = tokens.values().cloned().collect();
 
@@ -236,28 +236,45 @@ impl TokenRegistry {
         }
     }
 
-    /// Gets metadata for a token by its address.
+    /// Saves the registry unless it was saved less than `CACHE_SAVE_INTERVAL` ago.
     ///
-    /// # Parameters
-    /// * `address`: H160 - Token address
+    /// Callers on the hot path use it so the whole file isn't rewritten for every token; the
+    /// changes skipped are written by the next save, or by `flush` on shutdown.
+    pub fn save_to_cache_debounced(&self) {
+        let due = self
+            .last_saved
+            .lock()
+            .unwrap()
+            .map_or(true, |at| at.elapsed() >= CACHE_SAVE_INTERVAL);
+        if due {
+            self.save_to_cache();
+        } else {
+            self.dirty.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Writes the changes a debounced save skipped, if any.
     ///
     /// # Returns
-    /// * `Option<TokenMetadata>` - Token metadata if found
-    pub fn get_token(&self, address: H160) -> Option<TokenMetadata> {
-        let tokens = self.tokens.read().unwrap();
-        tokens.get(&address).cloned()
+    /// * `bool` - Whether the cache file was written.
+    pub fn flush(&self) -> bool {
+        if !self.dirty.load(Ordering::Relaxed) {
+            return false;
+        }
+        self.save_to_cache();
+        true
     }
 
-    /// Gets metadata for a main currency token by its enum value.
+    /// Gets metadata for a token by its address.
     ///
     /// # Parameters
-    /// * `currency`: MainCurrency - Main currency enum
+    /// * `address`: H160 - Token address
     ///
     /// # Returns
     /// * `Option<TokenMetadata>` - Token metadata if found
-    pub fn get_main_currency(&self, currency: &MainCurrency) -> Option<TokenMetadata> {
-        let address = currency.address();
-        self.get_token(address)
+    pub fn get_token(&self, address: H160) -> Option<TokenMetadata> {
+        let tokens = self.tokens.read().unwrap();
+        tokens.get(&address).cloned()
     }
 
     /// Gets all supported main currencies.
@@ -305,6 +322,57 @@ impl TokenRegistry {
         }
     }
 
+    /// Gets the cached safety report for a token.
+    ///
+    /// # Parameters
+    /// * `address`: H160 - Token address
+    ///
+    /// # Returns
+    /// * `Option<TokenSafetyReport>` - Safety report if the token was analyzed
+    pub fn get_token_safety(&self, address: H160) -> Option<TokenSafetyReport> {
+        let tokens = self.tokens.read().unwrap();
+        tokens.get(&address).and_then(|t| t.safety.clone())
+    }
+
+    /// Stores the safety report for a token.
+    ///
+    /// # Parameters
+    /// * `address`: H160 - Token address
+    /// * `report`: TokenSafetyReport - Result of the round trip simulation
+    ///
+    /// # Returns
+    /// * `Result<()>` - Success or error
+    pub fn update_token_safety(&self, address: H160, report: TokenSafetyReport) -> Result<()> {
+        let mut tokens = self.tokens.write().unwrap();
+        if let Some(token) = tokens.get_mut(&address) {
+            token.safety = Some(report);
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("Token not found"))
+        }
+    }
+
+    /// Stores a verified balance slot for a token.
+    ///
+    /// # Parameters
+    /// * `address`: H160 - Token address
+    /// * `slot`: &BalanceSlot - The discovered balance slot
+    ///
+    /// # Returns
+    /// * `Result<()>` - Success or error if the token is not registered
+    pub fn update_balance_slot(&self, address: H160, slot: &BalanceSlot) -> Result<()> {
+        let mut tokens = self.tokens.write().unwrap();
+        if let Some(token) = tokens.get_mut(&address) {
+            token.balance_slot = slot.slot;
+            token.balance_slot_layout = slot.layout;
+            token.balance_storage = (slot.storage_address != address).then_some(slot.storage_address);
+            token.balance_slot_verified = true;
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("Token not found"))
+        }
+    }
+
     /// Fetches on-chain token information and updates the registry.
     ///
     /// # Parameters
@@ -377,7 +445,7 @@ impl TokenRegistry {
             &name,
             &symbol,
             decimals,
-            0,     // Unknown balance slot
+            -1,    // Unknown balance slot
             None,  // No price feed
             false, // Not a main currency
             0,     // No weight
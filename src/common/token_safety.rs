@@ -0,0 +1,270 @@
+/// Fee-on-transfer and honeypot detection via simulated buy/sell round trips.
+///
+/// Runs a small buy → transfer → sell sequence against a token's main Uniswap V2 pool inside
+/// `EvmSimulator` and records the taxes and reverts observed along the way.
+use anyhow::{anyhow, Result};
+use ethers::providers::Middleware;
+use ethers::types::{Bytes, H160, U256};
+use serde::{Deserialize, Serialize};
+
+use crate::common::constants::BASIS_POINTS_DIVISOR;
+use crate::common::evm::{EvmSimulator, Tx};
+use crate::common::utils::create_new_wallet;
+use crate::sandwich::simulation::{get_v2_amount_out, main_currency_balance_slot};
+
+/// Outcome of a simulated buy → transfer → sell round trip for a single token.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TokenSafetyReport {
+    /// Pool the round trip was simulated against.
+    pub pool: H160,
+    /// Block the simulation was forked from.
+    pub block_number: u64,
+    /// Tax taken when buying from the pool, in basis points.
+    pub buy_tax_bps: u32,
+    /// Tax taken when transferring between two wallets, in basis points.
+    pub transfer_tax_bps: u32,
+    /// Tax taken when selling into the pool, in basis points.
+    pub sell_tax_bps: u32,
+    /// Whether the buy swap reverted (trading disabled, max-tx, blacklist...).
+    pub buy_reverted: bool,
+    /// Whether a wallet-to-wallet transfer reverted (max-tx, max-wallet, blacklist...).
+    pub transfer_reverted: bool,
+    /// Whether the sell reverted, which makes the token a honeypot for us.
+    pub sell_reverted: bool,
+}
+
+impl TokenSafetyReport {
+    /// Returns true if bought tokens cannot be sold back.
+    pub fn is_honeypot(&self) -> bool {
+        self.buy_reverted || self.sell_reverted || self.sell_tax_bps >= BASIS_POINTS_DIVISOR as u32
+    }
+
+    /// Returns true if any leg of the round trip is taxed.
+    pub fn is_taxed(&self) -> bool {
+        self.buy_tax_bps > 0 || self.transfer_tax_bps > 0 || self.sell_tax_bps > 0
+    }
+
+    /// Returns true if the token can be sandwiched with the given maximum tax per leg.
+    ///
+    /// The transfer tax counts as a leg too: the frontrun's tokens move through our inventory
+    /// before the backrun sells them.
+    ///
+    /// # Parameters
+    /// * `max_tax_bps`: u32 - Maximum tolerated buy, transfer or sell tax in basis points.
+    pub fn is_tradeable(&self, max_tax_bps: u32) -> bool {
+        !self.is_honeypot()
+            && !self.transfer_reverted
+            && self.buy_tax_bps <= max_tax_bps
+            && self.transfer_tax_bps <= max_tax_bps
+            && self.sell_tax_bps <= max_tax_bps
+    }
+
+    /// Returns true if an untradeable report is old enough to analyze the token again.
+    ///
+    /// Reverts and taxes can be temporary (trading not enabled yet, launch taxes, a cooldown), so
+    /// rejected tokens get another round trip after `max_age` blocks. Tradeable reports never
+    /// expire.
+    ///
+    /// # Parameters
+    /// * `block_number`: u64 - The block we're about to simulate in.
+    /// * `max_tax_bps`: u32 - Maximum tolerated tax per leg, as for `is_tradeable`.
+    /// * `max_age`: u64 - Number of blocks an untradeable report holds for.
+    pub fn is_expired(&self, block_number: u64, max_tax_bps: u32, max_age: u64) -> bool {
+        !self.is_tradeable(max_tax_bps) && block_number.saturating_sub(self.block_number) >= max_age
+    }
+
+    /// Returns what buying with `amount` and selling the tokens straight back loses to taxes.
+    ///
+    /// # Parameters
+    /// * `amount`: U256 - Amount of main currency spent on the buy.
+    ///
+    /// # Returns
+    /// * `U256` - The part of `amount` taken by the buy and sell taxes, in main currency.
+    pub fn round_trip_tax(&self, amount: U256) -> U256 {
+        amount - apply_tax(apply_tax(amount, self.buy_tax_bps), self.sell_tax_bps)
+    }
+
+    /// Reduces an amount we send into the pool by the sell tax.
+    ///
+    /// # Parameters
+    /// * `amount`: U256 - Amount of the taxed token we transfer to the pool.
+    ///
+    /// # Returns
+    /// * `U256` - Amount the pool actually receives.
+    pub fn apply_sell_tax(&self, amount: U256) -> U256 {
+        apply_tax(amount, self.sell_tax_bps)
+    }
+}
+
+/// Analyzer that measures transfer taxes and revert behaviour of a token on a forked state.
+pub struct TokenSafety<'a, M> {
+    /// The simulator whose state the round trip runs against.
+    pub simulator: &'a mut EvmSimulator<M>,
+}
+
+impl<'a, M: Middleware + 'static> TokenSafety<'a, M> {
+    /// Creates a new analyzer on top of an existing simulator.
+    ///
+    /// # Parameters
+    /// * `simulator`: &mut EvmSimulator<M> - Simulator forked at the block to analyze.
+    pub fn new(simulator: &'a mut EvmSimulator<M>) -> Self {
+        Self { simulator }
+    }
+
+    /// Simulates buy → transfer → sell on a Uniswap V2 pair and records the observed taxes.
+    ///
+    /// The state changes are committed to the simulator, so use a dedicated fork.
+    ///
+    /// # Parameters
+    /// * `token`: H160 - The token to analyze.
+    /// * `main_currency`: H160 - The main currency the token is paired with.
+    /// * `pair`: H160 - The Uniswap V2 pair trading `token` against `main_currency`.
+    /// * `amount_in`: U256 - Amount of main currency to buy with.
+    ///
+    /// # Returns
+    /// * `Result<TokenSafetyReport>` - The measured taxes and reverts.
+    pub fn analyze(
+        &mut self,
+        token: H160,
+        main_currency: H160,
+        pair: H160,
+        amount_in: U256,
+    ) -> Result<TokenSafetyReport> {
+        let mut report = TokenSafetyReport {
+            pool: pair,
+            block_number: self.simulator.block_number.as_u64(),
+            ..Default::default()
+        };
+
+        let buyer = create_new_wallet().1;
+        let receiver = create_new_wallet().1;
+
+        // Uniswap V2 sorts pair tokens by address
+        let token0_is_main = main_currency < token;
+
+        let balance_slot = main_currency_balance_slot(self.simulator, main_currency)?;
+        self.simulator
+            .set_token_balance_at(buyer, &balance_slot, amount_in.into())?;
+
+        // 1. Buy
+        let (reserve_main, reserve_token) = self.ordered_reserves(pair, token0_is_main)?;
+        let expected_out = get_v2_amount_out(amount_in, reserve_main, reserve_token);
+        if expected_out.is_zero() {
+            return Err(anyhow!("Pair {:?} has no liquidity for {:?}", pair, token));
+        }
+
+        self.transfer(main_currency, buyer, pair, amount_in)?;
+        let bought = match self.swap(pair, buyer, buyer, token0_is_main, expected_out) {
+            Ok(_) => self.simulator.get_token_balance(token, buyer)?,
+            Err(_) => {
+                report.buy_reverted = true;
+                return Ok(report);
+            }
+        };
+        report.buy_tax_bps = tax_bps(expected_out, bought);
+        if bought.is_zero() {
+            report.buy_reverted = true;
+            return Ok(report);
+        }
+
+        // 2. Wallet-to-wallet transfer
+        let seller = match self.transfer(token, buyer, receiver, bought) {
+            Ok(_) => {
+                let received = self.simulator.get_token_balance(token, receiver)?;
+                report.transfer_tax_bps = tax_bps(bought, received);
+                receiver
+            }
+            Err(_) => {
+                report.transfer_reverted = true;
+                buyer
+            }
+        };
+
+        // 3. Sell
+        let sell_amount = self.simulator.get_token_balance(token, seller)?;
+        let pair_balance_before = self.simulator.get_token_balance(token, pair)?;
+        if self.transfer(token, seller, pair, sell_amount).is_err() {
+            report.sell_reverted = true;
+            return Ok(report);
+        }
+        let pair_balance_after = self.simulator.get_token_balance(token, pair)?;
+        let pair_received = pair_balance_after
+            .checked_sub(pair_balance_before)
+            .unwrap_or_default();
+        report.sell_tax_bps = tax_bps(sell_amount, pair_received);
+
+        let (reserve_main, reserve_token) = self.ordered_reserves(pair, token0_is_main)?;
+        let expected_main_out = get_v2_amount_out(pair_received, reserve_token, reserve_main);
+        if expected_main_out.is_zero()
+            || self
+                .swap(pair, seller, seller, !token0_is_main, expected_main_out)
+                .is_err()
+        {
+            report.sell_reverted = true;
+        }
+
+        Ok(report)
+    }
+
+    fn ordered_reserves(&mut self, pair: H160, token0_is_main: bool) -> Result<(U256, U256)> {
+        let (reserve0, reserve1) = self.simulator.get_pair_reserves(pair)?;
+        if token0_is_main {
+            Ok((reserve0, reserve1))
+        } else {
+            Ok((reserve1, reserve0))
+        }
+    }
+
+    fn transfer(&mut self, token: H160, from: H160, to: H160, amount: U256) -> Result<()> {
+        let calldata = self.simulator.abi.token.encode("transfer", (to, amount))?;
+        self.execute(from, token, calldata)
+    }
+
+    fn swap(
+        &mut self,
+        pair: H160,
+        caller: H160,
+        to: H160,
+        zero_for_one: bool,
+        amount_out: U256,
+    ) -> Result<()> {
+        let (amount0_out, amount1_out) = if zero_for_one {
+            (U256::zero(), amount_out)
+        } else {
+            (amount_out, U256::zero())
+        };
+        let calldata = self.simulator.abi.pair.encode(
+            "swap",
+            (amount0_out, amount1_out, to, Bytes::default()),
+        )?;
+        self.execute(caller, pair, calldata)
+    }
+
+    fn execute(&mut self, caller: H160, to: H160, calldata: Bytes) -> Result<()> {
+        self.simulator.call(Tx {
+            caller,
+            transact_to: to,
+            data: calldata.0,
+            value: U256::zero(),
+            gas_price: U256::zero(),
+            gas_limit: 5000000,
+        })?;
+        Ok(())
+    }
+}
+
+/// Returns the shortfall of `actual` against `expected` in basis points.
+pub fn tax_bps(expected: U256, actual: U256) -> u32 {
+    if expected.is_zero() || actual >= expected {
+        return 0;
+    }
+    let shortfall = expected - actual;
+    (shortfall * U256::from(BASIS_POINTS_DIVISOR) / expected).as_u32()
+}
+
+/// Applies a tax in basis points to an amount.
+pub fn apply_tax(amount: U256, tax_bps: u32) -> U256 {
+    let divisor = U256::from(BASIS_POINTS_DIVISOR);
+    let tax_bps = U256::from(tax_bps).min(divisor);
+    amount * (divisor - tax_bps) / divisor
+}
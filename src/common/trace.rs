@@ -0,0 +1,312 @@
+/// Revm-native transaction tracing.
+///
+/// Runs a transaction in the forked EVM with an inspector that records the call tree and the
+/// logs that survive execution (logs of reverted frames are dropped), so swap detection works
+/// against any plain JSON-RPC node instead of relying on `debug_traceCall`.
+use anyhow::{anyhow, Result};
+use ethers::providers::Middleware;
+use ethers::types::{Bytes, H160, H256, I256, U256};
+use revm::interpreter::{CallInputs, CreateInputs, Gas, InstructionResult};
+use revm::primitives::{Bytes as rBytes, ExecutionResult, Output, TransactTo, B160, B256};
+use revm::{Database, EVMData, Inspector};
+
+use crate::common::evm::{EvmSimulator, Tx};
+
+/// `Swap(address,uint256,uint256,uint256,uint256,address)` of Uniswap V2 pairs.
+pub static V2_SWAP_TOPIC: &str =
+    "0xd78ad95fa46c994b6551d0da85fc275fe613ce37657fb8d5e3d130840159d822";
+/// `Swap(address,address,int256,int256,uint160,uint128,int24)` of Uniswap V3 pools.
+pub static V3_SWAP_TOPIC: &str =
+    "0xc42079f94a6350d7e6235f29174924f928cc2ac818eb64fed8004e115fbcca67";
+/// `Sync(uint112,uint112)` of Uniswap V2 pairs.
+pub static SYNC_TOPIC: &str = "0x1c411e9a96e071241c2f21f7726b17ae89e3cab4c78be50e062b03a9fffbbad1";
+/// `Transfer(address,address,uint256)` of ERC-20 tokens.
+pub static TRANSFER_TOPIC: &str =
+    "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+
+/// Kind of a log we care about when looking for swaps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TracedLogKind {
+    /// Uniswap V2 `Swap`
+    V2Swap,
+    /// Uniswap V3 `Swap`
+    V3Swap,
+    /// Uniswap V2 `Sync`
+    Sync,
+    /// ERC-20 `Transfer`
+    Transfer,
+}
+
+impl TracedLogKind {
+    /// Classifies a log by its first topic.
+    pub fn from_topic(topic: &H256) -> Option<Self> {
+        let topic = format!("{:?}", topic);
+        if topic == V2_SWAP_TOPIC {
+            Some(TracedLogKind::V2Swap)
+        } else if topic == V3_SWAP_TOPIC {
+            Some(TracedLogKind::V3Swap)
+        } else if topic == SYNC_TOPIC {
+            Some(TracedLogKind::Sync)
+        } else if topic == TRANSFER_TOPIC {
+            Some(TracedLogKind::Transfer)
+        } else {
+            None
+        }
+    }
+}
+
+/// A `Swap`, `Sync` or `Transfer` log emitted by a traced transaction.
+#[derive(Debug, Clone)]
+pub struct TracedLog {
+    /// Kind of the log.
+    pub kind: TracedLogKind,
+    /// Emitting contract.
+    pub address: H160,
+    /// Log topics.
+    pub topics: Vec<H256>,
+    /// Log data.
+    pub data: Bytes,
+}
+
+impl TracedLog {
+    /// Returns whether token0 was paid into the pool, for swap logs.
+    ///
+    /// V2: `amount0In > 0`. V3: `amount0 > 0` (positive amounts are paid into the pool).
+    pub fn token0_in(&self) -> Option<bool> {
+        match self.kind {
+            TracedLogKind::V2Swap if self.data.len() >= 128 => {
+                Some(!U256::from_big_endian(&self.data[0..32]).is_zero())
+            }
+            TracedLogKind::V3Swap if self.data.len() >= 64 => {
+                Some(I256::from_raw(U256::from_big_endian(&self.data[0..32])) > I256::zero())
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the net (token0, token1) amounts paid into the pool, for swap logs.
+    ///
+    /// Positive amounts were paid in, negative amounts were taken out.
+    pub fn pool_deltas(&self) -> Option<(I256, I256)> {
+        let word = |i: usize| I256::from_raw(U256::from_big_endian(&self.data[32 * i..32 * (i + 1)]));
+        match self.kind {
+            // amount0In, amount1In, amount0Out, amount1Out
+            TracedLogKind::V2Swap if self.data.len() >= 128 => {
+                Some((word(0) - word(2), word(1) - word(3)))
+            }
+            // amount0, amount1, sqrtPriceX96, liquidity, tick
+            TracedLogKind::V3Swap if self.data.len() >= 64 => Some((word(0), word(1))),
+            _ => None,
+        }
+    }
+}
+
+/// A call frame of a traced transaction.
+#[derive(Debug, Clone)]
+pub struct CallTrace {
+    /// Call depth (0 for the transaction itself).
+    pub depth: usize,
+    /// Caller of the frame.
+    pub caller: H160,
+    /// Called contract (the code address for delegatecalls).
+    pub to: H160,
+    /// First four bytes of the input, if any.
+    pub selector: Option<[u8; 4]>,
+    /// ETH sent with the call.
+    pub value: U256,
+    /// False if the frame reverted or halted.
+    pub success: bool,
+}
+
+/// Result of tracing a transaction.
+#[derive(Debug, Clone, Default)]
+pub struct TxTrace {
+    /// False if the transaction reverted or halted.
+    pub success: bool,
+    /// Gas used by the transaction.
+    pub gas_used: u64,
+    /// Return or revert data.
+    pub output: Bytes,
+    /// `Swap`, `Sync` and `Transfer` logs in emission order.
+    pub logs: Vec<TracedLog>,
+    /// Call frames in the order they were entered.
+    pub calls: Vec<CallTrace>,
+    /// Contracts that emitted a `Swap` or `Sync` log, in order of first appearance.
+    pub touched_pools: Vec<H160>,
+}
+
+impl TxTrace {
+    /// Returns the swap logs of the transaction.
+    pub fn swaps(&self) -> impl Iterator<Item = &TracedLog> {
+        self.logs
+            .iter()
+            .filter(|log| matches!(log.kind, TracedLogKind::V2Swap | TracedLogKind::V3Swap))
+    }
+}
+
+/// Inspector recording the call tree and the logs of frames that didn't revert.
+#[derive(Debug, Default)]
+pub struct LogTracer {
+    /// Entered call frames.
+    pub calls: Vec<CallTrace>,
+    /// Logs of the outermost frame once execution is finished.
+    pub logs: Vec<TracedLog>,
+    /// (index in `calls`, logs emitted so far) of every open frame.
+    frames: Vec<(Option<usize>, Vec<TracedLog>)>,
+}
+
+impl LogTracer {
+    fn enter(&mut self, call: Option<CallTrace>) {
+        let index = call.map(|call| {
+            self.calls.push(call);
+            self.calls.len() - 1
+        });
+        self.frames.push((index, Vec::new()));
+    }
+
+    fn exit(&mut self, result: InstructionResult) {
+        let success = matches!(
+            result,
+            InstructionResult::Stop | InstructionResult::Return | InstructionResult::SelfDestruct
+        );
+        if let Some((index, logs)) = self.frames.pop() {
+            if let Some(index) = index {
+                self.calls[index].success = success;
+            }
+            if success {
+                match self.frames.last_mut() {
+                    Some((_, parent_logs)) => parent_logs.extend(logs),
+                    None => self.logs.extend(logs),
+                }
+            }
+        }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for LogTracer {
+    fn log(
+        &mut self,
+        _evm_data: &mut EVMData<'_, DB>,
+        address: &B160,
+        topics: &[B256],
+        data: &rBytes,
+    ) {
+        let topics: Vec<H256> = topics.iter().map(|topic| H256::from(topic.0)).collect();
+        let kind = match topics.first().and_then(TracedLogKind::from_topic) {
+            Some(kind) => kind,
+            None => return,
+        };
+        let log = TracedLog {
+            kind,
+            address: (*address).into(),
+            topics,
+            data: data.clone().into(),
+        };
+        match self.frames.last_mut() {
+            Some((_, logs)) => logs.push(log),
+            None => self.logs.push(log),
+        }
+    }
+
+    fn call(
+        &mut self,
+        _data: &mut EVMData<'_, DB>,
+        inputs: &mut CallInputs,
+    ) -> (InstructionResult, Gas, rBytes) {
+        let selector = inputs.input.get(..4).map(|s| [s[0], s[1], s[2], s[3]]);
+        self.enter(Some(CallTrace {
+            depth: self.frames.len(),
+            caller: inputs.context.caller.into(),
+            to: inputs.context.code_address.into(),
+            selector,
+            value: inputs.transfer.value.into(),
+            success: false,
+        }));
+        (InstructionResult::Continue, Gas::new(0), rBytes::new())
+    }
+
+    fn call_end(
+        &mut self,
+        _data: &mut EVMData<'_, DB>,
+        _inputs: &CallInputs,
+        remaining_gas: Gas,
+        ret: InstructionResult,
+        out: rBytes,
+    ) -> (InstructionResult, Gas, rBytes) {
+        self.exit(ret);
+        (ret, remaining_gas, out)
+    }
+
+    fn create(
+        &mut self,
+        _data: &mut EVMData<'_, DB>,
+        _inputs: &mut CreateInputs,
+    ) -> (InstructionResult, Option<B160>, Gas, rBytes) {
+        self.enter(None);
+        (InstructionResult::Continue, None, Gas::new(0), rBytes::new())
+    }
+
+    fn create_end(
+        &mut self,
+        _data: &mut EVMData<'_, DB>,
+        _inputs: &CreateInputs,
+        ret: InstructionResult,
+        address: Option<B160>,
+        remaining_gas: Gas,
+        out: rBytes,
+    ) -> (InstructionResult, Option<B160>, Gas, rBytes) {
+        self.exit(ret);
+        (ret, address, remaining_gas, out)
+    }
+}
+
+impl<M: Middleware + 'static> EvmSimulator<M> {
+    /// Traces a transaction without committing it.
+    ///
+    /// # Parameters
+    /// * `tx`: Tx - The transaction to trace (e.g. `Tx::from(victim_tx)`).
+    ///
+    /// # Returns
+    /// * `Result<TxTrace>` - The emitted swap-related logs, call tree and touched pools.
+    pub fn trace_logs(&mut self, tx: Tx) -> Result<TxTrace> {
+        self.evm.env.tx.caller = tx.caller.into();
+        self.evm.env.tx.transact_to = TransactTo::Call(tx.transact_to.into());
+        self.evm.env.tx.data = tx.data;
+        self.evm.env.tx.value = tx.value.into();
+        self.evm.env.tx.gas_price = tx.gas_price.into();
+        self.evm.env.tx.gas_limit = tx.gas_limit;
+
+        let mut tracer = LogTracer::default();
+        let result = self
+            .evm
+            .inspect_ref(&mut tracer)
+            .map_err(|e| anyhow!("EVM trace failed: {:?}", e))?
+            .result;
+
+        let (success, output) = match &result {
+            ExecutionResult::Success { output, .. } => match output {
+                Output::Call(o) => (true, o.clone()),
+                Output::Create(o, _) => (true, o.clone()),
+            },
+            ExecutionResult::Revert { output, .. } => (false, output.clone()),
+            ExecutionResult::Halt { .. } => (false, rBytes::new()),
+        };
+
+        let mut touched_pools = Vec::new();
+        for log in &tracer.logs {
+            let is_pool_log = log.kind != TracedLogKind::Transfer;
+            if is_pool_log && !touched_pools.contains(&log.address) {
+                touched_pools.push(log.address);
+            }
+        }
+
+        Ok(TxTrace {
+            success,
+            gas_used: result.gas_used(),
+            output: output.into(),
+            logs: tracer.logs,
+            calls: tracer.calls,
+            touched_pools,
+        })
+    }
+}
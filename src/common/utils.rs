@@ -20,6 +20,7 @@ use std::str::FromStr;
 use std::sync::Arc;
 
 use crate::common::constants::*;
+use crate::common::quote_assets::get_quote_assets;
 
 /// Sets up a colored logger for the project.
 ///
@@ -194,7 +195,7 @@ pub fn is_weth(token_address: H160) -> bool {
     token_address == to_h160(WETH)
 }
 
-/// Checks if a token address is a main currency (WETH, USDT, USDC, WBTC, DAI, LINK, MKR).
+/// Checks if a token address is a configured quote asset (main currency).
 ///
 /// # Parameters
 /// * `token_address`: H160 - Token address.
@@ -202,135 +203,7 @@ pub fn is_weth(token_address: H160) -> bool {
 /// # Returns
 /// * `bool` - True if main currency.
 pub fn is_main_currency(token_address: H160) -> bool {
-    let main_currencies = vec![
-        to_h160(WETH),
-        to_h160(USDT),
-        to_h160(USDC),
-        to_h160(WBTC),
-        to_h160(DAI),
-        to_h160(LINK),
-        to_h160(MKR),
-    ];
-    main_currencies.contains(&token_address)
-}
-
-/// Main currency enum for classification.
-#[derive(Debug, Clone)]
-pub enum MainCurrency {
-    /// Wrapped Ether (WETH).
-    WETH,
-    /// Tether (USDT).
-    USDT,
-    /// USD Coin (USDC).
-    USDC,
-    /// Wrapped Bitcoin (WBTC).
-    WBTC,
-    /// Dai Stablecoin (DAI).
-    DAI,
-    /// Chainlink Token (LINK).
-    LINK,
-    /// Maker (MKR).
-    MKR,
-    /// Default (fallback to WETH).
-    Default, // Pairs that aren't WETH/Stable pairs. Default to WETH for now
-}
-
-impl MainCurrency {
-    /// Creates a MainCurrency from an address.
-    pub fn new(address: H160) -> Self {
-        if address == to_h160(WETH) {
-            MainCurrency::WETH
-        } else if address == to_h160(USDT) {
-            MainCurrency::USDT
-        } else if address == to_h160(USDC) {
-            MainCurrency::USDC
-        } else if address == to_h160(WBTC) {
-            MainCurrency::WBTC
-        } else if address == to_h160(DAI) {
-            MainCurrency::DAI
-        } else if address == to_h160(LINK) {
-            MainCurrency::LINK
-        } else if address == to_h160(MKR) {
-            MainCurrency::MKR
-        } else {
-            MainCurrency::Default
-        }
-    }
-
-    /// Returns the decimals for the main currency.
-    pub fn decimals(&self) -> u8 {
-        match self {
-            MainCurrency::WETH => WETH_DECIMALS,
-            MainCurrency::USDT => USDT_DECIMALS,
-            MainCurrency::USDC => USDC_DECIMALS,
-            MainCurrency::WBTC => WBTC_DECIMALS,
-            MainCurrency::DAI => DAI_DECIMALS,
-            MainCurrency::LINK => LINK_DECIMALS,
-            MainCurrency::MKR => MKR_DECIMALS,
-            MainCurrency::Default => WETH_DECIMALS,
-        }
-    }
-
-    /// Returns the storage slot for the main currency.
-    pub fn balance_slot(&self) -> i32 {
-        match self {
-            MainCurrency::WETH => WETH_BALANCE_SLOT,
-            MainCurrency::USDT => USDT_BALANCE_SLOT,
-            MainCurrency::USDC => USDC_BALANCE_SLOT,
-            MainCurrency::WBTC => WBTC_BALANCE_SLOT,
-            MainCurrency::DAI => DAI_BALANCE_SLOT,
-            MainCurrency::LINK => LINK_BALANCE_SLOT,
-            MainCurrency::MKR => MKR_BALANCE_SLOT,
-            MainCurrency::Default => WETH_BALANCE_SLOT,
-        }
-    }
-
-    /// Returns the weight (importance) of the main currency.
-    pub fn weight(&self) -> u8 {
-        match self {
-            MainCurrency::WETH => 7,    // Highest priority
-            MainCurrency::WBTC => 6,    // High priority for BTC
-            MainCurrency::USDT => 5,    // Stablecoin priority
-            MainCurrency::USDC => 4,    // Stablecoin priority
-            MainCurrency::DAI => 3,     // Stablecoin priority
-            MainCurrency::LINK => 2,    // Lower priority for LINK
-            MainCurrency::MKR => 1,     // Lowest priority for MKR
-            MainCurrency::Default => 7, // default is WETH
-        }
-    }
-
-    /// Returns the Chainlink price feed address for the token
-    pub fn chainlink_feed(&self) -> Option<&'static str> {
-        match self {
-            MainCurrency::WETH => Some(CHAINLINK_ETH_USD),
-            MainCurrency::USDT => Some(CHAINLINK_USDT_USD),
-            MainCurrency::USDC => Some(CHAINLINK_USDC_USD),
-            MainCurrency::WBTC => Some(CHAINLINK_BTC_USD),
-            MainCurrency::DAI => Some(CHAINLINK_DAI_USD),
-            MainCurrency::LINK => Some(CHAINLINK_LINK_USD),
-            MainCurrency::MKR => Some(CHAINLINK_MKR_USD),
-            MainCurrency::Default => None,
-        }
-    }
-
-    /// Returns the token address as a string
-    pub fn address_str(&self) -> &'static str {
-        match self {
-            MainCurrency::WETH => WETH,
-            MainCurrency::USDT => USDT,
-            MainCurrency::USDC => USDC,
-            MainCurrency::WBTC => WBTC,
-            MainCurrency::DAI => DAI,
-            MainCurrency::LINK => LINK,
-            MainCurrency::MKR => MKR,
-            MainCurrency::Default => WETH,
-        }
-    }
-
-    /// Returns the token address as H160
-    pub fn address(&self) -> H160 {
-        H160::from_str(self.address_str()).unwrap()
-    }
+    get_quote_assets().is_quote_asset(token_address)
 }
 
 /// Determines which token is the main currency and which is the target in a pair.
@@ -342,30 +215,5 @@ impl MainCurrency {
 /// # Returns
 /// * `Option<(H160, H160)>` - (main, target) or None if neither is main.
 pub fn return_main_and_target_currency(token0: H160, token1: H160) -> Option<(H160, H160)> {
-    let token0_supported = is_main_currency(token0);
-    let token1_supported = is_main_currency(token1);
-
-    if !token0_supported && !token1_supported {
-        return None;
-    }
-
-    if token0_supported && token1_supported {
-        let mc0 = MainCurrency::new(token0);
-        let mc1 = MainCurrency::new(token1);
-
-        let token0_weight = mc0.weight();
-        let token1_weight = mc1.weight();
-
-        if token0_weight > token1_weight {
-            return Some((token0, token1));
-        } else {
-            return Some((token1, token0));
-        }
-    }
-
-    if token0_supported {
-        return Some((token0, token1));
-    } else {
-        return Some((token1, token0));
-    }
+    get_quote_assets().main_and_target(token0, token1)
 }
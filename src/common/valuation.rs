@@ -0,0 +1,285 @@
+/// WETH-denominated valuation of quote asset balances.
+///
+/// Prices every quote asset at the mid-price of its deepest WETH pool in the simulated state and
+/// cross-checks that price against the asset's Chainlink feed, read in the same state.
+use anyhow::{anyhow, Result};
+use ethers::providers::Middleware;
+use ethers::types::{H160, I256, U256};
+use log::warn;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::common::constants::{BASIS_POINTS_DIVISOR, MAX_PRICE_DEVIATION_BPS, WETH};
+use crate::common::evm::{EvmSimulator, Tx};
+use crate::common::quote_assets::{get_quote_assets, QuoteAsset};
+
+/// Where the price of a quote asset came from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PriceSource {
+    /// The asset is WETH itself.
+    Weth,
+    /// Mid-price of a WETH pool in the simulated state.
+    Pool(H160),
+    /// Chainlink USD feeds of the asset and ETH.
+    Chainlink,
+}
+
+/// Price of one base unit of a quote asset in wei, as a ratio.
+#[derive(Debug, Clone)]
+pub struct AssetPrice {
+    /// Where the price came from.
+    pub source: PriceSource,
+    /// Wei side of the ratio.
+    pub numerator: U256,
+    /// Asset side of the ratio.
+    pub denominator: U256,
+}
+
+impl AssetPrice {
+    /// Converts an amount of the asset into wei.
+    ///
+    /// # Parameters
+    /// * `amount`: U256 - Amount in base units of the asset.
+    ///
+    /// # Returns
+    /// * `U256` - Value in wei.
+    pub fn to_eth(&self, amount: U256) -> U256 {
+        amount
+            .checked_mul(self.numerator)
+            .and_then(|v| v.checked_div(self.denominator))
+            .unwrap_or_default()
+    }
+}
+
+/// Balance change of a single quote asset and its value in ETH.
+#[derive(Debug, Clone, Default)]
+pub struct AssetDelta {
+    /// The quote asset.
+    pub asset: H160,
+    /// Balance before the simulation.
+    pub before: U256,
+    /// Balance after the simulation.
+    pub after: U256,
+    /// Balance change in base units of the asset.
+    pub delta: I256,
+    /// Balance change valued in wei.
+    pub eth_value: I256,
+}
+
+/// Values quote asset balances in ETH using prices captured from a simulated state.
+#[derive(Debug, Clone, Default)]
+pub struct Valuator {
+    prices: HashMap<H160, AssetPrice>,
+}
+
+impl Valuator {
+    /// Creates a valuator with no prices loaded.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the price of a quote asset, loading it from the simulator on first use.
+    ///
+    /// Load prices before running any transactions, so our own trades don't move them.
+    ///
+    /// # Parameters
+    /// * `simulator`: &mut EvmSimulator<M> - Simulator whose state the pools are read from.
+    /// * `asset`: H160 - The quote asset.
+    ///
+    /// # Returns
+    /// * `Result<AssetPrice>` - The price of one base unit in wei.
+    pub fn price<M: Middleware + 'static>(
+        &mut self,
+        simulator: &mut EvmSimulator<M>,
+        asset: H160,
+    ) -> Result<AssetPrice> {
+        if let Some(price) = self.prices.get(&asset) {
+            return Ok(price.clone());
+        }
+
+        let price = load_price(simulator, asset)?;
+        self.prices.insert(asset, price.clone());
+        Ok(price)
+    }
+
+    /// Values the balance change of a quote asset in ETH.
+    ///
+    /// # Parameters
+    /// * `asset`: H160 - The quote asset (its price must be loaded).
+    /// * `before`: U256 - Balance before the simulation.
+    /// * `after`: U256 - Balance after the simulation.
+    ///
+    /// # Returns
+    /// * `Result<AssetDelta>` - The raw and ETH-valued balance change.
+    pub fn value_delta(&self, asset: H160, before: U256, after: U256) -> Result<AssetDelta> {
+        let price = self
+            .prices
+            .get(&asset)
+            .ok_or(anyhow!("No price loaded for {:?}", asset))?;
+
+        let (delta, eth_value) = if after >= before {
+            let change = after - before;
+            (
+                I256::from_raw(change),
+                I256::from_raw(price.to_eth(change)),
+            )
+        } else {
+            let change = before - after;
+            (
+                -I256::from_raw(change),
+                -I256::from_raw(price.to_eth(change)),
+            )
+        };
+
+        Ok(AssetDelta {
+            asset,
+            before,
+            after,
+            delta,
+            eth_value,
+        })
+    }
+}
+
+fn load_price<M: Middleware + 'static>(
+    simulator: &mut EvmSimulator<M>,
+    asset: H160,
+) -> Result<AssetPrice> {
+    let weth = H160::from_str(WETH).unwrap();
+    if asset == weth {
+        return Ok(AssetPrice {
+            source: PriceSource::Weth,
+            numerator: U256::one(),
+            denominator: U256::one(),
+        });
+    }
+
+    let quote_asset = get_quote_assets()
+        .get(asset)
+        .ok_or(anyhow!("{:?} is not a quote asset", asset))?;
+
+    // pick the pool with the most WETH liquidity
+    let mut deepest: Option<(H160, U256, U256)> = None;
+    for pool in &quote_asset.weth_pools {
+        match pool_reserves(simulator, *pool, asset, weth) {
+            Ok((reserve_asset, reserve_weth)) => {
+                if reserve_asset.is_zero() || reserve_weth.is_zero() {
+                    continue;
+                }
+                if deepest.map_or(true, |(_, _, deepest_weth)| reserve_weth > deepest_weth) {
+                    deepest = Some((*pool, reserve_asset, reserve_weth));
+                }
+            }
+            Err(e) => warn!("Failed to read reserves of {:?}: {e:?}", pool),
+        }
+    }
+
+    let chainlink = chainlink_price(simulator, quote_asset);
+    match (deepest, chainlink) {
+        (Some((pool, reserve_asset, reserve_weth)), chainlink) => {
+            let pool_price = AssetPrice {
+                source: PriceSource::Pool(pool),
+                numerator: reserve_weth,
+                denominator: reserve_asset,
+            };
+            if let Some(chainlink) = chainlink {
+                let one = U256::exp10(quote_asset.decimals as usize);
+                let deviation = deviation_bps(pool_price.to_eth(one), chainlink.to_eth(one));
+                if deviation > MAX_PRICE_DEVIATION_BPS {
+                    warn!(
+                        "{} pool price deviates {} bps from Chainlink, using Chainlink",
+                        quote_asset.symbol, deviation
+                    );
+                    return Ok(chainlink);
+                }
+            }
+            Ok(pool_price)
+        }
+        (None, Some(chainlink)) => Ok(chainlink),
+        (None, None) => Err(anyhow!("No price available for {}", quote_asset.symbol)),
+    }
+}
+
+/// Returns (asset reserve, WETH reserve) of a V2 pair or the virtual reserves of a V3 pool.
+fn pool_reserves<M: Middleware + 'static>(
+    simulator: &mut EvmSimulator<M>,
+    pool: H160,
+    asset: H160,
+    weth: H160,
+) -> Result<(U256, U256)> {
+    let (reserve0, reserve1) = match simulator.get_pair_reserves(pool) {
+        Ok(reserves) => reserves,
+        Err(_) => simulator.get_v3_pool_reserves(pool)?,
+    };
+    // both V2 and V3 sort pool tokens by address
+    if asset < weth {
+        Ok((reserve0, reserve1))
+    } else {
+        Ok((reserve1, reserve0))
+    }
+}
+
+/// Derives a wei price from the Chainlink USD feeds of the asset and of ETH.
+///
+/// USD feeds share their decimals, so the ratio of the two answers is the asset's price in ETH.
+fn chainlink_price<M: Middleware + 'static>(
+    simulator: &mut EvmSimulator<M>,
+    asset: &QuoteAsset,
+) -> Option<AssetPrice> {
+    let weth = H160::from_str(WETH).unwrap();
+    let eth_feed = get_quote_assets().get(weth)?.price_feed?;
+    let asset_feed = asset.price_feed?;
+
+    let read = |simulator: &mut EvmSimulator<M>, feed: H160| match latest_answer(simulator, feed) {
+        Ok(answer) => answer,
+        Err(e) => {
+            warn!("Failed to read Chainlink feed {:?}: {e:?}", feed);
+            None
+        }
+    };
+    let asset_usd = read(simulator, asset_feed)?;
+    let eth_usd = read(simulator, eth_feed)?;
+
+    // wei per whole token, scaled down to base units by the denominator
+    Some(AssetPrice {
+        source: PriceSource::Chainlink,
+        numerator: asset_usd * U256::exp10(18),
+        denominator: eth_usd * U256::exp10(asset.decimals as usize),
+    })
+}
+
+/// Returns the latest answer of a Chainlink feed, None if it isn't positive.
+fn latest_answer<M: Middleware + 'static>(
+    simulator: &mut EvmSimulator<M>,
+    feed: H160,
+) -> Result<Option<U256>> {
+    let calldata = simulator.abi.price_feed.encode("latestRoundData", ())?;
+    let result = simulator.staticcall(Tx {
+        caller: simulator.owner,
+        transact_to: feed,
+        data: calldata.0,
+        value: U256::zero(),
+        gas_price: U256::zero(),
+        gas_limit: 5000000,
+    })?;
+    let (_, answer, _, _, _): (U256, I256, U256, U256, U256) = simulator
+        .abi
+        .price_feed
+        .decode_output("latestRoundData", result.output)?;
+    Ok((answer > I256::zero()).then(|| answer.into_raw()))
+}
+
+/// Returns the relative difference of `value` against `reference` in basis points.
+fn deviation_bps(value: U256, reference: U256) -> u64 {
+    if reference.is_zero() {
+        return u64::MAX;
+    }
+    let diff = if value > reference {
+        value - reference
+    } else {
+        reference - value
+    };
+    (diff * U256::from(BASIS_POINTS_DIVISOR) / reference)
+        .min(U256::from(u64::MAX))
+        .as_u64()
+}
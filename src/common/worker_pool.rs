@@ -0,0 +1,126 @@
+/// Bounded pool of async workers.
+///
+/// Jobs go through a bounded queue shared by a fixed number of worker tasks. `submit` never
+/// waits: when every worker is busy and the queue is full the job is dropped and counted, so a
+/// burst of pending transactions can't stall the engine loop or grow memory without bound.
+use anyhow::Result;
+use log::{debug, warn};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::sync::Mutex;
+
+use crate::common::metrics::Metrics;
+
+/// Workers a strategy runs its pending transactions on, unless configured otherwise.
+pub const DEFAULT_WORKERS: usize = 8;
+
+/// Pending transactions a strategy queues for its workers before dropping new ones.
+pub const DEFAULT_QUEUE_CAPACITY: usize = 256;
+
+/// Handle to a running pool. The workers stop once every handle is dropped and the queue drains.
+pub struct WorkerPool<J> {
+    name: String,
+    sender: mpsc::Sender<J>,
+    metrics: Arc<Metrics>,
+}
+
+impl<J> Clone for WorkerPool<J> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            sender: self.sender.clone(),
+            metrics: self.metrics.clone(),
+        }
+    }
+}
+
+impl<J: Send + 'static> WorkerPool<J> {
+    /// Spawns `workers` tasks running `handler` on the submitted jobs.
+    ///
+    /// # Parameters
+    /// * `name`: &str - Name used in logs.
+    /// * `workers`: usize - Number of jobs processed at once (at least 1).
+    /// * `capacity`: usize - Jobs waiting for a worker before new ones are dropped (at least 1).
+    /// * `metrics`: Arc<Metrics> - Counters the pool reports to.
+    /// * `handler`: F - Processes one job; errors are logged and counted.
+    ///
+    /// # Returns
+    /// * `WorkerPool<J>` - The handle jobs are submitted through.
+    pub fn spawn<F, Fut>(
+        name: &str,
+        workers: usize,
+        capacity: usize,
+        metrics: Arc<Metrics>,
+        handler: F,
+    ) -> Self
+    where
+        F: Fn(J) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel::<J>(capacity.max(1));
+        let receiver = Arc::new(Mutex::new(receiver));
+        let handler = Arc::new(handler);
+
+        for worker in 0..workers.max(1) {
+            let (receiver, handler, metrics) = (receiver.clone(), handler.clone(), metrics.clone());
+            let name = name.to_string();
+            tokio::spawn(async move {
+                loop {
+                    // hold the lock only while waiting, so other workers can take the next job
+                    let job = receiver.lock().await.recv().await;
+                    let job = match job {
+                        Some(job) => job,
+                        None => break,
+                    };
+
+                    metrics.job_started();
+                    let started = Instant::now();
+                    let result = handler(job).await;
+                    if let Err(e) = &result {
+                        warn!("{} worker {} error: {e:?}", name, worker);
+                    }
+                    metrics.job_finished(started.elapsed(), result.is_err());
+                }
+                debug!("{} worker {} stopped", name, worker);
+            });
+        }
+
+        Self {
+            name: name.to_string(),
+            sender,
+            metrics,
+        }
+    }
+
+    /// Queues a job without waiting.
+    ///
+    /// # Parameters
+    /// * `job`: J - The job.
+    ///
+    /// # Returns
+    /// * `bool` - False if the queue was full (the job is dropped) or the workers stopped.
+    pub fn submit(&self, job: J) -> bool {
+        match self.sender.try_send(job) {
+            Ok(()) => {
+                self.metrics.job_queued();
+                true
+            }
+            Err(TrySendError::Full(_)) => {
+                self.metrics.job_dropped();
+                debug!("{} queue full, job dropped", self.name);
+                false
+            }
+            Err(TrySendError::Closed(_)) => {
+                warn!("{} workers stopped", self.name);
+                false
+            }
+        }
+    }
+
+    /// Jobs waiting for a worker.
+    pub fn queued(&self) -> usize {
+        self.sender.max_capacity() - self.sender.capacity()
+    }
+}
@@ -1,7 +1,8 @@
 /// Main library module for the Sandooo project.
 ///
-/// This module exposes the primary submodules: `common` (shared utilities and types)
-/// and `sandwich` (core sandwich attack logic and strategies).
+/// This module exposes the primary submodules: `common` (shared utilities and types),
+/// `sandwich` (core sandwich attack logic and strategies) and `arbitrage` (backrun arbitrage).
 ///
+pub mod arbitrage;
 pub mod common;
 pub mod sandwich;
@@ -1,14 +1,17 @@
 //! Sandooo - MEV Sandwich Attack Bot
 use dotenv::dotenv;
-use ethers::providers::{Middleware, Provider, Ws};
-use log::info;
+use ethers::providers::{Provider, Ws};
+use log::{error, info};
 use std::sync::Arc;
 use tokio::sync::broadcast;
-use tokio::time;
 
-use sandooo::common::streams::{stream_new_blocks, stream_pending_txs, Event, NewBlock};
-use sandooo::common::utils;
-use sandooo::sandwich::strategy::run_sandwich_strategy;
+use sandooo::arbitrage::strategy::ArbitrageStrategy;
+use sandooo::common::constants::get_env;
+use sandooo::common::recorder::{record_events, ReplaySource};
+use sandooo::common::engine::{Engine, EngineContext};
+use sandooo::common::streams::Event;
+use sandooo::common::token_registry::get_token_registry;
+use sandooo::sandwich::strategy::{SandwichMode, SandwichStrategy};
 
 use fern::colors::ColoredLevelConfig;
 use colored::Colorize;
@@ -60,76 +63,50 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let ws = Ws::connect(&env.wss_url).await?;
     let provider = Arc::new(Provider::new(ws));
 
-    // Start a manual block polling mechanism as fallback
-    let fallback_sender = event_sender.clone();
-    let fallback_provider = provider.clone();
-    tokio::spawn(async move {
-        let mut last_block_number = ethers::types::U64::zero();
-        loop {
-            match fallback_provider.get_block_number().await {
-                Ok(block_number) => {
-                    if block_number > last_block_number
-                        && last_block_number != ethers::types::U64::zero()
-                    {
-                        eprintln!("DEBUG: Manual polling detected new block: {}", block_number);
-
-                        // Get the block details
-                        if let Ok(Some(block)) = fallback_provider.get_block(block_number).await {
-                            let base_fee = block.base_fee_per_gas.unwrap_or_default();
-                            let next_base_fee = utils::calculate_next_block_base_fee(
-                                block.gas_used,
-                                block.gas_limit,
-                                base_fee,
-                            );
-
-                            let event = Event::Block(NewBlock {
-                                block_number,
-                                base_fee,
-                                next_base_fee,
-                            });
-
-                            if fallback_sender.send(event).is_err() {
-                                eprintln!("DEBUG: Failed to send fallback block event");
-                            } else {
-                                eprintln!(
-                                    "DEBUG: Successfully sent fallback block event for #{}",
-                                    block_number
-                                );
-                            }
-                        }
-                    }
-                    last_block_number = block_number;
-                }
-                Err(e) => {
-                    eprintln!("DEBUG: Error in fallback block polling: {:?}", e);
-                }
+    // Load pools and tokens, and register the strategies.
+    let context = EngineContext::load(provider).await?;
+    let engine = Engine::new(context)
+        .with_strategy(SandwichStrategy::new(SandwichMode::SandoooV3))
+        .with_strategy(ArbitrageStrategy::new());
+
+    // Record every event to a log if RECORD_EVENTS is set.
+    let record_path = get_env("RECORD_EVENTS");
+    if !record_path.is_empty() {
+        let receiver = event_sender.subscribe();
+        tokio::spawn(async move {
+            if let Err(e) = record_events(receiver, record_path).await {
+                error!("Error in event recorder: {:?}", e);
             }
-
-            // Wait a few seconds before polling again
-            time::sleep(time::Duration::from_secs(5)).await;
-        }
-    });
-
-    // Start pending transactions stream.
-    let tx_sender = event_sender.clone();
-    let provider_clone1 = provider.clone();
-    tokio::spawn(async move {
-        if let Err(e) = stream_pending_txs(provider_clone1, tx_sender).await {
-            eprintln!("Error in pending tx stream: {:?}", e);
-        }
-    });
-
-    // Start new blocks stream.
-    let block_sender = event_sender.clone();
-    let provider_clone2 = provider.clone();
-    tokio::spawn(async move {
-        if let Err(e) = stream_new_blocks(provider_clone2, block_sender).await {
-            eprintln!("Error in new blocks stream: {:?}", e);
-        }
-    });
-
-    // Run the sandwich trading strategy.
-    // This function never returns normally (it has the "never" type '!' as return type)
-    // Any code after this line is unreachable
-    run_sandwich_strategy(provider, event_sender).await
+        });
+    }
+
+    let replay_path = get_env("REPLAY_EVENTS");
+    if !replay_path.is_empty() {
+        // Replay a recorded session instead of the live streams.
+        let speed = get_env("REPLAY_SPEED").parse::<f64>().unwrap_or(1.0);
+        let min_receivers = event_sender.receiver_count() + 1;
+        let replay = ReplaySource::load(&replay_path)?
+            .with_speed(speed)
+            .with_min_receivers(min_receivers);
+        let replay_sender = event_sender.clone();
+        tokio::spawn(async move {
+            match replay.run(replay_sender).await {
+                Ok(sent) => info!("Replayed {} events from {}", sent, replay_path),
+                Err(e) => error!("Error in event replay: {:?}", e),
+            }
+        });
+    } else {
+        // Start the block and pending transaction streams, with the fallback block poller.
+        engine.spawn_streams(&event_sender);
+    }
+
+    // Run the strategies until the event channel closes or the bot is interrupted.
+    tokio::select! {
+        res = engine.run(event_sender) => res?,
+        _ = tokio::signal::ctrl_c() => info!("Shutting down"),
+    }
+
+    // Write the token registry changes the debounced saves skipped.
+    get_token_registry().flush();
+    Ok(())
 }
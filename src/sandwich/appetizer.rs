@@ -4,15 +4,96 @@
 use anyhow::Result;
 use ethers::{
     providers::{Provider, Ws},
-    types::{H160, H256, U256},
+    types::{H160, H256, I256, U256},
 };
-use log::warn;
+use log::{debug, warn};
 use std::{collections::HashMap, sync::Arc};
 
-use crate::common::evm::VictimTx;
+use crate::common::constants::{MAX_TOKEN_TAX_BPS, TOKEN_SAFETY_RECHECK_BLOCKS};
+use crate::common::evm::{EvmSimulator, NextBlockEnv, VictimTx};
+use crate::common::pools::DexVariant;
+use crate::common::quote_assets::get_quote_assets;
 use crate::common::streams::NewBlock;
-use crate::common::utils::{is_weth, MainCurrency};
-use crate::sandwich::simulation::{BatchSandwich, PendingTxInfo, Sandwich, SwapDirection};
+use crate::common::token_registry::get_token_registry;
+use crate::common::token_safety::{TokenSafety, TokenSafetyReport};
+use crate::sandwich::simulation::{
+    BatchSandwich, PendingTxInfo, Sandwich, SimulatedSandwich, SwapDirection, SwapInfo,
+};
+
+/// Returns the safety report of a sandwich target token, simulating a round trip if it isn't cached.
+///
+/// Reports that rejected the token are analyzed again once they're `TOKEN_SAFETY_RECHECK_BLOCKS`
+/// old.
+///
+/// # Parameters
+/// * `provider`: Ethereum provider.
+/// * `next_block`: Block to simulate in when the token hasn't been analyzed yet.
+/// * `info`: Swap info of the victim transaction.
+/// * `amount_in`: Amount of main currency to buy with.
+///
+/// # Returns
+/// * `Result<Option<TokenSafetyReport>>` - The report, or None for pools we can't analyze (V3).
+pub async fn get_token_safety(
+    provider: &Arc<Provider<Ws>>,
+    next_block: &NextBlockEnv,
+    info: &SwapInfo,
+    amount_in: U256,
+) -> Result<Option<TokenSafetyReport>> {
+    let registry = get_token_registry();
+    if let Some(report) = registry.get_token_safety(info.target_token) {
+        let block_number = next_block.number.as_u64();
+        if !report.is_expired(block_number, MAX_TOKEN_TAX_BPS, TOKEN_SAFETY_RECHECK_BLOCKS) {
+            return Ok(Some(report));
+        }
+    }
+
+    if info.version != DexVariant::UniswapV2 {
+        return Ok(None);
+    }
+
+    let mut simulator = EvmSimulator::new(provider.clone(), None, next_block);
+    let report = TokenSafety::new(&mut simulator).analyze(
+        info.target_token,
+        info.main_currency,
+        info.target_pair,
+        amount_in,
+    )?;
+
+    registry.fetch_token_info(provider, info.target_token).await?;
+    registry.update_token_safety(info.target_token, report.clone())?;
+    registry.save_to_cache_debounced();
+
+    Ok(Some(report))
+}
+
+/// Returns true if the probe sandwich is worth optimizing.
+///
+/// Taxed tokens are priced with their tax: the small probe amount pays the buy and sell taxes in
+/// full while the victim's price impact barely covers them, so the probe's round-trip tax is added
+/// back to its main currency result. Larger amounts can then still come out ahead, which the
+/// optimizer's simulations (taxes included) decide.
+///
+/// # Parameters
+/// * `simulated`: &SimulatedSandwich - The probe simulation.
+/// * `main_currency`: H160 - The main currency the probe traded with.
+/// * `amount_in`: U256 - The probe amount.
+/// * `safety`: Option<&TokenSafetyReport> - The target token's safety report, if analyzed.
+pub fn probe_is_promising(
+    simulated: &SimulatedSandwich,
+    main_currency: H160,
+    amount_in: U256,
+    safety: Option<&TokenSafetyReport>,
+) -> bool {
+    let report = match safety {
+        Some(report) if report.is_taxed() => report,
+        _ => return simulated.profit > 0,
+    };
+    let delta = match simulated.asset_deltas.iter().find(|d| d.asset == main_currency) {
+        Some(delta) => delta.delta,
+        None => return simulated.profit > 0,
+    };
+    delta + I256::from_raw(report.round_trip_tax(amount_in)) > I256::zero()
+}
 
 /// Scans a pending transaction for sandwich opportunities, simulates, and records promising sandwiches.
 ///
@@ -61,15 +142,37 @@ pub async fn appetizer(
             _ => {}
         }
 
-        let main_currency = info.main_currency;
-        let mc = MainCurrency::new(main_currency);
-        let decimals = mc.decimals();
+        let quote_asset = match get_quote_assets().get(info.main_currency) {
+            Some(asset) => asset,
+            None => continue,
+        };
+        let small_amount_in = quote_asset.probe_amount_in();
 
-        let small_amount_in = if is_weth(main_currency) {
-            U256::from(10).pow(U256::from(decimals - 2)) // 0.01 WETH
-        } else {
-            U256::from(10) * U256::from(10).pow(U256::from(decimals)) // 10 USDT, 10 USDC
+        // skip honeypots and heavily taxed tokens before running any sandwich simulations
+        let safety = match get_token_safety(provider, &new_block.next_block, info, small_amount_in)
+            .await
+        {
+            Ok(Some(report)) => {
+                if !report.is_tradeable(MAX_TOKEN_TAX_BPS) {
+                    debug!(
+                        "Skipping {:?}: buy tax {} / transfer tax {} / sell tax {} / honeypot {}",
+                        info.target_token,
+                        report.buy_tax_bps,
+                        report.transfer_tax_bps,
+                        report.sell_tax_bps,
+                        report.is_honeypot()
+                    );
+                    continue;
+                }
+                Some(report)
+            }
+            Ok(None) => None,
+            Err(e) => {
+                warn!("TokenSafety.analyze error: {e:?}");
+                continue;
+            }
         };
+
         let base_fee = new_block.next_base_fee;
         let max_fee = base_fee;
 
@@ -96,7 +199,7 @@ pub async fn appetizer(
             .simulate(
                 provider.clone(),
                 None,
-                new_block.block_number,
+                &new_block.next_block,
                 base_fee,
                 max_fee,
                 None,
@@ -110,19 +213,16 @@ pub async fn appetizer(
             continue;
         }
         let simulated_sandwich = simulated_sandwich.unwrap();
-        // profit should be greater than 0 to simulate/optimize any further
-        if simulated_sandwich.profit <= 0 {
+        // the probe should be profitable, taxes priced in, to simulate/optimize any further
+        let safety = safety.as_ref();
+        if !probe_is_promising(&simulated_sandwich, info.main_currency, small_amount_in, safety) {
             continue;
         }
-        let ceiling_amount_in = if is_weth(main_currency) {
-            U256::from(100) * U256::from(10).pow(U256::from(18)) // 100 ETH
-        } else {
-            U256::from(300000) * U256::from(10).pow(U256::from(decimals)) // 300000 USDT/USDC (both 6 decimals)
-        };
+        let ceiling_amount_in = quote_asset.max_amount_in();
         let optimized_sandwich = sandwich
             .optimize(
                 provider.clone(),
-                new_block.block_number,
+                &new_block.next_block,
                 ceiling_amount_in,
                 base_fee,
                 max_fee,
@@ -0,0 +1,502 @@
+/// Backtesting the sandwich pipeline over historical blocks.
+///
+/// For every block of a range, candidate victims are rebuilt either from the block's own
+/// transactions or from a recorded mempool log, and run through `extract_swap_info` and
+/// `appetizer` on a fork of the parent block. Each promising sandwich is then priced the way
+/// the main dish prices it (simulation, contention-adjusted revenue, bribe) for every strategy
+/// version under test, and compared against the sandwiches that actually landed in the block.
+use anyhow::Result;
+use ethers::providers::{Provider, Ws};
+use ethers::types::{Block, Transaction, TransactionReceipt, H160, H256, U256, U64};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::common::competitors::{detect_sandwiches, get_competitor_db};
+use crate::common::evm::NextBlockEnv;
+use crate::common::pools::Pool;
+use crate::common::recorder::RecordedEvent;
+use crate::common::streams::{Event, NewBlock, NewPendingTx};
+use crate::sandwich::appetizer::appetizer;
+use crate::sandwich::contention::risk_adjusted_revenue;
+use crate::sandwich::inspect::BlockFixture;
+use crate::sandwich::simulation::{
+    extract_swap_info, BatchSandwich, PendingTxInfo, SandoContract, Sandwich,
+};
+
+/// A strategy configuration to backtest.
+#[derive(Debug, Clone)]
+pub struct StrategyVersion {
+    /// Name the version is reported under.
+    pub label: String,
+    /// Contract executing the legs.
+    pub contract: SandoContract,
+    /// Share of the revenue paid as bribe, in bps.
+    pub bribe_pct: U256,
+}
+
+impl StrategyVersion {
+    pub fn new(label: &str, contract: SandoContract, bribe_pct: U256) -> Self {
+        Self {
+            label: label.to_string(),
+            contract,
+            bribe_pct,
+        }
+    }
+}
+
+/// Where candidate victims come from.
+#[derive(Debug, Clone)]
+pub enum VictimSource {
+    /// The transactions of the mined block itself (excluding the legs of real sandwiches).
+    MinedBlocks,
+    /// Pending transactions of a recorded mempool, keyed by the block they were pending for.
+    Recorded(BTreeMap<u64, Vec<NewPendingTx>>),
+}
+
+/// Groups recorded pending transactions by the block they were pending for.
+///
+/// A transaction received while the head was block `n` is a candidate for block `n + 1`;
+/// transactions received before the first block event are dropped.
+///
+/// # Parameters
+/// * `events`: &[RecordedEvent] - A recorded event log in arrival order.
+///
+/// # Returns
+/// * `BTreeMap<u64, Vec<NewPendingTx>>` - Pending transactions by target block, deduplicated.
+pub fn pending_by_block(events: &[RecordedEvent]) -> BTreeMap<u64, Vec<NewPendingTx>> {
+    let mut by_block: BTreeMap<u64, Vec<NewPendingTx>> = BTreeMap::new();
+    let mut seen = HashSet::new();
+    let mut head: Option<u64> = None;
+    for recorded in events {
+        match &recorded.event {
+            Event::Block(block) => head = Some(block.block_number.as_u64()),
+            Event::PendingTransaction(pending) => {
+                let head = match head {
+                    Some(head) => head,
+                    None => continue,
+                };
+                if seen.insert(pending.tx.hash) {
+                    by_block.entry(head + 1).or_default().push(pending.clone());
+                }
+            }
+        }
+    }
+    by_block
+}
+
+/// Returns the searcher contract of every victim that was really sandwiched in the block.
+///
+/// # Parameters
+/// * `block`: &Block<Transaction> - The mined block.
+/// * `receipts`: &[TransactionReceipt] - Its receipts.
+///
+/// # Returns
+/// * `HashMap<H256, H160>` - Searcher contract by victim tx hash.
+pub fn real_victims(
+    block: &Block<Transaction>,
+    receipts: &[TransactionReceipt],
+) -> HashMap<H256, H160> {
+    detect_sandwiches(&block.transactions, receipts)
+        .into_iter()
+        .map(|s| (s.victim, s.contract))
+        .collect()
+}
+
+/// What happened to the victim of a backtested opportunity in the real block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Outcome {
+    /// The victim landed and nobody sandwiched it.
+    Unclaimed,
+    /// The victim landed inside someone else's sandwich.
+    Sandwiched(H160),
+    /// The victim did not land in the block.
+    NotIncluded,
+}
+
+impl Outcome {
+    /// Classifies a victim against the real block.
+    pub fn of(victim: H256, included: &HashSet<H256>, real: &HashMap<H256, H160>) -> Self {
+        match real.get(&victim) {
+            Some(contract) => Self::Sandwiched(*contract),
+            None if included.contains(&victim) => Self::Unclaimed,
+            None => Self::NotIncluded,
+        }
+    }
+}
+
+/// A sandwich the strategy would have sent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Opportunity {
+    /// Block the bundle would have targeted.
+    pub block_number: u64,
+    pub victim: H256,
+    pub pair: H160,
+    pub main_currency: H160,
+    pub amount_in: U256,
+    /// Simulated revenue, before gas and bribe.
+    pub revenue: i128,
+    /// Revenue expected once contending pending swaps are accounted for.
+    pub risk_adjusted_revenue: i128,
+    pub gas_cost: i128,
+    pub bribe: U256,
+    /// Risk adjusted revenue minus the bribe.
+    pub profit: i128,
+    pub outcome: Outcome,
+}
+
+/// Backtest results of one strategy version.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BacktestReport {
+    pub label: String,
+    pub blocks: u64,
+    /// Candidate transactions that touched a known pool.
+    pub swaps: u64,
+    pub opportunities: Vec<Opportunity>,
+    /// Victims sandwiched by someone in the real blocks.
+    pub real_sandwiches: u64,
+}
+
+impl BacktestReport {
+    pub fn new(label: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Sum of the opportunities' profit after bribes.
+    pub fn total_profit(&self) -> i128 {
+        self.opportunities.iter().map(|o| o.profit).sum()
+    }
+
+    /// Sum of the opportunities' simulated revenue.
+    pub fn total_revenue(&self) -> i128 {
+        self.opportunities.iter().map(|o| o.revenue).sum()
+    }
+
+    /// Opportunities whose victim was really sandwiched by someone else.
+    pub fn overlap(&self) -> usize {
+        self.opportunities
+            .iter()
+            .filter(|o| matches!(o.outcome, Outcome::Sandwiched(_)))
+            .count()
+    }
+
+    /// Opportunities whose victim landed without anybody sandwiching it.
+    pub fn unclaimed(&self) -> usize {
+        self.opportunities
+            .iter()
+            .filter(|o| o.outcome == Outcome::Unclaimed)
+            .count()
+    }
+
+    /// One line summary for logs.
+    pub fn summary(&self) -> String {
+        format!(
+            "[{}] blocks {} / swaps {} / opportunities {} / revenue {} / profit {} / \
+             overlap {} of {} real sandwiches / unclaimed {}",
+            self.label,
+            self.blocks,
+            self.swaps,
+            self.opportunities.len(),
+            self.total_revenue(),
+            self.total_profit(),
+            self.overlap(),
+            self.real_sandwiches,
+            self.unclaimed(),
+        )
+    }
+}
+
+/// Replays blocks through the sandwich pipeline for several strategy versions.
+pub struct Backtester {
+    provider: Arc<Provider<Ws>>,
+    pools_map: HashMap<H160, Pool>,
+    source: VictimSource,
+    fixtures_dir: Option<PathBuf>,
+    reports: Vec<(StrategyVersion, BacktestReport)>,
+}
+
+impl Backtester {
+    /// Creates a backtester.
+    ///
+    /// # Parameters
+    /// * `provider`: Arc<Provider<Ws>> - Provider with archive state for the backtested range.
+    /// * `pools_map`: HashMap<H160, Pool> - Known pools.
+    /// * `source`: VictimSource - Where candidate victims come from.
+    /// * `versions`: Vec<StrategyVersion> - Strategy versions to compare.
+    pub fn new(
+        provider: Arc<Provider<Ws>>,
+        pools_map: HashMap<H160, Pool>,
+        source: VictimSource,
+        versions: Vec<StrategyVersion>,
+    ) -> Self {
+        let reports = versions
+            .into_iter()
+            .map(|v| {
+                let report = BacktestReport::new(&v.label);
+                (v, report)
+            })
+            .collect();
+        Self {
+            provider,
+            pools_map,
+            source,
+            fixtures_dir: None,
+            reports,
+        }
+    }
+
+    /// Reads blocks from `<dir>/<number>.json` fixtures (as recorded by `sandooo-inspect
+    /// --record`) when present, instead of fetching them.
+    pub fn with_fixtures<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.fixtures_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    async fn fixture(&self, number: u64) -> Result<BlockFixture> {
+        if let Some(dir) = &self.fixtures_dir {
+            let path = dir.join(format!("{}.json", number));
+            if path.exists() {
+                return BlockFixture::load(path);
+            }
+        }
+        BlockFixture::fetch(&self.provider, number).await
+    }
+
+    fn candidates(&self, fixture: &BlockFixture) -> Vec<NewPendingTx> {
+        match &self.source {
+            VictimSource::MinedBlocks => {
+                // the legs of real sandwiches are swaps too, keep their victims only
+                let legs: HashSet<H256> =
+                    detect_sandwiches(&fixture.block.transactions, &fixture.receipts)
+                        .into_iter()
+                        .flat_map(|s| [s.frontrun, s.backrun])
+                        .collect();
+                fixture
+                    .block
+                    .transactions
+                    .iter()
+                    .filter(|tx| tx.to.is_some() && !legs.contains(&tx.hash))
+                    .map(|tx| NewPendingTx {
+                        tx: tx.clone(),
+                        added_block: None,
+                    })
+                    .collect()
+            }
+            VictimSource::Recorded(by_block) => by_block
+                .get(&fixture.block_number())
+                .cloned()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Backtests one block: every version sees the same candidates and promising sandwiches.
+    ///
+    /// # Parameters
+    /// * `number`: u64 - The mined block to backtest (simulations fork at its parent).
+    pub async fn run_block(&mut self, number: u64) -> Result<()> {
+        let fixture = self.fixture(number).await?;
+        let block = &fixture.block;
+        let next_block = NextBlockEnv::of_block(block)?;
+        // the base fee of the mined block is known, no need to predict it
+        let new_block = NewBlock {
+            block_number: next_block.parent_number(),
+            base_fee: next_block.base_fee,
+            next_base_fee: next_block.base_fee,
+            next_block,
+        };
+
+        let real = real_victims(block, &fixture.receipts);
+        let included: HashSet<H256> = block.transactions.iter().map(|tx| tx.hash).collect();
+
+        let mut pending_txs: HashMap<H256, PendingTxInfo> = HashMap::new();
+        let mut victims = Vec::new();
+        for mut pending_tx in self.candidates(&fixture) {
+            let victim_gas_price = match pending_tx.tx.transaction_type {
+                Some(tx_type) if tx_type == U64::from(2) => {
+                    pending_tx.tx.max_fee_per_gas.unwrap_or_default()
+                }
+                _ => pending_tx.tx.gas_price.unwrap_or_default(),
+            };
+            if victim_gas_price < new_block.next_base_fee {
+                continue;
+            }
+            let swap_info =
+                match extract_swap_info(&self.provider, &new_block, &pending_tx, &self.pools_map)
+                    .await
+                {
+                    Ok(swap_info) if !swap_info.is_empty() => swap_info,
+                    Ok(_) => continue,
+                    Err(e) => {
+                        warn!("extract_swap_info error: {e:?}");
+                        continue;
+                    }
+                };
+            pending_tx.added_block = Some(new_block.block_number);
+            let tx_hash = pending_tx.tx.hash;
+            pending_txs.insert(
+                tx_hash,
+                PendingTxInfo {
+                    pending_tx,
+                    touched_pairs: swap_info,
+                },
+            );
+            victims.push((tx_hash, victim_gas_price));
+        }
+
+        let mut promising_sandwiches: HashMap<H256, Vec<Sandwich>> = HashMap::new();
+        for (tx_hash, victim_gas_price) in &victims {
+            if let Err(e) = appetizer(
+                &self.provider,
+                &new_block,
+                *tx_hash,
+                *victim_gas_price,
+                &pending_txs,
+                &mut promising_sandwiches,
+            )
+            .await
+            {
+                warn!("appetizer error: {e:?}");
+            }
+        }
+
+        let mut reports = std::mem::take(&mut self.reports);
+        for (version, report) in reports.iter_mut() {
+            report.blocks += 1;
+            report.swaps += victims.len() as u64;
+            report.real_sandwiches += real.len() as u64;
+            for (tx_hash, sandwiches) in &promising_sandwiches {
+                for sandwich in sandwiches {
+                    match self
+                        .price(version, &new_block, sandwich, &pending_txs)
+                        .await
+                    {
+                        Ok(Some(mut opportunity)) => {
+                            opportunity.outcome = Outcome::of(*tx_hash, &included, &real);
+                            report.opportunities.push(opportunity);
+                        }
+                        Ok(None) => {}
+                        Err(e) => warn!("[{}] pricing error: {e:?}", version.label),
+                    }
+                }
+            }
+        }
+        self.reports = reports;
+
+        info!(
+            "Block {}: {} swaps / {} promising / {} real sandwiches",
+            number,
+            victims.len(),
+            promising_sandwiches.len(),
+            real.len()
+        );
+        Ok(())
+    }
+
+    /// Prices an optimized sandwich like the main dish: simulates the bundle, bribes on the
+    /// contention-adjusted revenue, and returns None where the main dish wouldn't send it.
+    async fn price(
+        &self,
+        version: &StrategyVersion,
+        new_block: &NewBlock,
+        sandwich: &Sandwich,
+        pending_txs: &HashMap<H256, PendingTxInfo>,
+    ) -> Result<Option<Opportunity>> {
+        let optimized = match sandwich.optimized_sandwich.as_ref() {
+            Some(optimized) => optimized,
+            None => return Ok(None),
+        };
+        let swap_info = &sandwich.swap_info;
+        let mut final_sandwich = sandwich.clone();
+        final_sandwich.amount_in = optimized.amount_in;
+        final_sandwich.optimized_sandwich = None;
+
+        let mut batch = match version.contract {
+            SandoContract::Sandooo => BatchSandwich::new(H160::zero()),
+            SandoContract::SandoooV3 => BatchSandwich::new(swap_info.main_currency),
+        };
+        batch.swap_info_vec.push(swap_info.clone());
+        batch.sandwiches.push(final_sandwich);
+
+        let base_fee = new_block.next_base_fee;
+        let max_fee = base_fee;
+        let simulated = batch
+            .simulate_with(
+                version.contract,
+                self.provider.clone(),
+                None,
+                &new_block.next_block,
+                base_fee,
+                max_fee,
+                None,
+                None,
+                None,
+            )
+            .await?;
+        if !simulated.errors.is_empty() || simulated.revenue <= 0 {
+            return Ok(None);
+        }
+
+        let revenue = risk_adjusted_revenue(
+            &batch,
+            version.contract,
+            &self.provider,
+            None,
+            &new_block.next_block,
+            base_fee,
+            max_fee,
+            None,
+            &simulated,
+            pending_txs,
+            version.bribe_pct,
+            &get_competitor_db(),
+        )
+        .await;
+        if revenue <= 0 {
+            return Ok(None);
+        }
+        let bribe = U256::from(revenue) * version.bribe_pct / U256::from(10000);
+
+        Ok(Some(Opportunity {
+            block_number: new_block.next_block.number.as_u64(),
+            victim: swap_info.tx_hash,
+            pair: swap_info.target_pair,
+            main_currency: swap_info.main_currency,
+            amount_in: optimized.amount_in,
+            revenue: simulated.revenue,
+            risk_adjusted_revenue: revenue,
+            gas_cost: simulated.gas_cost,
+            bribe,
+            profit: revenue - bribe.as_u128() as i128,
+            outcome: Outcome::NotIncluded,
+        }))
+    }
+
+    /// Backtests an inclusive range of blocks; blocks that fail to load are skipped.
+    ///
+    /// # Parameters
+    /// * `from_block`: u64 - First block.
+    /// * `to_block`: u64 - Last block.
+    ///
+    /// # Returns
+    /// * `Vec<BacktestReport>` - One report per strategy version, in the order given.
+    pub async fn run(mut self, from_block: u64, to_block: u64) -> Vec<BacktestReport> {
+        for number in from_block..=to_block {
+            if let Err(e) = self.run_block(number).await {
+                warn!("Block {} skipped: {e:?}", number);
+            }
+        }
+        self.reports.into_iter().map(|(_, report)| report).collect()
+    }
+}
+
+/// Writes the reports to a JSON file.
+pub fn write_reports<P: AsRef<Path>>(path: P, reports: &[BacktestReport]) -> Result<()> {
+    let file = std::fs::File::create(path)?;
+    serde_json::to_writer_pretty(std::io::BufWriter::new(file), reports)?;
+    Ok(())
+}
@@ -0,0 +1,361 @@
+/// Mempool-aware sandwich simulation.
+///
+/// Our legs are encoded against the reserves at the fork, but other pending swaps on the same
+/// pools (including competitors' frontruns) may be included before our bundle. `PoolContention`
+/// collects those swaps from `pending_txs`, flags the ones sent by competing searchers,
+/// re-simulates the sandwich with each one landing first, and weights the outcomes into a
+/// risk-adjusted revenue used to size the bribe.
+use ethers::providers::{Provider, Ws};
+use ethers::types::{transaction::eip2930::AccessList, H160, H256, U256};
+use log::{debug, info};
+use std::{collections::HashMap, sync::Arc};
+
+use crate::common::competitors::{is_pegged_above, CompetitorDb};
+use crate::common::constants::{
+    BASIS_POINTS_DIVISOR, COMPETITOR_AHEAD_BPS, COMPETITOR_BEHIND_BPS, CONTENTION_AHEAD_BPS,
+    CONTENTION_BEHIND_BPS, MAX_CONTENDING_SWAPS,
+};
+use crate::common::evm::{NextBlockEnv, Tx, VictimTx};
+use crate::common::routers::is_known_router;
+use crate::common::sim_error::LegError;
+use crate::common::streams::NewPendingTx;
+use crate::sandwich::simulation::{BatchSandwich, PendingTxInfo, SandoContract, SimulatedSandwich};
+
+/// Another pending swap on one of our target pools.
+#[derive(Debug, Clone)]
+pub struct ContendingSwap {
+    /// Hash of the pending transaction.
+    pub tx_hash: H256,
+    /// Target pool the swap touches.
+    pub pair: H160,
+    /// Priority fee the transaction pays on top of the next base fee.
+    pub priority_fee: U256,
+    /// Sent to a known searcher contract, or priced just above one of our victims.
+    pub competitor: bool,
+    /// The transaction, ready to simulate.
+    pub tx: Tx,
+}
+
+/// Pending swaps competing with a sandwich for the same pools, highest priority fee first.
+#[derive(Debug, Clone, Default)]
+pub struct PoolContention {
+    pub swaps: Vec<ContendingSwap>,
+}
+
+/// Outcome of the sandwich when one contending swap lands before it.
+#[derive(Debug, Clone)]
+pub struct ContentionScenario {
+    /// The swap landing first.
+    pub tx_hash: H256,
+    /// Chance of that happening, in basis points.
+    pub probability_bps: u64,
+    /// Revenue of our bundle (0 if any of its transactions fail, since it won't be included).
+    pub revenue: i128,
+    /// The failure that made the bundle worthless, if any.
+    pub error: Option<LegError>,
+}
+
+/// Risk-adjusted outcome of a sandwich under mempool contention.
+#[derive(Debug, Clone, Default)]
+pub struct ContentionReport {
+    /// Revenue if no contending swap lands before us.
+    pub baseline_revenue: i128,
+    /// One scenario per simulated contending swap.
+    pub scenarios: Vec<ContentionScenario>,
+    /// Baseline revenue adjusted by the probability-weighted loss of every scenario.
+    pub expected_revenue: i128,
+    /// Lowest revenue over the baseline and all scenarios.
+    pub worst_revenue: i128,
+    /// Number of contending swaps flagged as competitors.
+    pub competitors: usize,
+}
+
+/// Returns the priority fee a pending transaction pays at `base_fee`.
+///
+/// # Parameters
+/// * `pending_tx`: &NewPendingTx - The pending transaction.
+/// * `base_fee`: U256 - Base fee of the block it would be included in.
+///
+/// # Returns
+/// * `U256` - Effective priority fee per gas (0 if it can't pay the base fee).
+pub fn effective_priority_fee(pending_tx: &NewPendingTx, base_fee: U256) -> U256 {
+    let tx = &pending_tx.tx;
+    match (tx.max_fee_per_gas, tx.max_priority_fee_per_gas) {
+        (Some(max_fee), Some(priority_fee)) => {
+            std::cmp::min(priority_fee, max_fee.saturating_sub(base_fee))
+        }
+        _ => tx.gas_price.unwrap_or_default().saturating_sub(base_fee),
+    }
+}
+
+impl PoolContention {
+    /// Collects the pending swaps touching the pools of a sandwich.
+    ///
+    /// # Parameters
+    /// * `batch_sandwich`: &BatchSandwich - The sandwich we want to send.
+    /// * `pending_txs`: &HashMap<H256, PendingTxInfo> - Map of all pending transactions.
+    /// * `base_fee`: U256 - Base fee of the next block.
+    /// * `competitor_db`: &CompetitorDb - Known searcher contracts.
+    ///
+    /// # Returns
+    /// * `PoolContention` - Other swaps on our pools, highest priority fee first.
+    pub fn new(
+        batch_sandwich: &BatchSandwich,
+        pending_txs: &HashMap<H256, PendingTxInfo>,
+        base_fee: U256,
+        competitor_db: &CompetitorDb,
+    ) -> Self {
+        let target_pairs = batch_sandwich.target_pairs();
+        let victim_tx_hashes = batch_sandwich.victim_tx_hashes();
+        let victim_gas_prices: Vec<U256> = batch_sandwich
+            .sandwiches
+            .iter()
+            .map(|sandwich| sandwich.victim_tx.gas_price)
+            .collect();
+
+        let mut swaps = Vec::new();
+        for (tx_hash, info) in pending_txs {
+            if victim_tx_hashes.contains(tx_hash) {
+                continue;
+            }
+            let pair = match info
+                .touched_pairs
+                .iter()
+                .find(|swap_info| target_pairs.contains(&swap_info.target_pair))
+            {
+                Some(swap_info) => swap_info.target_pair,
+                None => continue,
+            };
+            let pending_tx = &info.pending_tx.tx;
+            let to = pending_tx.to.unwrap_or_default();
+            let gas_price = pending_tx
+                .max_fee_per_gas
+                .or(pending_tx.gas_price)
+                .unwrap_or_default();
+            let competitor = competitor_db.is_competitor(pending_tx)
+                || (!is_known_router(&to)
+                    && victim_gas_prices
+                        .iter()
+                        .any(|victim_gas_price| is_pegged_above(gas_price, *victim_gas_price)));
+            let victim_tx = VictimTx {
+                tx_hash: *tx_hash,
+                from: pending_tx.from,
+                to,
+                data: pending_tx.input.0.clone().into(),
+                value: pending_tx.value,
+                gas_price: base_fee,
+                gas_limit: Some(pending_tx.gas.as_u64()),
+            };
+            swaps.push(ContendingSwap {
+                tx_hash: *tx_hash,
+                pair,
+                priority_fee: effective_priority_fee(&info.pending_tx, base_fee),
+                competitor,
+                tx: Tx::from(victim_tx),
+            });
+        }
+
+        // highest priority fee first, hash order for ties so the result is deterministic
+        swaps.sort_by(|a, b| {
+            b.priority_fee
+                .cmp(&a.priority_fee)
+                .then(a.tx_hash.cmp(&b.tx_hash))
+        });
+
+        Self { swaps }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.swaps.is_empty()
+    }
+
+    /// Returns the contending swaps flagged as competitors.
+    pub fn competitors(&self) -> impl Iterator<Item = &ContendingSwap> {
+        self.swaps.iter().filter(|swap| swap.competitor)
+    }
+
+    /// Chance (in basis points) that a swap is included before a bundle paying `our_priority_fee`.
+    pub fn ahead_probability_bps(swap: &ContendingSwap, our_priority_fee: U256) -> u64 {
+        match (swap.competitor, swap.priority_fee >= our_priority_fee) {
+            (true, true) => COMPETITOR_AHEAD_BPS,
+            (true, false) => COMPETITOR_BEHIND_BPS,
+            (false, true) => CONTENTION_AHEAD_BPS,
+            (false, false) => CONTENTION_BEHIND_BPS,
+        }
+    }
+
+    /// Simulates the sandwich with each of the top contending swaps landing first.
+    ///
+    /// Scenarios are evaluated one swap at a time; the expected revenue subtracts each
+    /// scenario's loss weighted by its probability and is floored at the worst scenario.
+    ///
+    /// # Parameters
+    /// * `batch_sandwich`: &BatchSandwich - The sandwich to simulate.
+    /// * `contract`: SandoContract - The contract the legs go through.
+    /// * `provider`: Arc<Provider<Ws>> - Provider to fork from.
+    /// * `owner`: Option<H160> - Bot owner (None to simulate with a fresh wallet).
+    /// * `next_block`: &NextBlockEnv - Block the bundle targets.
+    /// * `base_fee`: U256 - Base fee of the next block.
+    /// * `max_fee`: U256 - Max fee of the backrun.
+    /// * `front_access_list`, `back_access_list`: AccessList - Access lists of the baseline.
+    /// * `bot_address`: Option<H160> - Deployed bot (None to deploy one in the fork).
+    /// * `baseline`: &SimulatedSandwich - Simulation without contention.
+    /// * `our_priority_fee`: U256 - Priority fee our bundle pays.
+    ///
+    /// # Returns
+    /// * `ContentionReport` - Per-scenario and risk-adjusted revenues.
+    pub async fn simulate(
+        &self,
+        batch_sandwich: &BatchSandwich,
+        contract: SandoContract,
+        provider: Arc<Provider<Ws>>,
+        owner: Option<H160>,
+        next_block: &NextBlockEnv,
+        base_fee: U256,
+        max_fee: U256,
+        front_access_list: AccessList,
+        back_access_list: AccessList,
+        bot_address: Option<H160>,
+        baseline: &SimulatedSandwich,
+        our_priority_fee: U256,
+    ) -> ContentionReport {
+        let baseline_revenue = if baseline.errors.is_empty() {
+            baseline.revenue
+        } else {
+            0
+        };
+
+        let mut scenarios = Vec::new();
+        // competitors first, they are the swaps most likely to front us
+        let mut swaps: Vec<&ContendingSwap> = self.competitors().collect();
+        swaps.extend(self.swaps.iter().filter(|swap| !swap.competitor));
+        for swap in swaps.into_iter().take(MAX_CONTENDING_SWAPS) {
+            let simulated = batch_sandwich
+                .simulate_after(
+                    contract,
+                    provider.clone(),
+                    owner,
+                    next_block,
+                    base_fee,
+                    max_fee,
+                    Some(front_access_list.clone()),
+                    Some(back_access_list.clone()),
+                    bot_address,
+                    &[swap.tx.clone()],
+                )
+                .await;
+            let (revenue, error) = match simulated {
+                Ok(simulated) => match simulated.errors.first() {
+                    Some(error) => (0, Some(error.clone())),
+                    None => (simulated.revenue, None),
+                },
+                Err(e) => {
+                    debug!("contention scenario {:?} failed: {e:?}", swap.tx_hash);
+                    (0, None)
+                }
+            };
+            scenarios.push(ContentionScenario {
+                tx_hash: swap.tx_hash,
+                probability_bps: Self::ahead_probability_bps(swap, our_priority_fee),
+                revenue,
+                error,
+            });
+        }
+
+        let worst_revenue = scenarios
+            .iter()
+            .map(|scenario| scenario.revenue)
+            .fold(baseline_revenue, i128::min);
+        let mut expected_revenue = baseline_revenue;
+        for scenario in &scenarios {
+            let loss = baseline_revenue - scenario.revenue;
+            expected_revenue -= loss * scenario.probability_bps as i128 / BASIS_POINTS_DIVISOR as i128;
+        }
+
+        ContentionReport {
+            baseline_revenue,
+            scenarios,
+            expected_revenue: std::cmp::max(expected_revenue, worst_revenue),
+            worst_revenue,
+            competitors: self.competitors().count(),
+        }
+    }
+}
+
+/// Revenue of a sandwich after accounting for the other pending swaps on its pools.
+///
+/// The bribe we'd pay on the baseline revenue sets our priority fee, which decides how likely
+/// each contending swap is to land before us.
+///
+/// # Parameters
+/// * `batch_sandwich`: &BatchSandwich - The sandwich to simulate.
+/// * `contract`: SandoContract - The contract the legs go through.
+/// * `provider`: &Arc<Provider<Ws>> - Provider to fork from.
+/// * `owner`: Option<H160> - Bot owner (None to simulate with a fresh wallet).
+/// * `next_block`: &NextBlockEnv - Block the bundle targets.
+/// * `base_fee`: U256 - Base fee of the next block.
+/// * `max_fee`: U256 - Max fee of the backrun.
+/// * `bot_address`: Option<H160> - Deployed bot (None to deploy one in the fork).
+/// * `baseline`: &SimulatedSandwich - Simulation without contention.
+/// * `pending_txs`: &HashMap<H256, PendingTxInfo> - Map of all pending transactions.
+/// * `bribe_pct`: U256 - Share of the revenue paid as bribe, in basis points.
+/// * `competitor_db`: &CompetitorDb - Known searcher contracts.
+///
+/// # Returns
+/// * `i128` - Risk-adjusted revenue (the baseline revenue if nothing contends).
+pub async fn risk_adjusted_revenue(
+    batch_sandwich: &BatchSandwich,
+    contract: SandoContract,
+    provider: &Arc<Provider<Ws>>,
+    owner: Option<H160>,
+    next_block: &NextBlockEnv,
+    base_fee: U256,
+    max_fee: U256,
+    bot_address: Option<H160>,
+    baseline: &SimulatedSandwich,
+    pending_txs: &HashMap<H256, PendingTxInfo>,
+    bribe_pct: U256,
+    competitor_db: &CompetitorDb,
+) -> i128 {
+    let contention = PoolContention::new(batch_sandwich, pending_txs, base_fee, competitor_db);
+    if contention.is_empty() || baseline.revenue <= 0 {
+        return baseline.revenue;
+    }
+
+    let bribe = U256::from(baseline.revenue) * bribe_pct / U256::from(BASIS_POINTS_DIVISOR);
+    let back_gas = std::cmp::max(baseline.back_gas_used * 105 / 100, 1);
+    let our_priority_fee = bribe / U256::from(back_gas);
+
+    let report = contention
+        .simulate(
+            batch_sandwich,
+            contract,
+            provider.clone(),
+            owner,
+            next_block,
+            base_fee,
+            max_fee,
+            baseline.front_access_list.clone(),
+            baseline.back_access_list.clone(),
+            bot_address,
+            baseline,
+            our_priority_fee,
+        )
+        .await;
+    info!(
+        "> Contention: {} pending swaps ({} competitors) / revenue {} / expected {} / worst {}",
+        contention.swaps.len(),
+        report.competitors,
+        report.baseline_revenue,
+        report.expected_revenue,
+        report.worst_revenue
+    );
+    for scenario in &report.scenarios {
+        debug!(
+            "  {:?} ahead ({} bps): revenue {} {:?}",
+            scenario.tx_hash, scenario.probability_bps, scenario.revenue, scenario.error
+        );
+    }
+
+    report.expected_revenue
+}
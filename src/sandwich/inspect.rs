@@ -0,0 +1,272 @@
+/// Historical sandwich inspection.
+///
+/// Finds the sandwiches of mined blocks with `detect_sandwiches`, prices them from the pools'
+/// `Swap` logs and the pool map, and measures what each one paid the block's coinbase, either
+/// from receipts alone (priority fees) or by replaying the block prefix in revm (direct
+/// transfers). Used by the `sandooo-inspect` binary to calibrate our bidding.
+use anyhow::{anyhow, Result};
+use ethers::providers::Middleware;
+use ethers::types::{Block, Transaction, TransactionReceipt, H160, H256, I256, U256};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::common::competitors::detect_sandwiches;
+use crate::common::evm::{EvmSimulator, NextBlockEnv};
+use crate::common::pools::Pool;
+use crate::common::trace::{TracedLog, TracedLogKind};
+
+/// A mined block with its receipts, as recorded with `sandooo-inspect --record`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockFixture {
+    pub block: Block<Transaction>,
+    pub receipts: Vec<TransactionReceipt>,
+}
+
+impl BlockFixture {
+    /// Fetches a block and its receipts.
+    pub async fn fetch<M: Middleware + 'static>(provider: &Arc<M>, number: u64) -> Result<Self> {
+        let block = provider
+            .get_block_with_txs(number)
+            .await
+            .map_err(|e| anyhow!("Failed to fetch block {}: {:?}", number, e))?
+            .ok_or_else(|| anyhow!("Block {} not found", number))?;
+        let receipts = provider
+            .get_block_receipts(number)
+            .await
+            .map_err(|e| anyhow!("Failed to fetch receipts of {}: {:?}", number, e))?;
+        Ok(Self { block, receipts })
+    }
+
+    /// Loads a fixture from a JSON file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(path)?;
+        Ok(serde_json::from_reader(BufReader::new(file))?)
+    }
+
+    /// Saves the fixture to a JSON file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let file = File::create(path)?;
+        serde_json::to_writer(BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    pub fn block_number(&self) -> u64 {
+        self.block.number.unwrap_or_default().as_u64()
+    }
+}
+
+/// A sandwich found in a mined block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InspectedSandwich {
+    pub block_number: u64,
+    /// EOA that sent the frontrun.
+    pub searcher: H160,
+    /// Contract both legs went through.
+    pub contract: H160,
+    pub pool: H160,
+    pub frontrun: H256,
+    pub victim: H256,
+    pub backrun: H256,
+    /// Token the frontrun paid into the pool (the token profit is measured in).
+    pub profit_token: H160,
+    /// Backrun output minus frontrun input of `profit_token`, before gas.
+    pub profit: I256,
+    /// Token the frontrun bought.
+    pub target_token: H160,
+    /// Frontrun output minus backrun input of `target_token` (left in the searcher's inventory).
+    pub inventory_delta: I256,
+    /// Priority fees of both legs.
+    pub priority_fees: U256,
+    /// ETH both legs transferred to the coinbase directly (0 unless replayed).
+    pub coinbase_transfers: U256,
+}
+
+impl InspectedSandwich {
+    /// Total payment of both legs to the block's coinbase.
+    pub fn coinbase_payment(&self) -> U256 {
+        self.priority_fees + self.coinbase_transfers
+    }
+}
+
+/// Returns the net amounts the transaction paid into `pool`, summed over its swaps.
+fn pool_deltas(receipt: &TransactionReceipt, pool: H160) -> (I256, I256) {
+    receipt
+        .logs
+        .iter()
+        .filter(|log| log.address == pool)
+        .filter_map(|log| {
+            let kind = TracedLogKind::from_topic(log.topics.first()?)?;
+            TracedLog {
+                kind,
+                address: log.address,
+                topics: log.topics.clone(),
+                data: log.data.clone(),
+            }
+            .pool_deltas()
+        })
+        .fold((I256::zero(), I256::zero()), |(a0, a1), (d0, d1)| {
+            (a0 + d0, a1 + d1)
+        })
+}
+
+fn priority_fee(receipt: &TransactionReceipt, base_fee: U256) -> U256 {
+    let gas_price = receipt.effective_gas_price.unwrap_or_default();
+    receipt.gas_used.unwrap_or_default() * gas_price.saturating_sub(base_fee)
+}
+
+/// Finds and prices the sandwiches of a mined block.
+///
+/// Only sandwiches on pools of `pools_map` are reported, since V2 `Swap` logs don't name the
+/// tokens. `coinbase_transfers` is left at 0; see `replay_coinbase_transfers`.
+///
+/// # Parameters
+/// * `block`: &Block<Transaction> - The mined block with its transactions.
+/// * `receipts`: &[TransactionReceipt] - Its receipts.
+/// * `pools_map`: &HashMap<H160, Pool> - Known pools.
+///
+/// # Returns
+/// * `Vec<InspectedSandwich>` - Sandwiches in block order.
+pub fn inspect_block(
+    block: &Block<Transaction>,
+    receipts: &[TransactionReceipt],
+    pools_map: &HashMap<H160, Pool>,
+) -> Vec<InspectedSandwich> {
+    let block_number = block.number.unwrap_or_default().as_u64();
+    let base_fee = block.base_fee_per_gas.unwrap_or_default();
+    let receipts_map: HashMap<H256, &TransactionReceipt> =
+        receipts.iter().map(|r| (r.transaction_hash, r)).collect();
+
+    let mut sandwiches = Vec::new();
+    for detected in detect_sandwiches(&block.transactions, receipts) {
+        let pool = match pools_map.get(&detected.pool) {
+            Some(pool) => pool,
+            None => continue,
+        };
+        let (front, back) = match (
+            receipts_map.get(&detected.frontrun),
+            receipts_map.get(&detected.backrun),
+        ) {
+            (Some(front), Some(back)) => (*front, *back),
+            _ => continue,
+        };
+
+        let (front0, front1) = pool_deltas(front, pool.address);
+        let (back0, back1) = pool_deltas(back, pool.address);
+        // profit is what the pool paid back minus what was put in, in the frontrun's input token
+        let (profit_token, profit, target_token, inventory_delta) = if front0 > I256::zero() {
+            (pool.token0, -(front0 + back0), pool.token1, -(front1 + back1))
+        } else {
+            (pool.token1, -(front1 + back1), pool.token0, -(front0 + back0))
+        };
+
+        sandwiches.push(InspectedSandwich {
+            block_number,
+            searcher: detected.searcher,
+            contract: detected.contract,
+            pool: pool.address,
+            frontrun: detected.frontrun,
+            victim: detected.victim,
+            backrun: detected.backrun,
+            profit_token,
+            profit,
+            target_token,
+            inventory_delta,
+            priority_fees: priority_fee(front, base_fee) + priority_fee(back, base_fee),
+            coinbase_transfers: U256::zero(),
+        });
+    }
+    sandwiches
+}
+
+/// Measures the direct coinbase transfers of each sandwich by replaying the block in revm.
+///
+/// Forks at the parent block and replays every transaction up to the last backrun with
+/// `simulate_bundle`, reading each leg's `eth_sent_to_coinbase`.
+///
+/// # Parameters
+/// * `provider`: Arc<M> - Provider to fork from (needs state at the parent block).
+/// * `block`: &Block<Transaction> - The mined block with its transactions.
+/// * `sandwiches`: &mut [InspectedSandwich] - Sandwiches of that block to update.
+///
+/// # Returns
+/// * `Result<()>` - Error if the block prefix could not be replayed.
+pub fn replay_coinbase_transfers<M: Middleware + 'static>(
+    provider: Arc<M>,
+    block: &Block<Transaction>,
+    sandwiches: &mut [InspectedSandwich],
+) -> Result<()> {
+    let index_of = |tx_hash: H256| block.transactions.iter().position(|tx| tx.hash == tx_hash);
+    let last = match sandwiches.iter().filter_map(|s| index_of(s.backrun)).max() {
+        Some(last) => last,
+        None => return Ok(()),
+    };
+
+    let env = NextBlockEnv::of_block(block)?;
+    let mut simulator = EvmSimulator::new(provider, None, &env);
+    let raw_txs: Vec<_> = block.transactions[..=last].iter().map(|tx| tx.rlp()).collect();
+    let replay = simulator.simulate_bundle(&raw_txs)?;
+
+    let sent_to_coinbase = |tx_hash: H256| {
+        index_of(tx_hash)
+            .and_then(|i| replay.results.get(i))
+            .map(|result| result.eth_sent_to_coinbase)
+            .unwrap_or_default()
+    };
+    for sandwich in sandwiches.iter_mut() {
+        sandwich.coinbase_transfers =
+            sent_to_coinbase(sandwich.frontrun) + sent_to_coinbase(sandwich.backrun);
+    }
+    Ok(())
+}
+
+/// Writes sandwiches to a CSV file.
+///
+/// # Parameters
+/// * `path`: P - Output file.
+/// * `sandwiches`: &[InspectedSandwich] - Sandwiches to write.
+///
+/// # Returns
+/// * `Result<()>` - Ok if successful.
+pub fn write_csv<P: AsRef<Path>>(path: P, sandwiches: &[InspectedSandwich]) -> Result<()> {
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record(&[
+        "block_number",
+        "searcher",
+        "contract",
+        "pool",
+        "frontrun",
+        "victim",
+        "backrun",
+        "profit_token",
+        "profit",
+        "target_token",
+        "inventory_delta",
+        "priority_fees",
+        "coinbase_transfers",
+        "coinbase_payment",
+    ])?;
+    for s in sandwiches {
+        writer.write_record(&[
+            s.block_number.to_string(),
+            format!("{:?}", s.searcher),
+            format!("{:?}", s.contract),
+            format!("{:?}", s.pool),
+            format!("{:?}", s.frontrun),
+            format!("{:?}", s.victim),
+            format!("{:?}", s.backrun),
+            format!("{:?}", s.profit_token),
+            s.profit.to_string(),
+            format!("{:?}", s.target_token),
+            s.inventory_delta.to_string(),
+            s.priority_fees.to_string(),
+            s.coinbase_transfers.to_string(),
+            s.coinbase_payment().to_string(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
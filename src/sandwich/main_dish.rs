@@ -1,22 +1,27 @@
 /// Sandwich attack execution and bundle submission (main dish phase).
 ///
 /// Handles scoring, selection, and execution of optimized sandwich attacks, including bundle construction and submission to relays.
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use bounded_vec_deque::BoundedVecDeque;
 use ethers::{
-    providers::{Provider, Ws},
-    types::{H160, H256, U256, U64},
+    providers::{Middleware, Provider, Ws},
+    types::{Bytes, H160, H256, U256},
 };
 use log::{info, warn};
-use std::str::FromStr;
 use std::{collections::HashMap, sync::Arc};
 
-use crate::common::alert::Alert;
+use crate::common::bundle_sim::BundleSimResult;
+use crate::common::competitors::get_competitor_db;
 use crate::common::constants::*;
-use crate::common::execution::{Executor, SandoBundle};
+use crate::common::engine::{Action, BundleSubmission};
+use crate::common::evm::{EvmSimulator, NextBlockEnv};
+use crate::common::execution::{signed_bundle_request, Executor, SandoBundle, SendBundleResponse};
+use crate::common::quote_assets::get_quote_assets;
 use crate::common::streams::NewBlock;
 use crate::common::utils::get_token_balance;
-use crate::sandwich::simulation::{BatchSandwich, PendingTxInfo, Sandwich};
+use crate::sandwich::contention::risk_adjusted_revenue;
+use crate::sandwich::packer::{confirm_packed, pack_sandwiches, to_batch, PackingLimits};
+use crate::sandwich::simulation::{PendingTxInfo, SandoContract, Sandwich};
 
 /// Queries token balances for a list of tokens and an owner address.
 ///
@@ -42,63 +47,131 @@ pub async fn get_token_balances(
     token_balances
 }
 
-/// Sends a sandwich bundle request to the relays.
+/// Simulates a signed sandwich bundle locally in the block it targets.
+///
+/// # Parameters
+/// * `simulator`: Simulator on the state and `NextBlockEnv` the bundle targets.
+/// * `signed_txs`: Signed bundle transactions.
+///
+/// # Returns
+/// * `Result<BundleSimResult>` - The simulation, or an error if any transaction failed.
+pub fn verify_sando_bundle<M: Middleware + 'static>(
+    simulator: &mut EvmSimulator<M>,
+    signed_txs: &[Bytes],
+) -> Result<BundleSimResult> {
+    let result = simulator.simulate_bundle(signed_txs)?;
+    if let Some(failed) = result.first_failure() {
+        return Err(anyhow!(
+            "Bundle tx {:?} failed: {:?} {:?}",
+            failed.tx_hash,
+            failed.output,
+            failed.error
+        ));
+    }
+    Ok(result)
+}
+
+/// Verifies a signed bundle in the block of every builder it is sent to.
+///
+/// Fees and bribes go to `block.coinbase`, so the bundle is simulated once per builder fee
+/// recipient (`builder_fee_recipient`). Builders without a known fee recipient keep
+/// `next_block.coinbase`.
+///
+/// # Parameters
+/// * `simulator`: Simulator on the state the bundle targets.
+/// * `signed_txs`: Signed bundle transactions.
+/// * `next_block`: Block the bundle targets.
+/// * `builders`: Names of the builders the bundle is sent to.
+///
+/// # Returns
+/// * `HashMap<String, BundleSimResult>` - The simulation of every builder the bundle passed for.
+pub fn verify_sando_bundle_for_builders<M: Middleware + 'static>(
+    simulator: &mut EvmSimulator<M>,
+    signed_txs: &[Bytes],
+    next_block: &NextBlockEnv,
+    builders: &[String],
+) -> HashMap<String, BundleSimResult> {
+    let mut by_coinbase: HashMap<H160, Vec<&String>> = HashMap::new();
+    for builder in builders {
+        let coinbase = builder_fee_recipient(builder).unwrap_or(next_block.coinbase);
+        by_coinbase.entry(coinbase).or_default().push(builder);
+    }
+
+    let mut verified = HashMap::new();
+    for (coinbase, builders) in by_coinbase {
+        let block = next_block.clone().with_coinbase(coinbase);
+        let mut builder_simulator = EvmSimulator::new_with_db(
+            simulator.provider.clone(),
+            Some(simulator.owner),
+            &block,
+            simulator.clone_db(),
+        );
+        match verify_sando_bundle(&mut builder_simulator, signed_txs) {
+            Ok(simulated) => {
+                for builder in builders {
+                    verified.insert(builder.clone(), simulated.clone());
+                }
+            }
+            Err(e) => warn!("Bundle fails for {:?} (coinbase {:?}): {e:?}", builders, coinbase),
+        }
+    }
+    verified
+}
+
+/// Verifies a sandwich bundle locally and sends it to the builders it passed for.
 ///
 /// # Parameters
 /// * `executor`: Transaction executor.
 /// * `sando_bundle`: Constructed sandwich bundle.
-/// * `block_number`: Block number for submission.
-/// * `alert`: Alert system for notifications.
+/// * `next_block`: Block the bundle targets.
 ///
 /// # Returns
-/// * `Result<()>` - Ok if successful.
+/// * `Result<HashMap<String, SendBundleResponse>>` - Builder responses by builder name.
 pub async fn send_sando_bundle_request(
     executor: &Executor,
     sando_bundle: SandoBundle,
-    block_number: U64,
-    alert: &Alert,
-) -> Result<()> {
-    let bundle_request = executor
-        .to_sando_bundle_request(sando_bundle, block_number, 1)
-        .await?;
-    // If you want to check the simulation results provided by Flashbots
-    // run the following code, but this will take something like 0.1 ~ 0.3 seconds
-    // executor.simulate_bundle(&bundle_request).await;
-    let response = executor.broadcast_bundle(bundle_request).await?;
-    info!("Bundle sent: {:?}", response);
-    match alert
-        .send(&format!("[{:?}] Bundle sent", block_number))
-        .await
-    {
-        _ => {}
+    next_block: &NextBlockEnv,
+) -> Result<HashMap<String, SendBundleResponse>> {
+    let block_number = next_block.parent_number();
+    // verify exactly what we sign and send those bytes, so a bundle that would fail on the
+    // builder is never sent
+    let signed_txs = executor.sign_sando_bundle(&sando_bundle).await?;
+    let mut simulator = EvmSimulator::new(executor.provider.clone(), None, next_block);
+    let builders: Vec<String> = executor.builder_urls.keys().cloned().collect();
+    let verified =
+        verify_sando_bundle_for_builders(&mut simulator, &signed_txs, next_block, &builders);
+    if verified.is_empty() {
+        return Err(anyhow!("Bundle failed verification for every builder"));
+    }
+    for (builder, simulated) in &verified {
+        info!(
+            "Bundle simulated for {}: gas used {:?} / coinbase diff {:?} / bundle gas price {:?}",
+            builder, simulated.total_gas_used, simulated.coinbase_diff, simulated.bundle_gas_price
+        );
     }
-    Ok(())
-}
 
-/// Ingredients for a sandwich attack, used in scoring and selection.
-#[derive(Debug, Clone)]
-pub struct Ingredients {
-    /// Transaction hash of the sandwich opportunity.
-    pub tx_hash: H256,
-    /// Pair address of the sandwich opportunity.
-    pub pair: H160,
-    /// Main currency address of the sandwich opportunity.
-    pub main_currency: H160,
-    /// Amount in of the sandwich opportunity.
-    pub amount_in: U256,
-    /// Maximum revenue of the sandwich opportunity.
-    pub max_revenue: U256,
-    /// Score of the sandwich opportunity.
-    pub score: f64,
-    /// Sandwich object containing the opportunity's details.
-    pub sandwich: Sandwich,
+    let builder_urls = executor
+        .builder_urls
+        .iter()
+        .filter(|(builder, _)| verified.contains_key(*builder))
+        .map(|(builder, url)| (builder.clone(), url.clone()))
+        .collect();
+    let bundle_request = signed_bundle_request(&signed_txs, block_number, 1);
+    let response = executor
+        .broadcast_bundle_to(bundle_request, &builder_urls)
+        .await?;
+    info!("Bundle sent: {:?}", response);
+    Ok(response)
 }
 
-/// Executes the main sandwich attack logic: sorts, scores, and submits bundles.
+/// Executes the main sandwich attack logic: packs the promising sandwiches into one bundle.
+///
+/// The packer picks the compatible set with the largest ETH revenue (no shared victim, pair or
+/// target token, within the bot's balances and the gas budget) and confirms it by simulating
+/// it as one `BatchSandwich`; the bribe is then set on that simulation.
 ///
 /// # Parameters
 /// * `provider`: Ethereum provider.
-/// * `alert`: Alert system for notifications.
 /// * `executor`: Transaction executor.
 /// * `new_block`: Current block info.
 /// * `owner`: Owner address.
@@ -109,10 +182,9 @@ pub struct Ingredients {
 /// * `pending_txs`: Map of all pending transactions.
 ///
 /// # Returns
-/// * `Result<()>` - Ok if successful.
+/// * `Result<Vec<Action>>` - The bundles to submit and their alerts.
 pub async fn main_dish(
     provider: &Arc<Provider<Ws>>,
-    alert: &Alert,
     executor: &Executor,
     new_block: &NewBlock,
     owner: H160,
@@ -121,257 +193,195 @@ pub async fn main_dish(
     promising_sandwiches: &HashMap<H256, Vec<Sandwich>>,
     simulated_bundle_ids: &mut BoundedVecDeque<String>,
     pending_txs: &HashMap<H256, PendingTxInfo>,
-) -> Result<()> {
+) -> Result<Vec<Action>> {
     let env = Env::new();
+    let mut actions = Vec::new();
 
-    let weth = H160::from_str(WETH).unwrap();
-    let usdt = H160::from_str(USDT).unwrap();
-    let usdc = H160::from_str(USDC).unwrap();
+    let quote_assets = get_quote_assets().addresses();
 
     let bot_balances = if env.debug {
         // assume you have infinite funds when debugging
         let mut bot_balances = HashMap::new();
-        bot_balances.insert(weth, U256::MAX);
-        bot_balances.insert(usdt, U256::MAX);
-        bot_balances.insert(usdc, U256::MAX);
+        for quote_asset in &quote_assets {
+            bot_balances.insert(*quote_asset, U256::MAX);
+        }
         bot_balances
     } else {
-        let bot_balances =
-            get_token_balances(&provider, bot_address, &vec![weth, usdt, usdc]).await;
+        let bot_balances = get_token_balances(&provider, bot_address, &quote_assets).await;
         bot_balances
     };
 
-    let mut plate = Vec::new();
-    for (promising_tx_hash, sandwiches) in promising_sandwiches {
-        for sandwich in sandwiches {
-            let optimized_sandwich = sandwich.optimized_sandwich.as_ref().unwrap();
-            let amount_in = optimized_sandwich.amount_in;
-            let max_revenue = optimized_sandwich.max_revenue;
-            let score = (max_revenue.as_u128() as f64) / (amount_in.as_u128() as f64);
-            let clean_sandwich = Sandwich {
-                amount_in,
-                swap_info: sandwich.swap_info.clone(),
-                victim_tx: sandwich.victim_tx.clone(),
-                optimized_sandwich: None,
-            };
-            let ingredients = Ingredients {
-                tx_hash: *promising_tx_hash,
-                pair: sandwich.swap_info.target_pair,
-                main_currency: sandwich.swap_info.main_currency,
-                amount_in,
-                max_revenue,
-                score,
-                sandwich: clean_sandwich,
-            };
-            plate.push(ingredients);
-        }
+    // a victim that is no longer pending can't go into the bundle
+    let promising_sandwiches: HashMap<H256, Vec<Sandwich>> = promising_sandwiches
+        .iter()
+        .filter(|(tx_hash, _)| pending_txs.contains_key(tx_hash))
+        .map(|(tx_hash, sandwiches)| (*tx_hash, sandwiches.clone()))
+        .collect();
+
+    // the most valuable compatible set, confirmed by simulating it as one bundle
+    let packed = pack_sandwiches(&promising_sandwiches, &PackingLimits::new(bot_balances));
+    if packed.is_empty() {
+        return Ok(actions);
     }
 
-    /*
-    [Multi-sandwich algorithm] Sorting by score.
-
-    Score is calculated as: revenue / amount_in
-    Sorting by score in descending order will place the sandwich opportunities with higer scores at the top
-    This is good because we want to invest in opportunities that have the greatest return over cost ratios
-
-    * Note:
-    Score for WETH pairs and USDT/USDC pairs will be different in scale.
-    USDT/USDC pairs will always have bigger scores, because amount_in is represented as stable amounts (decimals = 6)
-    and max_revenue is represented as WETH amount (decimals = 18)
-    However, this is good, because we can pick up stable sandwiches first (where there's less competition)
-
-    After we've go through all stable pair sandwiches, we next pick up WETH pairs by score order
-    */
-    plate.sort_by(|x, y| y.score.partial_cmp(&x.score).unwrap());
-
-    /*
-    Say you have: [sando1, sando2, sando3] on your plate
-    We then want to send bundles as such:
-    - <sando1>
-    - <sando1, sando2>
-    - <sando1, sando2, sando3>
-    3 bundles in total. This way you can optimize your profits.
-    However, if you have infinite funds, you can always group all of the sandwich opportunities.
-    */
-    for i in 0..plate.len() {
-        let mut balances = bot_balances.clone();
-        let mut sandwiches = Vec::new();
-        let mut all_swap_infos = Vec::new();
-
-        for j in 0..(i + 1) {
-            let ingredient = &plate[j];
-            let main_currency = ingredient.main_currency;
-            let balance = *balances.get(&main_currency).unwrap();
-            let optimized = ingredient.amount_in;
-            let amount_in = std::cmp::min(balance, optimized);
-
-            let mut final_sandwich = ingredient.sandwich.clone();
-            final_sandwich.amount_in = amount_in;
-
-            let new_balance = balance - amount_in;
-            balances.insert(main_currency, new_balance);
-
-            sandwiches.push(final_sandwich);
-            all_swap_infos.push(ingredient.sandwich.swap_info.clone());
-        }
-
-        let final_batch_sandwich = BatchSandwich {
-            sandwiches,
-            swap_info_vec: all_swap_infos.clone(),
-            flashloan_asset: H160::zero(), // Default to zero address as flashloan asset
-        };
+    let base_fee = new_block.next_base_fee;
+    let max_fee = base_fee;
 
-        let bundle_id = final_batch_sandwich.bundle_id();
+    let (owner, bot_address) = if env.debug {
+        (None, None)
+    } else {
+        (Some(owner), Some(bot_address))
+    };
 
-        if simulated_bundle_ids.contains(&bundle_id) {
-            continue;
-        }
+    let packed_id = to_batch(&packed).bundle_id();
+    if simulated_bundle_ids.contains(&packed_id) {
+        return Ok(actions);
+    }
+    simulated_bundle_ids.push_back(packed_id);
+
+    let (final_batch_sandwich, simulated_sandwich) = match confirm_packed(
+        packed,
+        SandoContract::Sandooo,
+        provider.clone(),
+        owner,
+        &new_block.next_block,
+        base_fee,
+        max_fee,
+        bot_address,
+    )
+    .await
+    {
+        Some(confirmed) => confirmed,
+        None => return Ok(actions),
+    };
 
+    let bundle_id = final_batch_sandwich.bundle_id();
+    if !simulated_bundle_ids.contains(&bundle_id) {
         simulated_bundle_ids.push_back(bundle_id.clone());
+    }
 
-        let base_fee = new_block.next_base_fee;
-        let max_fee = base_fee;
-
-        let (owner, bot_address) = if env.debug {
-            (None, None)
-        } else {
-            (Some(owner), Some(bot_address))
-        };
-
-        // set bribe amount as 1 initially, just so we can add the bribe operation gas usage
-        // we'll figure out the priority fee and the bribe amount after this simulation
-        let (bribe_amount, front_access_list, back_access_list) = match final_batch_sandwich
-            .simulate(
-                provider.clone(),
-                owner,
-                new_block.block_number,
-                base_fee,
-                max_fee,
-                None,
-                None,
-                bot_address,
-            )
-            .await
-        {
-            Ok(simulated_sandwich) => {
-                if simulated_sandwich.revenue > 0 {
-                    let bribe_amount =
-                        (U256::from(simulated_sandwich.revenue) * bribe_pct) / U256::from(10000);
-                    (
-                        bribe_amount,
-                        Some(simulated_sandwich.front_access_list),
-                        Some(simulated_sandwich.back_access_list),
-                    )
-                } else {
-                    (U256::zero(), None, None)
-                }
-            }
-            Err(e) => {
-                warn!("bribe_amount simulated failed: {e:?}");
-                (U256::zero(), None, None)
-            }
-        };
-
-        if bribe_amount.is_zero() {
-            continue;
-        }
-
-        // final simulation
-        let simulated_sandwich = final_batch_sandwich
-            .simulate(
-                provider.clone(),
-                owner,
-                new_block.block_number,
-                base_fee,
-                max_fee,
-                front_access_list,
-                back_access_list,
-                bot_address,
-            )
-            .await;
-        if simulated_sandwich.is_err() {
-            let e = simulated_sandwich.as_ref().err().unwrap();
-            warn!("BatchSandwich.simulate error: {e:?}");
-            continue;
-        }
-        let simulated_sandwich = simulated_sandwich.unwrap();
-        if simulated_sandwich.revenue <= 0 {
-            continue;
-        }
-        // set limit as 30% above what we simulated
-        let front_gas_limit = (simulated_sandwich.front_gas_used * 13) / 10;
-        let back_gas_limit = (simulated_sandwich.back_gas_used * 13) / 10;
-
-        let realistic_back_gas_limit = (simulated_sandwich.back_gas_used * 105) / 100;
-        let max_priority_fee_per_gas = bribe_amount / U256::from(realistic_back_gas_limit);
-        let max_fee_per_gas = base_fee + max_priority_fee_per_gas;
-
-        info!(
-            "🥪🥪🥪 Sandwiches: {:?} ({})",
-            final_batch_sandwich.sandwiches.len(),
-            bundle_id
-        );
-        info!(
-            "> Base fee: {:?} / Priority fee: {:?} / Max fee: {:?} / Bribe: {:?}",
-            base_fee, max_priority_fee_per_gas, max_fee_per_gas, bribe_amount
-        );
-        info!(
-            "> Revenue: {:?} / Profit: {:?} / Gas cost: {:?}",
-            simulated_sandwich.revenue, simulated_sandwich.profit, simulated_sandwich.gas_cost
-        );
-        info!(
-            "> Front gas: {:?} / Back gas: {:?}",
-            simulated_sandwich.front_gas_used, simulated_sandwich.back_gas_used
-        );
-
-        let message = format!(
-            "[{:?}] Front: {:?} / Back: {:?} / Bribe: {:?}",
-            bundle_id,
-            simulated_sandwich.front_gas_used,
-            simulated_sandwich.back_gas_used,
-            bribe_amount,
-        );
-        match alert.send(&message).await {
-            Err(e) => warn!("Telegram error: {e:?}"),
-            _ => {}
-        }
-
-        let victim_tx_hashes = final_batch_sandwich.victim_tx_hashes();
-        let mut victim_txs = Vec::new();
-        for tx_hash in victim_tx_hashes {
-            if let Some(tx_info) = pending_txs.get(&tx_hash) {
-                let tx = tx_info.pending_tx.tx.clone();
-                victim_txs.push(tx);
-            }
-        }
-
-        let sando_bundle = executor
-            .create_sando_bundle(
-                victim_txs,
-                simulated_sandwich.front_calldata,
-                simulated_sandwich.back_calldata,
-                simulated_sandwich.front_access_list,
-                simulated_sandwich.back_access_list,
-                front_gas_limit,
-                back_gas_limit,
-                base_fee,
-                max_priority_fee_per_gas,
-                max_fee_per_gas,
-            )
-            .await;
-        if sando_bundle.is_err() {
-            let e = sando_bundle.as_ref().err().unwrap();
-            warn!("Executor.create_sando_bundle error: {e:?}");
-            continue;
-        }
-        let sando_bundle = sando_bundle.unwrap();
-        match send_sando_bundle_request(&executor, sando_bundle, new_block.block_number, &alert)
-            .await
-        {
-            Err(e) => warn!("send_sando_bundle_request error: {e:?}"),
-            _ => {}
+    // other pending swaps on our pools may land first, bribe on what we expect to make
+    let revenue = risk_adjusted_revenue(
+        &final_batch_sandwich,
+        SandoContract::Sandooo,
+        provider,
+        owner,
+        &new_block.next_block,
+        base_fee,
+        max_fee,
+        bot_address,
+        &simulated_sandwich,
+        pending_txs,
+        bribe_pct,
+        &get_competitor_db(),
+    )
+    .await;
+    if revenue <= 0 {
+        return Ok(actions);
+    }
+    let bribe_amount = (U256::from(revenue) * bribe_pct) / U256::from(10000);
+    let front_access_list = Some(simulated_sandwich.front_access_list);
+    let back_access_list = Some(simulated_sandwich.back_access_list);
+
+    // final simulation
+    let simulated_sandwich = final_batch_sandwich
+        .simulate(
+            provider.clone(),
+            owner,
+            &new_block.next_block,
+            base_fee,
+            max_fee,
+            front_access_list,
+            back_access_list,
+            bot_address,
+        )
+        .await;
+    if simulated_sandwich.is_err() {
+        let e = simulated_sandwich.as_ref().err().unwrap();
+        warn!("BatchSandwich.simulate error: {e:?}");
+        return Ok(actions);
+    }
+    let simulated_sandwich = simulated_sandwich.unwrap();
+    if let Some(error) = simulated_sandwich.our_leg_error() {
+        warn!("Our sandwich leg failed ({}): {}", bundle_id, error);
+        return Ok(actions);
+    }
+    if let Some(error) = simulated_sandwich.victim_error() {
+        info!("Victim tx reverts after our frontrun ({}): {}", bundle_id, error);
+        return Ok(actions);
+    }
+    if simulated_sandwich.revenue <= 0 {
+        return Ok(actions);
+    }
+    // set limit as 30% above what we simulated
+    let front_gas_limit = (simulated_sandwich.front_gas_used * 13) / 10;
+    let back_gas_limit = (simulated_sandwich.back_gas_used * 13) / 10;
+
+    let realistic_back_gas_limit = (simulated_sandwich.back_gas_used * 105) / 100;
+    let max_priority_fee_per_gas = bribe_amount / U256::from(realistic_back_gas_limit);
+    let max_fee_per_gas = base_fee + max_priority_fee_per_gas;
+
+    info!(
+        "🥪🥪🥪 Sandwiches: {:?} ({})",
+        final_batch_sandwich.sandwiches.len(),
+        bundle_id
+    );
+    info!(
+        "> Base fee: {:?} / Priority fee: {:?} / Max fee: {:?} / Bribe: {:?}",
+        base_fee, max_priority_fee_per_gas, max_fee_per_gas, bribe_amount
+    );
+    info!(
+        "> Revenue: {:?} / Profit: {:?} / Gas cost: {:?}",
+        simulated_sandwich.revenue, simulated_sandwich.profit, simulated_sandwich.gas_cost
+    );
+    info!(
+        "> Front gas: {:?} / Back gas: {:?}",
+        simulated_sandwich.front_gas_used, simulated_sandwich.back_gas_used
+    );
+
+    let message = format!(
+        "[{:?}] Front: {:?} / Back: {:?} / Bribe: {:?}",
+        bundle_id,
+        simulated_sandwich.front_gas_used,
+        simulated_sandwich.back_gas_used,
+        bribe_amount,
+    );
+    actions.push(Action::Alert(message));
+
+    let victim_tx_hashes = final_batch_sandwich.victim_tx_hashes();
+    let mut victim_txs = Vec::new();
+    for tx_hash in victim_tx_hashes {
+        if let Some(tx_info) = pending_txs.get(&tx_hash) {
+            let tx = tx_info.pending_tx.tx.clone();
+            victim_txs.push(tx);
         }
     }
 
-    Ok(())
+    let sando_bundle = executor
+        .create_sando_bundle(
+            victim_txs,
+            simulated_sandwich.front_calldata,
+            simulated_sandwich.back_calldata,
+            simulated_sandwich.front_access_list,
+            simulated_sandwich.back_access_list,
+            front_gas_limit,
+            back_gas_limit,
+            base_fee,
+            max_priority_fee_per_gas,
+            max_fee_per_gas,
+        )
+        .await;
+    if sando_bundle.is_err() {
+        let e = sando_bundle.as_ref().err().unwrap();
+        warn!("Executor.create_sando_bundle error: {e:?}");
+        return Ok(actions);
+    }
+    let sando_bundle = sando_bundle.unwrap();
+    actions.push(Action::SubmitBundle(BundleSubmission {
+        id: bundle_id,
+        bundle: sando_bundle,
+        next_block: new_block.next_block.clone(),
+    }));
+
+    Ok(actions)
 }
@@ -4,8 +4,14 @@
 /// main dish (core sandwich logic), simulation tools, and high-level strategy orchestration.
 ///
 pub mod appetizer;
+pub mod backtest;
+pub mod contention;
+pub mod inspect;
 pub mod main_dish;
 pub mod main_dish_v3;
+pub mod opportunity_book;
+pub mod packer;
 pub mod simulation;
 pub mod strategy;
+pub mod swap_cache;
 pub mod token_pair_selector;
@@ -0,0 +1,120 @@
+/// Per-slot book of sandwich opportunities.
+///
+/// The sandwich coordinator collects promising sandwiches in an `OpportunityBook` during a slot
+/// and builds bundles from it at the `SlotClock` cut-offs instead of on every new victim. The
+/// book remembers what it was worth when last submitted, so a later cut-off only re-submits when
+/// better opportunities arrived, and the bundle ids it sent, so the same group of victims is
+/// not sent twice for one block.
+use bounded_vec_deque::BoundedVecDeque;
+use ethers::types::{H256, U256, U64};
+use std::collections::HashMap;
+
+use crate::sandwich::simulation::Sandwich;
+
+/// Bundle ids remembered per slot.
+const SUBMITTED_IDS_PER_SLOT: usize = 256;
+
+/// Promising sandwiches of the slot of `target_block`.
+#[derive(Debug)]
+pub struct OpportunityBook {
+    target_block: U64,
+    sandwiches: HashMap<H256, Vec<Sandwich>>,
+    submitted_value: Option<U256>,
+    submitted_bundle_ids: BoundedVecDeque<String>,
+}
+
+impl OpportunityBook {
+    /// Creates an empty book for `target_block`.
+    pub fn new(target_block: U64) -> Self {
+        Self {
+            target_block,
+            sandwiches: HashMap::new(),
+            submitted_value: None,
+            submitted_bundle_ids: BoundedVecDeque::new(SUBMITTED_IDS_PER_SLOT),
+        }
+    }
+
+    /// The block the book's bundles target.
+    pub fn target_block(&self) -> U64 {
+        self.target_block
+    }
+
+    /// Moves the book to the slot of `target_block`.
+    ///
+    /// Bundles are pinned to their block, so the victims still pending are kept and will be
+    /// submitted again for the new block.
+    ///
+    /// # Parameters
+    /// * `target_block`: U64 - The block of the new slot.
+    /// * `is_pending`: impl Fn(&H256) -> bool - True for victims that are still pending.
+    pub fn roll(&mut self, target_block: U64, is_pending: impl Fn(&H256) -> bool) {
+        self.target_block = target_block;
+        self.sandwiches.retain(|tx_hash, _| is_pending(tx_hash));
+        self.submitted_value = None;
+        self.submitted_bundle_ids.clear();
+    }
+
+    /// Records the promising sandwiches of a victim, replacing earlier ones.
+    pub fn insert(&mut self, tx_hash: H256, sandwiches: Vec<Sandwich>) {
+        if sandwiches.is_empty() {
+            self.sandwiches.remove(&tx_hash);
+        } else {
+            self.sandwiches.insert(tx_hash, sandwiches);
+        }
+    }
+
+    /// Drops the sandwiches of a victim, e.g. once it is confirmed.
+    pub fn remove(&mut self, tx_hash: &H256) {
+        self.sandwiches.remove(tx_hash);
+    }
+
+    /// The promising sandwiches by victim.
+    pub fn sandwiches(&self) -> &HashMap<H256, Vec<Sandwich>> {
+        &self.sandwiches
+    }
+
+    /// Number of victims in the book.
+    pub fn len(&self) -> usize {
+        self.sandwiches.len()
+    }
+
+    /// True if the book has no victims.
+    pub fn is_empty(&self) -> bool {
+        self.sandwiches.is_empty()
+    }
+
+    /// Sum over the victims of their best sandwich's revenue.
+    pub fn value(&self) -> U256 {
+        self.sandwiches
+            .values()
+            .map(|sandwiches| {
+                sandwiches
+                    .iter()
+                    .filter_map(|s| s.optimized_sandwich.as_ref())
+                    .map(|optimized| optimized.max_revenue)
+                    .max()
+                    .unwrap_or_default()
+            })
+            .fold(U256::zero(), |total, revenue| total.saturating_add(revenue))
+    }
+
+    /// True if the book holds opportunities and is worth more than when last submitted.
+    pub fn should_submit(&self) -> bool {
+        !self.is_empty()
+            && self
+                .submitted_value
+                .map_or(true, |value| self.value() > value)
+    }
+
+    /// Records that the book was submitted at its current value.
+    pub fn mark_submitted(&mut self) {
+        self.submitted_value = Some(self.value());
+    }
+
+    /// The sandwiches and the ids of the bundles sent in this slot, as `main_dish` takes them.
+    pub fn for_submission(
+        &mut self,
+    ) -> (&HashMap<H256, Vec<Sandwich>>, &mut BoundedVecDeque<String>) {
+        (&self.sandwiches, &mut self.submitted_bundle_ids)
+    }
+}
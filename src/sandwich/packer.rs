@@ -0,0 +1,362 @@
+/// Conflict-aware packing of sandwiches into one bundle.
+///
+/// Both legs of a bundle are encoded against the reserves and balances before it, so two
+/// sandwiches on the same victim, target pair or target token can't share a bundle: the second
+/// trade would be priced on state the first one already moved. `pack` picks the compatible set
+/// with the largest ETH revenue that also fits the bot's capital in each quote asset and the
+/// bundle gas budget, with a branch-and-bound search. `confirm_packed` then simulates the set as
+/// one `BatchSandwich`, since sandwiches that don't conflict on paper can still interact (a
+/// shared route, a token with transfer hooks...).
+use ethers::{
+    providers::{Provider, Ws},
+    types::{H160, H256, U256},
+};
+use log::{debug, info, warn};
+use std::{collections::HashMap, sync::Arc};
+
+use crate::common::evm::NextBlockEnv;
+use crate::sandwich::simulation::{BatchSandwich, SandoContract, Sandwich, SimulatedSandwich};
+
+/// Gas budget of the legs of a packed bundle.
+pub const MAX_BUNDLE_GAS: u64 = 3_000_000;
+
+/// Search nodes visited before the best set found so far is kept.
+const MAX_SEARCH_NODES: usize = 100_000;
+
+/// Combined simulations tried before giving up on a packed set.
+const MAX_CONFIRM_ROUNDS: usize = 3;
+
+/// A sandwich that may go into the bundle.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    /// The victim.
+    pub tx_hash: H256,
+    /// The sandwich, with the amount it trades and without its optimization result.
+    pub sandwich: Sandwich,
+    /// Revenue expected from its optimization, in ETH.
+    pub value: U256,
+    /// Gas used by both legs when it was simulated alone.
+    pub gas: u64,
+}
+
+impl Candidate {
+    /// Creates the candidate of an optimized sandwich, trading its optimized amount.
+    ///
+    /// The revenue was quoted at that amount only, so a sandwich the balance can't fully fund is
+    /// left out rather than traded smaller at a revenue it was never quoted for.
+    ///
+    /// # Parameters
+    /// * `tx_hash`: H256 - The victim.
+    /// * `sandwich`: &Sandwich - The sandwich, optimized by the appetizer.
+    /// * `balance`: U256 - The bot's balance of the sandwich's quote asset.
+    ///
+    /// # Returns
+    /// * `Option<Candidate>` - None if the sandwich isn't optimized, earns nothing or can't be funded.
+    pub fn new(tx_hash: H256, sandwich: &Sandwich, balance: U256) -> Option<Self> {
+        let optimized = sandwich.optimized_sandwich.as_ref()?;
+        let amount_in = optimized.amount_in;
+        if amount_in.is_zero() || amount_in > balance || optimized.max_revenue.is_zero() {
+            return None;
+        }
+
+        let mut sandwich = sandwich.clone();
+        sandwich.amount_in = amount_in;
+        sandwich.optimized_sandwich = None;
+        Some(Self {
+            tx_hash,
+            value: optimized.max_revenue,
+            gas: optimized.front_gas_used + optimized.back_gas_used,
+            sandwich,
+        })
+    }
+
+    /// The quote asset the sandwich spends.
+    pub fn quote_asset(&self) -> H160 {
+        self.sandwich.swap_info.main_currency
+    }
+
+    /// Amount of the quote asset the sandwich spends.
+    pub fn amount_in(&self) -> U256 {
+        self.sandwich.amount_in
+    }
+
+    /// True if both can't go into the same bundle.
+    pub fn conflicts_with(&self, other: &Candidate) -> bool {
+        let (a, b) = (&self.sandwich.swap_info, &other.sandwich.swap_info);
+        self.tx_hash == other.tx_hash
+            || a.target_pair == b.target_pair
+            || a.target_token == b.target_token
+    }
+}
+
+/// Lists the candidates of the promising sandwiches.
+///
+/// # Parameters
+/// * `promising_sandwiches`: &HashMap<H256, Vec<Sandwich>> - Promising sandwiches by victim.
+/// * `balances`: &HashMap<H160, U256> - The bot's balance of each quote asset.
+///
+/// # Returns
+/// * `Vec<Candidate>` - The candidates, most valuable first.
+pub fn candidates(
+    promising_sandwiches: &HashMap<H256, Vec<Sandwich>>,
+    balances: &HashMap<H160, U256>,
+) -> Vec<Candidate> {
+    let mut candidates: Vec<Candidate> = promising_sandwiches
+        .iter()
+        .flat_map(|(tx_hash, sandwiches)| {
+            sandwiches.iter().filter_map(|sandwich| {
+                let balance = balances
+                    .get(&sandwich.swap_info.main_currency)
+                    .copied()
+                    .unwrap_or_default();
+                Candidate::new(*tx_hash, sandwich, balance)
+            })
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.value.cmp(&a.value).then(a.tx_hash.cmp(&b.tx_hash)));
+    candidates
+}
+
+/// What a packed bundle may use.
+#[derive(Debug, Clone)]
+pub struct PackingLimits {
+    /// Capital available in each quote asset.
+    pub balances: HashMap<H160, U256>,
+    /// Gas budget of the legs.
+    pub max_gas: u64,
+}
+
+impl PackingLimits {
+    /// Limits for the given balances and the default gas budget.
+    pub fn new(balances: HashMap<H160, U256>) -> Self {
+        Self {
+            balances,
+            max_gas: MAX_BUNDLE_GAS,
+        }
+    }
+
+    /// Sets the gas budget of the legs.
+    pub fn with_max_gas(mut self, max_gas: u64) -> Self {
+        self.max_gas = max_gas;
+        self
+    }
+}
+
+/// Depth-first search over include / exclude decisions, most valuable candidates first.
+struct Search<'a> {
+    candidates: &'a [Candidate],
+    limits: &'a PackingLimits,
+    order: Vec<usize>,
+    /// Value of the candidates from each depth on, the bound on what is left to gain.
+    remaining: Vec<U256>,
+    chosen: Vec<usize>,
+    spent: HashMap<H160, U256>,
+    gas: u64,
+    best: (U256, Vec<usize>),
+    nodes: usize,
+}
+
+impl<'a> Search<'a> {
+    fn new(candidates: &'a [Candidate], limits: &'a PackingLimits) -> Self {
+        let mut order: Vec<usize> = (0..candidates.len()).collect();
+        order.sort_by(|a, b| candidates[*b].value.cmp(&candidates[*a].value));
+        let mut remaining = vec![U256::zero(); order.len() + 1];
+        for depth in (0..order.len()).rev() {
+            remaining[depth] = remaining[depth + 1].saturating_add(candidates[order[depth]].value);
+        }
+        Self {
+            candidates,
+            limits,
+            order,
+            remaining,
+            chosen: Vec::new(),
+            spent: HashMap::new(),
+            gas: 0,
+            best: (U256::zero(), Vec::new()),
+            nodes: 0,
+        }
+    }
+
+    fn fits(&self, index: usize) -> bool {
+        let candidate = &self.candidates[index];
+        if self
+            .chosen
+            .iter()
+            .any(|c| self.candidates[*c].conflicts_with(candidate))
+        {
+            return false;
+        }
+        if self.gas + candidate.gas > self.limits.max_gas {
+            return false;
+        }
+        let asset = candidate.quote_asset();
+        let spent = self.spent.get(&asset).copied().unwrap_or_default();
+        let balance = self
+            .limits
+            .balances
+            .get(&asset)
+            .copied()
+            .unwrap_or_default();
+        spent.saturating_add(candidate.amount_in()) <= balance
+    }
+
+    fn visit(&mut self, depth: usize, value: U256) {
+        self.nodes += 1;
+        if value > self.best.0 {
+            self.best = (value, self.chosen.clone());
+        }
+        // the first leaf reached is the greedy pick, so a cut search still returns that
+        if depth == self.order.len()
+            || self.nodes > MAX_SEARCH_NODES
+            || value.saturating_add(self.remaining[depth]) <= self.best.0
+        {
+            return;
+        }
+
+        let index = self.order[depth];
+        if self.fits(index) {
+            let candidate = &self.candidates[index];
+            let (asset, amount_in, gas) = (
+                candidate.quote_asset(),
+                candidate.amount_in(),
+                candidate.gas,
+            );
+            let candidate_value = candidate.value;
+
+            self.chosen.push(index);
+            *self.spent.entry(asset).or_default() += amount_in;
+            self.gas += gas;
+            self.visit(depth + 1, value.saturating_add(candidate_value));
+            self.gas -= gas;
+            *self.spent.entry(asset).or_default() -= amount_in;
+            self.chosen.pop();
+        }
+        self.visit(depth + 1, value);
+    }
+}
+
+/// Picks the compatible set of candidates with the largest total value.
+///
+/// # Parameters
+/// * `candidates`: &[Candidate] - The candidates.
+/// * `limits`: &PackingLimits - Capital and gas the set may use.
+///
+/// # Returns
+/// * `Vec<usize>` - Indices of the chosen candidates, most valuable first; empty if none fits.
+pub fn pack(candidates: &[Candidate], limits: &PackingLimits) -> Vec<usize> {
+    let mut search = Search::new(candidates, limits);
+    search.visit(0, U256::zero());
+    if search.nodes > MAX_SEARCH_NODES {
+        debug!(
+            "Packing search cut after {} nodes ({} candidates)",
+            MAX_SEARCH_NODES,
+            candidates.len()
+        );
+    }
+    search.best.1
+}
+
+/// Packs the promising sandwiches into the most valuable compatible set.
+///
+/// # Parameters
+/// * `promising_sandwiches`: &HashMap<H256, Vec<Sandwich>> - Promising sandwiches by victim.
+/// * `limits`: &PackingLimits - Capital and gas the bundle may use.
+///
+/// # Returns
+/// * `Vec<Candidate>` - The chosen candidates, most valuable first.
+pub fn pack_sandwiches(
+    promising_sandwiches: &HashMap<H256, Vec<Sandwich>>,
+    limits: &PackingLimits,
+) -> Vec<Candidate> {
+    let candidates = candidates(promising_sandwiches, &limits.balances);
+    pack(&candidates, limits)
+        .into_iter()
+        .map(|index| candidates[index].clone())
+        .collect()
+}
+
+/// The batch of the candidates, in their order.
+pub fn to_batch(packed: &[Candidate]) -> BatchSandwich {
+    BatchSandwich {
+        sandwiches: packed.iter().map(|c| c.sandwich.clone()).collect(),
+        swap_info_vec: packed
+            .iter()
+            .map(|c| c.sandwich.swap_info.clone())
+            .collect(),
+        flashloan_asset: H160::zero(), // Default to zero address as flashloan asset
+    }
+}
+
+/// Simulates the packed candidates as one bundle, dropping the least valuable until it passes.
+///
+/// # Parameters
+/// * `packed`: Vec<Candidate> - The packed candidates, most valuable first.
+/// * `contract`: SandoContract - The contract the legs go through.
+/// * `provider`: Arc<Provider<Ws>> - The Ethereum provider.
+/// * `owner`: Option<H160> - The bot's owner, None to simulate a deployed copy.
+/// * `next_block`: &NextBlockEnv - The block the bundle targets.
+/// * `base_fee`: U256 - Base fee of the target block.
+/// * `max_fee`: U256 - Max fee of the legs.
+/// * `bot_address`: Option<H160> - The deployed bot, None to simulate a deployed copy.
+///
+/// # Returns
+/// * `Option<(BatchSandwich, SimulatedSandwich)>` - The confirmed batch and its simulation, None
+///   if no subset passed within the allowed rounds.
+pub async fn confirm_packed(
+    mut packed: Vec<Candidate>,
+    contract: SandoContract,
+    provider: Arc<Provider<Ws>>,
+    owner: Option<H160>,
+    next_block: &NextBlockEnv,
+    base_fee: U256,
+    max_fee: U256,
+    bot_address: Option<H160>,
+) -> Option<(BatchSandwich, SimulatedSandwich)> {
+    for _ in 0..MAX_CONFIRM_ROUNDS {
+        if packed.is_empty() {
+            return None;
+        }
+        let expected = packed
+            .iter()
+            .fold(U256::zero(), |total, c| total.saturating_add(c.value));
+        let batch = to_batch(&packed);
+
+        match batch
+            .simulate_with(
+                contract,
+                provider.clone(),
+                owner,
+                next_block,
+                base_fee,
+                max_fee,
+                None,
+                None,
+                bot_address,
+            )
+            .await
+        {
+            Ok(simulated) if simulated.errors.is_empty() && simulated.revenue > 0 => {
+                if U256::from(simulated.revenue as u128) < expected {
+                    info!(
+                        "Packed sandwiches interact ({}): expected {:?}, simulated {:?}",
+                        batch.bundle_id(),
+                        expected,
+                        simulated.revenue
+                    );
+                }
+                return Some((batch, simulated));
+            }
+            Ok(simulated) => {
+                let reason = simulated
+                    .errors
+                    .first()
+                    .map(|e| e.to_string())
+                    .unwrap_or_else(|| "no revenue".to_string());
+                debug!("Packed bundle {} fails: {}", batch.bundle_id(), reason);
+            }
+            Err(e) => warn!("BatchSandwich.simulate_with error: {e:?}"),
+        }
+        packed.pop();
+    }
+    None
+}
@@ -1,22 +1,28 @@
-use anyhow::Result;
-use eth_encode_packed::ethabi::ethereum_types::{H160 as eH160, U256 as eU256};
-use eth_encode_packed::{SolidityDataType, TakeLastXBytes};
+use anyhow::{anyhow, Result};
 use ethers::prelude::*;
 use ethers::providers::{Provider, Ws};
-use ethers::types::{transaction::eip2930::AccessList, Bytes, H160, H256, I256, U256, U64};
+use ethers::types::{transaction::eip2930::AccessList, Bytes, H160, H256, I256, U256};
 use log::{debug, info, warn};
-use revm::primitives::{Bytecode, U256 as rU256};
+use revm::primitives::{Bytecode, Log, U256 as rU256};
 use std::{collections::HashMap, default::Default, str::FromStr, sync::Arc};
 
-use crate::common::bytecode::SANDOOO_BYTECODE;
-use crate::common::constants::{DAI, LINK, MKR, USDC, USDT, WBTC, WETH};
-use crate::common::evm::{EvmSimulator, Tx, VictimTx};
+use crate::common::abi::Abi;
+use crate::common::balance_slot::{resolve_balance_slot, BalanceSlot};
+use crate::common::bytecode::{SANDOOO_BYTECODE, SANDOOO_V3_BYTECODE};
+use crate::common::constants::{
+    AAVE_V3_POOL, BASIS_POINTS_DIVISOR, FLASHLOAN_FEE_BASIS_POINTS, V3_MIN_OUT_SLIPPAGE_BPS,
+};
+use crate::common::evm::{EvmSimulator, NextBlockEnv, Tx, VictimTx};
+use crate::common::execution_v3::{encode_flashloan_call, encode_leg_call};
 use crate::common::pools::{DexVariant, Pool};
+use crate::common::quote_assets::get_quote_assets;
+use crate::common::sando_calldata::{SandoPayload, SandoTrade, TradeLayout};
+use crate::common::sim_error::{LegError, SimLeg};
 use crate::common::routers::{is_known_router, is_known_swap_selector};
 use crate::common::streams::{NewBlock, NewPendingTx};
-use crate::common::utils::{
-    create_new_wallet, is_weth, return_main_and_target_currency, MainCurrency,
-};
+use crate::common::token_registry::get_token_registry;
+use crate::common::utils::{create_new_wallet, return_main_and_target_currency};
+use crate::common::valuation::{AssetDelta, Valuator};
 use crate::common::classifier::{classify_transaction, TxKind};
 use ethers::abi::{decode, ParamType, Token};
 
@@ -52,6 +58,67 @@ pub struct Sandwich {
     pub optimized_sandwich: Option<OptimizedSandwich>,
 }
 
+/// Contract that executes the frontrun and backrun legs of a sandwich.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SandoContract {
+    /// `Sandooo`, called with the packed leg data through its fallback
+    Sandooo,
+    /// `SandoooV3`, called with `executeLeg(bytes)` and funded from its inventory
+    SandoooV3,
+}
+
+impl SandoContract {
+    /// Wraps packed leg data (8-byte block number + N trade entries) into a call to the contract.
+    pub fn encode_leg(&self, abi: &Abi, leg_data: Bytes) -> Result<Bytes> {
+        match self {
+            SandoContract::Sandooo => Ok(leg_data),
+            SandoContract::SandoooV3 => encode_leg_call(abi, leg_data),
+        }
+    }
+
+    /// Returns the trade entry layout the contract parses.
+    pub fn layout(&self) -> TradeLayout {
+        match self {
+            SandoContract::Sandooo => TradeLayout::Sandooo,
+            SandoContract::SandoooV3 => TradeLayout::SandoooV3,
+        }
+    }
+}
+
+/// Builds the trade entry for a swap on the sandwiched pool.
+///
+/// V3 entries carry the minimum output and no price limit.
+///
+/// # Parameters
+/// * `swap_info`: &SwapInfo - The pool being traded.
+/// * `zero_for_one`: bool - Direction of the trade.
+/// * `token_in`: H160 - Token paid into the pool.
+/// * `amount_in`: U256 - Amount paid into the pool.
+/// * `amount_out`: U256 - Estimated output of the trade.
+///
+/// # Returns
+/// * `SandoTrade` - The trade entry.
+pub fn sando_trade(
+    swap_info: &SwapInfo,
+    zero_for_one: bool,
+    token_in: H160,
+    amount_in: U256,
+    amount_out: U256,
+) -> SandoTrade {
+    let pair = swap_info.target_pair;
+    match swap_info.version {
+        DexVariant::UniswapV2 => SandoTrade::v2(zero_for_one, pair, token_in, amount_in, amount_out),
+        DexVariant::UniswapV3 => SandoTrade::v3(
+            zero_for_one,
+            pair,
+            token_in,
+            amount_in,
+            min_amount_out(swap_info.version, amount_out),
+            swap_info.fee,
+        ),
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct BatchSandwich {
     pub sandwiches: Vec<Sandwich>,
@@ -116,8 +183,56 @@ impl BatchSandwich {
         self.sandwiches.iter().map(|s| s.swap_info.target_pair).collect()
     }
 
+    /// Reads the reserves of every target pair from the simulator (V2 with V3 fallback and vice versa).
+    pub fn fetch_reserves<M: Middleware + 'static>(
+        &self,
+        simulator: &mut EvmSimulator<M>,
+    ) -> HashMap<H160, (U256, U256)> {
+        let mut reserves_before = HashMap::new();
+
+        // Fetch reserves per pool version with fallback: V2 tries V3 on revert
+        for sandwich in &self.sandwiches {
+            let pair = sandwich.swap_info.target_pair;
+            let mut reserves_opt: Option<(U256, U256)> = None;
+            match sandwich.swap_info.version {
+                DexVariant::UniswapV2 => {
+                    match simulator.get_pair_reserves(pair) {
+                        Ok(res) => reserves_opt = Some(res),
+                        Err(e) => {
+                            warn!("get V2 reserves reverted for {:?}, falling back to V3: {:?}", pair, e);
+                            match simulator.get_v3_pool_reserves(pair) {
+                                Ok(res_v3) => reserves_opt = Some(res_v3),
+                                Err(e) => warn!("fallback V3 get_v3_pool_reserves failed for {:?}: {:?}", pair, e),
+                            }
+                        }
+                    }
+                }
+                DexVariant::UniswapV3 => {
+                    match simulator.get_v3_pool_reserves(pair) {
+                        Ok(res) => reserves_opt = Some(res),
+                        Err(e) => {
+                            warn!("get V3 reserves reverted for {:?}, falling back to V2: {:?}", pair, e);
+                            match simulator.get_pair_reserves(pair) {
+                                Ok(res_v2) => reserves_opt = Some(res_v2),
+                                Err(e) => warn!("fallback V2 get_pair_reserves failed for {:?}: {:?}", pair, e),
+                            }
+                        }
+                    }
+                }
+            }
+            if let Some(reserves) = reserves_opt {
+                reserves_before.insert(pair, reserves);
+            } else {
+                warn!("Missing reserves for pair {:?}, skipping sandwich", pair);
+            }
+        }
+
+        reserves_before
+    }
+
     pub fn encode_frontrun_tx(
         &self,
+        contract: SandoContract,
         block_number: U256,
         pair_reserves: &HashMap<H160, (U256, U256)>,
     ) -> Result<(Bytes, Vec<Tx>, HashMap<H160, U256>)> {
@@ -126,12 +241,7 @@ impl BatchSandwich {
         let mut added_tx_hash = HashMap::new();
         let mut victim_txs = Vec::new();
 
-        let mut frontrun_swap_params = Vec::new();
-
-        let block_number_u256 = eU256::from_dec_str(&block_number.to_string())?;
-        frontrun_swap_params.push(
-            SolidityDataType::NumberWithShift(block_number_u256, TakeLastXBytes(64)), // blockNumber (uint64)
-        );
+        let mut frontrun_payload = SandoPayload::new(block_number.as_u64());
 
         for sandwich in &self.sandwiches {
             let tx_hash = sandwich.victim_tx.tx_hash;
@@ -149,8 +259,7 @@ impl BatchSandwich {
                 .amount_in
                 .checked_sub(U256::from(1))
                 .unwrap_or(U256::zero());
-            let amount_in_u256 = eU256::from_dec_str(&new_amount_in.to_string())?;
-            let amount_out_u256 = {
+            let amount_out = {
                 // unified support for Uniswap V2 & V3 using on-chain reserves
                 match pair_reserves.get(&sandwich.swap_info.target_pair) {
                     Some(reserves) => {
@@ -164,7 +273,7 @@ impl BatchSandwich {
                         } else {
                             get_v3_amount_out(new_amount_in, reserve_in, reserve_out, sandwich.swap_info.fee)
                         };
-                        eU256::from_dec_str(&amount_out.to_string())?
+                        amount_out
                     }
                     None => {
                         warn!("Missing reserves for pair {:?}, skipping sandwich", sandwich.swap_info.target_pair);
@@ -173,10 +282,6 @@ impl BatchSandwich {
                 }
             };
 
-            let pair = eH160::from_str(&format!("{:?}", sandwich.swap_info.target_pair)).unwrap();
-            let token_in =
-                eH160::from_str(&format!("{:?}", sandwich.swap_info.main_currency)).unwrap();
-
             let main_currency = sandwich.swap_info.main_currency;
             if starting_mc_values.contains_key(&main_currency) {
                 let prev_mc_value = *starting_mc_values.get(&main_currency).unwrap();
@@ -185,50 +290,48 @@ impl BatchSandwich {
                 starting_mc_values.insert(main_currency, new_amount_in);
             }
 
-            frontrun_swap_params.extend(vec![
-                SolidityDataType::NumberWithShift(
-                    eU256::from(zero_for_one as u8),
-                    TakeLastXBytes(8),
-                ), // zeroForOne (uint8)
-                SolidityDataType::Address(pair),     // pair (address)
-                SolidityDataType::Address(token_in), // tokenIn (address)
-                SolidityDataType::NumberWithShift(amount_in_u256, TakeLastXBytes(256)), // amountIn (uint256)
-                SolidityDataType::NumberWithShift(amount_out_u256, TakeLastXBytes(256)), // amountOut (uint256)
-            ]);
+            frontrun_payload.trades.push(sando_trade(
+                &sandwich.swap_info,
+                zero_for_one,
+                main_currency,
+                new_amount_in,
+                amount_out,
+            ));
         }
 
-        let frontrun_calldata = eth_encode_packed::abi::encode_packed(&frontrun_swap_params);
-        let frontrun_calldata_bytes = Bytes::from_str(&frontrun_calldata.1).unwrap_or_default();
+        let frontrun_calldata_bytes = frontrun_payload.encode(contract.layout())?;
 
         Ok((frontrun_calldata_bytes, victim_txs, starting_mc_values))
     }
 
     pub fn encode_backrun_tx(
         &self,
+        contract: SandoContract,
         block_number: U256,
         pair_reserves: &HashMap<H160, (U256, U256)>,
         token_balances: &HashMap<H160, U256>,
     ) -> Result<Bytes> {
-        let mut backrun_swap_params = Vec::new();
-
-        let block_number_u256 = eU256::from_dec_str(&block_number.to_string())?;
-        backrun_swap_params.push(
-            SolidityDataType::NumberWithShift(block_number_u256, TakeLastXBytes(64)), // blockNumber (uint64)
-        );
+        let registry = get_token_registry();
+        let mut backrun_payload = SandoPayload::new(block_number.as_u64());
 
         for sandwich in &self.sandwiches {
             let amount_in = *token_balances
                 .get(&sandwich.swap_info.target_token)
                 .unwrap_or(&U256::zero());
             let new_amount_in = amount_in.checked_sub(U256::from(1)).unwrap_or(U256::zero());
-            let amount_in_u256 = eU256::from_dec_str(&new_amount_in.to_string())?;
+
+            // fee-on-transfer tokens: the pair only receives what's left after the sell tax
+            let pair_amount_in = match registry.get_token_safety(sandwich.swap_info.target_token) {
+                Some(report) => report.apply_sell_tax(new_amount_in),
+                None => new_amount_in,
+            };
 
             // this means that the buy order is token0 -> token1
             let zero_for_one = sandwich.swap_info.token0_is_main;
 
             // in backrun tx we sell tokens we bought in our frontrun tx
             // so it's important to flip the boolean value of zero_for_one
-            let amount_out_u256 = {
+            let amount_out = {
                 // unified support for Uniswap V2 & V3 using on-chain reserves
                 match pair_reserves.get(&sandwich.swap_info.target_pair) {
                     Some(reserves) => {
@@ -240,11 +343,11 @@ impl BatchSandwich {
                             (reserves.0, reserves.1)
                         };
                         let amount_out = if sandwich.swap_info.version == DexVariant::UniswapV2 {
-                            get_v2_amount_out(new_amount_in, reserve_in, reserve_out)
+                            get_v2_amount_out(pair_amount_in, reserve_in, reserve_out)
                         } else {
-                            get_v3_amount_out(new_amount_in, reserve_in, reserve_out, sandwich.swap_info.fee)
+                            get_v3_amount_out(pair_amount_in, reserve_in, reserve_out, sandwich.swap_info.fee)
                         };
-                        eU256::from_dec_str(&amount_out.to_string())?
+                        amount_out
                     }
                     None => {
                         warn!("Missing reserves for pair {:?}, skipping sandwich", sandwich.swap_info.target_pair);
@@ -253,24 +356,16 @@ impl BatchSandwich {
                 }
             };
 
-            let pair = eH160::from_str(&format!("{:?}", sandwich.swap_info.target_pair)).unwrap();
-            let token_in =
-                eH160::from_str(&format!("{:?}", sandwich.swap_info.target_token)).unwrap();
-
-            backrun_swap_params.extend(vec![
-                SolidityDataType::NumberWithShift(
-                    eU256::from(!zero_for_one as u8), // <-- make sure to flip boolean value (it's a sell now, not buy)
-                    TakeLastXBytes(8),
-                ), // zeroForOne (uint8)
-                SolidityDataType::Address(pair),     // pair (address)
-                SolidityDataType::Address(token_in), // tokenIn (address)
-                SolidityDataType::NumberWithShift(amount_in_u256, TakeLastXBytes(256)), // amountIn (uint256)
-                SolidityDataType::NumberWithShift(amount_out_u256, TakeLastXBytes(256)), // amountOut (uint256)
-            ]);
+            backrun_payload.trades.push(sando_trade(
+                &sandwich.swap_info,
+                !zero_for_one, // <-- make sure to flip boolean value (it's a sell now, not buy)
+                sandwich.swap_info.target_token,
+                new_amount_in,
+                amount_out,
+            ));
         }
 
-        let backrun_calldata = eth_encode_packed::abi::encode_packed(&backrun_swap_params);
-        let backrun_calldata_bytes = Bytes::from_str(&backrun_calldata.1).unwrap_or_default();
+        let backrun_calldata_bytes = backrun_payload.encode(contract.layout())?;
 
         Ok(backrun_calldata_bytes)
     }
@@ -279,15 +374,106 @@ impl BatchSandwich {
         &self,
         provider: Arc<Provider<Ws>>,
         owner: Option<H160>,
-        block_number: U64,
+        next_block: &NextBlockEnv,
+        base_fee: U256,
+        max_fee: U256,
+        front_access_list: Option<AccessList>,
+        back_access_list: Option<AccessList>,
+        bot_address: Option<H160>,
+    ) -> Result<SimulatedSandwich> {
+        self.simulate_with(
+            SandoContract::Sandooo,
+            provider,
+            owner,
+            next_block,
+            base_fee,
+            max_fee,
+            front_access_list,
+            back_access_list,
+            bot_address,
+        )
+        .await
+    }
+
+    /// Simulates frontrun, victim txs and backrun through the given contract.
+    ///
+    /// `front_calldata` and `back_calldata` of the result are the exact contract calls to send.
+    /// Both legs are pinned to `next_block`.
+    pub async fn simulate_with(
+        &self,
+        contract: SandoContract,
+        provider: Arc<Provider<Ws>>,
+        owner: Option<H160>,
+        next_block: &NextBlockEnv,
+        base_fee: U256,
+        max_fee: U256,
+        front_access_list: Option<AccessList>,
+        back_access_list: Option<AccessList>,
+        bot_address: Option<H160>,
+    ) -> Result<SimulatedSandwich> {
+        self.simulate_after(
+            contract,
+            provider,
+            owner,
+            next_block,
+            base_fee,
+            max_fee,
+            front_access_list,
+            back_access_list,
+            bot_address,
+            &[],
+        )
+        .await
+    }
+
+    /// Like `simulate_with`, but lands `preceding_txs` between building and executing our legs.
+    ///
+    /// Our calldata is still encoded against the reserves at the fork, as it would be when we
+    /// send the bundle, so this shows what happens if other pending swaps on the same pools are
+    /// included before us. Preceding txs that fail are skipped, like a builder would drop them.
+    pub async fn simulate_after(
+        &self,
+        contract: SandoContract,
+        provider: Arc<Provider<Ws>>,
+        owner: Option<H160>,
+        next_block: &NextBlockEnv,
         base_fee: U256,
         max_fee: U256,
         front_access_list: Option<AccessList>,
         back_access_list: Option<AccessList>,
         bot_address: Option<H160>,
+        preceding_txs: &[Tx],
     ) -> Result<SimulatedSandwich> {
-        let mut simulator = EvmSimulator::new(provider.clone(), owner, block_number);
+        let mut simulator = EvmSimulator::new(provider.clone(), owner, next_block);
+        self.simulate_in(
+            &mut simulator,
+            contract,
+            owner,
+            base_fee,
+            max_fee,
+            front_access_list,
+            back_access_list,
+            bot_address,
+            preceding_txs,
+        )
+    }
 
+    /// Runs the `simulate_after` sequence on an existing simulator.
+    ///
+    /// The simulator must be fresh: forked at the parent of the target block, or restored from
+    /// a `StateSnapshot` to simulate without a network.
+    pub fn simulate_in<M: Middleware + 'static>(
+        &self,
+        simulator: &mut EvmSimulator<M>,
+        contract: SandoContract,
+        owner: Option<H160>,
+        base_fee: U256,
+        max_fee: U256,
+        front_access_list: Option<AccessList>,
+        back_access_list: Option<AccessList>,
+        bot_address: Option<H160>,
+        preceding_txs: &[Tx],
+    ) -> Result<SimulatedSandwich> {
         // set ETH balance so that it's enough to cover gas fees
         match owner {
             None => {
@@ -301,69 +487,38 @@ impl BatchSandwich {
         let target_pairs = self.target_pairs();
         let target_tokens = self.target_tokens();
 
-        let mut reserves_before = HashMap::new();
-
-        // Fetch reserves per pool version with fallback: V2 tries V3 on revert
-        for sandwich in &self.sandwiches {
-            let pair = sandwich.swap_info.target_pair;
-            let mut reserves_opt: Option<(U256, U256)> = None;
-            match sandwich.swap_info.version {
-                DexVariant::UniswapV2 => {
-                    match simulator.get_pair_reserves(pair) {
-                        Ok(res) => reserves_opt = Some(res),
-                        Err(e) => {
-                            warn!("get V2 reserves reverted for {:?}, falling back to V3: {:?}", pair, e);
-                            match simulator.get_v3_pool_reserves(pair) {
-                                Ok(res_v3) => reserves_opt = Some(res_v3),
-                                Err(e) => warn!("fallback V3 get_v3_pool_reserves failed for {:?}: {:?}", pair, e),
-                            }
-                        }
-                    }
-                }
-                DexVariant::UniswapV3 => {
-                    match simulator.get_v3_pool_reserves(pair) {
-                        Ok(res) => reserves_opt = Some(res),
-                        Err(e) => {
-                            warn!("get V3 reserves reverted for {:?}, falling back to V2: {:?}", pair, e);
-                            match simulator.get_pair_reserves(pair) {
-                                Ok(res_v2) => reserves_opt = Some(res_v2),
-                                Err(e) => warn!("fallback V2 get_pair_reserves failed for {:?}: {:?}", pair, e),
-                            }
-                        }
-                    }
-                }
-            }
-            if let Some(reserves) = reserves_opt {
-                reserves_before.insert(pair, reserves);
-            } else {
-                warn!("Missing reserves for pair {:?}, skipping sandwich", pair);
-            }
-        }
+        let reserves_before = self.fetch_reserves(simulator);
 
         let next_block_number = simulator.get_block_number();
 
         // create frontrun tx calldata and inject main_currency token balance to bot contract
         let (frontrun_calldata, victim_txs, starting_mc_values) =
-            self.encode_frontrun_tx(next_block_number, &reserves_before)?;
+            self.encode_frontrun_tx(contract, next_block_number, &reserves_before)?;
 
-        // deploy Sandooo bot
+        // deploy the bot and fund its inventory
         let bot_address = match bot_address {
             Some(bot_address) => bot_address,
             None => {
-                let bot_address = create_new_wallet().1;
-                simulator.deploy(bot_address, Bytecode::new_raw((*SANDOOO_BYTECODE.0).into()));
-
-                // override owner slot
-                let owner_ru256 = rU256::from_str(&format!("{:?}", simulator.owner)).unwrap();
-                simulator.insert_account_storage(bot_address, rU256::from(0), owner_ru256)?;
+                let bot_address = match contract {
+                    SandoContract::Sandooo => {
+                        let bot_address = create_new_wallet().1;
+                        simulator
+                            .deploy(bot_address, Bytecode::new_raw((*SANDOOO_BYTECODE.0).into()));
+
+                        // override owner slot
+                        let owner_ru256 =
+                            rU256::from_str(&format!("{:?}", simulator.owner)).unwrap();
+                        simulator.insert_account_storage(bot_address, rU256::from(0), owner_ru256)?;
+                        bot_address
+                    }
+                    SandoContract::SandoooV3 => deploy_sando_v3(simulator)?,
+                };
 
                 for (main_currency, starting_value) in &starting_mc_values {
-                    let mc = MainCurrency::new(*main_currency);
-                    let balance_slot = mc.balance_slot();
-                    simulator.set_token_balance(
-                        *main_currency,
+                    let balance_slot = main_currency_balance_slot(simulator, *main_currency)?;
+                    simulator.set_token_balance_at(
                         bot_address,
-                        balance_slot,
+                        &balance_slot,
                         (*starting_value).into(),
                     )?;
                 }
@@ -372,6 +527,12 @@ impl BatchSandwich {
             }
         };
 
+        // capture quote asset prices before our trades move them
+        let mut valuator = Valuator::new();
+        for (main_currency, _) in &starting_mc_values {
+            valuator.price(simulator, *main_currency)?;
+        }
+
         // check ETH, MC balance before any txs are run
         let eth_balance_before = simulator.get_eth_balance_of(simulator.owner);
         let mut mc_balances_before = HashMap::new();
@@ -383,6 +544,15 @@ impl BatchSandwich {
         // set base fee so that gas fees are taken into account
         simulator.set_base_fee(base_fee);
 
+        let frontrun_calldata = contract.encode_leg(&simulator.abi, frontrun_calldata)?;
+
+        // Other pending swaps that land before our bundle
+        for tx in preceding_txs {
+            if let Err(e) = simulator.call(tx.clone()) {
+                debug!("preceding tx to {:?} dropped: {}", tx.transact_to, e);
+            }
+        }
+
         // Frontrun
         let front_tx = Tx {
             caller: simulator.owner,
@@ -400,15 +570,27 @@ impl BatchSandwich {
             },
         };
         simulator.set_access_list(front_access_list.clone());
+        let mut errors = Vec::new();
         let front_gas_used = match simulator.call(front_tx) {
             Ok(result) => result.gas_used,
-            Err(_) => 0,
+            Err(e) => {
+                let error = LegError::from_call_error(SimLeg::Frontrun, None, &e);
+                let gas_used = error.gas_used;
+                errors.push(error);
+                gas_used
+            }
         };
 
-        // Victim Txs
-        for victim_tx in victim_txs {
-            match simulator.call(victim_tx) {
-                _ => {}
+        // Victim Txs (same order as encode_frontrun_tx)
+        let mut victim_tx_hashes = Vec::new();
+        for tx_hash in self.victim_tx_hashes() {
+            if !victim_tx_hashes.contains(&tx_hash) {
+                victim_tx_hashes.push(tx_hash);
+            }
+        }
+        for (victim_tx, tx_hash) in victim_txs.into_iter().zip(victim_tx_hashes) {
+            if let Err(e) = simulator.call(victim_tx) {
+                errors.push(LegError::from_call_error(SimLeg::Victim, Some(tx_hash), &e));
             }
         }
 
@@ -463,7 +645,8 @@ impl BatchSandwich {
         simulator.set_base_fee(base_fee);
 
         let backrun_calldata =
-            self.encode_backrun_tx(next_block_number, &reserves_after, &token_balances)?;
+            self.encode_backrun_tx(contract, next_block_number, &reserves_after, &token_balances)?;
+        let backrun_calldata = contract.encode_leg(&simulator.abi, backrun_calldata)?;
 
         // Backrun
         let back_tx = Tx {
@@ -485,7 +668,12 @@ impl BatchSandwich {
         simulator.set_access_list(back_access_list.clone());
         let back_gas_used = match simulator.call(back_tx) {
             Ok(result) => result.gas_used,
-            Err(_) => 0,
+            Err(e) => {
+                let error = LegError::from_call_error(SimLeg::Backrun, None, &e);
+                let gas_used = error.gas_used;
+                errors.push(error);
+                gas_used
+            }
         };
 
         simulator.set_base_fee(U256::zero());
@@ -502,40 +690,20 @@ impl BatchSandwich {
             .unwrap_or(eth_balance_before);
         let eth_used_as_gas_i256 = I256::from_dec_str(&eth_used_as_gas.to_string())?;
 
-        let usdt = H160::from_str(USDT).unwrap();
-        let usdc = H160::from_str(USDC).unwrap();
-
-        let mut weth_before_i256 = I256::zero();
-        let mut weth_after_i256 = I256::zero();
+        let mut asset_deltas = Vec::new();
+        let mut profit_i256 = I256::zero();
 
         for (main_currency, _) in &starting_mc_values {
             let mc_balance_before = *mc_balances_before.get(&main_currency).unwrap();
             let mc_balance_after = *mc_balances_after.get(&main_currency).unwrap();
 
-            let (mc_balance_before, mc_balance_after) = if *main_currency == usdt {
-                let before =
-                    convert_usdt_to_weth(&mut simulator, mc_balance_before).unwrap_or_default();
-                let after =
-                    convert_usdt_to_weth(&mut simulator, mc_balance_after).unwrap_or_default();
-                (before, after)
-            } else if *main_currency == usdc {
-                let before =
-                    convert_usdc_to_weth(&mut simulator, mc_balance_before).unwrap_or_default();
-                let after =
-                    convert_usdc_to_weth(&mut simulator, mc_balance_after).unwrap_or_default();
-                (before, after)
-            } else {
-                (mc_balance_before, mc_balance_after)
-            };
-
-            let mc_balance_before_i256 = I256::from_dec_str(&mc_balance_before.to_string())?;
-            let mc_balance_after_i256 = I256::from_dec_str(&mc_balance_after.to_string())?;
-
-            weth_before_i256 += mc_balance_before_i256;
-            weth_after_i256 += mc_balance_after_i256;
+            let asset_delta =
+                valuator.value_delta(*main_currency, mc_balance_before, mc_balance_after)?;
+            profit_i256 += asset_delta.eth_value;
+            asset_deltas.push(asset_delta);
         }
 
-        let profit = (weth_after_i256 - weth_before_i256).as_i128();
+        let profit = profit_i256.as_i128();
         let gas_cost = eth_used_as_gas_i256.as_i128();
         let revenue = profit - gas_cost;
 
@@ -549,10 +717,242 @@ impl BatchSandwich {
             back_access_list,
             front_calldata: frontrun_calldata,
             back_calldata: backrun_calldata,
+            asset_deltas,
+            flashloan_premium: U256::zero(),
+            errors,
         };
 
         Ok(simulated_sandwich)
     }
+
+    /// Returns the reserves and our token balances after the frontrun trades, computed off-chain.
+    ///
+    /// Used to price the backrun leg when both legs execute inside one flashloan transaction.
+    pub fn reserves_after_frontrun(
+        &self,
+        pair_reserves: &HashMap<H160, (U256, U256)>,
+    ) -> (HashMap<H160, (U256, U256)>, HashMap<H160, U256>) {
+        let registry = get_token_registry();
+        let mut reserves = pair_reserves.clone();
+        let mut token_balances = HashMap::new();
+
+        for sandwich in &self.sandwiches {
+            let pair = sandwich.swap_info.target_pair;
+            let (reserve0, reserve1) = match reserves.get(&pair) {
+                Some(reserves) => *reserves,
+                None => continue,
+            };
+            let zero_for_one = sandwich.swap_info.token0_is_main;
+            let (reserve_in, reserve_out) = if zero_for_one {
+                (reserve0, reserve1)
+            } else {
+                (reserve1, reserve0)
+            };
+
+            // same amounts as encode_frontrun_tx
+            let amount_in = sandwich.amount_in.checked_sub(U256::from(1)).unwrap_or_default();
+            let amount_out = if sandwich.swap_info.version == DexVariant::UniswapV2 {
+                get_v2_amount_out(amount_in, reserve_in, reserve_out)
+            } else {
+                get_v3_amount_out(amount_in, reserve_in, reserve_out, sandwich.swap_info.fee)
+            };
+
+            let (reserve_in, reserve_out) = (reserve_in + amount_in, reserve_out - amount_out);
+            let updated = if zero_for_one {
+                (reserve_in, reserve_out)
+            } else {
+                (reserve_out, reserve_in)
+            };
+            reserves.insert(pair, updated);
+
+            // V3 trades only guarantee the minimum output, and buy tax on fee-on-transfer tokens
+            // reduces what we actually receive
+            let guaranteed = min_amount_out(sandwich.swap_info.version, amount_out);
+            let received = match registry.get_token_safety(sandwich.swap_info.target_token) {
+                Some(report) => crate::common::token_safety::apply_tax(guaranteed, report.buy_tax_bps),
+                None => guaranteed,
+            };
+            *token_balances
+                .entry(sandwich.swap_info.target_token)
+                .or_insert(U256::zero()) += received;
+        }
+
+        (reserves, token_balances)
+    }
+
+    /// Encodes the `sandwichData` payload of `SandoooV3.executeSandwichWithFlashloan`.
+    ///
+    /// # Returns
+    /// * `Result<(Bytes, Vec<Tx>, HashMap<H160, U256>)>` - Payload (8-byte block number + front and
+    ///   back trades), victim txs and the amount of each main currency the frontrun needs.
+    pub fn encode_flashloan_data(
+        &self,
+        block_number: U256,
+        pair_reserves: &HashMap<H160, (U256, U256)>,
+    ) -> Result<(Bytes, Vec<Tx>, HashMap<H160, U256>)> {
+        let (frontrun_data, victim_txs, starting_mc_values) =
+            self.encode_frontrun_tx(SandoContract::SandoooV3, block_number, pair_reserves)?;
+        let (reserves_after_front, token_balances) = self.reserves_after_frontrun(pair_reserves);
+        let backrun_data =
+            self.encode_backrun_tx(
+                SandoContract::SandoooV3,
+                block_number,
+                &reserves_after_front,
+                &token_balances,
+            )?;
+
+        // both legs share the block number header
+        let layout = SandoContract::SandoooV3.layout();
+        let mut payload = SandoPayload::decode(&frontrun_data, layout)?;
+        payload
+            .trades
+            .extend(SandoPayload::decode(&backrun_data, layout)?.trades);
+
+        Ok((payload.encode(layout)?, victim_txs, starting_mc_values))
+    }
+
+    /// Simulates the sandwich through `SandoooV3.executeSandwichWithFlashloan` on forked Aave state.
+    ///
+    /// Deploys SandoooV3 through its constructor (unless `bot_address` is given), runs the real
+    /// `flashLoanSimple` and reports the premium charged by the pool. `front_calldata` holds the
+    /// exact contract call to send, and `front_gas_used` the gas of the whole transaction.
+    pub async fn simulate_flashloan(
+        &self,
+        provider: Arc<Provider<Ws>>,
+        owner: Option<H160>,
+        next_block: &NextBlockEnv,
+        base_fee: U256,
+        access_list: Option<AccessList>,
+        bot_address: Option<H160>,
+    ) -> Result<SimulatedSandwich> {
+        let mut simulator = EvmSimulator::new(provider.clone(), owner, next_block);
+
+        // set ETH balance so that it's enough to cover gas fees
+        if owner.is_none() {
+            let initial_eth_balance = U256::from(100) * U256::from(10).pow(U256::from(18));
+            simulator.set_eth_balance(simulator.owner, initial_eth_balance);
+        }
+
+        let reserves_before = self.fetch_reserves(&mut simulator);
+        let next_block_number = simulator.get_block_number();
+
+        let (sandwich_data, _victim_txs, starting_mc_values) =
+            self.encode_flashloan_data(next_block_number, &reserves_before)?;
+
+        let asset = self.flashloan_asset;
+        if starting_mc_values.len() != 1 || !starting_mc_values.contains_key(&asset) {
+            return Err(anyhow!(
+                "Flashloan sandwiches must only trade against the borrowed asset {:?}",
+                asset
+            ));
+        }
+        let amount = *starting_mc_values.get(&asset).unwrap();
+
+        let bot_address = match bot_address {
+            Some(bot_address) => bot_address,
+            None => deploy_sando_v3(&mut simulator)?,
+        };
+
+        let calldata = encode_flashloan_call(&simulator.abi, asset, amount, sandwich_data)?;
+
+        let mut valuator = Valuator::new();
+        valuator.price(&mut simulator, asset)?;
+
+        // the contract sweeps profit to the owner, so track both balances
+        let eth_balance_before = simulator.get_eth_balance_of(simulator.owner);
+        let asset_balance_before = simulator.get_token_balance(asset, simulator.owner)?
+            + simulator.get_token_balance(asset, bot_address)?;
+
+        simulator.set_base_fee(base_fee);
+
+        let tx = Tx {
+            caller: simulator.owner,
+            transact_to: bot_address,
+            data: calldata.0.clone(),
+            value: U256::zero(),
+            gas_price: base_fee,
+            gas_limit: 5000000,
+        };
+        let access_list = match access_list {
+            Some(access_list) => access_list,
+            None => simulator.get_access_list(tx.clone()).unwrap_or_default(),
+        };
+        simulator.set_access_list(access_list.clone());
+        let result = simulator
+            .call(tx)
+            .map_err(|e| anyhow!("{}", LegError::from_call_error(SimLeg::Frontrun, None, &e)))?;
+
+        let flashloan_premium = match result.logs.as_ref().and_then(|logs| parse_flashloan_premium(logs)) {
+            Some(premium) => premium,
+            None => {
+                warn!("FlashLoan event not found, estimating the premium");
+                amount * U256::from(FLASHLOAN_FEE_BASIS_POINTS) / U256::from(BASIS_POINTS_DIVISOR)
+            }
+        };
+
+        simulator.set_base_fee(U256::zero());
+
+        let eth_balance_after = simulator.get_eth_balance_of(simulator.owner);
+        let asset_balance_after = simulator.get_token_balance(asset, simulator.owner)?
+            + simulator.get_token_balance(asset, bot_address)?;
+
+        let eth_used_as_gas = eth_balance_before
+            .checked_sub(eth_balance_after)
+            .unwrap_or(eth_balance_before);
+        let eth_used_as_gas_i256 = I256::from_dec_str(&eth_used_as_gas.to_string())?;
+
+        // the premium was repaid to Aave inside the transaction, so it is already in the delta
+        let asset_delta = valuator.value_delta(asset, asset_balance_before, asset_balance_after)?;
+
+        let profit = asset_delta.eth_value.as_i128();
+        let gas_cost = eth_used_as_gas_i256.as_i128();
+        let revenue = profit - gas_cost;
+
+        Ok(SimulatedSandwich {
+            revenue,
+            profit,
+            gas_cost,
+            front_gas_used: result.gas_used,
+            back_gas_used: 0,
+            front_access_list: access_list,
+            back_access_list: AccessList::default(),
+            front_calldata: calldata,
+            back_calldata: Bytes::default(),
+            asset_deltas: vec![asset_delta],
+            flashloan_premium,
+            errors: Vec::new(),
+        })
+    }
+}
+
+/// Deploys `SANDOOO_V3_BYTECODE` through its constructor, so the owner and `AAVE_POOL`
+/// immutables are set, and returns the contract address.
+pub fn deploy_sando_v3<M: Middleware + 'static>(simulator: &mut EvmSimulator<M>) -> Result<H160> {
+    let aave_pool = H160::from_str(AAVE_V3_POOL).unwrap();
+    let mut init_code = SANDOOO_V3_BYTECODE.to_vec();
+    init_code.extend(ethers::abi::encode(&[Token::Address(aave_pool)]));
+    simulator.create(simulator.owner, init_code.into())
+}
+
+/// Returns the premium of the first Aave V3 `FlashLoan` event in a list of logs.
+pub fn parse_flashloan_premium(logs: &[Log]) -> Option<U256> {
+    let event_id = H256::from(ethers::utils::keccak256(
+        "FlashLoan(address,address,address,uint256,uint8,uint256,uint16)",
+    ));
+    let pool = H160::from_str(AAVE_V3_POOL).unwrap();
+
+    for log in logs {
+        let address: H160 = log.address.into();
+        let topic0 = match log.topics.first() {
+            Some(topic) => H256::from_slice(topic.as_bytes()),
+            None => continue,
+        };
+        // data: initiator, amount, interestRateMode, premium
+        if address == pool && topic0 == event_id && log.data.len() >= 128 {
+            return Some(U256::from_big_endian(&log.data[96..128]));
+        }
+    }
+    None
 }
 
 #[derive(Debug, Default, Clone)]
@@ -566,6 +966,24 @@ pub struct SimulatedSandwich {
     pub back_access_list: AccessList,
     pub front_calldata: Bytes,
     pub back_calldata: Bytes,
+    /// Balance change of every quote asset used, valued in ETH (summed into `profit`)
+    pub asset_deltas: Vec<AssetDelta>,
+    /// Premium charged by Aave (flashloan simulations only, already deducted from `profit`)
+    pub flashloan_premium: U256,
+    /// Transactions that reverted or halted, in execution order
+    pub errors: Vec<LegError>,
+}
+
+impl SimulatedSandwich {
+    /// Returns the first failed frontrun or backrun, if any.
+    pub fn our_leg_error(&self) -> Option<&LegError> {
+        self.errors.iter().find(|e| e.leg != SimLeg::Victim)
+    }
+
+    /// Returns the first failed victim tx, if any (e.g. a slippage revert caused by our frontrun).
+    pub fn victim_error(&self) -> Option<&LegError> {
+        self.errors.iter().find(|e| e.leg == SimLeg::Victim)
+    }
 }
 
 #[derive(Debug, Default, Clone)]
@@ -580,8 +998,6 @@ pub struct OptimizedSandwich {
     pub back_calldata: Bytes,
 }
 
-pub static V2_SWAP_EVENT_ID: &str = "0xd78ad95f";
-
 // Router addresses for DEXes and aggregators
 pub static UNISWAP_V2_ROUTER: &str = "0x7a250d5630B4cF539739dF2C5dAcb4c659F2488D";
 pub static UNISWAP_V3_ROUTER: &str = "0xE592427A0AEce92De3Edee1F18E0157C05861564";
@@ -635,9 +1051,22 @@ pub static SWAP_FOR_0: &str = "0xcdd6cda9"; // swapFor0
 pub static SWAP_FOR_1: &str = "0xd50e6fcd"; // swapFor1
 pub static UNISWAP_V3_POOL_SWAP: &str = "0x128acb08"; // swap - UniswapV3Pool
 
-pub async fn extract_swap_info(
-    provider: &Arc<Provider<Ws>>,
-    _new_block: &NewBlock,
+/// Returns the swaps a pending transaction makes on known pools.
+///
+/// Known routers are decoded from their calldata; anything else is traced with
+/// `trace_swap_info`. Generic over the provider, so it runs against a node or a `MockMiddleware`.
+///
+/// # Parameters
+/// * `provider`: &Arc<M> - The Ethereum provider.
+/// * `new_block`: &NewBlock - The latest block.
+/// * `pending_tx`: &NewPendingTx - The pending transaction.
+/// * `pools_map`: &HashMap<H160, Pool> - Known pools.
+///
+/// # Returns
+/// * `Result<Vec<SwapInfo>>` - One entry per touched pool.
+pub async fn extract_swap_info<M: Middleware + 'static>(
+    provider: &Arc<M>,
+    new_block: &NewBlock,
     pending_tx: &NewPendingTx,
     pools_map: &HashMap<H160, Pool>,
 ) -> Result<Vec<SwapInfo>> {
@@ -647,21 +1076,14 @@ pub async fn extract_swap_info(
     let input = pending_tx.tx.input.clone();
     let mut swap_info_vec = Vec::new();
 
-    println!(
-        "DEBUG extract_swap_info START: tx_hash={:?}, input.len={}, to_address={:?}",
-        tx_hash,
-        input.0.len(),
-        pending_tx.tx.to.unwrap_or_default()
-    );
-
     let to_address = pending_tx.tx.to.unwrap_or_default();
-    println!("DEBUG extract_swap_info: to_address={:?}", to_address);
 
     info!("Analyzing transaction: {:?}", tx_hash);
     info!("Total number of pools in pool map: {}", pools_map.len());
 
-    // Unified swap detection via classifier, fallback to full trace-based scan
-    let tx_kind = classify_transaction(provider, &pending_tx.tx).await;
+    // known routers are decoded from their calldata; anything else, direct pool calls included,
+    // is traced
+    let tx_kind = classify_transaction(provider.as_ref(), &pending_tx.tx).await;
     debug!("Initial classify_transaction: {:?}", tx_kind);
     let need_trace = tx_kind != TxKind::Swap
         || pending_tx
@@ -671,18 +1093,9 @@ pub async fn extract_swap_info(
             .unwrap_or(true);
 
     if need_trace {
-        if let Ok(Some(trace_frame)) = debug_trace_call(provider, _new_block, pending_tx).await {
-            let mut logs = Vec::new();
-            extract_logs(&trace_frame, &mut logs);
-            for log in logs {
-                if let Some(addr) = log.address {
-                    if let Some(pool) = pools_map.get(&addr) {
-                        if let Some(si) = pool_direct_swap(tx_hash, addr, pool) {
-                            swap_info_vec.push(si);
-                        }
-                    }
-                }
-            }
+        match trace_swap_info(provider, new_block, pending_tx, pools_map).await {
+            Ok(traced) => swap_info_vec = traced,
+            Err(e) => debug!("trace_swap_info error: {e:?}"),
         }
         return Ok(swap_info_vec);
     }
@@ -694,112 +1107,63 @@ pub async fn extract_swap_info(
             tx_hash, method_id
         );
 
-        let is_router = is_known_router(&to_address);
-        println!("DEBUG extract_swap_info: is_router={}", is_router);
-
-        // Pool detection
-        let is_pool = pools_map.contains_key(&to_address);
-        println!("DEBUG extract_swap_info: is_pool={}", is_pool);
-
-        info!("Is router: {}, is known pool: {}", is_router, is_pool);
-
-        // Process direct pool transactions
-        if pools_map.contains_key(&to_address) {
-            let pool = pools_map.get(&to_address).unwrap();
-            info!(
-                "Found transaction to known pool: {:?}, token0: {:?}, token1: {:?}",
-                to_address, pool.token0, pool.token1
-            );
-
-            // Check for common swap function signatures
-            info!("Transaction function signature: 0x{}", method_id);
-
-            // Check if it's a direct swap on the pool
-            let direct_pool_swaps = [
-                "022c0d9f", // swap - UniswapV2Pair
-                "cdd6cda9", // swapFor0
-                "d50e6fcd", // swapFor1
-                "128acb08", // swap - UniswapV3Pool
-            ];
-
-            // Identify this as a direct pool swap
-            let method_id_no_prefix = method_id.trim_start_matches("0x");
-            let is_swap = direct_pool_swaps.contains(&method_id_no_prefix);
-            info!(
-                "Is direct pool swap: {} (method_id: {}, without prefix: {})",
-                is_swap, method_id, method_id_no_prefix
-            );
-
-            if is_swap {
-                let token0 = pool.token0;
-                let token1 = pool.token1;
-
-                // Determine which is the main currency (prioritize WETH, then stablecoins)
-                let weth_address = H160::from_str(WETH).unwrap_or_default();
-                let usdt_address = H160::from_str(USDT).unwrap_or_default();
-                let usdc_address = H160::from_str(USDC).unwrap_or_default();
-                let wbtc_address = H160::from_str(WBTC).unwrap_or_default();
-                let dai_address = H160::from_str(DAI).unwrap_or_default();
-                let link_address = H160::from_str(LINK).unwrap_or_default();
-                let mkr_address = H160::from_str(MKR).unwrap_or_default();
-
-                // For debugging: show token addresses
-                info!(
-                    "Main currency addresses - WETH: {:?}, USDT: {:?}, USDC: {:?}",
-                    weth_address, usdt_address, usdc_address
-                );
-                info!("Pool tokens - token0: {:?}, token1: {:?}", token0, token1);
-
-                let token0_is_main = token0 == weth_address
-                    || token0 == usdt_address
-                    || token0 == usdc_address
-                    || token0 == wbtc_address
-                    || token0 == dai_address
-                    || token0 == link_address
-                    || token0 == mkr_address;
-
-                let token1_is_main = token1 == weth_address
-                    || token1 == usdt_address
-                    || token1 == usdc_address
-                    || token1 == wbtc_address
-                    || token1 == dai_address
-                    || token1 == link_address
-                    || token1 == mkr_address;
-
-                // Only proceed if at least one token is a main currency
-                let has_main_currency = token0_is_main || token1_is_main;
+        info!("Detected transaction to router: {:?}", to_address);
+
+        // Swap selector detection
+        let selector_bytes: [u8; 4] = input.0[..4].try_into().unwrap();
+        if is_known_swap_selector(&selector_bytes) {
+            // Extract and process token paths
+            let paths = get_token_paths(&method_id, &input.0);
+            for path in paths {
+                // TODO: Support multi-hop paths by iterating through each adjacent token pair in `path`:
+                // 1. Initialize `current_amount = amount_in` from the frontrun or router input.
+                // 2. For each (token_in, token_out) window in `path.windows(2)`,
+                //    a) lookup `(pair_address, pool)` via `get_pool_by_tokens`,
+                //    b) fetch reserves via `simulator.get_pair_reserves(pair_address)`,
+                //    c) compute next_amount = if pool.version==V2 then get_v2_amount_out else get_v3_amount_out using `pool.fee`,
+                //    d) set `current_amount = next_amount` and proceed to next hop.
+                // 3. After all hops, `current_amount` is the final output, `pair_address` and `token_out` of last hop are used for SwapInfo.
+                if path.len() != 2 {
+                    warn!("Skipping multi-hop path with {} tokens", path.len());
+                    continue;
+                }
+                let token_in = path[0];
+                let token_out = path[1];
+
                 info!(
-                    "token0_is_main: {}, token1_is_main: {}, has_main_currency: {}",
-                    token0_is_main, token1_is_main, has_main_currency
+                    "Extracted path from router call: in={:?}, out={:?}",
+                    token_in, token_out
                 );
 
-                if has_main_currency {
-                    // Determine main currency and target token
-                    let (main_currency, target_token, is_token0_main) = if token0_is_main {
-                        (token0, token1, true)
+                // Check for the pool that would be used for this token pair
+                if let Some((pair_address, pool)) =
+                    get_pool_by_tokens(pools_map, token_in, token_out)
+                {
+                    // the pair's quote asset is the main currency whichever way it trades
+                    let (main_currency, target_token) =
+                        match return_main_and_target_currency(pool.token0, pool.token1) {
+                            Some(currencies) => currencies,
+                            None => continue,
+                        };
+                    let is_token0_main = main_currency == pool.token0;
+
+                    // Determine swap direction based on token flow
+                    let direction = if token_in == main_currency {
+                        SwapDirection::Buy
                     } else {
-                        (token1, token0, false)
+                        SwapDirection::Sell
                     };
 
-                    // Default to Buy direction for now
-                    let direction = SwapDirection::Buy;
-
-                    info!("SUCCESS: Detected direct pool swap: pool={:?}, direction={:?}, main={:?}, target={:?}",
-                          to_address, direction, main_currency, target_token);
-
-                    // Create SwapInfo with appropriate pool version
-                    let version = match pool.version {
-                        DexVariant::UniswapV2 => DexVariant::UniswapV2,
-                        DexVariant::UniswapV3 => DexVariant::UniswapV3,
-                    };
+                    info!("SUCCESS: Extracted swap from router call: pair={:?}, in={:?}, out={:?}, direction={:?}",
+                         pair_address, token_in, token_out, direction);
 
                     // Create SwapInfo
                     let swap_info = SwapInfo {
                         tx_hash,
-                        target_pair: to_address,
+                        target_pair: pair_address,
                         main_currency,
                         target_token,
-                        version,
+                        version: pool.version,
                         token0_is_main: is_token0_main,
                         fee: pool.fee,
                         direction,
@@ -809,137 +1173,11 @@ pub async fn extract_swap_info(
                 }
             }
         }
-        // Process router transactions
-        else if is_router {
-            info!("Detected transaction to router: {:?}", to_address);
-
-            // Swap selector detection
-            let selector_bytes: [u8; 4] = input.0[..4].try_into().unwrap();
-            if is_known_swap_selector(&selector_bytes) {
-                // Extract and process token paths
-                let paths = get_token_paths(&method_id, &input.0);
-                for path in paths {
-                    // TODO: Support multi-hop paths by iterating through each adjacent token pair in `path`:
-                    // 1. Initialize `current_amount = amount_in` from the frontrun or router input.
-                    // 2. For each (token_in, token_out) window in `path.windows(2)`,
-                    //    a) lookup `(pair_address, pool)` via `get_pool_by_tokens`,
-                    //    b) fetch reserves via `simulator.get_pair_reserves(pair_address)`,
-                    //    c) compute next_amount = if pool.version==V2 then get_v2_amount_out else get_v3_amount_out using `pool.fee`,
-                    //    d) set `current_amount = next_amount` and proceed to next hop.
-                    // 3. After all hops, `current_amount` is the final output, `pair_address` and `token_out` of last hop are used for SwapInfo.
-                    if path.len() != 2 {
-                        warn!("Skipping multi-hop path with {} tokens", path.len());
-                        continue;
-                    }
-                    let token_in = path[0];
-                    let token_out = path[1];
-
-                    info!(
-                        "Extracted path from router call: in={:?}, out={:?}",
-                        token_in, token_out
-                    );
-
-                    // Check for the pool that would be used for this token pair
-                    if let Some((pair_address, pool)) =
-                        get_pool_by_tokens(pools_map, token_in, token_out)
-                    {
-                        let token0 = pool.token0;
-                        let token1 = pool.token1;
-
-                        // Check if either token is a main currency (WETH or stablecoins)
-                        let weth_address = H160::from_str(WETH).unwrap_or_default();
-                        let usdt_address = H160::from_str(USDT).unwrap_or_default();
-                        let usdc_address = H160::from_str(USDC).unwrap_or_default();
-                        let wbtc_address = H160::from_str(WBTC).unwrap_or_default();
-                        let dai_address = H160::from_str(DAI).unwrap_or_default();
-                        let link_address = H160::from_str(LINK).unwrap_or_default();
-                        let mkr_address = H160::from_str(MKR).unwrap_or_default();
-
-                        let is_token0_main = token0 == weth_address
-                            || token0 == usdt_address
-                            || token0 == usdc_address
-                            || token0 == wbtc_address
-                            || token0 == dai_address
-                            || token0 == link_address
-                            || token0 == mkr_address;
-
-                        let is_token1_main = token1 == weth_address
-                            || token1 == usdt_address
-                            || token1 == usdc_address
-                            || token1 == wbtc_address
-                            || token1 == dai_address
-                            || token1 == link_address
-                            || token1 == mkr_address;
-
-                        let token0_is_main = (token0 == token_in && is_token0_main)
-                            || (token1 == token_in && is_token1_main);
-
-                        // Determine main currency and target token
-                        let (main_currency, target_token, is_token0_main) = if token0_is_main {
-                            (token0, token1, true)
-                        } else {
-                            (token1, token0, false)
-                        };
-
-                        // Determine swap direction based on token flow
-                        let direction = if token_in == main_currency {
-                            SwapDirection::Buy
-                        } else {
-                            SwapDirection::Sell
-                        };
-
-                        info!("SUCCESS: Extracted swap from router call: pair={:?}, in={:?}, out={:?}, direction={:?}",
-                             pair_address, token_in, token_out, direction);
-
-                        // Create SwapInfo
-                        let swap_info = SwapInfo {
-                            tx_hash,
-                            target_pair: pair_address,
-                            main_currency,
-                            target_token,
-                            version: pool.version,
-                            token0_is_main: is_token0_main,
-                            fee: pool.fee,
-                            direction,
-                        };
-
-                        swap_info_vec.push(swap_info);
-                    }
-                }
-            }
-        }
-        // Fallback: scan transaction logs for Swap events if no path detected
+        // Fallback: trace the transaction for Swap events if no path detected
         if swap_info_vec.is_empty() {
-            if let Ok(Some(receipt)) = provider.get_transaction_receipt(tx_hash).await {
-                for log in receipt.logs {
-                    let addr = log.address;
-                    if pools_map.contains_key(&addr) {
-                        if let Some(topic0) = log.topics.get(0) {
-                            if format!("{:?}", topic0) == V2_SWAP_EVENT_ID {
-                                let t1 = log.topics.get(1).cloned().unwrap_or_default();
-                                let t2 = log.topics.get(2).cloned().unwrap_or_default();
-                                let token0 = H160::from_slice(&t1.as_bytes()[12..]);
-                                let token1 = H160::from_slice(&t2.as_bytes()[12..]);
-                                let pool = pools_map.get(&addr).unwrap();
-                                let main = if is_main_token(token0) { token0 } else { token1 };
-                                let target = if is_main_token(token0) { token1 } else { token0 };
-                                let dir = if is_main_token(token0) { SwapDirection::Buy } else { SwapDirection::Sell };
-                                let swap_info = SwapInfo {
-                                    tx_hash,
-                                    target_pair: addr,
-                                    main_currency: main,
-                                    target_token: target,
-                                    version: pool.version,
-                                    token0_is_main: is_main_token(token0),
-                                    fee: pool.fee,
-                                    direction: dir,
-                                };
-                                info!("DEBUG: fallback swap event detected: {:?}", swap_info);
-                                swap_info_vec.push(swap_info);
-                            }
-                        }
-                    }
-                }
+            match trace_swap_info(provider, new_block, pending_tx, pools_map).await {
+                Ok(traced) => swap_info_vec = traced,
+                Err(e) => debug!("trace_swap_info error: {e:?}"),
             }
         }
     }
@@ -958,54 +1196,101 @@ pub async fn extract_swap_info(
     Ok(swap_info_vec)
 }
 
-pub async fn debug_trace_call(
-    provider: &Arc<Provider<Ws>>,
+/// Runs a pending transaction in the forked EVM and returns the swaps it makes on known pools.
+///
+/// Works against any JSON-RPC node: the transaction is traced locally with `trace_logs` in the
+/// next block env, and the direction of each swap is read from its `Swap` log. The trace blocks
+/// on revm state reads, so it runs on a blocking thread.
+///
+/// # Parameters
+/// * `provider`: &Arc<M> - The Ethereum provider.
+/// * `new_block`: &NewBlock - The latest block.
+/// * `pending_tx`: &NewPendingTx - The transaction to trace.
+/// * `pools_map`: &HashMap<H160, Pool> - Known pools.
+///
+/// # Returns
+/// * `Result<Vec<SwapInfo>>` - One entry per known pool that emitted a `Swap` log.
+pub async fn trace_swap_info<M: Middleware + 'static>(
+    provider: &Arc<M>,
     new_block: &NewBlock,
     pending_tx: &NewPendingTx,
-) -> Result<Option<CallFrame>> {
-    let mut opts = GethDebugTracingCallOptions::default();
-    let mut call_config = CallConfig::default();
-    call_config.with_log = Some(true);
+    pools_map: &HashMap<H160, Pool>,
+) -> Result<Vec<SwapInfo>> {
+    let tx = &pending_tx.tx;
+    let to = match tx.to {
+        Some(to) => to,
+        None => return Ok(Vec::new()), // contract creation
+    };
 
-    opts.tracing_options.tracer = Some(GethDebugTracerType::BuiltInTracer(
-        GethDebugBuiltInTracerType::CallTracer,
-    ));
-    opts.tracing_options.tracer_config = Some(GethDebugTracerConfig::BuiltInTracer(
-        GethDebugBuiltInTracerConfig::CallTracer(call_config),
-    ));
+    let traced_tx = Tx {
+        caller: tx.from,
+        transact_to: to,
+        data: tx.input.0.clone(),
+        value: tx.value,
+        gas_price: U256::zero(),
+        gas_limit: tx.gas.as_u64(),
+    };
+    let (provider, next_block) = (provider.clone(), new_block.next_block.clone());
+    let trace = tokio::task::spawn_blocking(move || {
+        let mut simulator = EvmSimulator::new(provider, None, &next_block);
+        simulator.trace_logs(traced_tx)
+    })
+    .await??;
 
-    let block_number = new_block.block_number;
-    let mut tx = pending_tx.tx.clone();
-    let nonce = provider
-        .get_transaction_count(tx.from, Some(block_number.into()))
-        .await
-        .unwrap_or_default();
-    tx.nonce = nonce;
-
-    let trace = provider
-        .debug_trace_call(&tx, Some(block_number.into()), opts)
-        .await;
-
-    match trace {
-        Ok(trace) => match trace {
-            GethTrace::Known(call_tracer) => match call_tracer {
-                GethTraceFrame::CallTracer(frame) => Ok(Some(frame)),
-                _ => Ok(None),
-            },
-            _ => Ok(None),
-        },
-        _ => Ok(None),
+    let mut swap_info_vec = Vec::new();
+    for log in trace.swaps() {
+        let pool = match pools_map.get(&log.address) {
+            Some(pool) => pool,
+            None => continue,
+        };
+        if let Some(mut swap_info) = pool_direct_swap(tx.hash, log.address, pool) {
+            // the main currency is paid into the pool when the victim buys
+            if let Some(token0_in) = log.token0_in() {
+                swap_info.direction = if token0_in == swap_info.token0_is_main {
+                    SwapDirection::Buy
+                } else {
+                    SwapDirection::Sell
+                };
+            }
+            swap_info_vec.push(swap_info);
+        }
     }
+    Ok(swap_info_vec)
 }
 
-pub fn extract_logs(call_frame: &CallFrame, logs: &mut Vec<CallLogFrame>) {
-    if let Some(ref logs_vec) = call_frame.logs {
-        logs.extend(logs_vec.iter().cloned());
+/// Returns the balance slot used to fund the bot with a main currency in simulations.
+///
+/// Resolves the slot through the token registry (discovering it if needed) and falls back to the
+/// slot configured for the quote asset.
+///
+/// # Parameters
+/// * `simulator`: &mut EvmSimulator<M> - The simulator to discover the slot with.
+/// * `main_currency`: H160 - The main currency address.
+///
+/// # Returns
+/// * `Result<BalanceSlot>` - The balance slot.
+pub fn main_currency_balance_slot<M: Middleware + 'static>(
+    simulator: &mut EvmSimulator<M>,
+    main_currency: H160,
+) -> Result<BalanceSlot> {
+    if let Some(slot) = resolve_balance_slot(simulator, main_currency)? {
+        return Ok(slot);
     }
+    match get_quote_assets().get(main_currency) {
+        Some(asset) => Ok(asset.balance_slot()),
+        None => Err(anyhow!("No balance slot for {:?}", main_currency)),
+    }
+}
 
-    if let Some(ref calls_vec) = call_frame.calls {
-        for call in calls_vec {
-            extract_logs(call, logs);
+/// Returns the output a trade entry asks for: exact for V2, with slippage room for V3.
+///
+/// V3 outputs are estimated from virtual reserves and the contract treats them as a minimum.
+pub fn min_amount_out(version: DexVariant, amount_out: U256) -> U256 {
+    match version {
+        DexVariant::UniswapV2 => amount_out,
+        DexVariant::UniswapV3 => {
+            amount_out * U256::from(BASIS_POINTS_DIVISOR - V3_MIN_OUT_SLIPPAGE_BPS)
+                / U256::from(BASIS_POINTS_DIVISOR)
         }
     }
 }
@@ -1018,30 +1303,6 @@ pub fn get_v2_amount_out(amount_in: U256, reserve_in: U256, reserve_out: U256) -
     amount_out.unwrap_or_default()
 }
 
-pub fn convert_usdt_to_weth(
-    simulator: &mut EvmSimulator<Provider<Ws>>,
-    amount: U256,
-) -> Result<U256> {
-    let conversion_pair = H160::from_str("0x0d4a11d5EEaaC28EC3F61d100daF4d40471f1852").unwrap();
-    // token0: WETH / token1: USDT
-    let reserves = simulator.get_pair_reserves(conversion_pair)?;
-    let (reserve_in, reserve_out) = (reserves.1, reserves.0);
-    let weth_out = get_v2_amount_out(amount, reserve_in, reserve_out);
-    Ok(weth_out)
-}
-
-pub fn convert_usdc_to_weth(
-    simulator: &mut EvmSimulator<Provider<Ws>>,
-    amount: U256,
-) -> Result<U256> {
-    let conversion_pair = H160::from_str("0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc").unwrap();
-    // token0: USDC / token1: WETH
-    let reserves = simulator.get_pair_reserves(conversion_pair)?;
-    let (reserve_in, reserve_out) = (reserves.0, reserves.1);
-    let weth_out = get_v2_amount_out(amount, reserve_in, reserve_out);
-    Ok(weth_out)
-}
-
 impl Sandwich {
     pub fn is_optimized(&mut self) -> bool {
         self.optimized_sandwich.is_some()
@@ -1073,7 +1334,7 @@ impl Sandwich {
     pub async fn optimize(
         &mut self,
         provider: Arc<Provider<Ws>>,
-        block_number: U64,
+        next_block: &NextBlockEnv,
         amount_in_ceiling: U256,
         base_fee: U256,
         max_fee: U256,
@@ -1084,10 +1345,9 @@ impl Sandwich {
 
         let mut min_amount_in = U256::zero();
         let mut max_amount_in = amount_in_ceiling;
-        let tolerance = if is_weth(main_currency) {
-            U256::from(1) * U256::from(10).pow(U256::from(14))
-        } else {
-            U256::from(1) * U256::from(10).pow(U256::from(3))
+        let tolerance = match get_quote_assets().get(main_currency) {
+            Some(asset) => asset.tolerance(),
+            None => U256::from(10).pow(U256::from(14)),
         };
 
         if max_amount_in < min_amount_in {
@@ -1133,7 +1393,7 @@ impl Sandwich {
                 let sim = tokio::task::spawn(simulate_sandwich(
                     idx,
                     provider.clone(),
-                    block_number,
+                    next_block.clone(),
                     self.clone(),
                     *input,
                     base_fee,
@@ -1218,7 +1478,7 @@ impl Sandwich {
 pub async fn simulate_sandwich(
     idx: usize,
     provider: Arc<Provider<Ws>>,
-    block_number: U64,
+    next_block: NextBlockEnv,
     sandwich: Sandwich,
     amount_in: U256,
     base_fee: U256,
@@ -1243,7 +1503,7 @@ pub async fn simulate_sandwich(
     let maybe_simulated_sandwich = match futures::executor::block_on(batch_sandwich.simulate(
         provider,
         None,
-        block_number,
+        &next_block,
         base_fee,
         max_fee,
         Some(front_access_list),
@@ -1257,6 +1517,15 @@ pub async fn simulate_sandwich(
         }
     };
 
+    // a bundle with a failing transaction won't land, so it earns nothing
+    if let Some(error) = maybe_simulated_sandwich
+        .as_ref()
+        .and_then(|sim| sim.errors.first())
+    {
+        debug!("simulate_sandwich [{}] amount_in {:?}: {}", idx, amount_in, error);
+        return (idx, amount_in, 0, 0, 0, Bytes::default(), Bytes::default());
+    }
+
     maybe_simulated_sandwich
         .map(|sim| {
             (
@@ -1289,47 +1558,123 @@ pub fn get_pool_by_tokens(
     None
 }
 
-// Helper function to decode token paths from input data based on method signature
+/// Decodes the token paths of a router call, in swap order.
+///
+/// Uniswap V2 router calls carry an `address[]` path; Uniswap V3 `SwapRouter` calls a
+/// token pair or a packed `bytes` path (reversed for `exactOutput`).
+///
+/// # Parameters
+/// * `method_id`: &str - The selector as hex, without `0x`.
+/// * `input`: &[u8] - The full calldata.
+///
+/// # Returns
+/// * `Vec<Vec<H160>>` - The decoded paths, empty for unsupported selectors or bad calldata.
 pub fn get_token_paths(method_id: &str, input: &[u8]) -> Vec<Vec<H160>> {
     let mut result = Vec::new();
     if input.len() <= 4 {
         return result;
     }
     let data = &input[4..];
-    // Decode V2 style path: address[]
-    let v2_sigs = ["7ff36ab5","fb3bdb41","b6f9de95","38ed1739","4a25d94a","18cbafe5","8803dbee","472b43f3","5c11d795","791ac947"];
-    if v2_sigs.contains(&method_id) {
-        if let Ok(tokens) = decode(&[ParamType::Array(Box::new(ParamType::Address))], data) {
-            if let Token::Array(arr) = &tokens[0] {
-                let path = arr.iter().filter_map(|t| if let Token::Address(a) = t { Some(*a) } else { None }).collect::<Vec<H160>>();
-                if path.len() >= 2 {
-                    result.push(path);
-                }
-            }
+    let uint = || ParamType::Uint(256);
+    let address_array = || ParamType::Array(Box::new(ParamType::Address));
+
+    let path = match method_id {
+        // swapExactETHForTokens, swapETHForExactTokens, and the fee-on-transfer variant
+        "7ff36ab5" | "fb3bdb41" | "b6f9de95" => {
+            let params = [uint(), address_array(), ParamType::Address, uint()];
+            decode(&params, data).ok().map(|tokens| addresses(&tokens[1]))
         }
-    }
-    // Decode V3 multi-hop path: bytes
-    let v3_sigs = ["04e45aaf","c04b8d59","5023b4df","09b81346"];
-    if v3_sigs.contains(&method_id) {
-        if let Ok(tokens) = decode(&[ParamType::Bytes], data) {
-            if let Token::Bytes(bytes) = &tokens[0] {
-                let mut path = Vec::new();
-                let mut i = 0;
-                while i + 20 <= bytes.len() {
-                    let mut addr = [0u8;20];
-                    addr.copy_from_slice(&bytes[i..i+20]);
-                    path.push(H160::from(addr));
-                    i += 23; // 20 bytes address + 3 bytes fee
-                }
-                if path.len() >= 2 {
-                    result.push(path);
-                }
-            }
+        // (amountIn, amountOut, path, to, deadline) token swaps
+        "38ed1739" | "4a25d94a" | "18cbafe5" | "8803dbee" | "5c11d795" | "791ac947" => {
+            let params = [uint(), uint(), address_array(), ParamType::Address, uint()];
+            decode(&params, data).ok().map(|tokens| addresses(&tokens[2]))
+        }
+        // SwapRouter02 swapExactTokensForTokens, without deadline
+        "472b43f3" => {
+            let params = [uint(), uint(), address_array(), ParamType::Address];
+            decode(&params, data).ok().map(|tokens| addresses(&tokens[2]))
+        }
+        // exactInputSingle / exactOutputSingle: (tokenIn, tokenOut, fee, recipient, ...)
+        "04e45aaf" | "5023b4df" => {
+            let params = ParamType::Tuple(vec![
+                ParamType::Address,
+                ParamType::Address,
+                ParamType::Uint(24),
+                ParamType::Address,
+                uint(),
+                uint(),
+                ParamType::Uint(160),
+            ]);
+            decode(&[params], data).ok().and_then(|tokens| match &tokens[0] {
+                Token::Tuple(fields) => Some(addresses(&Token::Array(fields[..2].to_vec()))),
+                _ => None,
+            })
+        }
+        // exactInput((path, recipient, deadline, amountIn, amountOutMinimum))
+        "c04b8d59" => {
+            let params = ParamType::Tuple(vec![
+                ParamType::Bytes,
+                ParamType::Address,
+                uint(),
+                uint(),
+                uint(),
+            ]);
+            decode(&[params], data).ok().and_then(|tokens| v3_path(&tokens[0]))
+        }
+        // exactOutput((path, recipient, amountOut, amountInMaximum)), path from tokenOut
+        "09b81346" => {
+            let params =
+                ParamType::Tuple(vec![ParamType::Bytes, ParamType::Address, uint(), uint()]);
+            decode(&[params], data).ok().and_then(|tokens| {
+                v3_path(&tokens[0]).map(|mut path| {
+                    path.reverse();
+                    path
+                })
+            })
+        }
+        _ => None,
+    };
+
+    if let Some(path) = path {
+        if path.len() >= 2 {
+            result.push(path);
         }
     }
     result
 }
 
+/// Returns the addresses of an `address[]` token.
+fn addresses(token: &Token) -> Vec<H160> {
+    match token {
+        Token::Array(items) => items
+            .iter()
+            .filter_map(|t| match t {
+                Token::Address(a) => Some(*a),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Returns the tokens of a packed V3 path (20 byte token, 3 byte fee, ...) in a call tuple.
+fn v3_path(params: &Token) -> Option<Vec<H160>> {
+    let bytes = match params {
+        Token::Tuple(fields) => match fields.first() {
+            Some(Token::Bytes(bytes)) => bytes,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let mut path = Vec::new();
+    let mut i = 0;
+    while i + 20 <= bytes.len() {
+        path.push(H160::from_slice(&bytes[i..i + 20]));
+        i += 23; // 20 bytes address + 3 bytes fee
+    }
+    Some(path)
+}
+
 // Process direct pool swap - helper for simulating direct pool transactions
 pub fn pool_direct_swap(tx_hash: H256, pool_address: H160, pool: &Pool) -> Option<SwapInfo> {
     if let Some((main_currency, target_token)) =
@@ -1354,45 +1699,18 @@ pub fn pool_direct_swap(tx_hash: H256, pool_address: H160, pool: &Pool) -> Optio
     None
 }
 
-// Helper function to check if a token is a main token (WETH or stablecoin)
-pub fn is_main_token(token: H160) -> bool {
-    let main_tokens = [
-        H160::from_str(WETH).unwrap_or_default(),
-        H160::from_str(USDT).unwrap_or_default(),
-        H160::from_str(USDC).unwrap_or_default(),
-        H160::from_str(DAI).unwrap_or_default(),
-    ];
-
-    main_tokens.contains(&token)
-}
-
 // Helper function to get token name from address
 pub fn token_address_to_name(token: H160) -> String {
-    match token {
-        t if t == H160::from_str(WETH).unwrap_or_default() => "WETH".to_string(),
-        t if t == H160::from_str(USDT).unwrap_or_default() => "USDT".to_string(),
-        t if t == H160::from_str(USDC).unwrap_or_default() => "USDC".to_string(),
-        t if t == H160::from_str(DAI).unwrap_or_default() => "DAI".to_string(),
-        t if t == H160::from_str(WBTC).unwrap_or_default() => "WBTC".to_string(),
-        t if t == H160::from_str(LINK).unwrap_or_default() => "LINK".to_string(),
-        t if t == H160::from_str(MKR).unwrap_or_default() => "MKR".to_string(),
-        _ => "Unknown".to_string(),
-    }
+    get_quote_assets().symbol(token)
 }
 
 // Helper function to get the method ID from input data
 pub fn get_method_id_hex(input: &[u8]) -> Option<String> {
-    let len = input.len();
-    println!("DEBUG get_method_id_hex: input.len={}", len);
-
-    if len < 4 {
+    if input.len() < 4 {
         return None;
     }
 
-    let first4 = hex::encode(&input[..4]);
-    println!("DEBUG get_method_id_hex: first4=0x{}", first4);
-
-    Some(first4)
+    Some(hex::encode(&input[..4]))
 }
 
 /// Calculates output amount for Uniswap V3 pools with a given fee tier (fee in hundredths of a bip).
@@ -1401,6 +1719,6 @@ pub fn get_v3_amount_out(amount_in: U256, reserve_in: U256, reserve_out: U256, f
     let fee_amount = U256::from(fee);
     let amount_in_with_fee = amount_in * (fee_denominator - fee_amount) / fee_denominator;
     let numerator = amount_in_with_fee * reserve_out;
-    let denominator = reserve_in * fee_denominator + amount_in_with_fee;
+    let denominator = reserve_in + amount_in_with_fee;
     numerator.checked_div(denominator).unwrap_or_default()
 }
@@ -1,452 +1,483 @@
 /// Sandwich attack strategy orchestration.
 ///
-/// Coordinates the full sandwich attack pipeline: pool/token discovery, event handling, opportunity detection, and bundle execution.
-use bounded_vec_deque::BoundedVecDeque;
+/// `SandwichStrategy` tracks pending swaps on known pools, looks for sandwich opportunities
+/// (`appetizer`) and builds their bundles (`main_dish` / `main_dish_v3`). It runs on the shared
+/// `Engine`, which owns the streams, pools, executor and alerting.
+///
+/// Pending transactions fan out to a `WorkerPool`: each worker detects the swaps of one
+/// transaction and simulates its sandwiches, reading the shared pools from the context. The
+/// simulated opportunities go to a single coordinator task, which owns the pending swaps and
+/// the slot's `OpportunityBook` and makes the batching decisions, so bundles are built from one
+/// consistent view of every opportunity. Bundles are built at the `SlotClock` cut-offs rather
+/// than on every new victim.
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
 use ethers::signers::{LocalWallet, Signer};
 use ethers::{
     providers::{Middleware, Provider, Ws},
-    types::{BlockNumber, H160, H256, U256, U64},
+    types::{H160, H256, U256, U64},
 };
-use log::{error, info};
-use std::{collections::HashMap, str::FromStr, sync::Arc};
-use tokio::sync::broadcast::Sender;
-use tokio::time::Duration;
-use ethers::types::Filter;
-use ethers::abi::{decode as abi_decode, ParamType};
-use futures::StreamExt;
-
-use crate::common::alert::Alert;
+use log::{debug, error, info, warn};
+use std::{collections::HashMap, str::FromStr, sync::Arc, time::Duration};
+use tokio::runtime::Handle;
+use tokio::sync::mpsc;
+
 use crate::common::constants::Env;
+use crate::common::engine::{
+    Action, ActionSender, BundleResult, EngineContext, PendingTxJob, Strategy,
+};
 use crate::common::execution::Executor;
-use crate::common::pools::{load_all_pools, Pool};
-use crate::common::streams::{Event, NewBlock};
-use crate::common::tokens::load_all_tokens;
-use crate::common::utils::calculate_next_block_base_fee;
+use crate::common::slot_clock::{now_ms, SlotClock, DEFAULT_CUTOFFS};
+use crate::common::streams::{NewBlock, NewPendingTx};
+use crate::common::worker_pool::{WorkerPool, DEFAULT_QUEUE_CAPACITY, DEFAULT_WORKERS};
 use crate::sandwich::appetizer::appetizer;
 use crate::sandwich::main_dish::main_dish;
 use crate::sandwich::main_dish_v3::main_dish_v3;
-use crate::sandwich::simulation::{extract_swap_info, PendingTxInfo, Sandwich};
+use crate::sandwich::opportunity_book::OpportunityBook;
+use crate::sandwich::simulation::{PendingTxInfo, Sandwich};
+
+/// Pending transactions are dropped this many blocks after they were first seen.
+const PENDING_TX_MAX_AGE: u64 = 3;
 
 /// Sandwich execution mode selection
+#[derive(Debug, Clone, Copy)]
 pub enum SandwichMode {
     /// Traditional sandwich with separate front-run and back-run transactions
     Traditional,
-    /// Aave V3 flashloan-based sandwich with atomic execution
-    FlashloanV3,
+    /// SandoooV3 sandwich with inventory-funded `executeLeg` legs around the victim
+    SandoooV3,
 }
 
-/// Runs the full sandwich attack strategy event loop.
-///
-/// Handles:
-/// - Loading pools and tokens
-/// - Filtering and updating pending transactions
-/// - Detecting sandwich opportunities
-/// - Executing and submitting bundles
+/// A pending swap simulated by a worker.
+#[derive(Debug, Clone)]
+pub struct SimulatedOpportunity {
+    /// The victim and the swaps it makes.
+    pub info: PendingTxInfo,
+    /// Its promising sandwiches, empty if none.
+    pub sandwiches: Vec<Sandwich>,
+}
+
+/// Messages to the coordinator task.
+#[derive(Debug)]
+enum CoordinatorMessage {
+    /// A new block was mined.
+    Block {
+        new_block: NewBlock,
+        block_txs: Vec<H256>,
+    },
+    /// A pending transaction turned out to be confirmed.
+    Confirmed(H256),
+    /// A worker simulated a pending swap.
+    Opportunity(Box<SimulatedOpportunity>),
+}
+
+/// Sandwiches pending swaps on known pools.
+pub struct SandwichStrategy {
+    mode: SandwichMode,
+    owner: H160,
+    bot_address: H160,
+    bribe_pct: U256,
+    workers: usize,
+    queue_capacity: usize,
+    cutoffs: Vec<Duration>,
+    pool: Option<WorkerPool<PendingTxJob>>,
+    coordinator: Option<mpsc::UnboundedSender<CoordinatorMessage>>,
+}
+
+impl SandwichStrategy {
+    /// Creates a strategy for the bot and owner configured in the environment.
+    ///
+    /// # Parameters
+    /// * `mode`: SandwichMode - The contract the bundles go through.
+    ///
+    /// # Returns
+    /// * `SandwichStrategy` - The strategy, bribing 99% of the revenue.
+    pub fn new(mode: SandwichMode) -> Self {
+        let env = Env::new();
+        let bot_address = H160::from_str(&env.bot_address).unwrap();
+        let owner = env
+            .private_key
+            .parse::<LocalWallet>()
+            .unwrap()
+            .with_chain_id(1 as u64)
+            .address();
+        Self {
+            mode,
+            owner,
+            bot_address,
+            bribe_pct: U256::from(9900), // 99%
+            workers: DEFAULT_WORKERS,
+            queue_capacity: DEFAULT_QUEUE_CAPACITY,
+            cutoffs: DEFAULT_CUTOFFS.to_vec(),
+            pool: None,
+            coordinator: None,
+        }
+    }
+
+    /// Sets the share of the revenue paid as bribe, in basis points.
+    pub fn with_bribe_pct(mut self, bribe_pct: U256) -> Self {
+        self.bribe_pct = bribe_pct;
+        self
+    }
+
+    /// Sets the number of workers and the pending transactions queued for them.
+    pub fn with_workers(mut self, workers: usize, queue_capacity: usize) -> Self {
+        self.workers = workers;
+        self.queue_capacity = queue_capacity;
+        self
+    }
+
+    /// Sets when bundles are built within the slot, as offsets from its start.
+    ///
+    /// Opportunities are collected until the first cut-off; later ones re-submit if better
+    /// opportunities arrived in between, as does any opportunity found after the last. Without
+    /// cut-offs, every better opportunity is submitted as soon as it is found.
+    pub fn with_cutoffs(mut self, cutoffs: Vec<Duration>) -> Self {
+        self.cutoffs = cutoffs;
+        self
+    }
+}
+
+/// Detects the swaps of a pending transaction and simulates its sandwiches.
 ///
 /// # Parameters
-/// * `provider`: Ethereum provider.
-/// * `event_sender`: Channel to receive block and transaction events.
-pub async fn run_sandwich_strategy(provider: Arc<Provider<Ws>>, event_sender: Sender<Event>) -> ! {
-    // Add debugging logs
-    eprintln!("DEBUG: Initializing sandwich strategy");
-
-    let env = Env::new();
-    eprintln!("DEBUG: Environment loaded. WSS URL: {}", env.wss_url);
-
-    // Determine sandwich mode - could be loaded from environment or config
-    // For now, we'll use Flashloan V3 as the default
-    let sandwich_mode = SandwichMode::FlashloanV3;
-
-    info!(
-        "Starting sandwich strategy with mode: {}",
-        match sandwich_mode {
-            SandwichMode::Traditional => "Traditional",
-            SandwichMode::FlashloanV3 => "Aave V3 Flashloan",
+/// * `ctx`: &EngineContext - The shared state.
+/// * `job`: PendingTxJob - The pending transaction and the block it was seen at.
+/// * `coordinator`: &mpsc::UnboundedSender<CoordinatorMessage> - Where results are sent.
+///
+/// # Returns
+/// * `Result<()>` - An error if the swaps could not be detected.
+async fn process_pending_tx(
+    ctx: &EngineContext,
+    job: PendingTxJob,
+    coordinator: &mpsc::UnboundedSender<CoordinatorMessage>,
+) -> Result<()> {
+    let PendingTxJob {
+        new_block,
+        mut pending_tx,
+    } = job;
+    let tx_hash = pending_tx.tx.hash;
+
+    // a receipt means the tx is already confirmed
+    match ctx.provider.get_transaction_receipt(tx_hash).await {
+        Ok(Some(_)) => {
+            let _ = coordinator.send(CoordinatorMessage::Confirmed(tx_hash));
+            return Ok(());
         }
-    );
-
-    eprintln!("DEBUG: About to load pools. Start block: 22413000, Chunk: 50000");
-
-    let (pools, prev_pool_id) = load_all_pools(env.wss_url.clone(), 22413000, 50000)
-        .await
-        .unwrap();
-
-    eprintln!(
-        "DEBUG: Pools loaded successfully. Count: {}. prev_pool_id: {}",
-        pools.len(),
-        prev_pool_id
-    );
-
-    eprintln!("DEBUG: Getting current block number");
-    let block_number = provider.get_block_number().await.unwrap();
-    eprintln!("DEBUG: Current block number: {}", block_number);
-
-    eprintln!("DEBUG: Loading tokens for pools");
-    let tokens_map = load_all_tokens(&provider, block_number, &pools, prev_pool_id)
-        .await
-        .unwrap();
-    eprintln!(
-        "DEBUG: Tokens loaded successfully. Count: {}",
-        tokens_map.len()
-    );
-    info!("Tokens map count: {:?}", tokens_map.len());
-
-    // filter pools that don't have both token0 / token1 info
-    eprintln!("DEBUG: Filtering pools based on token existence");
-    let pools_vec: Vec<Pool> = pools
-        .into_iter()
-        .filter(|p| {
-            let token0_exists = tokens_map.contains_key(&p.token0);
-            let token1_exists = tokens_map.contains_key(&p.token1);
-            token0_exists && token1_exists
-        })
-        .collect();
-    eprintln!("DEBUG: Filtered pools count: {}", pools_vec.len());
-    info!("Filtered pools by tokens count: {:?}", pools_vec.len());
-
-    eprintln!("DEBUG: Creating pools map");
-    let pools_map: HashMap<H160, Pool> = pools_vec
-        .clone()
-        .into_iter()
-        .map(|p| (p.address, p))
-        .collect();
-    eprintln!("DEBUG: Pools map created with {} entries", pools_map.len());
-
-    // Subscribe to Sync events to update pool reserves
-    eprintln!("DEBUG: Subscribing to Sync events for pool reserves");
-    let sync_addresses = pools_map.keys().cloned().collect::<Vec<H160>>();
-    let sync_filter = Filter::new().event("Sync(uint112,uint112)").address(sync_addresses);
-
-    // Spawn a task to subscribe to Sync events without borrowing `provider`
-    let provider_clone = provider.clone();
-    let filter_clone = sync_filter.clone();
-    tokio::spawn(async move {
-        let mut sync_stream = provider_clone.subscribe_logs(&filter_clone).await.unwrap();
-        let mut pool_reserves: HashMap<H160, (U256, U256)> = HashMap::new();
-        while let Some(log) = sync_stream.next().await {
-            if let Ok(tokens) = abi_decode(&[ParamType::Uint(112), ParamType::Uint(112)], &log.data) {
-                let r0 = tokens[0].clone().into_uint().unwrap();
-                let r1 = tokens[1].clone().into_uint().unwrap();
-                pool_reserves.insert(log.address, (r0, r1));
-            }
+        Ok(None) => {}
+        Err(e) => warn!("get_transaction_receipt error: {e:?}"),
+    }
+
+    let victim_gas_price = match pending_tx.tx.transaction_type {
+        Some(tx_type) if tx_type == U64::from(2) => {
+            pending_tx.tx.max_fee_per_gas.unwrap_or_default()
         }
-    });
-
-    eprintln!("DEBUG: Getting latest block");
-    let block = provider
-        .get_block(BlockNumber::Latest)
-        .await
-        .unwrap()
-        .unwrap();
-    eprintln!("DEBUG: Latest block retrieved: #{}", block.number.unwrap());
-
-    eprintln!("DEBUG: Creating new block structure");
-    let mut new_block = NewBlock {
-        block_number: block.number.unwrap(),
-        base_fee: block.base_fee_per_gas.unwrap(),
-        next_base_fee: calculate_next_block_base_fee(
-            block.gas_used,
-            block.gas_limit,
-            block.base_fee_per_gas.unwrap(),
-        ),
+        _ => pending_tx.tx.gas_price.unwrap_or_default(),
+    };
+
+    let swap_info = ctx
+        .swaps
+        .get_or_extract(&ctx.provider, &new_block, &pending_tx, &ctx.pools_map)
+        .await?;
+    if swap_info.is_empty() {
+        return Ok(());
+    }
+
+    pending_tx.added_block = Some(new_block.block_number);
+    let info = PendingTxInfo {
+        pending_tx,
+        touched_pairs: swap_info,
     };
-    eprintln!(
-        "DEBUG: New block created with next base fee: {}",
-        new_block.next_base_fee
-    );
-
-    eprintln!("DEBUG: Initializing alert system");
-    let alert = Alert::new();
-    eprintln!("DEBUG: Initializing executor");
-    let executor = Executor::new(provider.clone());
-
-    eprintln!("DEBUG: Setting up wallet and addresses");
-    let bot_address = H160::from_str(&env.bot_address).unwrap();
-    let wallet = env
-        .private_key
-        .parse::<LocalWallet>()
-        .unwrap()
-        .with_chain_id(1 as u64);
-    let owner = wallet.address();
-    eprintln!("DEBUG: Bot address: {:?}, Owner: {:?}", bot_address, owner);
-
-    eprintln!("DEBUG: Creating event subscription");
-    let mut event_receiver = event_sender.subscribe();
-
-    eprintln!("DEBUG: Initializing data structures");
-    let mut pending_txs: HashMap<H256, PendingTxInfo> = HashMap::new();
-    let mut promising_sandwiches: HashMap<H256, Vec<Sandwich>> = HashMap::new();
-    let mut simulated_bundle_ids = BoundedVecDeque::new(30);
-
-    eprintln!("DEBUG: Initialization complete, entering main loop");
-
-    let _last_block_number = U64::zero();
-
-    loop {
-        eprintln!("DEBUG: Waiting for next event...");
-        match event_receiver.recv().await {
-            Ok(event) => match event {
-                Event::Block(block) => {
-                    eprintln!(
-                        "DEBUG: Received Block event for block #{}",
-                        block.block_number
-                    );
-                    new_block = block;
-                    info!("[Block #{:?}]", new_block.block_number);
-
-                    // remove confirmed transactions
-                    eprintln!("DEBUG: Fetching block with transactions");
-                    let block_with_txs = provider
-                        .get_block_with_txs(new_block.block_number)
-                        .await
-                        .unwrap()
-                        .unwrap();
-
-                    let txs: Vec<H256> = block_with_txs
-                        .transactions
-                        .into_iter()
-                        .map(|tx| tx.hash)
-                        .collect();
-                    eprintln!("DEBUG: Block contained {} transactions", txs.len());
-
-                    let mut removed_count = 0;
-                    for tx_hash in &txs {
-                        if pending_txs.contains_key(tx_hash) {
-                            let _ = pending_txs.remove(tx_hash); // Ignoring the removed value
-                            removed_count += 1;
-                        }
+
+    // the appetizer only reads the victim's own entry
+    let pending_txs = HashMap::from([(tx_hash, info)]);
+    // its simulations block on revm state reads, so it runs off the async workers
+    let provider = ctx.provider.clone();
+    let runtime = Handle::current();
+    let (pending_txs, mut promising_sandwiches) = tokio::task::spawn_blocking(move || {
+        let mut promising_sandwiches = HashMap::new();
+        if let Err(e) = runtime.block_on(appetizer(
+            &provider,
+            &new_block,
+            tx_hash,
+            victim_gas_price,
+            &pending_txs,
+            &mut promising_sandwiches,
+        )) {
+            error!("appetizer error: {e:?}");
+        }
+        (pending_txs, promising_sandwiches)
+    })
+    .await?;
+
+    let opportunity = SimulatedOpportunity {
+        info: pending_txs.into_values().next().unwrap(),
+        sandwiches: promising_sandwiches.remove(&tx_hash).unwrap_or_default(),
+    };
+    if !opportunity.sandwiches.is_empty() {
+        ctx.metrics.opportunity();
+    }
+    coordinator
+        .send(CoordinatorMessage::Opportunity(Box::new(opportunity)))
+        .map_err(|_| anyhow!("sandwich coordinator stopped"))
+}
+
+/// Owns the pending swaps and the opportunity book and batches them into bundles.
+struct Coordinator {
+    mode: SandwichMode,
+    owner: H160,
+    bot_address: H160,
+    bribe_pct: U256,
+    provider: Arc<Provider<Ws>>,
+    executor: Arc<Executor>,
+    actions: ActionSender,
+    new_block: NewBlock,
+    pending_txs: HashMap<H256, PendingTxInfo>,
+    clock: SlotClock,
+    book: OpportunityBook,
+}
+
+impl Coordinator {
+    /// Handles messages and cut-offs until every sender is dropped.
+    async fn run(mut self, mut receiver: mpsc::UnboundedReceiver<CoordinatorMessage>) {
+        self.clock.on_block(&self.new_block.next_block);
+        loop {
+            let cutoff = self.clock.next_cutoff();
+            let until = cutoff.map_or(Duration::ZERO, |c| SlotClock::until(&c, now_ms()));
+            tokio::select! {
+                message = receiver.recv() => match message {
+                    Some(CoordinatorMessage::Block {
+                        new_block,
+                        block_txs,
+                    }) => self.on_block(new_block, block_txs),
+                    Some(CoordinatorMessage::Confirmed(tx_hash)) => {
+                        self.pending_txs.remove(&tx_hash);
+                        self.book.remove(&tx_hash);
                     }
-                    eprintln!(
-                        "DEBUG: Removed {} confirmed transactions, {} pending remaining",
-                        removed_count,
-                        pending_txs.len()
-                    );
-
-                    // CRITICAL: Remove pending txs older than 3 blocks
-                    // This was missing and causing the bot to get stuck
-                    eprintln!("DEBUG: Cleaning up old pending transactions");
-                    let old_count = pending_txs.len();
-                    pending_txs.retain(|_, v| {
-                        v.pending_tx
-                            .added_block
-                            .map_or(true, |block| {
-                                (new_block.block_number - block) < U64::from(3)
-                            })
-                    });
-                    promising_sandwiches.retain(|h, _| pending_txs.contains_key(h));
-
-                    eprintln!(
-                        "DEBUG: Removed {} old pending transactions, {} pending remaining",
-                        old_count - pending_txs.len(),
-                        pending_txs.len()
-                    );
-                }
-                Event::PendingTransaction(mut pending_tx) => {
-                    eprintln!(
-                        "DEBUG: Received PendingTransaction event: {:?}",
-                        pending_tx.tx.hash
-                    );
-                    let tx_hash = pending_tx.tx.hash;
-                    let mut should_add = false;
-
-                    // Check if tx is already mined
-                    eprintln!("DEBUG: Checking if transaction is already mined");
-                    match provider.get_transaction_receipt(tx_hash).await {
-                        Ok(receipt) => {
-                            match receipt {
-                                Some(_) => {
-                                    eprintln!("DEBUG: Transaction already mined, removing");
-                                    // returning a receipt means that the tx is confirmed
-                                    // should not be in pending_txs
-                                    pending_txs.remove(&tx_hash);
-                                }
-                                None => {
-                                    eprintln!("DEBUG: Transaction not yet mined");
-                                    should_add = true;
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            eprintln!("DEBUG: Error checking transaction receipt: {:?}", e);
-                        }
+                    Some(CoordinatorMessage::Opportunity(opportunity)) => {
+                        self.on_opportunity(*opportunity).await
                     }
+                    None => break,
+                },
+                _ = tokio::time::sleep(until), if cutoff.is_some() => self.on_cutoff().await,
+            }
+        }
+        debug!("sandwich coordinator stopped");
+    }
 
-                    let mut victim_gas_price = U256::zero();
-
-                    match pending_tx.tx.transaction_type {
-                        Some(tx_type) => {
-                            if tx_type == U64::zero() {
-                                victim_gas_price = pending_tx.tx.gas_price.unwrap_or_default();
-                                should_add = victim_gas_price >= new_block.base_fee;
-                                eprintln!("DEBUG: Legacy transaction type (0), gas price: {}, should_add: {}", 
-                                          victim_gas_price, should_add);
-                            } else if tx_type == U64::from(2) {
-                                victim_gas_price =
-                                    pending_tx.tx.max_fee_per_gas.unwrap_or_default();
-                                should_add = victim_gas_price >= new_block.base_fee;
-                                eprintln!("DEBUG: EIP-1559 transaction type (2), max fee: {}, should_add: {}", 
-                                          victim_gas_price, should_add);
-                            }
-                        }
-                        _ => {}
-                    }
+    fn on_block(&mut self, new_block: NewBlock, block_txs: Vec<H256>) {
+        // remove confirmed transactions
+        for tx_hash in &block_txs {
+            self.pending_txs.remove(tx_hash);
+        }
 
-                    eprintln!(
-                        "DEBUG: Checking for swap in transaction (should_add={})",
-                        should_add
-                    );
-
-                    // TEMPORARY DEBUG MODE: Force check for swaps even when should_add is false
-                    // This helps us test the swap detection logic without being limited by gas filters
-                    let force_check = true; // Set to true to force checking all transactions
-
-                    let swap_info = if should_add || force_check {
-                        match extract_swap_info(&provider, &new_block, &pending_tx, &pools_map)
-                            .await
-                        {
-                            Ok(swap_info) => {
-                                eprintln!(
-                                    "DEBUG: extract_swap_info returned {} items",
-                                    swap_info.len()
-                                );
-                                swap_info
-                            }
-                            Err(e) => {
-                                eprintln!("DEBUG: extract_swap_info error: {:?}", e);
-                                error!("extract_swap_info error: {e:?}");
-                                Vec::new()
-                            }
-                        }
-                    } else {
-                        eprintln!("DEBUG: Skipping swap check as should_add=false");
-                        Vec::new()
-                    };
-
-                    if swap_info.len() > 0 {
-                        eprintln!("DEBUG: Found {} swap pairs in transaction", swap_info.len());
-                        pending_tx.added_block = Some(new_block.block_number);
-                        let pending_tx_info = PendingTxInfo {
-                            pending_tx: pending_tx.clone(),
-                            touched_pairs: swap_info.clone(),
-                        };
-                        pending_txs.insert(tx_hash, pending_tx_info.clone());
-                        eprintln!(
-                            "DEBUG: Added transaction to pending_txs, count now: {}",
-                            pending_txs.len()
-                        );
-                        // info!(
-                        //     "🔴 V{:?} TX ADDED: {:?} / Pending txs: {:?}",
-                        //     pending_tx_info.touched_pairs.get(0).unwrap().version,
-                        //     tx_hash,
-                        //     pending_txs.len()
-                        // );
-
-                        eprintln!("DEBUG: Calling appetizer for potential sandwich opportunity");
-                        match appetizer(
-                            &provider,
-                            &new_block,
-                            tx_hash,
-                            victim_gas_price,
-                            &pending_txs,
-                            &mut promising_sandwiches,
-                        )
-                        .await
-                        {
-                            Ok(_) => {
-                                eprintln!("DEBUG: appetizer returned successfully, promising sandwiches: {}", 
-                                          promising_sandwiches.len());
-                            }
-                            Err(e) => {
-                                eprintln!("DEBUG: appetizer error: {:?}", e);
-                                error!("appetizer error: {e:?}");
-                            }
-                        }
-
-                        if promising_sandwiches.len() > 0 {
-                            eprintln!(
-                                "DEBUG: Found {} promising sandwiches, executing...",
-                                promising_sandwiches.len()
-                            );
-                            // Choose execution strategy based on sandwich mode
-                            match sandwich_mode {
-                                SandwichMode::Traditional => {
-                                    match main_dish(
-                                        &provider,
-                                        &alert,
-                                        &executor,
-                                        &new_block,
-                                        owner,
-                                        bot_address,
-                                        U256::from(9900), // 99%
-                                        &promising_sandwiches,
-                                        &mut simulated_bundle_ids,
-                                        &pending_txs,
-                                    )
-                                    .await
-                                    {
-                                        Err(e) => {
-                                            eprintln!("DEBUG: main_dish error: {:?}", e);
-                                            error!("main_dish error: {e:?}");
-                                        }
-                                        _ => {}
-                                    }
-                                }
-                                SandwichMode::FlashloanV3 => {
-                                    match main_dish_v3(
-                                        &provider,
-                                        &alert,
-                                        &executor,
-                                        &new_block,
-                                        owner,
-                                        bot_address,
-                                        U256::from(9900), // 99%
-                                        &promising_sandwiches,
-                                        &mut simulated_bundle_ids,
-                                        &pending_txs,
-                                    )
-                                    .await
-                                    {
-                                        Err(e) => {
-                                            eprintln!("DEBUG: main_dish_v3 error: {:?}", e);
-                                            error!("main_dish_v3 error: {e:?}");
-                                        }
-                                        _ => {}
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            },
-            _ => {}
+        // victims that did not land in a few blocks are unlikely to
+        let block_number = new_block.block_number;
+        self.pending_txs.retain(|_, v| {
+            v.pending_tx.added_block.map_or(true, |added| {
+                block_number.saturating_sub(added) < U64::from(PENDING_TX_MAX_AGE)
+            })
+        });
+
+        // a new slot: what is still pending goes into the next block's bundles
+        let pending_txs = &self.pending_txs;
+        self.book
+            .roll(new_block.next_block.number, |h| pending_txs.contains_key(h));
+        self.clock.on_block(&new_block.next_block);
+
+        self.new_block = new_block;
+        info!(
+            "Number of pending transactions: {} / Promising: {}",
+            self.pending_txs.len(),
+            self.book.len()
+        );
+    }
+
+    async fn on_opportunity(&mut self, opportunity: SimulatedOpportunity) {
+        let tx_hash = opportunity.info.pending_tx.tx.hash;
+
+        // blocks arrived while the job waited, the victim is past the age `on_block` keeps
+        let block_number = self.new_block.block_number;
+        let expired = opportunity
+            .info
+            .pending_tx
+            .added_block
+            .map_or(false, |added| {
+                block_number.saturating_sub(added) >= U64::from(PENDING_TX_MAX_AGE)
+            });
+        if expired {
+            return;
         }
 
-        // Check for transactions that need to be flushed (too old)
-        let current_block = new_block.block_number;
-        let cutoff_block = if current_block > U64::from(5) {
-            current_block - U64::from(5)
-        } else {
-            U64::zero()
+        self.pending_txs.insert(tx_hash, opportunity.info);
+        if opportunity.sandwiches.is_empty() {
+            return;
+        }
+        self.book.insert(tx_hash, opportunity.sandwiches);
+
+        // after the last cut-off, better opportunities are re-submitted right away
+        if self.clock.is_late() && self.book.should_submit() {
+            info!(
+                "Late re-submission for block {:?}",
+                self.book.target_block()
+            );
+            self.submit().await;
+        }
+    }
+
+    async fn on_cutoff(&mut self) {
+        let cutoff = match self.clock.pass_cutoff() {
+            Some(cutoff) => cutoff,
+            None => return,
         };
+        if !self.book.should_submit() {
+            return;
+        }
+        info!(
+            "Cut-off {} of block {:?}: {} victims, {:?} revenue",
+            cutoff.index,
+            self.book.target_block(),
+            self.book.len(),
+            self.book.value()
+        );
+        self.submit().await;
+    }
 
-        // Filter out old transactions
-        let old_txs: Vec<H256> = pending_txs
-            .iter()
-            .filter(|(_, v)| {
-                v.pending_tx
-                    .added_block
-                    .map_or(false, |block| block < cutoff_block)
-            })
-            .map(|(k, _)| *k)
-            .collect();
+    /// Builds the bundles of the book and sends them to the engine.
+    async fn submit(&mut self) {
+        let (promising_sandwiches, submitted_bundle_ids) = self.book.for_submission();
+        let result = match self.mode {
+            SandwichMode::Traditional => {
+                main_dish(
+                    &self.provider,
+                    &self.executor,
+                    &self.new_block,
+                    self.owner,
+                    self.bot_address,
+                    self.bribe_pct,
+                    promising_sandwiches,
+                    submitted_bundle_ids,
+                    &self.pending_txs,
+                )
+                .await
+            }
+            SandwichMode::SandoooV3 => {
+                main_dish_v3(
+                    &self.provider,
+                    &self.executor,
+                    &self.new_block,
+                    self.owner,
+                    self.bot_address,
+                    self.bribe_pct,
+                    promising_sandwiches,
+                    submitted_bundle_ids,
+                    &self.pending_txs,
+                )
+                .await
+            }
+        };
+        self.book.mark_submitted();
+        match result {
+            Ok(actions) => {
+                self.actions.send(actions);
+            }
+            Err(e) => error!("main_dish error: {e:?}"),
+        }
+    }
+}
 
-        for tx_hash in old_txs {
-            pending_txs.remove(&tx_hash);
+#[async_trait]
+impl Strategy for SandwichStrategy {
+    fn name(&self) -> &str {
+        match self.mode {
+            SandwichMode::Traditional => "sandwich",
+            SandwichMode::SandoooV3 => "sandwich-v3",
         }
+    }
+
+    async fn start(&mut self, ctx: &EngineContext, actions: ActionSender) -> Result<()> {
+        let (coordinator, receiver) = mpsc::unbounded_channel();
+        let state = Coordinator {
+            mode: self.mode,
+            owner: self.owner,
+            bot_address: self.bot_address,
+            bribe_pct: self.bribe_pct,
+            provider: ctx.provider.clone(),
+            executor: ctx.executor.clone(),
+            actions,
+            new_block: ctx.new_block.clone(),
+            pending_txs: HashMap::new(),
+            clock: SlotClock::new(self.cutoffs.clone()),
+            book: OpportunityBook::new(ctx.new_block.next_block.number),
+        };
+        tokio::spawn(state.run(receiver));
+
+        let (worker_ctx, worker_coordinator) = (ctx.clone(), coordinator.clone());
+        let pool = WorkerPool::spawn(
+            self.name(),
+            self.workers,
+            self.queue_capacity,
+            ctx.metrics.clone(),
+            move |job: PendingTxJob| {
+                let (ctx, coordinator) = (worker_ctx.clone(), worker_coordinator.clone());
+                async move { process_pending_tx(&ctx, job, &coordinator).await }
+            },
+        );
 
-        info!("Number of pending transactions: {}", pending_txs.len());
+        self.pool = Some(pool);
+        self.coordinator = Some(coordinator);
+        Ok(())
+    }
 
-        // Small delay to avoid high CPU usage
-        tokio::time::sleep(Duration::from_millis(10)).await;
+    async fn on_block(&mut self, ctx: &EngineContext) -> Result<Vec<Action>> {
+        let coordinator = self
+            .coordinator
+            .as_ref()
+            .ok_or_else(|| anyhow!("{} not started", self.name()))?;
+        coordinator
+            .send(CoordinatorMessage::Block {
+                new_block: ctx.new_block.clone(),
+                block_txs: ctx.block_txs.clone(),
+            })
+            .map_err(|_| anyhow!("sandwich coordinator stopped"))?;
+        Ok(Vec::new())
+    }
+
+    async fn on_pending_tx(
+        &mut self,
+        ctx: &EngineContext,
+        pending_tx: &NewPendingTx,
+    ) -> Result<Vec<Action>> {
+        let pool = self
+            .pool
+            .as_ref()
+            .ok_or_else(|| anyhow!("{} not started", self.name()))?;
+        pool.submit(PendingTxJob {
+            new_block: ctx.new_block.clone(),
+            pending_tx: pending_tx.clone(),
+        });
+        Ok(Vec::new())
+    }
+
+    async fn on_bundle_result(
+        &mut self,
+        _ctx: &EngineContext,
+        result: &BundleResult,
+    ) -> Result<Vec<Action>> {
+        match &result.responses {
+            Ok(responses) => {
+                let message = format!(
+                    "[{:?}] Bundle sent to {} builders ({})",
+                    result.target_block,
+                    responses.len(),
+                    result.id
+                );
+                Ok(vec![Action::Alert(message)])
+            }
+            Err(e) => {
+                warn!("send_sando_bundle_request error ({}): {e:?}", result.id);
+                Ok(Vec::new())
+            }
+        }
     }
 }
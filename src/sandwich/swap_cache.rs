@@ -0,0 +1,87 @@
+/// Swaps of pending transactions, extracted once and shared by every strategy.
+///
+/// The sandwich and arbitrage strategies both need the swaps of each pending transaction, and
+/// extracting them can take a full EVM trace. The `SwapCache` in the `EngineContext` runs
+/// `extract_swap_info` once per transaction: concurrent callers for the same transaction wait for
+/// the first one's result. Entries only hold for the block they were extracted against and are
+/// dropped when a newer block comes in.
+use anyhow::Result;
+use ethers::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OnceCell;
+
+use crate::common::pools::Pool;
+use crate::common::streams::{NewBlock, NewPendingTx};
+use crate::sandwich::simulation::{extract_swap_info, SwapInfo};
+
+#[derive(Debug, Default)]
+struct Entries {
+    block_number: U64,
+    swaps: HashMap<H256, Arc<OnceCell<Vec<SwapInfo>>>>,
+}
+
+/// Swaps by pending transaction, for the latest block.
+#[derive(Debug, Default)]
+pub struct SwapCache {
+    entries: Mutex<Entries>,
+}
+
+impl SwapCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the swaps of a pending transaction, extracting them on the first call.
+    ///
+    /// A failed extraction is not cached, so the next caller tries again. Calls against an older
+    /// block than the cached one bypass the cache.
+    ///
+    /// # Parameters
+    /// * `provider`: &Arc<M> - The Ethereum provider.
+    /// * `new_block`: &NewBlock - The latest block.
+    /// * `pending_tx`: &NewPendingTx - The pending transaction.
+    /// * `pools_map`: &HashMap<H160, Pool> - Known pools.
+    ///
+    /// # Returns
+    /// * `Result<Vec<SwapInfo>>` - One entry per touched pool.
+    pub async fn get_or_extract<M: Middleware + 'static>(
+        &self,
+        provider: &Arc<M>,
+        new_block: &NewBlock,
+        pending_tx: &NewPendingTx,
+        pools_map: &HashMap<H160, Pool>,
+    ) -> Result<Vec<SwapInfo>> {
+        let cell = {
+            let mut entries = self.entries.lock().unwrap();
+            if new_block.block_number > entries.block_number {
+                entries.block_number = new_block.block_number;
+                entries.swaps.clear();
+            }
+            if new_block.block_number < entries.block_number {
+                None
+            } else {
+                Some(entries.swaps.entry(pending_tx.tx.hash).or_default().clone())
+            }
+        };
+
+        match cell {
+            Some(cell) => {
+                let swaps = cell
+                    .get_or_try_init(|| extract_swap_info(provider, new_block, pending_tx, pools_map))
+                    .await?;
+                Ok(swaps.clone())
+            }
+            None => extract_swap_info(provider, new_block, pending_tx, pools_map).await,
+        }
+    }
+
+    /// Number of transactions cached for the current block.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().swaps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
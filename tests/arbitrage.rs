@@ -0,0 +1,245 @@
+//! Tests for the arbitrage cycle search, quoting and `executeRoute` encoding.
+//!
+//! Everything here runs on hand-built pools and reserves, without a node.
+use ethers::types::{Bytes, H160, U256};
+use std::collections::HashMap;
+
+use sandooo::arbitrage::cycles::{Cycle, Hop, PoolGraph};
+use sandooo::common::abi::Abi;
+use sandooo::common::execution_v3::{encode_route_call, route_block_number};
+use sandooo::common::pools::{DexVariant, Pool};
+use sandooo::common::sando_calldata::{SandoPayload, TradeLayout};
+use sandooo::sandwich::simulation::min_amount_out;
+
+mod common;
+use common::{ether, pool, weth};
+
+fn token_a() -> H160 {
+    H160::repeat_byte(0xaa)
+}
+
+fn token_b() -> H160 {
+    H160::repeat_byte(0xbb)
+}
+
+fn address(byte: u8) -> H160 {
+    H160::repeat_byte(byte)
+}
+
+/// WETH/A on V2 (0x01) and V3 (0x02), A/B on V2 (0x03), B/WETH on V2 (0x04) and an
+/// unrelated pool (0x05).
+fn pools_map() -> HashMap<H160, Pool> {
+    [
+        pool(address(0x01), DexVariant::UniswapV2, weth(), token_a(), 3000),
+        pool(address(0x02), DexVariant::UniswapV3, weth(), token_a(), 500),
+        pool(address(0x03), DexVariant::UniswapV2, token_a(), token_b(), 3000),
+        pool(address(0x04), DexVariant::UniswapV2, token_b(), weth(), 3000),
+        pool(
+            address(0x05),
+            DexVariant::UniswapV2,
+            H160::repeat_byte(0xcc),
+            H160::repeat_byte(0xdd),
+            3000,
+        ),
+    ]
+    .into_iter()
+    .map(|p| (p.address, p))
+    .collect()
+}
+
+fn pool_at(pools_map: &HashMap<H160, Pool>, byte: u8) -> Pool {
+    pools_map[&address(byte)]
+}
+
+fn pool_ids(cycle: &Cycle) -> Vec<u8> {
+    cycle.pools().iter().map(|p| p.as_bytes()[0]).collect()
+}
+
+fn assert_closed(cycle: &Cycle) {
+    assert_eq!(cycle.start_token(), weth());
+    for pair in cycle.hops.windows(2) {
+        assert_eq!(pair[0].token_out, pair[1].token_in, "{}", cycle.id());
+    }
+    assert_eq!(
+        cycle.hops.last().unwrap().token_out,
+        weth(),
+        "{}",
+        cycle.id()
+    );
+}
+
+#[test]
+fn cycles_through_a_weth_pool() {
+    let pools_map = pools_map();
+    let graph = PoolGraph::new(&pools_map);
+
+    let cycles = graph.cycles_through(&pool_at(&pools_map, 0x01), weth());
+    cycles.iter().for_each(assert_closed);
+
+    let mut routes: Vec<Vec<u8>> = cycles.iter().map(pool_ids).collect();
+    routes.sort();
+    assert_eq!(
+        routes,
+        vec![
+            vec![0x01, 0x02],
+            vec![0x01, 0x03, 0x04],
+            vec![0x02, 0x01],
+            vec![0x04, 0x03, 0x01],
+        ]
+    );
+}
+
+#[test]
+fn cycles_through_a_middle_pool() {
+    let pools_map = pools_map();
+    let graph = PoolGraph::new(&pools_map);
+
+    let cycles = graph.cycles_through(&pool_at(&pools_map, 0x03), weth());
+    cycles.iter().for_each(assert_closed);
+
+    let mut routes: Vec<Vec<u8>> = cycles.iter().map(pool_ids).collect();
+    routes.sort();
+    assert_eq!(
+        routes,
+        vec![
+            vec![0x01, 0x03, 0x04],
+            vec![0x02, 0x03, 0x04],
+            vec![0x04, 0x03, 0x01],
+            vec![0x04, 0x03, 0x02],
+        ]
+    );
+}
+
+#[test]
+fn unconnected_pools_have_no_cycles() {
+    let pools_map = pools_map();
+    let graph = PoolGraph::new(&pools_map);
+
+    assert!(graph
+        .cycles_through(&pool_at(&pools_map, 0x05), weth())
+        .is_empty());
+}
+
+/// Buys A where it is cheap (0x02, 2100 A per WETH) and sells it where it is dear (0x01).
+#[test]
+fn v3_hops_quote_on_constant_product() {
+    let pools_map = pools_map();
+    let v3 = pool_at(&pools_map, 0x02);
+
+    // 1 WETH at 0.05% into 100 WETH / 200 A:
+    // 0.9995 * 200 / (100 + 0.9995) = 1.979217718899598512 A
+    let out = Hop::new(&v3, weth()).amount_out(ether(1), (ether(100), ether(200)));
+    assert_eq!(out, U256::from(1_979_217_718_899_598_512u64));
+
+    // the other way round reads the reserves swapped
+    let back = Hop::new(&v3, token_a()).amount_out(ether(2), (ether(100), ether(200)));
+    assert_eq!(back, U256::from(989_608_859_449_799_256u64));
+
+    // at the V2 fee tier both quotes agree
+    let v2 = pool_at(&pools_map, 0x01);
+    let v3_at_v2_fee = pool(address(0x06), DexVariant::UniswapV3, weth(), token_a(), 3000);
+    assert_eq!(
+        Hop::new(&v3_at_v2_fee, weth()).amount_out(ether(1), (ether(100), ether(200))),
+        Hop::new(&v2, weth()).amount_out(ether(1), (ether(100), ether(200)))
+    );
+}
+
+fn price_gap() -> (Cycle, HashMap<H160, (U256, U256)>) {
+    let pools_map = pools_map();
+    let (cheap, dear) = (pool_at(&pools_map, 0x02), pool_at(&pools_map, 0x01));
+    let reserves = HashMap::from([
+        (dear.address, (ether(1_000), ether(2_000_000))),
+        (cheap.address, (ether(1_000), ether(2_100_000))),
+    ]);
+    let cycle = Cycle {
+        hops: vec![Hop::new(&cheap, weth()), Hop::new(&dear, token_a())],
+    };
+    (cycle, reserves)
+}
+
+#[test]
+fn optimize_finds_the_most_profitable_input() {
+    let (cycle, reserves) = price_gap();
+
+    let (amount_in, profit) = cycle.optimize(&reserves, ether(1_000)).unwrap();
+    assert_eq!(cycle.quote(amount_in, &reserves), amount_in + profit);
+
+    let profit_at = |amount: U256| cycle.quote(amount, &reserves).saturating_sub(amount);
+    assert!(profit_at(amount_in * 9 / 10) <= profit);
+    assert!(profit_at(amount_in * 11 / 10) <= profit);
+}
+
+#[test]
+fn optimize_is_bounded_by_the_inventory() {
+    let (cycle, reserves) = price_gap();
+
+    let (amount_in, _) = cycle.optimize(&reserves, ether(1)).unwrap();
+    assert!(amount_in <= ether(1));
+}
+
+#[test]
+fn unprofitable_directions_are_skipped() {
+    let (cycle, reserves) = price_gap();
+    let reversed = Cycle {
+        hops: vec![
+            Hop::new(&cycle.hops[1].pool, weth()),
+            Hop::new(&cycle.hops[0].pool, token_a()),
+        ],
+    };
+    assert!(reversed.optimize(&reserves, ether(1_000)).is_none());
+}
+
+#[test]
+fn payload_chains_hops() {
+    let (cycle, reserves) = price_gap();
+    let amount_in = ether(5);
+
+    let (payload, min_return) = cycle.payload(42, amount_in, &reserves).unwrap();
+    assert_eq!(payload.block_number, 42);
+    assert_eq!(payload.trades.len(), 2);
+
+    // the V3 hop carries the input and its minimum output
+    let v3_out = cycle.hops[0].amount_out(amount_in, reserves[&cycle.hops[0].pool.address]);
+    let v3_min_out = min_amount_out(DexVariant::UniswapV3, v3_out);
+    let first = &payload.trades[0];
+    assert_eq!(first.dex, DexVariant::UniswapV3);
+    assert_eq!(first.amount_in, amount_in);
+    assert_eq!(first.amount_out, v3_min_out);
+    assert_eq!(first.fee, 500);
+    assert!(first.zero_for_one);
+
+    // the V2 hop spends the previous output and asks for what the minimum buys
+    let v2_out = cycle.hops[1].amount_out(v3_min_out, reserves[&cycle.hops[1].pool.address]);
+    let second = &payload.trades[1];
+    assert_eq!(second.dex, DexVariant::UniswapV2);
+    assert_eq!(second.amount_in, U256::zero());
+    assert_eq!(second.amount_out, v2_out);
+    assert_eq!(second.token_in, token_a());
+    assert!(!second.zero_for_one);
+    assert_eq!(min_return, v2_out);
+}
+
+#[test]
+fn route_call_round_trips() {
+    let abi = Abi::new();
+    let (cycle, reserves) = price_gap();
+    let (payload, _) = cycle.payload(42, ether(5), &reserves).unwrap();
+    let route_data = payload.encode(TradeLayout::SandoooV3).unwrap();
+
+    let calldata = encode_route_call(&abi, ether(1), route_data).unwrap();
+    assert_eq!(route_block_number(&abi, &calldata).unwrap(), 42);
+
+    let (min_profit, decoded): (U256, Bytes) =
+        abi.sando_v3.decode("executeRoute", &calldata).unwrap();
+    assert_eq!(min_profit, ether(1));
+    assert_eq!(
+        SandoPayload::decode(&decoded, TradeLayout::SandoooV3).unwrap(),
+        payload
+    );
+
+    // an empty route would revert with InvalidData
+    let empty = SandoPayload::new(42)
+        .encode(TradeLayout::SandoooV3)
+        .unwrap();
+    assert!(encode_route_call(&abi, U256::zero(), empty).is_err());
+}
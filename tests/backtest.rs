@@ -0,0 +1,122 @@
+//! Tests for the backtester's bookkeeping: grouping a recorded mempool by block, and comparing
+//! opportunities against the sandwiches of the real block.
+use ethers::types::{Block, Transaction, H160, H256, U256, U64};
+use std::collections::HashSet;
+
+use sandooo::common::recorder::RecordedEvent;
+use sandooo::common::streams::{Event, NewBlock, NewPendingTx};
+use sandooo::sandwich::backtest::{
+    pending_by_block, real_victims, BacktestReport, Opportunity, Outcome,
+};
+use sandooo::sandwich::inspect::BlockFixture;
+
+fn block_event(number: u64) -> Event {
+    let block: Block<H256> = Block {
+        number: Some(U64::from(number)),
+        base_fee_per_gas: Some(U256::from(20_000_000_000u64)),
+        gas_limit: U256::from(30_000_000u64),
+        ..Default::default()
+    };
+    Event::Block(NewBlock::from_block(&block).unwrap())
+}
+
+fn pending_event(hash: H256) -> Event {
+    Event::PendingTransaction(NewPendingTx {
+        tx: Transaction {
+            hash,
+            ..Default::default()
+        },
+        added_block: None,
+    })
+}
+
+fn recorded(events: Vec<Event>) -> Vec<RecordedEvent> {
+    events
+        .into_iter()
+        .enumerate()
+        .map(|(i, event)| RecordedEvent {
+            received_at_ms: i as u64,
+            event,
+        })
+        .collect()
+}
+
+fn opportunity(profit: i128, outcome: Outcome) -> Opportunity {
+    Opportunity {
+        block_number: 19000001,
+        victim: H256::random(),
+        pair: H160::random(),
+        main_currency: H160::random(),
+        amount_in: U256::exp10(18),
+        revenue: profit * 2,
+        risk_adjusted_revenue: profit * 2,
+        gas_cost: 0,
+        bribe: U256::from(profit),
+        profit,
+        outcome,
+    }
+}
+
+#[test]
+fn groups_recorded_mempool_by_target_block() {
+    let (a, b, c) = (H256::random(), H256::random(), H256::random());
+    let events = recorded(vec![
+        pending_event(H256::random()), // before any block, dropped
+        block_event(100),
+        pending_event(a),
+        pending_event(b),
+        block_event(101),
+        pending_event(a), // re-broadcast, already a candidate for 101
+        pending_event(c),
+    ]);
+
+    let by_block = pending_by_block(&events);
+    assert_eq!(by_block.len(), 2);
+    let hashes = |n: u64| -> Vec<H256> { by_block[&n].iter().map(|p| p.tx.hash).collect() };
+    assert_eq!(hashes(101), vec![a, b]);
+    assert_eq!(hashes(102), vec![c]);
+}
+
+#[test]
+fn classifies_victims_against_real_block() {
+    let fixture = BlockFixture::load(format!(
+        "{}/tests/fixtures/blocks/sandwich_v2.json",
+        env!("CARGO_MANIFEST_DIR")
+    ))
+    .unwrap();
+    let txs = &fixture.block.transactions;
+    let real = real_victims(&fixture.block, &fixture.receipts);
+    assert_eq!(real.len(), 1);
+    assert_eq!(real.get(&txs[2].hash), txs[1].to.as_ref());
+
+    let included: HashSet<H256> = txs.iter().map(|tx| tx.hash).collect();
+    assert_eq!(
+        Outcome::of(txs[2].hash, &included, &real),
+        Outcome::Sandwiched(txs[1].to.unwrap())
+    );
+    assert_eq!(Outcome::of(txs[4].hash, &included, &real), Outcome::Unclaimed);
+    assert_eq!(Outcome::of(H256::random(), &included, &real), Outcome::NotIncluded);
+}
+
+#[test]
+fn report_totals_and_overlap() {
+    let mut report = BacktestReport::new("0.1.0-v3");
+    report.blocks = 2;
+    report.real_sandwiches = 3;
+    report.opportunities = vec![
+        opportunity(100, Outcome::Sandwiched(H160::random())),
+        opportunity(50, Outcome::Unclaimed),
+        opportunity(25, Outcome::NotIncluded),
+    ];
+
+    assert_eq!(report.total_profit(), 175);
+    assert_eq!(report.total_revenue(), 350);
+    assert_eq!(report.overlap(), 1);
+    assert_eq!(report.unclaimed(), 1);
+    assert!(report.summary().starts_with("[0.1.0-v3] blocks 2"));
+
+    let json = serde_json::to_string(&[report]).unwrap();
+    let parsed: Vec<BacktestReport> = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed[0].opportunities.len(), 3);
+    assert_eq!(parsed[0].opportunities[1].outcome, Outcome::Unclaimed);
+}
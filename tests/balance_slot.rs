@@ -0,0 +1,121 @@
+//! Balance slot discovery tests on handmade tokens served by a `MockMiddleware`.
+//!
+//! Each token's `balanceOf` first reads an unrelated slot, like a `paused` flag, then its balances
+//! mapping, so discovery has to pick the right SLOAD out of the trace.
+use ethers::types::{Bytes, H160, U256};
+use std::sync::Arc;
+
+use sandooo::common::balance_slot::{BalanceSlot, MappingLayout};
+use sandooo::common::evm::EvmSimulator;
+use sandooo::common::mock_middleware::MockMiddleware;
+
+mod common;
+use common::block_env;
+
+/// `balanceOf(owner)` returning `mapping[owner]` at `slot`, after a decoy SLOAD(0).
+///
+/// Solidity hashes `owner ‖ slot`, Vyper `slot ‖ owner`.
+fn token_code(layout: MappingLayout, slot: u8) -> Bytes {
+    let (owner_at, slot_at) = match layout {
+        MappingLayout::Solidity => (0x00, 0x20),
+        MappingLayout::Vyper => (0x20, 0x00),
+    };
+    Bytes::from(vec![
+        0x60, 0x00, 0x54, 0x50, // POP(SLOAD(0))
+        0x60, 0x04, 0x35, 0x60, owner_at, 0x52, // MSTORE(owner_at, CALLDATALOAD(4))
+        0x60, slot, 0x60, slot_at, 0x52, // MSTORE(slot_at, slot)
+        0x60, 0x40, 0x60, 0x00, 0x20, 0x54, // SLOAD(KECCAK256(0, 64))
+        0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3, // MSTORE(0), RETURN(0, 32)
+    ])
+}
+
+/// Forwards every call to `implementation` with DELEGATECALL and returns its 32-byte output.
+fn proxy_code(implementation: H160) -> Bytes {
+    let mut code = vec![
+        0x36, 0x60, 0x00, 0x60, 0x00, 0x37, // CALLDATACOPY(0, 0, CALLDATASIZE)
+        0x60, 0x20, 0x60, 0x00, 0x36, 0x60, 0x00, 0x73, // out (0, 32), in (0, CALLDATASIZE)
+    ];
+    code.extend(implementation.as_bytes());
+    code.extend([
+        0x5a, 0xf4, 0x50, // POP(DELEGATECALL(GAS, implementation, ...))
+        0x60, 0x20, 0x60, 0x00, 0xf3, // RETURN(0, 32)
+    ]);
+    code.into()
+}
+
+fn simulator(accounts: Vec<(H160, Bytes)>) -> EvmSimulator<MockMiddleware> {
+    let mut mock = MockMiddleware::new();
+    for (address, code) in accounts {
+        mock = mock.with_code(address, code);
+    }
+    EvmSimulator::new(Arc::new(mock), None, &block_env())
+}
+
+#[test]
+fn finds_solidity_balance_slot() {
+    let token = H160::repeat_byte(0x70);
+    let mut simulator = simulator(vec![(token, token_code(MappingLayout::Solidity, 3))]);
+
+    let slot = simulator.find_balance_slot(token).unwrap();
+    assert_eq!(slot, Some(BalanceSlot::solidity(token, 3)));
+}
+
+#[test]
+fn finds_vyper_balance_slot() {
+    let token = H160::repeat_byte(0x71);
+    let mut simulator = simulator(vec![(token, token_code(MappingLayout::Vyper, 5))]);
+
+    let slot = simulator.find_balance_slot(token).unwrap().unwrap();
+    assert_eq!(slot.slot, 5);
+    assert_eq!(slot.layout, MappingLayout::Vyper);
+    assert_eq!(slot.storage_address, token);
+
+    // the discovered slot sets balances
+    let holder = H160::repeat_byte(0x0e);
+    simulator
+        .set_token_balance_at(holder, &slot, U256::from(1234).into())
+        .unwrap();
+    assert_eq!(
+        simulator.get_token_balance(token, holder).unwrap(),
+        U256::from(1234)
+    );
+}
+
+#[test]
+fn proxy_balances_live_in_the_proxy() {
+    let proxy = H160::repeat_byte(0x72);
+    let implementation = H160::repeat_byte(0x73);
+    let mut simulator = simulator(vec![
+        (proxy, proxy_code(implementation)),
+        (implementation, token_code(MappingLayout::Solidity, 51)),
+    ]);
+
+    let slot = simulator.find_balance_slot(proxy).unwrap();
+    assert_eq!(
+        slot,
+        Some(BalanceSlot {
+            slot: 51,
+            layout: MappingLayout::Solidity,
+            storage_address: proxy,
+        })
+    );
+}
+
+#[test]
+fn wrong_candidates_fail_verification() {
+    let token = H160::repeat_byte(0x74);
+    let mut simulator = simulator(vec![(token, token_code(MappingLayout::Solidity, 3))]);
+
+    assert!(simulator
+        .verify_balance_slot(token, &BalanceSlot::solidity(token, 3))
+        .unwrap());
+    assert!(!simulator
+        .verify_balance_slot(token, &BalanceSlot::solidity(token, 4))
+        .unwrap());
+    let vyper = BalanceSlot {
+        slot: 3,
+        layout: MappingLayout::Vyper,
+        storage_address: token,
+    };
+    assert!(!simulator.verify_balance_slot(token, &vyper).unwrap());
+}
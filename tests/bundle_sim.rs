@@ -0,0 +1,229 @@
+//! Tests for local bundle simulation and the checks run before a sandwich bundle is sent.
+//!
+//! Bundles are signed by a dev wallet and run on an offline snapshot holding every account they
+//! touch.
+use ethers::providers::{MockProvider, Provider};
+use ethers::signers::{LocalWallet, Signer};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Bytes, Eip1559TransactionRequest, H160, H256, U256, U64};
+use ethers::utils::keccak256;
+
+use sandooo::common::bundle_sim::bundle_transactions;
+use sandooo::common::constants::builder_fee_recipient;
+use sandooo::common::evm::{EvmSimulator, NextBlockEnv};
+use sandooo::common::execution::signed_bundle_request;
+use sandooo::common::snapshot::{SnapshotAccount, StateSnapshot};
+use sandooo::sandwich::main_dish::{verify_sando_bundle, verify_sando_bundle_for_builders};
+
+/// Anvil's default dev account 0.
+const SENDER_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+const GWEI: u64 = 1_000_000_000;
+
+fn coinbase() -> H160 {
+    H160::from_low_u64_be(0xc0ffee)
+}
+
+fn recipient() -> H160 {
+    H160::from_low_u64_be(0xbeef)
+}
+
+/// Always reverts: 0 0 REVERT
+fn reverter() -> H160 {
+    H160::from_low_u64_be(0xdead)
+}
+
+fn beaverbuild() -> H160 {
+    builder_fee_recipient("beaverbuild").unwrap()
+}
+
+/// Reverts unless the block is built by beaverbuild:
+/// COINBASE beaverbuild EQ 0x1f JUMPI 0 0 REVERT JUMPDEST STOP
+fn beaverbuild_only() -> H160 {
+    H160::from_low_u64_be(0xbeaf)
+}
+
+fn beaverbuild_only_code() -> Bytes {
+    let mut code = vec![0x41, 0x73];
+    code.extend(beaverbuild().as_bytes());
+    code.extend([
+        0x14, 0x60, 0x1f, 0x57, 0x60, 0x00, 0x60, 0x00, 0xfd, 0x5b, 0x00,
+    ]);
+    Bytes::from(code)
+}
+
+fn wallet() -> LocalWallet {
+    SENDER_KEY
+        .parse::<LocalWallet>()
+        .unwrap()
+        .with_chain_id(1u64)
+}
+
+fn simulator_block() -> NextBlockEnv {
+    NextBlockEnv {
+        number: U64::from(19000001),
+        timestamp: U256::from(1_705_000_000u64),
+        base_fee: U256::from(20 * GWEI),
+        gas_limit: U256::from(30_000_000u64),
+        prevrandao: H256::zero(),
+        coinbase: coinbase(),
+        chain_id: 1,
+    }
+}
+
+fn simulator() -> EvmSimulator<Provider<MockProvider>> {
+    let sender = wallet().address();
+    let mut snapshot = StateSnapshot::new(simulator_block(), sender);
+    snapshot.accounts.insert(
+        sender,
+        SnapshotAccount {
+            balance: U256::exp10(19),
+            ..Default::default()
+        },
+    );
+    for coinbase in [
+        coinbase(),
+        builder_fee_recipient("flashbots").unwrap(),
+        beaverbuild(),
+    ] {
+        snapshot
+            .accounts
+            .insert(coinbase, SnapshotAccount::default());
+    }
+    snapshot
+        .accounts
+        .insert(recipient(), SnapshotAccount::default());
+    snapshot.accounts.insert(
+        beaverbuild_only(),
+        SnapshotAccount {
+            code: beaverbuild_only_code(),
+            ..Default::default()
+        },
+    );
+    snapshot.accounts.insert(
+        reverter(),
+        SnapshotAccount {
+            code: Bytes::from(vec![0x60, 0x00, 0x60, 0x00, 0xfd]),
+            ..Default::default()
+        },
+    );
+    EvmSimulator::from_snapshot(&snapshot).unwrap()
+}
+
+/// Signs a call paying 2 gwei priority fee on top of the 20 gwei base fee.
+fn signed(nonce: u64, to: H160, value: U256) -> Bytes {
+    let tx = TypedTransaction::Eip1559(
+        Eip1559TransactionRequest::new()
+            .from(wallet().address())
+            .to(to)
+            .value(value)
+            .nonce(nonce)
+            .gas(100_000)
+            .max_priority_fee_per_gas(2 * GWEI)
+            .max_fee_per_gas(30 * GWEI)
+            .chain_id(1u64),
+    );
+    let signature = wallet().sign_transaction_sync(&tx).unwrap();
+    tx.rlp_signed(&signature)
+}
+
+#[test]
+fn bundle_pays_the_coinbase_and_leaves_state_untouched() {
+    let mut simulator = simulator();
+    let bribe = U256::exp10(17);
+    let txs = vec![
+        signed(0, recipient(), U256::exp10(18)),
+        signed(1, coinbase(), bribe),
+    ];
+
+    let result = simulator.simulate_bundle(&txs).unwrap();
+    assert!(result.first_failure().is_none());
+    assert_eq!(result.results.len(), 2);
+    assert_eq!(result.results[0].tx_hash, H256::from(keccak256(&txs[0])));
+    assert_eq!(result.results[0].from, wallet().address());
+    assert_eq!(result.results[0].to, Some(recipient()));
+
+    let gas_fees = U256::from(2 * 21_000) * U256::from(2 * GWEI);
+    assert_eq!(result.total_gas_used, 2 * 21_000);
+    assert_eq!(result.gas_fees, gas_fees);
+    assert_eq!(result.eth_sent_to_coinbase, bribe);
+    assert_eq!(result.coinbase_diff, bribe + gas_fees);
+    assert_eq!(
+        result.bundle_gas_price,
+        (bribe + gas_fees) / U256::from(2 * 21_000)
+    );
+
+    // the simulator state is rolled back
+    assert_eq!(simulator.get_eth_balance_of(recipient()), U256::zero());
+    assert!(simulator.simulate_bundle(&txs).is_ok());
+}
+
+#[test]
+fn reverting_tx_fails_verification() {
+    let mut simulator = simulator();
+    let txs = vec![
+        signed(0, recipient(), U256::exp10(18)),
+        signed(1, reverter(), U256::zero()),
+    ];
+
+    let result = simulator.simulate_bundle(&txs).unwrap();
+    let failed = result.first_failure().unwrap();
+    assert_eq!(failed.tx_hash, H256::from(keccak256(&txs[1])));
+    assert!(result.results[0].success);
+
+    let error = verify_sando_bundle(&mut simulator, &txs).unwrap_err();
+    assert!(error.to_string().contains(&format!("{:?}", failed.tx_hash)));
+    assert!(verify_sando_bundle(&mut simulator, &txs[..1]).is_ok());
+}
+
+#[test]
+fn invalid_txs_fail_the_whole_bundle() {
+    let mut simulator = simulator();
+
+    // nonce gap
+    let txs = vec![signed(1, recipient(), U256::zero())];
+    assert!(simulator.simulate_bundle(&txs).is_err());
+    assert!(verify_sando_bundle(&mut simulator, &txs).is_err());
+
+    // more value than the sender holds
+    let txs = vec![signed(0, recipient(), U256::exp10(20))];
+    assert!(simulator.simulate_bundle(&txs).is_err());
+
+    // not a signed transaction
+    let txs = vec![Bytes::from(vec![0x01, 0x02])];
+    assert!(simulator.simulate_bundle(&txs).is_err());
+}
+
+#[test]
+fn bundle_request_sends_the_verified_bytes() {
+    let txs = vec![
+        signed(0, recipient(), U256::exp10(18)),
+        signed(1, coinbase(), U256::exp10(17)),
+    ];
+    let bundle = signed_bundle_request(&txs, U64::from(19000000), 1);
+
+    assert_eq!(bundle_transactions(&bundle), txs);
+    assert_eq!(bundle.block(), Some(U64::from(19000001)));
+    assert_eq!(bundle.simulation_block(), Some(U64::from(19000000)));
+}
+
+#[test]
+fn bundle_is_verified_against_each_builder_coinbase() {
+    let mut simulator = simulator();
+    let builders: Vec<String> = ["flashbots", "beaverbuild", "unknown"]
+        .iter()
+        .map(|builder| builder.to_string())
+        .collect();
+
+    let txs = vec![signed(0, beaverbuild_only(), U256::zero())];
+    let verified =
+        verify_sando_bundle_for_builders(&mut simulator, &txs, &simulator_block(), &builders);
+    assert_eq!(verified.len(), 1);
+    assert!(verified["beaverbuild"].coinbase_diff > U256::zero());
+
+    // a bundle that doesn't look at the coinbase passes for every builder
+    let txs = vec![signed(0, recipient(), U256::exp10(18))];
+    let verified =
+        verify_sando_bundle_for_builders(&mut simulator, &txs, &simulator_block(), &builders);
+    assert_eq!(verified.len(), 3);
+}
@@ -0,0 +1,76 @@
+//! Helpers shared by the integration tests.
+//!
+//! Every test binary compiles its own copy with `mod common;` and only uses part of it.
+#![allow(dead_code)]
+
+use ethers::providers::{Middleware, Provider, Ws};
+use ethers::types::{H160, H256, U256, U64};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use sandooo::common::constants::WETH;
+use sandooo::common::evm::{EvmSimulator, NextBlockEnv};
+use sandooo::common::pools::{DexVariant, Pool};
+
+/// Uniswap V2 WETH/USDT pair (token0 = WETH)
+pub const V2_WETH_USDT: &str = "0x0d4a11d5EEaaC28EC3F61d100daF4d40471f1852";
+
+pub const UNISWAP_V2_ROUTER: &str = "0x7a250d5630B4cF539739dF2C5dAcb4c659F2488D";
+
+pub fn weth() -> H160 {
+    H160::from_str(WETH).unwrap()
+}
+
+pub fn ether(amount: u64) -> U256 {
+    U256::from(amount) * U256::exp10(18)
+}
+
+/// The block offline simulators run in.
+pub fn block_env() -> NextBlockEnv {
+    NextBlockEnv {
+        number: U64::from(19000001),
+        timestamp: U256::from(1_705_000_000u64),
+        base_fee: U256::from(20_000_000_000u64),
+        gas_limit: U256::from(30_000_000u64),
+        prevrandao: H256::zero(),
+        coinbase: H160::from_low_u64_be(0xc0ffee),
+        chain_id: 1,
+    }
+}
+
+/// A pool at `address`, indexed at block 0.
+pub fn pool(address: H160, version: DexVariant, token0: H160, token1: H160, fee: u32) -> Pool {
+    Pool {
+        id: 0,
+        address,
+        version,
+        token0,
+        token1,
+        fee,
+        block_number: 0,
+        timestamp: 0,
+    }
+}
+
+/// Connects to `WSS_URL`, or returns None so fork tests can skip when it is not set.
+pub async fn connect() -> Option<Arc<Provider<Ws>>> {
+    dotenv::dotenv().ok();
+    match std::env::var("WSS_URL") {
+        Ok(url) if !url.is_empty() => Some(Arc::new(Provider::<Ws>::connect(url).await.unwrap())),
+        _ => {
+            eprintln!("WSS_URL not set, skipping fork test");
+            None
+        }
+    }
+}
+
+/// A simulator forked at the latest block, its owner funded with 100 ETH.
+pub async fn fork() -> Option<EvmSimulator<Provider<Ws>>> {
+    let provider = connect().await?;
+    let block_number = provider.get_block_number().await.unwrap();
+    let next_block = NextBlockEnv::fetch(&provider, block_number).await.unwrap();
+
+    let mut simulator = EvmSimulator::new(provider, None, &next_block);
+    simulator.set_eth_balance(simulator.owner, ether(100));
+    Some(simulator)
+}
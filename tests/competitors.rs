@@ -0,0 +1,162 @@
+//! Tests for sandwich detection in mined blocks and competitor gas pegging.
+use ethers::abi::{encode, Token};
+use ethers::types::{Bytes, Log, Transaction, TransactionReceipt, H160, H256, U256};
+use std::str::FromStr;
+
+use sandooo::common::competitors::{detect_sandwiches, is_pegged_above, CompetitorDb};
+use sandooo::common::trace::V2_SWAP_TOPIC;
+
+/// V2 `Swap` log on `pool`, paying token0 in if `token0_in`.
+fn swap_log(pool: H160, token0_in: bool) -> Log {
+    let (amount0_in, amount1_in) = if token0_in {
+        (U256::exp10(18), U256::zero())
+    } else {
+        (U256::zero(), U256::exp10(18))
+    };
+    Log {
+        address: pool,
+        topics: vec![H256::from_str(V2_SWAP_TOPIC).unwrap()],
+        data: Bytes::from(encode(&[
+            Token::Uint(amount0_in),
+            Token::Uint(amount1_in),
+            Token::Uint(amount1_in),
+            Token::Uint(amount0_in),
+        ])),
+        ..Default::default()
+    }
+}
+
+fn cache_path() -> String {
+    std::env::temp_dir()
+        .join(format!("sandooo-competitors-{:?}.json", H256::random()))
+        .to_string_lossy()
+        .to_string()
+}
+
+fn tx(from: H160, to: H160, selector: [u8; 4]) -> Transaction {
+    Transaction {
+        hash: H256::random(),
+        from,
+        to: Some(to),
+        input: Bytes::from(selector.to_vec()),
+        ..Default::default()
+    }
+}
+
+fn receipt(tx: &Transaction, logs: Vec<Log>) -> TransactionReceipt {
+    TransactionReceipt {
+        transaction_hash: tx.hash,
+        logs,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn detects_sandwich_around_victim() {
+    let (searcher, bot, pool) = (H160::random(), H160::random(), H160::random());
+    let front = tx(searcher, bot, [0x01, 0x02, 0x03, 0x04]);
+    let other = tx(H160::random(), H160::random(), [0xaa; 4]);
+    let victim = tx(H160::random(), H160::random(), [0xbb; 4]);
+    let back = tx(searcher, bot, [0x05, 0x06, 0x07, 0x08]);
+
+    let receipts = vec![
+        receipt(&front, vec![swap_log(pool, true)]),
+        receipt(&other, vec![]),
+        receipt(&victim, vec![swap_log(pool, true)]),
+        receipt(&back, vec![swap_log(pool, false)]),
+    ];
+    let txs = vec![front.clone(), other, victim.clone(), back.clone()];
+
+    let sandwiches = detect_sandwiches(&txs, &receipts);
+    assert_eq!(sandwiches.len(), 1);
+    let sandwich = &sandwiches[0];
+    assert_eq!(sandwich.contract, bot);
+    assert_eq!(sandwich.searcher, searcher);
+    assert_eq!(sandwich.pool, pool);
+    assert_eq!(
+        (sandwich.frontrun, sandwich.victim, sandwich.backrun),
+        (front.hash, victim.hash, back.hash)
+    );
+    assert_eq!(sandwich.selectors, vec![[1, 2, 3, 4], [5, 6, 7, 8]]);
+
+    let path = cache_path();
+    let db = CompetitorDb::with_cache_path(&path);
+    assert!(db.record(sandwich, 100));
+    assert!(!db.record(sandwich, 101));
+    assert!(db.is_searcher(&bot));
+    let known = db.get(&bot).unwrap();
+    assert_eq!((known.first_seen_block, known.last_seen_block, known.sandwiches), (100, 101, 2));
+    assert_eq!(known.selectors, vec!["0x01020304", "0x05060708"]);
+
+    // saved contracts are known to the next run
+    db.save_to_cache();
+    assert!(CompetitorDb::with_cache_path(&path).is_searcher(&bot));
+    std::fs::remove_file(&path).ok();
+}
+
+#[test]
+fn ignores_same_direction_and_missing_victim() {
+    let (searcher, bot, pool) = (H160::random(), H160::random(), H160::random());
+
+    // two buys through the same contract are not a sandwich
+    let first = tx(searcher, bot, [1; 4]);
+    let victim = tx(H160::random(), H160::random(), [2; 4]);
+    let second = tx(searcher, bot, [1; 4]);
+    let receipts = vec![
+        receipt(&first, vec![swap_log(pool, true)]),
+        receipt(&victim, vec![swap_log(pool, true)]),
+        receipt(&second, vec![swap_log(pool, true)]),
+    ];
+    assert!(detect_sandwiches(&[first, victim, second], &receipts).is_empty());
+
+    // a round trip with nothing swapping in between is not a sandwich either
+    let buy = tx(searcher, bot, [1; 4]);
+    let unrelated = tx(H160::random(), H160::random(), [2; 4]);
+    let sell = tx(searcher, bot, [1; 4]);
+    let receipts = vec![
+        receipt(&buy, vec![swap_log(pool, true)]),
+        receipt(&unrelated, vec![swap_log(H160::random(), true)]),
+        receipt(&sell, vec![swap_log(pool, false)]),
+    ];
+    assert!(detect_sandwiches(&[buy, unrelated, sell], &receipts).is_empty());
+}
+
+#[test]
+fn gas_pegged_just_above_victim() {
+    let victim = U256::from(20_000_000_000u64);
+    assert!(is_pegged_above(victim + 1, victim));
+    assert!(is_pegged_above(victim + victim / 100, victim));
+    assert!(!is_pegged_above(victim, victim));
+    assert!(!is_pegged_above(victim * 2, victim));
+    assert!(!is_pegged_above(victim - 1, victim));
+}
+
+#[test]
+fn known_searchers_are_competitors_on_new_contracts() {
+    let (searcher, bot, pool) = (H160::random(), H160::random(), H160::random());
+    let front = tx(searcher, bot, [0x01, 0x02, 0x03, 0x04]);
+    let victim = tx(H160::random(), H160::random(), [0xbb; 4]);
+    let back = tx(searcher, bot, [0x05, 0x06, 0x07, 0x08]);
+    let receipts = vec![
+        receipt(&front, vec![swap_log(pool, true)]),
+        receipt(&victim, vec![swap_log(pool, true)]),
+        receipt(&back, vec![swap_log(pool, false)]),
+    ];
+    let sandwiches = detect_sandwiches(&[front, victim, back], &receipts);
+
+    let path = cache_path();
+    let db = CompetitorDb::with_cache_path(&path);
+    assert!(db.is_empty());
+    db.record(&sandwiches[0], 100);
+    assert!(!db.is_empty());
+
+    // anyone calling the known contract
+    assert!(db.is_competitor(&tx(H160::random(), bot, [0xcc; 4])));
+    // the same searcher with a learned selector on a contract we haven't seen
+    let redeployed = H160::random();
+    assert!(db.is_competitor(&tx(searcher, redeployed, [0x05, 0x06, 0x07, 0x08])));
+    // but not with another selector, nor someone else with the learned one
+    assert!(!db.is_competitor(&tx(searcher, redeployed, [0xcc; 4])));
+    assert!(!db.is_competitor(&tx(H160::random(), redeployed, [0x01, 0x02, 0x03, 0x04])));
+    std::fs::remove_file(&path).ok();
+}
@@ -0,0 +1,149 @@
+//! Tests for collecting contending pending swaps with `PoolContention`.
+//!
+//! Known searchers come from a `CompetitorDb` on a temp cache file, not the `cache/` directory.
+use ethers::types::{Transaction, H160, H256, U256};
+use std::collections::HashMap;
+
+use sandooo::common::competitors::{CompetitorDb, DetectedSandwich};
+use sandooo::common::evm::VictimTx;
+use sandooo::common::pools::DexVariant;
+use sandooo::common::streams::NewPendingTx;
+use sandooo::sandwich::contention::{effective_priority_fee, PoolContention};
+use sandooo::sandwich::simulation::{
+    BatchSandwich, PendingTxInfo, Sandwich, SwapDirection, SwapInfo,
+};
+
+/// An empty competitor database, on a cache file that is never written.
+fn competitor_db() -> CompetitorDb {
+    let path = std::env::temp_dir().join(format!("sandooo-competitors-{:?}.json", H256::random()));
+    CompetitorDb::with_cache_path(&path.to_string_lossy())
+}
+
+fn gwei(amount: u64) -> U256 {
+    U256::from(amount) * U256::exp10(9)
+}
+
+fn swap_info(tx_hash: H256, pair: H160) -> SwapInfo {
+    SwapInfo {
+        tx_hash,
+        target_pair: pair,
+        main_currency: H160::from_low_u64_be(1),
+        target_token: H160::from_low_u64_be(2),
+        version: DexVariant::UniswapV2,
+        token0_is_main: true,
+        fee: 3000,
+        direction: SwapDirection::Buy,
+    }
+}
+
+fn pending(tx_hash: H256, pair: H160, max_fee: U256, priority_fee: U256) -> PendingTxInfo {
+    let tx = Transaction {
+        hash: tx_hash,
+        from: H160::random(),
+        to: Some(H160::random()),
+        gas: U256::from(200000),
+        max_fee_per_gas: Some(max_fee),
+        max_priority_fee_per_gas: Some(priority_fee),
+        ..Default::default()
+    };
+    PendingTxInfo {
+        pending_tx: NewPendingTx {
+            tx,
+            added_block: None,
+        },
+        touched_pairs: vec![swap_info(tx_hash, pair)],
+    }
+}
+
+fn batch(victim: H256, pair: H160) -> BatchSandwich {
+    let mut batch = BatchSandwich::new(H160::zero());
+    let swap_info = swap_info(victim, pair);
+    batch.sandwiches.push(Sandwich {
+        amount_in: U256::exp10(18),
+        swap_info: swap_info.clone(),
+        victim_tx: VictimTx {
+            tx_hash: victim,
+            from: H160::random(),
+            to: H160::random(),
+            data: Default::default(),
+            value: U256::zero(),
+            gas_price: gwei(10),
+            gas_limit: Some(200000),
+        },
+        optimized_sandwich: None,
+    });
+    batch.swap_info_vec.push(swap_info);
+    batch
+}
+
+#[test]
+fn priority_fee_is_capped_by_max_fee() {
+    let info = pending(H256::random(), H160::random(), gwei(12), gwei(5));
+    assert_eq!(effective_priority_fee(&info.pending_tx, gwei(10)), gwei(2));
+    assert_eq!(effective_priority_fee(&info.pending_tx, gwei(5)), gwei(5));
+    assert_eq!(effective_priority_fee(&info.pending_tx, gwei(20)), U256::zero());
+}
+
+#[test]
+fn collects_other_swaps_on_our_pools_by_priority_fee() {
+    let pair = H160::random();
+    let victim = H256::random();
+    let (low, high, elsewhere) = (H256::random(), H256::random(), H256::random());
+
+    let mut pending_txs = HashMap::new();
+    pending_txs.insert(victim, pending(victim, pair, gwei(100), gwei(50)));
+    pending_txs.insert(low, pending(low, pair, gwei(100), gwei(1)));
+    pending_txs.insert(high, pending(high, pair, gwei(100), gwei(30)));
+    pending_txs.insert(elsewhere, pending(elsewhere, H160::random(), gwei(100), gwei(90)));
+
+    let db = competitor_db();
+    let contention = PoolContention::new(&batch(victim, pair), &pending_txs, gwei(10), &db);
+
+    let hashes: Vec<H256> = contention.swaps.iter().map(|swap| swap.tx_hash).collect();
+    assert_eq!(hashes, vec![high, low]);
+    assert!(contention.swaps.iter().all(|swap| swap.pair == pair));
+    assert_eq!(contention.swaps[0].priority_fee, gwei(30));
+}
+
+#[test]
+fn flags_known_searchers_and_pegged_gas_prices() {
+    let pair = H160::random();
+    let victim = H256::random();
+    let (searcher, pegged, plain) = (H256::random(), H256::random(), H256::random());
+    let bot = H160::random();
+
+    let db = competitor_db();
+    db.record(
+        &DetectedSandwich {
+            contract: bot,
+            searcher: H160::random(),
+            pool: H160::random(),
+            frontrun: H256::random(),
+            victim: H256::random(),
+            backrun: H256::random(),
+            selectors: Vec::new(),
+        },
+        100,
+    );
+
+    let mut pending_txs = HashMap::new();
+    pending_txs.insert(victim, pending(victim, pair, gwei(100), gwei(50)));
+    let mut to_bot = pending(searcher, pair, gwei(100), gwei(1));
+    to_bot.pending_tx.tx.to = Some(bot);
+    pending_txs.insert(searcher, to_bot);
+    // the victim pays 10 gwei: one wei above it is a competitor's peg
+    pending_txs.insert(pegged, pending(pegged, pair, gwei(10) + 1, gwei(2)));
+    pending_txs.insert(plain, pending(plain, pair, gwei(100), gwei(3)));
+
+    let contention = PoolContention::new(&batch(victim, pair), &pending_txs, gwei(5), &db);
+    let competitors: Vec<H256> = contention.competitors().map(|swap| swap.tx_hash).collect();
+    assert_eq!(competitors.len(), 2);
+    assert!(competitors.contains(&searcher));
+    assert!(competitors.contains(&pegged));
+
+    // the same swaps are not competitors without the learnt contract
+    let empty = competitor_db();
+    let contention = PoolContention::new(&batch(victim, pair), &pending_txs, gwei(5), &empty);
+    let competitors: Vec<H256> = contention.competitors().map(|swap| swap.tx_hash).collect();
+    assert_eq!(competitors, vec![pegged]);
+}
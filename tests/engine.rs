@@ -0,0 +1,120 @@
+//! Tests for the strategy engine.
+//!
+//! The engine needs a node for its context; the test is skipped without `WSS_URL`.
+use anyhow::Result;
+use async_trait::async_trait;
+use ethers::providers::Middleware;
+use ethers::types::H256;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+use sandooo::common::engine::{Action, Engine, EngineContext, Strategy};
+use sandooo::common::streams::{Event, NewBlock, NewPendingTx};
+
+mod common;
+use common::connect;
+
+/// Anvil's default dev account 0, used as owner and relay identity.
+const DEV_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+/// Records the events it sees, tagged with its name.
+struct Recorder {
+    name: &'static str,
+    seen: Arc<Mutex<Vec<String>>>,
+}
+
+#[async_trait]
+impl Strategy for Recorder {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    async fn on_block(&mut self, ctx: &EngineContext) -> Result<Vec<Action>> {
+        let entry = format!(
+            "{} block {} ({} txs)",
+            self.name,
+            ctx.new_block.block_number,
+            ctx.block_txs.len()
+        );
+        self.seen.lock().unwrap().push(entry);
+        Ok(Vec::new())
+    }
+
+    async fn on_pending_tx(
+        &mut self,
+        _ctx: &EngineContext,
+        pending_tx: &NewPendingTx,
+    ) -> Result<Vec<Action>> {
+        let entry = format!("{} tx {:?}", self.name, pending_tx.tx.hash);
+        self.seen.lock().unwrap().push(entry);
+        Ok(Vec::new())
+    }
+}
+
+#[tokio::test]
+async fn events_reach_strategies_in_order() {
+    let provider = match connect().await {
+        Some(provider) => provider,
+        None => return,
+    };
+    for (key, value) in [
+        ("BOT_ADDRESS", "0x0000000000000000000000000000000000000001"),
+        ("PRIVATE_KEY", DEV_KEY),
+        ("IDENTITY_KEY", DEV_KEY),
+        ("USE_ALERT", "false"),
+        ("DEBUG", "false"),
+    ] {
+        if std::env::var(key).map_or(true, |v| v.is_empty()) {
+            std::env::set_var(key, value);
+        }
+    }
+
+    let block_number = provider.get_block_number().await.unwrap();
+    let block = provider.get_block(block_number).await.unwrap().unwrap();
+    let new_block = NewBlock::from_block(&block).unwrap();
+    let tx_count = block.transactions.len();
+    let parent = provider.get_block(block_number - 1).await.unwrap().unwrap();
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let mut context = EngineContext::new(provider, HashMap::new(), HashMap::new())
+        .await
+        .unwrap();
+    // start one block back, so the latest block is new to the engine
+    context.new_block = NewBlock::from_block(&parent).unwrap();
+    let engine = Engine::new(context)
+        .with_strategy(Recorder {
+            name: "first",
+            seen: seen.clone(),
+        })
+        .with_strategy(Recorder {
+            name: "second",
+            seen: seen.clone(),
+        });
+
+    let (event_sender, _) = broadcast::channel::<Event>(16);
+    let runner = tokio::spawn(engine.run(event_sender.clone()));
+    while event_sender.receiver_count() == 0 {
+        tokio::task::yield_now().await;
+    }
+
+    let mut pending_tx = NewPendingTx::default();
+    pending_tx.tx.hash = H256::repeat_byte(0x11);
+    event_sender.send(Event::Block(new_block.clone())).unwrap();
+    // the poller sends blocks the stream already delivered
+    event_sender.send(Event::Block(new_block)).unwrap();
+    event_sender.send(Event::PendingTransaction(pending_tx)).unwrap();
+    drop(event_sender);
+    runner.await.unwrap().unwrap();
+
+    let tx = format!("{:?}", H256::repeat_byte(0x11));
+    assert_eq!(
+        *seen.lock().unwrap(),
+        vec![
+            format!("first block {} ({} txs)", block_number, tx_count),
+            format!("second block {} ({} txs)", block_number, tx_count),
+            format!("first tx {}", tx),
+            format!("second tx {}", tx),
+        ]
+    );
+}
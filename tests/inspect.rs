@@ -0,0 +1,119 @@
+//! Tests for `inspect_block` against recorded block fixtures.
+//!
+//! Fixtures are `BlockFixture` JSON files as written by `sandooo-inspect --record`.
+//! `sandwich_v2.json` is still a synthetic block (a WETH/USDT V2 sandwich with unrelated
+//! transfers around it) until it is replaced by a recording. `recorded_block_round_trips` checks
+//! the `--record` path against a real block and is skipped when `WSS_URL` is not set.
+use ethers::types::{H160, H256, I256, U256};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use sandooo::common::constants::USDT;
+use sandooo::common::pools::{DexVariant, Pool};
+use sandooo::sandwich::inspect::{inspect_block, write_csv, BlockFixture};
+
+mod common;
+use common::{connect, pool, weth, V2_WETH_USDT};
+
+fn fixture(name: &str) -> BlockFixture {
+    BlockFixture::load(format!(
+        "{}/tests/fixtures/blocks/{}",
+        env!("CARGO_MANIFEST_DIR"),
+        name
+    ))
+    .unwrap()
+}
+
+fn pools_map() -> HashMap<H160, Pool> {
+    let address = H160::from_str(V2_WETH_USDT).unwrap();
+    let usdt = H160::from_str(USDT).unwrap();
+    HashMap::from([(address, pool(address, DexVariant::UniswapV2, weth(), usdt, 3000))])
+}
+
+#[test]
+fn finds_v2_sandwich_in_fixture() {
+    let fixture = fixture("sandwich_v2.json");
+    assert_eq!(fixture.block_number(), 19000001);
+
+    let sandwiches = inspect_block(&fixture.block, &fixture.receipts, &pools_map());
+    assert_eq!(sandwiches.len(), 1);
+
+    let sandwich = &sandwiches[0];
+    let txs = &fixture.block.transactions;
+    assert_eq!(sandwich.block_number, 19000001);
+    assert_eq!(sandwich.frontrun, txs[1].hash);
+    assert_eq!(sandwich.victim, txs[2].hash);
+    assert_eq!(sandwich.backrun, txs[3].hash);
+    assert_eq!(sandwich.searcher, txs[1].from);
+    assert_eq!(Some(sandwich.contract), txs[1].to);
+    assert_eq!(sandwich.pool, H160::from_str(V2_WETH_USDT).unwrap());
+
+    // 10 WETH in, 10.05 WETH out, all 20000 USDT sold back
+    assert_eq!(sandwich.profit_token, weth());
+    assert_eq!(sandwich.profit, I256::from(50_000_000_000_000_000i64));
+    assert_eq!(sandwich.target_token, H160::from_str(USDT).unwrap());
+    assert_eq!(sandwich.inventory_delta, I256::zero());
+
+    // 1 gwei * 100k gas + 40 gwei * 110k gas over the 20 gwei base fee
+    assert_eq!(sandwich.priority_fees, U256::from(4_500_000_000_000_000u64));
+    assert_eq!(sandwich.coinbase_transfers, U256::zero());
+    assert_eq!(sandwich.coinbase_payment(), sandwich.priority_fees);
+}
+
+#[test]
+fn skips_unknown_pools() {
+    let fixture = fixture("sandwich_v2.json");
+    assert!(inspect_block(&fixture.block, &fixture.receipts, &HashMap::new()).is_empty());
+}
+
+#[test]
+fn fixture_round_trips_and_exports_csv() {
+    let fixture = fixture("sandwich_v2.json");
+    let dir = std::env::temp_dir().join(format!("sandooo-inspect-{:?}", H256::random()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let path = dir.join("19000001.json");
+    fixture.save(&path).unwrap();
+    let reloaded = BlockFixture::load(&path).unwrap();
+    assert_eq!(reloaded.block, fixture.block);
+    assert_eq!(reloaded.receipts, fixture.receipts);
+
+    let sandwiches = inspect_block(&fixture.block, &fixture.receipts, &pools_map());
+    let csv_path = dir.join("sandwiches.csv");
+    write_csv(&csv_path, &sandwiches).unwrap();
+    let csv = std::fs::read_to_string(&csv_path).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("block_number,searcher,contract,pool"));
+    assert!(lines[1].starts_with("19000001,"));
+    assert!(lines[1].ends_with(",4500000000000000,0,4500000000000000"));
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+/// Any mined block: the recording only has to match what the node returned.
+const RECORDED_BLOCK: u64 = 19000001;
+
+#[tokio::test]
+async fn recorded_block_round_trips() {
+    let provider = match connect().await {
+        Some(provider) => provider,
+        None => return,
+    };
+    let fixture = BlockFixture::fetch(&provider, RECORDED_BLOCK).await.unwrap();
+    assert_eq!(fixture.block_number(), RECORDED_BLOCK);
+    assert_eq!(fixture.receipts.len(), fixture.block.transactions.len());
+
+    let path = std::env::temp_dir().join(format!("sandooo-inspect-{:?}.json", H256::random()));
+    fixture.save(&path).unwrap();
+    let reloaded = BlockFixture::load(&path).unwrap();
+    assert_eq!(reloaded.block, fixture.block);
+    assert_eq!(reloaded.receipts, fixture.receipts);
+
+    // a recording inspects like the block it was taken from
+    assert_eq!(
+        inspect_block(&reloaded.block, &reloaded.receipts, &pools_map()),
+        inspect_block(&fixture.block, &fixture.receipts, &pools_map())
+    );
+    std::fs::remove_file(&path).ok();
+}
@@ -0,0 +1,97 @@
+//! Tests for the devnet mock builder and builder endpoint configuration.
+//!
+//! The mock builder runs on a `MockProvider`, so these tests check the anvil calls it makes
+//! without starting anvil; `sandooo-devnet` runs the whole pipeline against a real one.
+use ethers::providers::Provider;
+use ethers::signers::LocalWallet;
+use ethers::types::{Bytes, H256, U64};
+use ethers::utils::keccak256;
+use ethers_flashbots::BundleRequest;
+use serde_json::json;
+use std::sync::Arc;
+
+use sandooo::common::execution::{default_builder_urls, parse_builder_urls, send_bundle};
+use sandooo::common::mock_builder::{bundle_hash, MockBuilder};
+
+const IDENTITY_KEY: &str = "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+
+#[test]
+fn builder_urls_parse() {
+    let spec = "devnet=http://127.0.0.1:9000, flashbots=https://relay.flashbots.net";
+    let urls = parse_builder_urls(spec).unwrap();
+    assert_eq!(urls.len(), 2);
+    assert_eq!(urls["devnet"].as_str(), "http://127.0.0.1:9000/");
+    assert_eq!(urls["flashbots"].host_str(), Some("relay.flashbots.net"));
+
+    assert!(parse_builder_urls("").unwrap().is_empty());
+    assert!(parse_builder_urls("http://127.0.0.1:9000").is_err());
+    assert!(parse_builder_urls("devnet=not a url").is_err());
+    assert!(default_builder_urls().contains_key("flashbots"));
+}
+
+#[tokio::test]
+async fn bundle_is_mined_in_order() {
+    let (provider, mock) = Provider::mocked();
+    let txs = vec![Bytes::from(vec![0x01, 0x02]), Bytes::from(vec![0x03, 0x04])];
+    let hashes: Vec<H256> = txs.iter().map(|tx| H256::from(keccak256(tx))).collect();
+
+    // responses are served last in, first out
+    mock.push(json!("0x0")).unwrap();
+    mock.push(hashes[1]).unwrap();
+    mock.push(hashes[0]).unwrap();
+    mock.push(json!(null)).unwrap();
+    mock.push(json!(null)).unwrap();
+
+    let builder = MockBuilder::new(Arc::new(provider));
+    let url = builder.spawn().await.unwrap();
+    let bundle = BundleRequest::new()
+        .push_transaction(txs[0].clone())
+        .push_transaction(txs[1].clone())
+        .set_block(U64::from(100));
+    let identity = IDENTITY_KEY.parse::<LocalWallet>().unwrap();
+    let (name, response) = send_bundle("devnet".to_string(), url, identity, bundle)
+        .await
+        .unwrap();
+    assert_eq!(name, "devnet");
+    assert_eq!(response.unwrap().bundle_hash, bundle_hash(&txs));
+
+    mock.assert_request("anvil_dropTransaction", [hashes[0]]).unwrap();
+    mock.assert_request("anvil_dropTransaction", [hashes[1]]).unwrap();
+    mock.assert_request("eth_sendRawTransaction", [&txs[0]]).unwrap();
+    mock.assert_request("eth_sendRawTransaction", [&txs[1]]).unwrap();
+    mock.assert_request("evm_mine", ()).unwrap();
+
+    let bundles = builder.bundles();
+    assert_eq!(bundles.len(), 1);
+    assert!(bundles[0].included);
+    assert_eq!(bundles[0].block_number, U64::from(100));
+    assert_eq!(bundles[0].txs, txs);
+}
+
+#[tokio::test]
+async fn failed_inclusion_is_recorded() {
+    // no responses: the first anvil call fails
+    let (provider, _mock) = Provider::mocked();
+    let builder = MockBuilder::new(Arc::new(provider));
+    let response = builder
+        .handle_request(&json!({
+            "jsonrpc": "2.0",
+            "id": 7,
+            "method": "eth_sendBundle",
+            "params": [{ "txs": ["0x01"], "blockNumber": "0x10" }],
+        }))
+        .await;
+    assert_eq!(response["id"], 7);
+    assert!(response["result"]["bundleHash"].is_string());
+    assert!(!builder.bundles()[0].included);
+}
+
+#[tokio::test]
+async fn other_methods_are_rejected() {
+    let (provider, _mock) = Provider::mocked();
+    let builder = MockBuilder::new(Arc::new(provider));
+    let request = json!({ "jsonrpc": "2.0", "id": 1, "method": "eth_callBundle", "params": [] });
+    let response = builder.handle_request(&request).await;
+    assert_eq!(response["error"]["code"], -32601);
+    assert!(builder.bundles().is_empty());
+}
@@ -0,0 +1,171 @@
+//! Tests for the conflict-aware bundle packer.
+//!
+//! Everything here runs on hand-built sandwiches, without a node.
+use ethers::types::{H160, H256, U256};
+use std::collections::HashMap;
+
+use sandooo::common::evm::VictimTx;
+use sandooo::common::pools::DexVariant;
+use sandooo::sandwich::packer::{
+    candidates, pack, pack_sandwiches, to_batch, Candidate, PackingLimits,
+};
+use sandooo::sandwich::simulation::{OptimizedSandwich, Sandwich, SwapDirection, SwapInfo};
+
+mod common;
+use common::{ether, weth};
+
+/// A sandwich of `victim` buying `token` on `pair`, optimized to `amount_in` WETH for `revenue`.
+fn sandwich(victim: u8, pair: u8, token: u8, amount_in: u64, revenue: u64) -> Sandwich {
+    let tx_hash = H256::repeat_byte(victim);
+    Sandwich {
+        amount_in: U256::zero(),
+        swap_info: SwapInfo {
+            tx_hash,
+            target_pair: H160::repeat_byte(pair),
+            main_currency: weth(),
+            target_token: H160::repeat_byte(token),
+            version: DexVariant::UniswapV2,
+            token0_is_main: true,
+            fee: 3000,
+            direction: SwapDirection::Buy,
+        },
+        victim_tx: VictimTx {
+            tx_hash,
+            ..Default::default()
+        },
+        optimized_sandwich: Some(OptimizedSandwich {
+            amount_in: ether(amount_in),
+            max_revenue: U256::from(revenue),
+            front_gas_used: 150_000,
+            back_gas_used: 150_000,
+            ..Default::default()
+        }),
+    }
+}
+
+fn candidate(victim: u8, pair: u8, token: u8, amount_in: u64, revenue: u64) -> Candidate {
+    let sandwich = sandwich(victim, pair, token, amount_in, revenue);
+    Candidate::new(H256::repeat_byte(victim), &sandwich, U256::MAX).unwrap()
+}
+
+fn limits(weth_balance: u64) -> PackingLimits {
+    PackingLimits::new(HashMap::from([(weth(), ether(weth_balance))]))
+}
+
+fn values(candidates: &[Candidate], chosen: &[usize]) -> Vec<U256> {
+    chosen.iter().map(|i| candidates[*i].value).collect()
+}
+
+#[test]
+fn sandwiches_sharing_a_victim_pair_or_token_conflict() {
+    let base = candidate(0x01, 0x10, 0x20, 1, 10);
+    assert!(base.conflicts_with(&candidate(0x01, 0x11, 0x21, 1, 10)));
+    assert!(base.conflicts_with(&candidate(0x02, 0x10, 0x21, 1, 10)));
+    assert!(base.conflicts_with(&candidate(0x02, 0x11, 0x20, 1, 10)));
+    assert!(!base.conflicts_with(&candidate(0x02, 0x11, 0x21, 1, 10)));
+}
+
+#[test]
+fn pack_beats_the_greedy_pick() {
+    // the best sandwich conflicts with two that are worth more together
+    let candidates = vec![
+        candidate(0x01, 0x10, 0x20, 1, 10),
+        candidate(0x02, 0x10, 0x21, 1, 6),
+        candidate(0x03, 0x11, 0x20, 1, 6),
+    ];
+    let chosen = pack(&candidates, &limits(100));
+    assert_eq!(values(&candidates, &chosen), vec![U256::from(6); 2]);
+}
+
+#[test]
+fn pack_respects_the_capital_per_quote_asset() {
+    let candidates = vec![
+        candidate(0x01, 0x10, 0x20, 6, 10),
+        candidate(0x02, 0x11, 0x21, 6, 9),
+        candidate(0x03, 0x12, 0x22, 4, 5),
+    ];
+    // 10 WETH: 6 + 4 fits, 6 + 6 doesn't
+    let chosen = pack(&candidates, &limits(10));
+    assert_eq!(
+        values(&candidates, &chosen),
+        vec![U256::from(10), U256::from(5)]
+    );
+
+    // nothing in another asset
+    let chosen = pack(&candidates, &PackingLimits::new(HashMap::new()));
+    assert!(chosen.is_empty());
+}
+
+#[test]
+fn pack_respects_the_gas_budget() {
+    let candidates = vec![
+        candidate(0x01, 0x10, 0x20, 1, 10),
+        candidate(0x02, 0x11, 0x21, 1, 9),
+        candidate(0x03, 0x12, 0x22, 1, 8),
+    ];
+    // each sandwich uses 300k gas
+    let chosen = pack(&candidates, &limits(100).with_max_gas(700_000));
+    assert_eq!(
+        values(&candidates, &chosen),
+        vec![U256::from(10), U256::from(9)]
+    );
+}
+
+#[test]
+fn candidates_need_their_whole_amount_funded() {
+    let promising = HashMap::from([
+        (
+            H256::repeat_byte(0x01),
+            vec![sandwich(0x01, 0x10, 0x20, 5, 10)],
+        ),
+        (
+            H256::repeat_byte(0x02),
+            vec![Sandwich {
+                optimized_sandwich: None,
+                ..sandwich(0x02, 0x11, 0x21, 1, 10)
+            }],
+        ),
+    ]);
+    let balances = HashMap::from([(weth(), ether(5))]);
+
+    // the unoptimized sandwich is skipped
+    let found = candidates(&promising, &balances);
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].amount_in(), ether(5));
+    assert_eq!(found[0].value, U256::from(10));
+    assert!(found[0].sandwich.optimized_sandwich.is_none());
+
+    // its revenue was quoted at 5 WETH, so it isn't traded smaller
+    let short = HashMap::from([(weth(), ether(5) - 1)]);
+    assert!(candidates(&promising, &short).is_empty());
+
+    // no balance, no candidate
+    assert!(candidates(&promising, &HashMap::new()).is_empty());
+}
+
+#[test]
+fn packed_sandwiches_form_one_batch() {
+    let promising = HashMap::from([
+        (
+            H256::repeat_byte(0x01),
+            vec![
+                sandwich(0x01, 0x10, 0x20, 1, 10),
+                sandwich(0x01, 0x11, 0x21, 1, 4),
+            ],
+        ),
+        (
+            H256::repeat_byte(0x02),
+            vec![sandwich(0x02, 0x12, 0x22, 1, 7)],
+        ),
+    ]);
+
+    // one sandwich per victim, the best of each
+    let packed = pack_sandwiches(&promising, &limits(100));
+    let batch = to_batch(&packed);
+    assert_eq!(batch.sandwiches.len(), 2);
+    assert_eq!(
+        batch.target_pairs(),
+        vec![H160::repeat_byte(0x10), H160::repeat_byte(0x12)]
+    );
+    assert_eq!(batch.swap_info_vec.len(), 2);
+}
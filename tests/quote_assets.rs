@@ -0,0 +1,96 @@
+//! Config parsing tests for the quote assets.
+use ethers::types::{H160, U256};
+use std::str::FromStr;
+
+use sandooo::common::constants::{USDT, WBTC, WETH};
+use sandooo::common::quote_assets::{parse_amount, QuoteAssets};
+
+/// A one-asset config with the given amounts.
+fn config(decimals: u8, probe_amount: &str, max_amount_in: &str, tolerance: &str) -> String {
+    format!(
+        r#"[{{
+            "address": "{}",
+            "name": "Wrapped Ether",
+            "symbol": "WETH",
+            "decimals": {},
+            "balance_slot": 3,
+            "weight": 7,
+            "probe_amount": "{}",
+            "max_amount_in": "{}",
+            "tolerance": "{}"
+        }}]"#,
+        WETH, decimals, probe_amount, max_amount_in, tolerance
+    )
+}
+
+#[test]
+fn amounts_parse_exactly() {
+    assert_eq!(parse_amount("1", 18).unwrap(), U256::exp10(18));
+    assert_eq!(parse_amount("0.01", 18).unwrap(), U256::exp10(16));
+    assert_eq!(
+        parse_amount("300000", 6).unwrap(),
+        U256::from(300_000_000_000u64)
+    );
+    assert_eq!(parse_amount(".5", 1).unwrap(), U256::from(5));
+    assert_eq!(parse_amount("7.", 0).unwrap(), U256::from(7));
+
+    // beyond what an f64 holds
+    assert_eq!(
+        parse_amount("1.000000000000000001", 18).unwrap(),
+        U256::exp10(18) + 1
+    );
+    assert_eq!(
+        parse_amount("123456789.123456789123456789", 18).unwrap(),
+        U256::from_dec_str("123456789123456789123456789").unwrap()
+    );
+}
+
+#[test]
+fn malformed_amounts_are_refused() {
+    for amount in ["", ".", "1e-06", "-1", "1.2.3", "0x10", " 1", "1,5"] {
+        assert!(parse_amount(amount, 18).is_err(), "{:?}", amount);
+    }
+    // more precision than the token has
+    assert!(parse_amount("0.0000001", 6).is_err());
+}
+
+#[test]
+fn builtin_config_is_valid() {
+    let assets = QuoteAssets::defaults();
+    let weth = assets.get(H160::from_str(WETH).unwrap()).unwrap();
+    assert!(weth.is_weth());
+    assert_eq!(weth.probe_amount_in(), U256::exp10(16));
+    assert_eq!(weth.max_amount_in(), U256::from(100) * U256::exp10(18));
+    assert_eq!(weth.tolerance(), U256::exp10(14));
+
+    let wbtc = assets.get(H160::from_str(WBTC).unwrap()).unwrap();
+    assert_eq!(wbtc.decimals, 8);
+    assert_eq!(wbtc.probe_amount_in(), U256::from(50_000));
+    assert_eq!(wbtc.tolerance(), U256::from(100));
+    assert!(!wbtc.weth_pools.is_empty());
+}
+
+#[test]
+fn config_amounts_are_checked_against_the_decimals() {
+    let assets = QuoteAssets::from_json(&config(6, "10", "300000", "0.001")).unwrap();
+    let asset = &assets.all()[0];
+    assert_eq!(asset.probe_amount_in(), U256::from(10_000_000));
+    assert_eq!(asset.to_units("2.5").unwrap(), U256::from(2_500_000));
+
+    assert!(QuoteAssets::from_json(&config(6, "10", "300000", "0.0000001")).is_err());
+    assert!(QuoteAssets::from_json(&config(18, "1e-2", "100", "0.0001")).is_err());
+    assert!(QuoteAssets::from_json("[]").is_err());
+}
+
+#[test]
+fn heavier_quote_asset_wins_a_pair() {
+    let assets = QuoteAssets::defaults();
+    let weth = H160::from_str(WETH).unwrap();
+    let usdt = H160::from_str(USDT).unwrap();
+    let target = H160::repeat_byte(0x42);
+
+    assert_eq!(assets.main_and_target(usdt, weth), Some((weth, usdt)));
+    assert_eq!(assets.main_and_target(weth, usdt), Some((weth, usdt)));
+    assert_eq!(assets.main_and_target(target, usdt), Some((usdt, target)));
+    assert_eq!(assets.main_and_target(target, H160::zero()), None);
+}
@@ -0,0 +1,112 @@
+//! Tests for recording the event stream and replaying it.
+use ethers::types::{Block, Transaction, H256, U256, U64};
+use tokio::sync::broadcast;
+
+use sandooo::common::recorder::{record_events, EventRecorder, RecordedEvent, ReplaySource};
+use sandooo::common::streams::{Event, NewBlock, NewPendingTx};
+
+fn block_event(number: u64) -> Event {
+    let block: Block<H256> = Block {
+        number: Some(U64::from(number)),
+        base_fee_per_gas: Some(U256::from(20_000_000_000u64)),
+        gas_limit: U256::from(30_000_000u64),
+        gas_used: U256::from(15_000_000u64),
+        timestamp: U256::from(1_700_000_000u64),
+        ..Default::default()
+    };
+    Event::Block(NewBlock::from_block(&block).unwrap())
+}
+
+fn pending_event() -> Event {
+    Event::PendingTransaction(NewPendingTx {
+        tx: Transaction {
+            hash: H256::random(),
+            ..Default::default()
+        },
+        added_block: None,
+    })
+}
+
+fn event_key(event: &Event) -> String {
+    match event {
+        Event::Block(block) => format!("block {}", block.block_number),
+        Event::PendingTransaction(pending) => format!("tx {:?}", pending.tx.hash),
+    }
+}
+
+fn temp_log() -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("sandooo-events-{:?}.jsonl", H256::random()))
+}
+
+#[test]
+fn recorded_log_round_trips() {
+    let path = temp_log();
+    let events = vec![block_event(100), pending_event(), pending_event(), block_event(101)];
+
+    let mut recorder = EventRecorder::new(&path).unwrap();
+    for (i, event) in events.iter().enumerate() {
+        recorder.record_at(event, 1_000 + i as u64 * 250).unwrap();
+    }
+    recorder.flush().unwrap();
+    assert_eq!(recorder.count(), 4);
+
+    let replay = ReplaySource::load(&path).unwrap();
+    assert_eq!(replay.len(), 4);
+    let keys: Vec<String> = replay.events().iter().map(|e| event_key(&e.event)).collect();
+    assert_eq!(keys, events.iter().map(event_key).collect::<Vec<_>>());
+    assert_eq!(replay.events()[3].received_at_ms, 1_750);
+
+    match (&replay.events()[0].event, &events[0]) {
+        (Event::Block(loaded), Event::Block(original)) => {
+            assert_eq!(loaded.next_block, original.next_block);
+            assert_eq!(loaded.next_base_fee, original.next_base_fee);
+        }
+        _ => panic!("first event is not a block"),
+    }
+
+    std::fs::remove_file(&path).ok();
+}
+
+#[tokio::test]
+async fn replays_in_order_into_channel() {
+    let events: Vec<RecordedEvent> = [block_event(200), pending_event(), block_event(201)]
+        .into_iter()
+        .enumerate()
+        .map(|(i, event)| RecordedEvent {
+            received_at_ms: 5_000 + i as u64 * 12_000,
+            event,
+        })
+        .collect();
+    let expected: Vec<String> = events.iter().map(|e| event_key(&e.event)).collect();
+
+    let (sender, _) = broadcast::channel::<Event>(16);
+    let replay = ReplaySource::new(events).with_speed(1000.0);
+    let handle = tokio::spawn(replay.run(sender.clone()));
+
+    // the replay waits for a subscriber before sending anything
+    let mut receiver = sender.subscribe();
+    let mut received = Vec::new();
+    for _ in 0..expected.len() {
+        received.push(event_key(&receiver.recv().await.unwrap()));
+    }
+    assert_eq!(received, expected);
+    assert_eq!(handle.await.unwrap().unwrap(), 3);
+}
+
+#[tokio::test]
+async fn records_from_channel_until_closed() {
+    let path = temp_log();
+    let (sender, receiver) = broadcast::channel::<Event>(16);
+    let recording = tokio::spawn(record_events(receiver, path.clone()));
+
+    sender.send(block_event(300)).unwrap();
+    sender.send(pending_event()).unwrap();
+    drop(sender);
+    assert_eq!(recording.await.unwrap().unwrap(), 2);
+
+    let replay = ReplaySource::load(&path).unwrap();
+    assert_eq!(replay.len(), 2);
+    assert!(replay.events()[0].received_at_ms <= replay.events()[1].received_at_ms);
+
+    std::fs::remove_file(&path).ok();
+}
@@ -0,0 +1,367 @@
+//! Round-trip and bytecode parity tests for the packed Sandooo calldata codec.
+//!
+//! Parity tests run `SANDOOO_BYTECODE` and `SANDOOO_V3_BYTECODE` in an in-memory revm against
+//! recorder contracts that log their calldata, then check the token transfers and V2 swaps the
+//! contract made against the trades that were encoded.
+use ethers::abi::Token;
+use ethers::types::{Bytes, H160, U256};
+use proptest::prelude::*;
+use revm::db::{CacheDB, EmptyDB};
+use revm::primitives::{
+    AccountInfo, Bytecode, CreateScheme, ExecutionResult, Log, Output, TransactTo, B160, B256,
+    U256 as rU256,
+};
+use revm::EVM;
+
+use sandooo::common::abi::Abi;
+use sandooo::common::bytecode::{SANDOOO_BYTECODE, SANDOOO_V3_BYTECODE};
+use sandooo::common::constants::AAVE_V3_POOL;
+use sandooo::common::execution_v3::{encode_leg_call, encode_route_call};
+use sandooo::common::sando_calldata::{decode, encode, SandoPayload, SandoTrade, TradeLayout};
+
+/// Logs its calldata (LOG0) and returns `uint256(1)`. `balanceOf` is answered without logging,
+/// since it arrives as a STATICCALL:
+/// 0 CALLDATALOAD 0xe0 SHR 0x70a08231 EQ 0x23 JUMPI
+/// CALLDATASIZE 0 0 CALLDATACOPY CALLDATASIZE 0 LOG0 1 0 MSTORE 32 0 RETURN
+/// JUMPDEST 1 0 MSTORE 32 0 RETURN
+const RECORDER_CODE: &str = "0x60003560e01c6370a08231146023573660006000373660\
+    00a0600160005260206000f35b600160005260206000f3";
+
+fn address() -> impl Strategy<Value = H160> {
+    any::<[u8; 20]>().prop_map(H160::from)
+}
+
+fn word() -> impl Strategy<Value = U256> {
+    any::<[u8; 32]>().prop_map(|bytes| U256::from_big_endian(&bytes))
+}
+
+fn v2_trade() -> impl Strategy<Value = SandoTrade> {
+    (any::<bool>(), address(), address(), word(), word()).prop_map(
+        |(zero_for_one, pair, token_in, amount_in, amount_out)| {
+            SandoTrade::v2(zero_for_one, pair, token_in, amount_in, amount_out)
+        },
+    )
+}
+
+fn v3_trade() -> impl Strategy<Value = SandoTrade> {
+    (
+        any::<bool>(),
+        address(),
+        address(),
+        word(),
+        word(),
+        0u32..(1 << 24),
+        any::<[u8; 20]>(),
+    )
+        .prop_map(
+            |(zero_for_one, pair, token_in, amount_in, amount_out, fee, limit)| {
+                let mut trade =
+                    SandoTrade::v3(zero_for_one, pair, token_in, amount_in, amount_out, fee);
+                trade.sqrt_price_limit_x96 = U256::from_big_endian(&limit);
+                trade
+            },
+        )
+}
+
+fn payload(trade: impl Strategy<Value = SandoTrade>) -> impl Strategy<Value = SandoPayload> {
+    (any::<u64>(), prop::collection::vec(trade, 0..8))
+        .prop_map(|(block_number, trades)| SandoPayload {
+            block_number,
+            trades,
+        })
+}
+
+proptest! {
+    #[test]
+    fn sandooo_round_trip(payload in payload(v2_trade())) {
+        let data = encode(&payload, TradeLayout::Sandooo).unwrap();
+        prop_assert_eq!(data.len(), 8 + payload.trades.len() * 105);
+        prop_assert_eq!(decode(&data, TradeLayout::Sandooo).unwrap(), payload);
+    }
+
+    #[test]
+    fn sando_v3_round_trip(payload in payload(prop_oneof![v2_trade(), v3_trade()])) {
+        let data = encode(&payload, TradeLayout::SandoooV3).unwrap();
+        prop_assert_eq!(data.len(), 8 + payload.trades.len() * 129);
+        prop_assert_eq!(decode(&data, TradeLayout::SandoooV3).unwrap(), payload);
+    }
+
+    #[test]
+    fn block_number_is_big_endian_header(block_number in any::<u64>()) {
+        let data = SandoPayload::new(block_number).encode(TradeLayout::SandoooV3).unwrap();
+        prop_assert_eq!(&data[..], &block_number.to_be_bytes()[..]);
+    }
+
+    #[test]
+    fn truncated_payloads_are_rejected(
+        payload in payload(v2_trade()),
+        cut in 1usize..105,
+    ) {
+        let data = encode(&payload, TradeLayout::Sandooo).unwrap();
+        prop_assume!(data.len() > cut);
+        prop_assert!(decode(&data[..data.len() - cut], TradeLayout::Sandooo).is_err());
+    }
+}
+
+#[test]
+fn sandooo_layout_rejects_v3_trades() {
+    let mut payload = SandoPayload::new(1);
+    payload.trades.push(SandoTrade::v3(
+        true,
+        H160::random(),
+        H160::random(),
+        U256::one(),
+        U256::one(),
+        500,
+    ));
+    assert!(encode(&payload, TradeLayout::Sandooo).is_err());
+}
+
+#[test]
+fn unknown_dex_kind_is_rejected() {
+    let mut payload = SandoPayload::new(1);
+    payload.trades.push(SandoTrade::v2(
+        true,
+        H160::random(),
+        H160::random(),
+        U256::one(),
+        U256::one(),
+    ));
+    let mut data = encode(&payload, TradeLayout::SandoooV3).unwrap().to_vec();
+    data[8] = 7;
+    assert!(decode(&data, TradeLayout::SandoooV3).is_err());
+}
+
+/// Calls the contract made as the recorders saw them.
+#[derive(Debug, PartialEq)]
+enum Recorded {
+    /// `tokenIn.transfer(to, amount)`
+    Transfer { token: H160, to: H160, amount: U256 },
+    /// `pair.swap(amount0Out, amount1Out, to, data)`
+    Swap {
+        pair: H160,
+        amount0_out: U256,
+        amount1_out: U256,
+        to: H160,
+    },
+}
+
+fn recorded(logs: &[Log]) -> Vec<Recorded> {
+    logs.iter()
+        .map(|log| {
+            let address: H160 = log.address.into();
+            let data = &log.data;
+            let word = |i: usize| U256::from_big_endian(&data[4 + 32 * i..4 + 32 * (i + 1)]);
+            let addr = |i: usize| H160::from_slice(&data[4 + 32 * i + 12..4 + 32 * (i + 1)]);
+            match &data[..4] {
+                [0xa9, 0x05, 0x9c, 0xbb] => Recorded::Transfer {
+                    token: address,
+                    to: addr(0),
+                    amount: word(1),
+                },
+                [0x02, 0x2c, 0x0d, 0x9f] => Recorded::Swap {
+                    pair: address,
+                    amount0_out: word(0),
+                    amount1_out: word(1),
+                    to: addr(2),
+                },
+                selector => panic!("unexpected call {:?} to {:?}", selector, address),
+            }
+        })
+        .collect()
+}
+
+/// What the contract should do for each V2 trade: pay in, then pull the output to itself.
+fn expected(payload: &SandoPayload, bot: H160) -> Vec<Recorded> {
+    payload
+        .trades
+        .iter()
+        .flat_map(|trade| {
+            let (amount0_out, amount1_out) = if trade.zero_for_one {
+                (U256::zero(), trade.amount_out)
+            } else {
+                (trade.amount_out, U256::zero())
+            };
+            vec![
+                Recorded::Transfer {
+                    token: trade.token_in,
+                    to: trade.pair,
+                    amount: trade.amount_in,
+                },
+                Recorded::Swap {
+                    pair: trade.pair,
+                    amount0_out,
+                    amount1_out,
+                    to: bot,
+                },
+            ]
+        })
+        .collect()
+}
+
+fn parity_payload(block_number: u64) -> SandoPayload {
+    let mut payload = SandoPayload::new(block_number);
+    for (i, zero_for_one) in [true, false, true].into_iter().enumerate() {
+        payload.trades.push(SandoTrade::v2(
+            zero_for_one,
+            H160::from_low_u64_be(0x1000 + i as u64),
+            H160::from_low_u64_be(0x2000 + i as u64),
+            U256::exp10(18) + U256::from(i),
+            U256::from(123456789u64) * U256::from(i + 1),
+        ));
+    }
+    payload
+}
+
+fn new_evm(block_number: u64, payload: &SandoPayload) -> EVM<CacheDB<EmptyDB>> {
+    let mut evm = EVM::new();
+    evm.database(CacheDB::new(EmptyDB::default()));
+    evm.env.block.number = rU256::from(block_number);
+
+    let recorder: Bytes = RECORDER_CODE.parse().unwrap();
+    for trade in &payload.trades {
+        for target in [trade.pair, trade.token_in] {
+            let info = AccountInfo::new(
+                rU256::ZERO,
+                0,
+                B256::zero(),
+                Bytecode::new_raw(recorder.0.clone()),
+            );
+            evm.db.as_mut().unwrap().insert_account_info(target.into(), info);
+        }
+    }
+    evm
+}
+
+fn transact(
+    evm: &mut EVM<CacheDB<EmptyDB>>,
+    caller: H160,
+    to: H160,
+    data: Bytes,
+) -> ExecutionResult {
+    evm.env.tx.caller = caller.into();
+    evm.env.tx.transact_to = TransactTo::Call(to.into());
+    evm.env.tx.data = data.0;
+    evm.env.tx.gas_limit = 5000000;
+    evm.transact_commit().unwrap()
+}
+
+fn call(evm: &mut EVM<CacheDB<EmptyDB>>, caller: H160, to: H160, data: Bytes) -> Vec<Log> {
+    match transact(evm, caller, to, data) {
+        ExecutionResult::Success { logs, .. } => logs,
+        other => panic!("call failed: {:?}", other),
+    }
+}
+
+/// Deploys `SANDOOO_V3_BYTECODE` through its constructor, so owner and AAVE_POOL are set.
+fn deploy_sando_v3(evm: &mut EVM<CacheDB<EmptyDB>>, owner: H160) -> H160 {
+    let aave_pool: H160 = AAVE_V3_POOL.parse().unwrap();
+    let mut init_code = SANDOOO_V3_BYTECODE.to_vec();
+    init_code.extend(encode_constructor(aave_pool));
+    evm.env.tx.caller = owner.into();
+    evm.env.tx.transact_to = TransactTo::Create(CreateScheme::Create);
+    evm.env.tx.data = init_code.into();
+    evm.env.tx.gas_limit = 10000000;
+    match evm.transact_commit().unwrap() {
+        ExecutionResult::Success {
+            output: Output::Create(_, Some(address)),
+            ..
+        } => address.into(),
+        other => panic!("deployment failed: {:?}", other),
+    }
+}
+
+#[test]
+fn sandooo_bytecode_parses_encoded_trades() {
+    let block_number = 19_000_000;
+    let owner = H160::from_low_u64_be(0xdead);
+    let bot = H160::from_low_u64_be(0xb07);
+    let payload = parity_payload(block_number);
+
+    let mut evm = new_evm(block_number, &payload);
+    let info = AccountInfo::new(
+        rU256::ZERO,
+        0,
+        B256::zero(),
+        Bytecode::new_raw(SANDOOO_BYTECODE.0.clone()),
+    );
+    let db = evm.db.as_mut().unwrap();
+    db.insert_account_info(bot.into(), info);
+    // owner is stored in slot 0
+    let owner_word = rU256::from_be_bytes(B256::from(B160::from(owner)).0);
+    db.insert_account_storage(bot.into(), rU256::ZERO, owner_word)
+        .unwrap();
+
+    let data = encode(&payload, TradeLayout::Sandooo).unwrap();
+    let logs = call(&mut evm, owner, bot, data);
+
+    assert_eq!(recorded(&logs), expected(&payload, bot));
+}
+
+/// Only V2 entries: the recorder cannot answer a V3 pool's `swap`. V3 dispatch is covered by the
+/// forge tests and `tests/sando_v3_parity.rs`.
+#[test]
+fn sando_v3_bytecode_parses_encoded_trades() {
+    let block_number = 19_000_000;
+    let owner = H160::from_low_u64_be(0xdead);
+    let payload = parity_payload(block_number);
+
+    let mut evm = new_evm(block_number, &payload);
+    let bot = deploy_sando_v3(&mut evm, owner);
+
+    let data = encode(&payload, TradeLayout::SandoooV3).unwrap();
+    let calldata = encode_leg_call(&Abi::new(), data).unwrap();
+    let logs = call(&mut evm, owner, bot, calldata);
+
+    assert_eq!(recorded(&logs), expected(&payload, bot));
+}
+
+/// Two V2 hops, the second spending the whole output of the first.
+fn route_payload(block_number: u64) -> SandoPayload {
+    let mut payload = parity_payload(block_number);
+    payload.trades.truncate(2);
+    payload.trades[1].amount_in = U256::zero();
+    payload
+}
+
+#[test]
+fn sando_v3_bytecode_executes_routes() {
+    let block_number = 19_000_000;
+    let owner = H160::from_low_u64_be(0xdead);
+    let payload = route_payload(block_number);
+
+    let mut evm = new_evm(block_number, &payload);
+    let bot = deploy_sando_v3(&mut evm, owner);
+
+    let data = encode(&payload, TradeLayout::SandoooV3).unwrap();
+    let calldata = encode_route_call(&Abi::new(), U256::zero(), data).unwrap();
+    let logs = call(&mut evm, owner, bot, calldata);
+
+    let mut hops = payload.clone();
+    hops.trades[1].amount_in = hops.trades[0].amount_out;
+    assert_eq!(recorded(&logs), expected(&hops, bot));
+}
+
+#[test]
+fn sando_v3_route_below_min_profit_reverts() {
+    let block_number = 19_000_000;
+    let owner = H160::from_low_u64_be(0xdead);
+    let payload = route_payload(block_number);
+
+    let mut evm = new_evm(block_number, &payload);
+    let bot = deploy_sando_v3(&mut evm, owner);
+
+    // the recorders report the same balance before and after
+    let data = encode(&payload, TradeLayout::SandoooV3).unwrap();
+    let calldata = encode_route_call(&Abi::new(), U256::one(), data).unwrap();
+    let result = transact(&mut evm, owner, bot, calldata);
+    assert!(
+        matches!(result, ExecutionResult::Revert { .. }),
+        "{:?}",
+        result
+    );
+}
+
+fn encode_constructor(aave_pool: H160) -> Vec<u8> {
+    ethers::abi::encode(&[Token::Address(aave_pool)])
+}
@@ -0,0 +1,163 @@
+//! Parity tests between the Rust trade encoder and the compiled SandoooV3 contract.
+//!
+//! Each test forks mainnet through `WSS_URL`, deploys the pinned `SANDOOO_V3_BYTECODE` inside
+//! `EvmSimulator`, runs the encoded frontrun and backrun legs against a live pool and checks the
+//! contract received what the encoder asked for. Tests are skipped when `WSS_URL` is not set.
+use ethers::providers::{Provider, Ws};
+use ethers::types::{Bytes, H160, U256};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use sandooo::common::constants::{SANDOOO_V3_TRADE_SIZE, USDC, USDT, WETH};
+use sandooo::common::evm::{EvmSimulator, Tx, VictimTx};
+use sandooo::common::execution_v3::encode_leg_call;
+use sandooo::common::pools::DexVariant;
+use sandooo::common::quote_assets::get_quote_assets;
+use sandooo::sandwich::simulation::{
+    deploy_sando_v3, BatchSandwich, SandoContract, Sandwich, SwapDirection, SwapInfo,
+};
+
+mod common;
+use common::{fork, V2_WETH_USDT};
+
+/// Uniswap V3 USDC/WETH 0.05% pool
+const V3_USDC_WETH_500: &str = "0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640";
+fn batch(
+    pair: &str,
+    version: DexVariant,
+    fee: u32,
+    target_token: &str,
+    token0_is_main: bool,
+) -> BatchSandwich {
+    let weth = H160::from_str(WETH).unwrap();
+    let swap_info = SwapInfo {
+        tx_hash: Default::default(),
+        target_pair: H160::from_str(pair).unwrap(),
+        main_currency: weth,
+        target_token: H160::from_str(target_token).unwrap(),
+        version,
+        token0_is_main,
+        fee,
+        direction: SwapDirection::Buy,
+    };
+    BatchSandwich {
+        sandwiches: vec![Sandwich {
+            amount_in: U256::exp10(18),
+            swap_info: swap_info.clone(),
+            victim_tx: VictimTx::default(),
+            optimized_sandwich: None,
+        }],
+        swap_info_vec: vec![swap_info],
+        flashloan_asset: weth,
+    }
+}
+
+/// Reads the amountOut field of the first trade entry of a leg.
+fn first_amount_out(leg_data: &[u8]) -> U256 {
+    assert_eq!((leg_data.len() - 8) % SANDOOO_V3_TRADE_SIZE, 0);
+    // dexKind (1) | zeroForOne (1) | pair (20) | tokenIn (20) | amountIn (32) | amountOut (32)
+    U256::from_big_endian(&leg_data[8 + 74..8 + 106])
+}
+
+/// Runs frontrun then backrun through the deployed contract.
+///
+/// Returns the encoded frontrun output, the tokens actually bought and the WETH held afterwards.
+fn round_trip(
+    simulator: &mut EvmSimulator<Provider<Ws>>,
+    batch: &BatchSandwich,
+) -> (U256, U256, U256) {
+    let weth = H160::from_str(WETH).unwrap();
+    let target_token = batch.sandwiches[0].swap_info.target_token;
+
+    let bot = deploy_sando_v3(simulator).unwrap();
+    simulator
+        .set_token_balance_at(
+            bot,
+            &get_quote_assets().get(weth).unwrap().balance_slot(),
+            U256::exp10(18).into(),
+        )
+        .unwrap();
+
+    let block_number = simulator.get_block_number();
+    let owner = simulator.owner;
+    let run_leg = |simulator: &mut EvmSimulator<Provider<Ws>>, leg_data: Bytes| {
+        let calldata = encode_leg_call(&simulator.abi, leg_data).unwrap();
+        simulator
+            .call(Tx {
+                caller: owner,
+                transact_to: bot,
+                data: calldata.0,
+                value: U256::zero(),
+                gas_price: U256::zero(),
+                gas_limit: 5000000,
+            })
+            .unwrap();
+    };
+
+    // Frontrun
+    let reserves = batch.fetch_reserves(simulator);
+    let (front, _, _) = batch
+        .encode_frontrun_tx(SandoContract::SandoooV3, block_number, &reserves)
+        .unwrap();
+    let min_out = first_amount_out(&front);
+    run_leg(simulator, front);
+    let bought = simulator.get_token_balance(target_token, bot).unwrap();
+
+    // Backrun
+    let reserves = batch.fetch_reserves(simulator);
+    let token_balances = HashMap::from([(target_token, bought)]);
+    let back = batch
+        .encode_backrun_tx(SandoContract::SandoooV3, block_number, &reserves, &token_balances)
+        .unwrap();
+    run_leg(simulator, back);
+    let weth_after = simulator.get_token_balance(weth, bot).unwrap();
+
+    (min_out, bought, weth_after)
+}
+
+#[tokio::test]
+async fn v3_legs_match_encoded_amounts() {
+    let mut simulator = match fork().await {
+        Some(simulator) => simulator,
+        None => return,
+    };
+    // token0 = USDC, token1 = WETH
+    let batch = batch(V3_USDC_WETH_500, DexVariant::UniswapV3, 500, USDC, false);
+
+    let (min_out, bought, weth_after) = round_trip(&mut simulator, &batch);
+
+    assert!(bought >= min_out, "bought {} < encoded minimum {}", bought, min_out);
+    // the estimate only leaves the slippage room on top of the minimum
+    assert!(bought <= min_out * U256::from(10020) / U256::from(10000));
+    // a round trip through a 0.05% pool loses about 0.1%
+    assert!(weth_after > U256::exp10(18) * U256::from(99) / U256::from(100));
+}
+
+#[tokio::test]
+async fn v2_legs_match_encoded_amounts() {
+    let mut simulator = match fork().await {
+        Some(simulator) => simulator,
+        None => return,
+    };
+    // token0 = WETH, token1 = USDT
+    let batch = batch(V2_WETH_USDT, DexVariant::UniswapV2, 3000, USDT, true);
+
+    let (amount_out, bought, weth_after) = round_trip(&mut simulator, &batch);
+
+    assert_eq!(bought, amount_out, "V2 legs receive exactly the encoded output");
+    assert!(weth_after > U256::exp10(18) * U256::from(98) / U256::from(100));
+}
+
+#[test]
+fn sandooo_rejects_v3_pools() {
+    let batch = batch(V3_USDC_WETH_500, DexVariant::UniswapV3, 500, USDC, false);
+    let pair = H160::from_str(V3_USDC_WETH_500).unwrap();
+    let reserves = HashMap::from([(pair, (U256::exp10(12), U256::exp10(21)))]);
+
+    assert!(batch
+        .encode_frontrun_tx(SandoContract::Sandooo, U256::one(), &reserves)
+        .is_err());
+    assert!(batch
+        .encode_frontrun_tx(SandoContract::SandoooV3, U256::one(), &reserves)
+        .is_ok());
+}
@@ -0,0 +1,80 @@
+//! Revert decoding tests for `SimError`.
+use ethers::abi::{encode, Token};
+use ethers::types::{Bytes, U256};
+use ethers::utils::id;
+
+use sandooo::common::sim_error::{LegError, SimError, SimLeg, TxFailure};
+
+fn with_selector(signature: &str, args: &[Token]) -> Vec<u8> {
+    let mut data = id(signature).to_vec();
+    data.extend(encode(args));
+    data
+}
+
+#[test]
+fn decodes_sando_v3_custom_errors() {
+    let cases = [
+        ("NotOwner()", SimError::NotOwner),
+        ("InvalidData()", SimError::InvalidData),
+        ("WrongBlock()", SimError::WrongBlock),
+        ("Unauthorized()", SimError::Unauthorized),
+        ("InsufficientProfit()", SimError::InsufficientProfit),
+        ("InsufficientOutput()", SimError::InsufficientOutput),
+    ];
+    for (signature, expected) in cases {
+        assert_eq!(SimError::from_revert(&with_selector(signature, &[])), expected);
+        assert!(expected.is_contract_error());
+    }
+}
+
+#[test]
+fn decodes_error_string_and_panic() {
+    let data = with_selector(
+        "Error(string)",
+        &[Token::String("UniswapV2Router: INSUFFICIENT_OUTPUT_AMOUNT".into())],
+    );
+    assert_eq!(
+        SimError::from_revert(&data),
+        SimError::Revert("UniswapV2Router: INSUFFICIENT_OUTPUT_AMOUNT".into())
+    );
+
+    // Sandooo.sol reports the owner check as a string
+    let data = with_selector("Error(string)", &[Token::String("NOT_OWNER".into())]);
+    assert_eq!(SimError::from_revert(&data), SimError::NotOwner);
+
+    // arithmetic overflow
+    let data = with_selector("Panic(uint256)", &[Token::Uint(U256::from(0x11))]);
+    assert_eq!(SimError::from_revert(&data), SimError::Panic(U256::from(0x11)));
+}
+
+#[test]
+fn keeps_unknown_and_empty_reverts() {
+    assert_eq!(SimError::from_revert(&[]), SimError::EmptyRevert);
+    assert_eq!(
+        SimError::from_revert(&[0xde, 0xad]),
+        SimError::UnknownRevert(Bytes::from(vec![0xde, 0xad]))
+    );
+    let data = with_selector("SomethingElse()", &[]);
+    assert_eq!(
+        SimError::from_revert(&data),
+        SimError::UnknownRevert(Bytes::from(data.clone()))
+    );
+}
+
+#[test]
+fn leg_error_recovers_tx_failure() {
+    let e: anyhow::Error = TxFailure {
+        error: SimError::WrongBlock,
+        gas_used: 21_500,
+    }
+    .into();
+    let error = LegError::from_call_error(SimLeg::Backrun, None, &e);
+    assert_eq!(error.leg, SimLeg::Backrun);
+    assert_eq!(error.error, SimError::WrongBlock);
+    assert_eq!(error.gas_used, 21_500);
+
+    let e = anyhow::anyhow!("db error");
+    let error = LegError::from_call_error(SimLeg::Frontrun, None, &e);
+    assert_eq!(error.error, SimError::Invalid("db error".into()));
+    assert_eq!(error.gas_used, 0);
+}
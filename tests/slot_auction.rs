@@ -0,0 +1,162 @@
+//! Tests for the slot clock and the per-slot opportunity book.
+//!
+//! Everything here runs on hand-built blocks and sandwiches, without a node.
+use ethers::types::{H160, H256, U256, U64};
+use std::time::Duration;
+
+use sandooo::common::evm::{NextBlockEnv, VictimTx};
+use sandooo::common::pools::DexVariant;
+use sandooo::common::slot_clock::{Cutoff, SlotClock, SLOT_DURATION};
+use sandooo::sandwich::opportunity_book::OpportunityBook;
+use sandooo::sandwich::simulation::{OptimizedSandwich, Sandwich, SwapDirection, SwapInfo};
+
+/// Timestamp of the parent block, in seconds.
+const PARENT_TIMESTAMP: u64 = 1_705_000_000;
+
+fn next_block(number: u64, parent_timestamp: u64) -> NextBlockEnv {
+    NextBlockEnv {
+        number: U64::from(number),
+        timestamp: U256::from(parent_timestamp + SLOT_DURATION.as_secs()),
+        base_fee: U256::from(20_000_000_000u64),
+        gas_limit: U256::from(30_000_000u64),
+        prevrandao: H256::zero(),
+        coinbase: H160::repeat_byte(0xc0),
+        chain_id: 1,
+    }
+}
+
+fn sandwich(tx_hash: H256, max_revenue: u64) -> Sandwich {
+    Sandwich {
+        amount_in: U256::exp10(18),
+        swap_info: SwapInfo {
+            tx_hash,
+            target_pair: H160::repeat_byte(0x01),
+            main_currency: H160::repeat_byte(0x02),
+            target_token: H160::repeat_byte(0x03),
+            version: DexVariant::UniswapV2,
+            token0_is_main: true,
+            fee: 3000,
+            direction: SwapDirection::Buy,
+        },
+        victim_tx: VictimTx {
+            tx_hash,
+            ..Default::default()
+        },
+        optimized_sandwich: Some(OptimizedSandwich {
+            amount_in: U256::exp10(18),
+            max_revenue: U256::from(max_revenue),
+            ..Default::default()
+        }),
+    }
+}
+
+#[test]
+fn cutoffs_follow_the_parent_timestamp() {
+    let mut clock = SlotClock::new(vec![
+        Duration::from_secs(11),
+        Duration::from_secs(9),
+        Duration::from_secs(15),
+    ]);
+    assert!(clock.next_cutoff().is_none());
+    assert!(!clock.is_late());
+
+    clock.on_block(&next_block(100, PARENT_TIMESTAMP));
+    assert_eq!(clock.target_block(), U64::from(100));
+    assert_eq!(clock.slot_start_ms(), PARENT_TIMESTAMP * 1000);
+
+    // sorted, and the one past the end of the slot is dropped
+    let first = clock.pass_cutoff().unwrap();
+    assert_eq!(
+        first,
+        Cutoff {
+            index: 0,
+            at_ms: PARENT_TIMESTAMP * 1000 + 9_000,
+        }
+    );
+    assert!(!clock.is_late());
+    let second = clock.pass_cutoff().unwrap();
+    assert_eq!(second.at_ms, PARENT_TIMESTAMP * 1000 + 11_000);
+    assert!(clock.pass_cutoff().is_none());
+    assert!(clock.is_late());
+
+    // a new block opens a new slot
+    clock.on_block(&next_block(101, PARENT_TIMESTAMP + 12));
+    assert!(!clock.is_late());
+    assert_eq!(
+        clock.next_cutoff().unwrap().at_ms,
+        (PARENT_TIMESTAMP + 12) * 1000 + 9_000
+    );
+}
+
+#[test]
+fn time_until_a_cutoff() {
+    let mut clock = SlotClock::default();
+    clock.on_block(&next_block(100, PARENT_TIMESTAMP));
+    let cutoff = clock.next_cutoff().unwrap();
+
+    let slot_start = PARENT_TIMESTAMP * 1000;
+    assert_eq!(
+        SlotClock::until(&cutoff, slot_start + 2_500),
+        Duration::from_millis(cutoff.at_ms - slot_start - 2_500)
+    );
+    assert_eq!(SlotClock::until(&cutoff, cutoff.at_ms + 1), Duration::ZERO);
+}
+
+#[test]
+fn no_cutoffs_is_always_late() {
+    let mut clock = SlotClock::new(Vec::new());
+    clock.on_block(&next_block(100, PARENT_TIMESTAMP));
+    assert!(clock.next_cutoff().is_none());
+    assert!(clock.is_late());
+}
+
+#[test]
+fn book_resubmits_only_when_better() {
+    let (victim_a, victim_b) = (H256::repeat_byte(0xaa), H256::repeat_byte(0xbb));
+    let mut book = OpportunityBook::new(U64::from(100));
+    assert!(!book.should_submit());
+
+    // the best sandwich of each victim counts
+    book.insert(victim_a, vec![sandwich(victim_a, 5), sandwich(victim_a, 8)]);
+    assert_eq!(book.value(), U256::from(8));
+    assert!(book.should_submit());
+    book.mark_submitted();
+    assert!(!book.should_submit());
+
+    // the same victim simulated again, for less
+    book.insert(victim_a, vec![sandwich(victim_a, 6)]);
+    assert!(!book.should_submit());
+
+    book.insert(victim_b, vec![sandwich(victim_b, 3)]);
+    assert_eq!(book.value(), U256::from(9));
+    assert!(book.should_submit());
+    book.mark_submitted();
+
+    // an empty result removes the victim
+    book.insert(victim_b, Vec::new());
+    assert_eq!(book.len(), 1);
+}
+
+#[test]
+fn book_rolls_pending_victims_into_the_next_slot() {
+    let (victim_a, victim_b) = (H256::repeat_byte(0xaa), H256::repeat_byte(0xbb));
+    let mut book = OpportunityBook::new(U64::from(100));
+    book.insert(victim_a, vec![sandwich(victim_a, 5)]);
+    book.insert(victim_b, vec![sandwich(victim_b, 3)]);
+    book.mark_submitted();
+    {
+        let (_, submitted_bundle_ids) = book.for_submission();
+        submitted_bundle_ids.push_back("0xaaaaaaaa".to_string());
+    }
+
+    // victim b landed in block 100
+    book.roll(U64::from(101), |tx_hash| *tx_hash == victim_a);
+    assert_eq!(book.target_block(), U64::from(101));
+    assert_eq!(book.len(), 1);
+    assert!(book.sandwiches().contains_key(&victim_a));
+
+    // bundles are pinned to their block, so the survivor goes out again
+    assert!(book.should_submit());
+    let (_, submitted_bundle_ids) = book.for_submission();
+    assert!(submitted_bundle_ids.is_empty());
+}
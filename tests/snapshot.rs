@@ -0,0 +1,293 @@
+//! Tests for offline state snapshots.
+//!
+//! `tests/fixtures/snapshots/*.json` is a corpus of recorded sandwich cases, replayed without a
+//! network and checked for profit, gas and calldata byte-for-byte. A case is recorded from a
+//! mined victim with
+//!
+//!   SNAPSHOT_CASE=name:victim_hash:pair:main_currency:target_token:v2|v3:fee cargo test \
+//!     --test snapshot record_sandwich_case
+//!
+//! which needs `WSS_URL` with archive state; the other fork test is skipped without `WSS_URL`.
+//! `sandwich_cases_replay` says so and passes while the corpus holds no cases.
+use ethers::providers::Middleware;
+use ethers::types::{Bytes, Transaction, H160, H256, U256};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use sandooo::common::constants::{USDT, WETH};
+use sandooo::common::evm::{EvmSimulator, NextBlockEnv, Tx, VictimTx};
+use sandooo::common::pools::DexVariant;
+use sandooo::common::snapshot::{SnapshotAccount, StateSnapshot};
+use sandooo::sandwich::simulation::{
+    BatchSandwich, SandoContract, Sandwich, SimulatedSandwich, SwapDirection, SwapInfo,
+};
+
+mod common;
+use common::{block_env, connect, V2_WETH_USDT};
+
+fn snapshots_dir() -> std::path::PathBuf {
+    std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/snapshots")
+}
+
+/// A contract returning its storage slot 0: SLOAD(0), MSTORE(0), RETURN(0, 32).
+fn handmade_snapshot() -> (StateSnapshot, H160) {
+    let env = block_env();
+    let owner = H160::from_low_u64_be(0x0a);
+    let contract = H160::from_low_u64_be(0x0b);
+    let mut snapshot = StateSnapshot::new(env.clone(), owner);
+    snapshot.accounts.insert(
+        owner,
+        SnapshotAccount {
+            balance: U256::exp10(18),
+            ..Default::default()
+        },
+    );
+    snapshot.accounts.insert(env.coinbase, SnapshotAccount::default());
+    snapshot.accounts.insert(
+        contract,
+        SnapshotAccount {
+            code: Bytes::from(hex::decode("60005460005260206000f3").unwrap()),
+            storage: BTreeMap::from([(U256::zero(), U256::from(42))]),
+            ..Default::default()
+        },
+    );
+    (snapshot, contract)
+}
+
+#[test]
+fn handmade_snapshot_runs_offline() {
+    let (snapshot, contract) = handmade_snapshot();
+    let mut simulator = EvmSimulator::from_snapshot(&snapshot).unwrap();
+    assert_eq!(simulator.owner, snapshot.owner);
+    assert_eq!(simulator.get_block_number(), U256::from(19000001));
+
+    let result = simulator
+        .staticcall(Tx {
+            caller: simulator.owner,
+            transact_to: contract,
+            data: Default::default(),
+            value: U256::zero(),
+            gas_price: U256::zero(),
+            gas_limit: 100000,
+        })
+        .unwrap();
+    assert_eq!(U256::from_big_endian(&result.output), U256::from(42));
+    assert_eq!(simulator.get_eth_balance_of(snapshot.owner), U256::exp10(18));
+}
+
+#[test]
+fn snapshot_round_trips_json() {
+    let (snapshot, _) = handmade_snapshot();
+    let path = std::env::temp_dir().join(format!("sandooo-snapshot-{:?}.json", H256::random()));
+    snapshot.save(&path).unwrap();
+    assert_eq!(StateSnapshot::load(&path).unwrap(), snapshot);
+    std::fs::remove_file(&path).ok();
+}
+
+/// A recorded sandwich: the victim and its swap, the state it read and what it simulated to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SandwichCase {
+    snapshot: StateSnapshot,
+    victim: Transaction,
+    pair: H160,
+    main_currency: H160,
+    target_token: H160,
+    version: DexVariant,
+    fee: u32,
+    amount_in: U256,
+    expected: CaseResult,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct CaseResult {
+    revenue: i128,
+    profit: i128,
+    gas_cost: i128,
+    front_gas_used: u64,
+    back_gas_used: u64,
+    front_calldata: Bytes,
+    back_calldata: Bytes,
+}
+
+impl From<&SimulatedSandwich> for CaseResult {
+    fn from(simulated: &SimulatedSandwich) -> Self {
+        Self {
+            revenue: simulated.revenue,
+            profit: simulated.profit,
+            gas_cost: simulated.gas_cost,
+            front_gas_used: simulated.front_gas_used,
+            back_gas_used: simulated.back_gas_used,
+            front_calldata: simulated.front_calldata.clone(),
+            back_calldata: simulated.back_calldata.clone(),
+        }
+    }
+}
+
+impl SandwichCase {
+    fn batch(&self) -> BatchSandwich {
+        let victim = &self.victim;
+        let swap_info = SwapInfo {
+            tx_hash: victim.hash,
+            target_pair: self.pair,
+            main_currency: self.main_currency,
+            target_token: self.target_token,
+            version: self.version,
+            token0_is_main: self.main_currency < self.target_token,
+            fee: self.fee,
+            direction: SwapDirection::Buy,
+        };
+        let victim_tx = VictimTx {
+            tx_hash: victim.hash,
+            from: victim.from,
+            to: victim.to.unwrap_or_default(),
+            data: victim.input.0.clone().into(),
+            value: victim.value,
+            gas_price: victim.gas_price.unwrap_or_default(),
+            gas_limit: Some(victim.gas.as_u64()),
+        };
+        let mut batch = BatchSandwich::new(self.main_currency);
+        batch.swap_info_vec.push(swap_info.clone());
+        batch.sandwiches.push(Sandwich {
+            amount_in: self.amount_in,
+            swap_info,
+            victim_tx,
+            optimized_sandwich: None,
+        });
+        batch
+    }
+
+    /// Simulates the case through SandoooV3, which is deployed from the snapshot owner.
+    fn simulate<M: Middleware + 'static>(
+        &self,
+        simulator: &mut EvmSimulator<M>,
+    ) -> SimulatedSandwich {
+        let base_fee = self.snapshot.block.base_fee;
+        self.batch()
+            .simulate_in(
+                simulator,
+                SandoContract::SandoooV3,
+                None,
+                base_fee,
+                base_fee,
+                None,
+                None,
+                None,
+                &[],
+            )
+            .unwrap()
+    }
+}
+
+#[test]
+fn sandwich_cases_replay() {
+    let entries = std::fs::read_dir(snapshots_dir()).unwrap();
+    let mut replayed = 0;
+    for entry in entries {
+        let path = entry.unwrap().path();
+        if path.extension().map_or(true, |ext| ext != "json") {
+            continue;
+        }
+        let file = std::fs::File::open(&path).unwrap();
+        let case: SandwichCase = serde_json::from_reader(file).unwrap();
+        let mut simulator = EvmSimulator::from_snapshot(&case.snapshot).unwrap();
+        let result = CaseResult::from(&case.simulate(&mut simulator));
+        assert_eq!(result, case.expected, "case {:?}", path);
+        replayed += 1;
+    }
+    if replayed == 0 {
+        eprintln!("empty snapshot corpus, record a case with record_sandwich_case");
+        return;
+    }
+    eprintln!("{} sandwich cases replayed", replayed);
+}
+
+#[tokio::test]
+async fn recorded_reads_replay_offline() {
+    let provider = match connect().await {
+        Some(provider) => provider,
+        None => return,
+    };
+    let block_number = provider.get_block_number().await.unwrap();
+    let next_block = NextBlockEnv::fetch(&provider, block_number).await.unwrap();
+
+    let pair = H160::from_str(V2_WETH_USDT).unwrap();
+    let weth = H160::from_str(WETH).unwrap();
+    let usdt = H160::from_str(USDT).unwrap();
+    let (mut simulator, recorder) = EvmSimulator::new_recording(provider, None, &next_block);
+    let reserves = simulator.get_pair_reserves(pair).unwrap();
+    let weth_balance = simulator.get_token_balance(weth, pair).unwrap();
+    let usdt_balance = simulator.get_token_balance(usdt, pair).unwrap();
+
+    let snapshot = recorder.snapshot();
+    assert!(snapshot.accounts.contains_key(&pair));
+    let mut offline = EvmSimulator::from_snapshot(&snapshot).unwrap();
+    assert_eq!(offline.get_pair_reserves(pair).unwrap(), reserves);
+    assert_eq!(offline.get_token_balance(weth, pair).unwrap(), weth_balance);
+    assert_eq!(offline.get_token_balance(usdt, pair).unwrap(), usdt_balance);
+}
+
+#[tokio::test]
+async fn record_sandwich_case() {
+    let spec = match std::env::var("SNAPSHOT_CASE") {
+        Ok(spec) if !spec.is_empty() => spec,
+        _ => return,
+    };
+    let provider = match connect().await {
+        Some(provider) => provider,
+        None => return,
+    };
+    let fields: Vec<&str> = spec.split(':').collect();
+    assert_eq!(fields.len(), 7, "name:victim_hash:pair:main_currency:target_token:v2|v3:fee");
+    let victim = provider
+        .get_transaction(H256::from_str(fields[1]).unwrap())
+        .await
+        .unwrap()
+        .expect("victim not found");
+    let block = provider
+        .get_block(victim.block_number.expect("victim is pending"))
+        .await
+        .unwrap()
+        .unwrap();
+    let main_currency = H160::from_str(fields[3]).unwrap();
+
+    let mut case = SandwichCase {
+        snapshot: StateSnapshot::new(NextBlockEnv::of_block(&block).unwrap(), H160::zero()),
+        victim,
+        pair: H160::from_str(fields[2]).unwrap(),
+        main_currency,
+        target_token: H160::from_str(fields[4]).unwrap(),
+        version: match fields[5] {
+            "v3" => DexVariant::UniswapV3,
+            _ => DexVariant::UniswapV2,
+        },
+        fee: fields[6].parse().unwrap(),
+        amount_in: sandooo::common::quote_assets::get_quote_assets()
+            .get(main_currency)
+            .expect("not a quote asset")
+            .probe_amount_in(),
+        expected: CaseResult {
+            revenue: 0,
+            profit: 0,
+            gas_cost: 0,
+            front_gas_used: 0,
+            back_gas_used: 0,
+            front_calldata: Bytes::default(),
+            back_calldata: Bytes::default(),
+        },
+    };
+    let (mut simulator, recorder) =
+        EvmSimulator::new_recording(provider, None, &case.snapshot.block);
+    let simulated = case.simulate(&mut simulator);
+    case.snapshot = recorder.snapshot();
+    case.expected = CaseResult::from(&simulated);
+
+    // the recording must replay on its own before it goes into the corpus
+    let mut offline = EvmSimulator::from_snapshot(&case.snapshot).unwrap();
+    assert_eq!(CaseResult::from(&case.simulate(&mut offline)), case.expected);
+
+    std::fs::create_dir_all(snapshots_dir()).unwrap();
+    let path = snapshots_dir().join(format!("{}.json", fields[0]));
+    serde_json::to_writer_pretty(std::fs::File::create(&path).unwrap(), &case).unwrap();
+    eprintln!("Recorded {:?}", path);
+}
@@ -0,0 +1,615 @@
+//! Table-driven tests of transaction classification and swap extraction on a `MockMiddleware`.
+//!
+//! Every selector in `SWAP_SELECTOR_SET` has a case with the swaps `extract_swap_info` should
+//! return. Router calls are decoded from calldata; direct pool calls are traced in revm against
+//! scripted pool code that emits the pool's `Swap` log. Router paths are decoded by the full ABI
+//! of each call, and the pair's quote asset is its main currency whichever way it trades. The
+//! `SwapCache` the strategies share extracts each transaction once per block.
+use ethers::abi::{encode, Token};
+use ethers::providers::Middleware;
+use ethers::types::{
+    Bytes, GethDebugTracingCallOptions, GethTrace, Transaction, TransactionReceipt,
+    TransactionRequest, H160, H256, U256, U64,
+};
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use sandooo::common::classifier::{classify_transaction, TxKind};
+use sandooo::common::evm::NextBlockEnv;
+use sandooo::common::mock_middleware::MockMiddleware;
+use sandooo::common::pools::{DexVariant, Pool};
+use sandooo::common::routers::{ROUTER_SET, SWAP_SELECTOR_SET};
+use sandooo::common::streams::{NewBlock, NewPendingTx};
+use sandooo::common::trace::{V2_SWAP_TOPIC, V3_SWAP_TOPIC};
+use sandooo::sandwich::simulation::{extract_swap_info, get_token_paths, SwapDirection};
+use sandooo::sandwich::swap_cache::SwapCache;
+
+mod common;
+use common::{pool, weth, UNISWAP_V2_ROUTER};
+
+const SWAP_ROUTER: &str = "0xE592427A0AEce92De3Edee1F18E0157C05861564";
+const SWAP_ROUTER_02: &str = "0x68b3465833fb72A70ecDF485E0e4C7bD8665Fc45";
+const UNIVERSAL_ROUTER: &str = "0xEf1c6E67703c7BD7107eed8303FBe6EC2554BF6B";
+
+fn address(s: &str) -> H160 {
+    H160::from_str(s).unwrap()
+}
+
+/// Target tokens sort after WETH, so WETH is token0 of both pools.
+fn token_a() -> H160 {
+    H160::repeat_byte(0xda)
+}
+
+fn token_b() -> H160 {
+    H160::repeat_byte(0xdb)
+}
+
+fn v2_pair() -> H160 {
+    H160::repeat_byte(0x02)
+}
+
+fn v3_pool() -> H160 {
+    H160::repeat_byte(0x03)
+}
+
+fn victim() -> H160 {
+    H160::repeat_byte(0x77)
+}
+
+fn pools_map() -> HashMap<H160, Pool> {
+    HashMap::from([
+        (v2_pair(), pool(v2_pair(), DexVariant::UniswapV2, weth(), token_a(), 300)),
+        (v3_pool(), pool(v3_pool(), DexVariant::UniswapV3, weth(), token_b(), 3000)),
+    ])
+}
+
+/// Code emitting `topic` with token0 paid in: MSTORE(0, 1), LOG1(0, size, topic), STOP.
+fn swap_log_code(topic: &str, size: u8) -> Bytes {
+    let mut code = vec![0x60, 0x01, 0x60, 0x00, 0x52, 0x7f];
+    code.extend(H256::from_str(topic).unwrap().as_bytes());
+    code.extend([0x60, size, 0x60, 0x00, 0xa1, 0x00]);
+    code.into()
+}
+
+fn mock() -> MockMiddleware {
+    MockMiddleware::new()
+        .with_code(v2_pair(), swap_log_code(V2_SWAP_TOPIC, 0x80))
+        .with_code(v3_pool(), swap_log_code(V3_SWAP_TOPIC, 0xa0))
+        .with_balance(victim(), U256::exp10(20))
+}
+
+fn new_block() -> NewBlock {
+    let next_block = NextBlockEnv {
+        number: U64::from(19000001),
+        timestamp: U256::from(1_705_000_000u64),
+        base_fee: U256::from(20_000_000_000u64),
+        gas_limit: U256::from(30_000_000u64),
+        prevrandao: H256::zero(),
+        coinbase: H160::repeat_byte(0xc0),
+        chain_id: 1,
+    };
+    NewBlock {
+        block_number: U64::from(19000000),
+        base_fee: next_block.base_fee,
+        next_base_fee: next_block.base_fee,
+        next_block,
+    }
+}
+
+fn pending_tx(to: Option<H160>, input: Vec<u8>) -> NewPendingTx {
+    NewPendingTx {
+        tx: Transaction {
+            hash: H256::repeat_byte(0x11),
+            from: victim(),
+            to,
+            input: input.into(),
+            gas: U256::from(500000),
+            ..Default::default()
+        },
+        added_block: None,
+    }
+}
+
+fn calldata(selector: [u8; 4], args: &[Token]) -> Vec<u8> {
+    let mut data = selector.to_vec();
+    data.extend(encode(args));
+    data
+}
+
+fn v2_path(tokens: &[H160]) -> Token {
+    Token::Array(tokens.iter().map(|t| Token::Address(*t)).collect())
+}
+
+fn v3_path(tokens: &[H160]) -> Token {
+    let mut path = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        if i > 0 {
+            path.extend([0x00, 0x0b, 0xb8]); // 3000
+        }
+        path.extend(token.as_bytes());
+    }
+    Token::Bytes(path)
+}
+
+/// A transaction for one selector and the swaps it should be extracted as.
+struct SwapCase {
+    name: &'static str,
+    selector: [u8; 4],
+    to: H160,
+    input: Vec<u8>,
+    /// (pool, is buy)
+    expected: Vec<(H160, bool)>,
+}
+
+fn swap_cases() -> Vec<SwapCase> {
+    let uint = |n: u64| Token::Uint(U256::from(n));
+    let (weth, token_a, token_b) = (weth(), token_a(), token_b());
+    let v2_router = address(UNISWAP_V2_ROUTER);
+    let buy_a = v2_path(&[weth, token_a]);
+    let sell_a = v2_path(&[token_a, weth]);
+    let deadline = Token::Uint(U256::MAX);
+    let to = Token::Address(victim());
+
+    let eth_in = |selector, path: &Token| {
+        calldata(selector, &[uint(0), path.clone(), to.clone(), deadline.clone()])
+    };
+    let tokens_in = |selector, path: &Token| {
+        calldata(selector, &[uint(1000), uint(0), path.clone(), to.clone(), deadline.clone()])
+    };
+    let single = |selector, token_in, token_out| {
+        let params = Token::Tuple(vec![
+            Token::Address(token_in),
+            Token::Address(token_out),
+            uint(3000),
+            to.clone(),
+            uint(1000),
+            uint(0),
+            uint(0),
+        ]);
+        calldata(selector, &[params])
+    };
+
+    let mut cases = vec![
+        SwapCase {
+            name: "swapExactETHForTokens",
+            selector: [0x7f, 0xf3, 0x6a, 0xb5],
+            to: v2_router,
+            input: eth_in([0x7f, 0xf3, 0x6a, 0xb5], &buy_a),
+            expected: vec![(v2_pair(), true)],
+        },
+        SwapCase {
+            name: "swapExactETHForTokensSupportingFeeOnTransferTokens",
+            selector: [0xb6, 0xf9, 0xde, 0x95],
+            to: v2_router,
+            input: eth_in([0xb6, 0xf9, 0xde, 0x95], &buy_a),
+            expected: vec![(v2_pair(), true)],
+        },
+        SwapCase {
+            name: "swapExactTokensForETH",
+            selector: [0x18, 0xcb, 0xaf, 0xe5],
+            to: v2_router,
+            input: tokens_in([0x18, 0xcb, 0xaf, 0xe5], &sell_a),
+            expected: vec![(v2_pair(), false)],
+        },
+        SwapCase {
+            name: "swapTokensForExactETH",
+            selector: [0x4a, 0x25, 0xd9, 0x4a],
+            to: v2_router,
+            input: tokens_in([0x4a, 0x25, 0xd9, 0x4a], &sell_a),
+            expected: vec![(v2_pair(), false)],
+        },
+        SwapCase {
+            name: "swapExactTokensForETHSupportingFeeOnTransferTokens",
+            selector: [0x79, 0x1a, 0xc9, 0x47],
+            to: v2_router,
+            input: tokens_in([0x79, 0x1a, 0xc9, 0x47], &sell_a),
+            expected: vec![(v2_pair(), false)],
+        },
+        SwapCase {
+            name: "swapExactTokensForTokens",
+            selector: [0x38, 0xed, 0x17, 0x39],
+            to: v2_router,
+            input: tokens_in([0x38, 0xed, 0x17, 0x39], &buy_a),
+            expected: vec![(v2_pair(), true)],
+        },
+        SwapCase {
+            name: "swapTokensForExactTokens",
+            selector: [0x88, 0x03, 0xdb, 0xee],
+            to: v2_router,
+            input: tokens_in([0x88, 0x03, 0xdb, 0xee], &sell_a),
+            expected: vec![(v2_pair(), false)],
+        },
+        SwapCase {
+            name: "swapExactTokensForTokensSupportingFeeOnTransferTokens",
+            selector: [0x5c, 0x11, 0xd7, 0x95],
+            to: v2_router,
+            input: tokens_in([0x5c, 0x11, 0xd7, 0x95], &buy_a),
+            expected: vec![(v2_pair(), true)],
+        },
+        SwapCase {
+            name: "exactInputSingle",
+            selector: [0x04, 0xe4, 0x5a, 0xaf],
+            to: address(SWAP_ROUTER_02),
+            input: single([0x04, 0xe4, 0x5a, 0xaf], weth, token_b),
+            expected: vec![(v3_pool(), true)],
+        },
+        SwapCase {
+            name: "exactOutputSingle",
+            selector: [0x50, 0x23, 0xb4, 0xdf],
+            to: address(SWAP_ROUTER_02),
+            input: single([0x50, 0x23, 0xb4, 0xdf], token_b, weth),
+            expected: vec![(v3_pool(), false)],
+        },
+        SwapCase {
+            name: "exactInput",
+            selector: [0xc0, 0x4b, 0x8d, 0x59],
+            to: address(SWAP_ROUTER),
+            input: calldata(
+                [0xc0, 0x4b, 0x8d, 0x59],
+                &[Token::Tuple(vec![
+                    v3_path(&[weth, token_b]),
+                    to.clone(),
+                    deadline.clone(),
+                    uint(1000),
+                    uint(0),
+                ])],
+            ),
+            expected: vec![(v3_pool(), true)],
+        },
+        SwapCase {
+            name: "exactOutput (path from tokenOut)",
+            selector: [0x09, 0xb8, 0x13, 0x46],
+            to: address(SWAP_ROUTER_02),
+            input: calldata(
+                [0x09, 0xb8, 0x13, 0x46],
+                &[Token::Tuple(vec![
+                    v3_path(&[token_b, weth]),
+                    to.clone(),
+                    uint(1000),
+                    uint(u64::MAX),
+                ])],
+            ),
+            expected: vec![(v3_pool(), true)],
+        },
+        SwapCase {
+            name: "UniswapV2Pair.swap (traced)",
+            selector: [0x02, 0x2c, 0x0d, 0x9f],
+            to: v2_pair(),
+            input: calldata(
+                [0x02, 0x2c, 0x0d, 0x9f],
+                &[uint(0), uint(1000), to.clone(), Token::Bytes(vec![])],
+            ),
+            expected: vec![(v2_pair(), true)],
+        },
+        SwapCase {
+            name: "UniswapV3Pool.swap (traced)",
+            selector: [0x12, 0x8a, 0xcb, 0x08],
+            to: v3_pool(),
+            input: calldata(
+                [0x12, 0x8a, 0xcb, 0x08],
+                &[to.clone(), Token::Bool(true), uint(1000), uint(0), Token::Bytes(vec![])],
+            ),
+            expected: vec![(v3_pool(), true)],
+        },
+    ];
+
+    // not decoded: these are traced, and the unscripted router code makes no swap
+    let traced_only: [(&str, [u8; 4]); 11] = [
+        ("Universal Router execute", [0x35, 0x93, 0x56, 0x4c]),
+        ("0x sellToUniswap", [0xd9, 0x62, 0x7a, 0xa4]),
+        ("0x sellTokenForTokenToUniswapV3", [0x6a, 0xf4, 0x79, 0xb2]),
+        ("1inch swap", [0x12, 0xaa, 0x3c, 0xaf]),
+        ("1inch unoswap", [0x2e, 0x95, 0xb6, 0xc8]),
+        ("1inch swap (v4)", [0x7c, 0x02, 0x52, 0x00]),
+        ("pepeSwap", [0xb8, 0x97, 0xda, 0xeb]),
+        ("1inch unoswapV5", [0x67, 0x74, 0xb8, 0x49]),
+        ("MetaMask simpleSwap", [0x2e, 0xb2, 0xc2, 0xd6]),
+        ("Curve remove_liquidity_imbalance", [0x3a, 0x45, 0x7b, 0x3a]),
+        ("Curve remove_liquidity", [0xe8, 0xe5, 0x25, 0xa6]),
+    ];
+    for (name, selector) in traced_only {
+        cases.push(SwapCase {
+            name,
+            selector,
+            to: address(UNIVERSAL_ROUTER),
+            input: calldata(selector, &[uint(0), uint(0)]),
+            expected: vec![],
+        });
+    }
+    cases
+}
+
+#[test]
+fn every_swap_selector_has_a_case() {
+    let covered: HashSet<[u8; 4]> = swap_cases().iter().map(|case| case.selector).collect();
+    for selector in SWAP_SELECTOR_SET.iter() {
+        assert!(covered.contains(selector), "no case for 0x{}", hex::encode(selector));
+    }
+}
+
+#[tokio::test]
+async fn swap_selectors_classify_as_swaps() {
+    let provider = MockMiddleware::new();
+    // an address without code, so only the selector can make it a swap
+    let to = H160::repeat_byte(0x55);
+    for selector in SWAP_SELECTOR_SET.iter() {
+        let tx = pending_tx(Some(to), calldata(*selector, &[])).tx;
+        assert_eq!(
+            classify_transaction(&provider, &tx).await,
+            TxKind::Swap,
+            "0x{}",
+            hex::encode(selector)
+        );
+    }
+}
+
+#[tokio::test]
+async fn router_calls_classify_as_swaps() {
+    let provider = MockMiddleware::new();
+    for router in ROUTER_SET.iter() {
+        let tx = pending_tx(Some(*router), vec![0xde, 0xad, 0xbe, 0xef]).tx;
+        assert_eq!(classify_transaction(&provider, &tx).await, TxKind::Swap, "{:?}", router);
+    }
+}
+
+#[tokio::test]
+async fn transactions_classify() {
+    let unknown = [0xde, 0xad, 0xbe, 0xef];
+    let router_like = H160::repeat_byte(0x51);
+    let pair_like = H160::repeat_byte(0x52);
+    let small = H160::repeat_byte(0x53);
+    let eoa = H160::repeat_byte(0x54);
+
+    // token0() and token1() selectors padded past the 100 byte minimum
+    let mut pair_code = vec![0x0d, 0xfe, 0x16, 0x81, 0xd2, 0x12, 0x20, 0xa7];
+    pair_code.resize(200, 0);
+    let provider = MockMiddleware::new()
+        .with_code(router_like, Bytes::from(vec![0u8; 2000]))
+        .with_code(pair_like, pair_code.into())
+        .with_code(small, Bytes::from(vec![0u8; 50]));
+
+    let transfer = calldata(
+        [0xa9, 0x05, 0x9c, 0xbb],
+        &[Token::Address(eoa), Token::Uint(U256::one())],
+    );
+    let approve = calldata(
+        [0x09, 0x5e, 0xa7, 0xb3],
+        &[Token::Address(eoa), Token::Uint(U256::MAX)],
+    );
+    let cases = [
+        ("eth transfer", Some(eoa), vec![], TxKind::EthTransfer),
+        ("erc20 transfer", Some(token_a()), transfer, TxKind::Erc20Transfer),
+        ("erc20 approve", Some(token_a()), approve, TxKind::Erc20Approve),
+        ("known router", Some(address(UNISWAP_V2_ROUTER)), vec![], TxKind::Swap),
+        ("router-like code", Some(router_like), unknown.to_vec(), TxKind::Swap),
+        ("pair-like code", Some(pair_like), unknown.to_vec(), TxKind::Swap),
+        ("small contract", Some(small), unknown.to_vec(), TxKind::Other),
+        ("no code", Some(eoa), unknown.to_vec(), TxKind::Other),
+        ("contract creation", None, unknown.to_vec(), TxKind::Other),
+    ];
+    for (name, to, input, expected) in cases {
+        let tx = pending_tx(to, input).tx;
+        assert_eq!(classify_transaction(&provider, &tx).await, expected, "{}", name);
+    }
+}
+
+#[tokio::test]
+async fn swaps_extract() {
+    let provider = Arc::new(mock());
+    let pools_map = pools_map();
+    let new_block = new_block();
+
+    for case in swap_cases() {
+        let pending_tx = pending_tx(Some(case.to), case.input.clone());
+        let swaps = extract_swap_info(&provider, &new_block, &pending_tx, &pools_map)
+            .await
+            .unwrap();
+        let extracted: Vec<(H160, bool)> = swaps
+            .iter()
+            .map(|swap| (swap.target_pair, matches!(swap.direction, SwapDirection::Buy)))
+            .collect();
+        assert_eq!(extracted, case.expected, "{}", case.name);
+
+        for swap in &swaps {
+            let pool = &pools_map[&swap.target_pair];
+            assert_eq!(swap.tx_hash, pending_tx.tx.hash, "{}", case.name);
+            assert_eq!(swap.main_currency, weth(), "{}", case.name);
+            assert_eq!(swap.target_token, pool.token1, "{}", case.name);
+            assert!(swap.token0_is_main, "{}", case.name);
+            assert_eq!(swap.version, pool.version, "{}", case.name);
+            assert_eq!(swap.fee, pool.fee, "{}", case.name);
+        }
+    }
+}
+
+#[tokio::test]
+async fn swap_cache_extracts_once_per_block() {
+    let cache = SwapCache::new();
+    let pools_map = pools_map();
+    let swap = calldata([0x02, 0x2c, 0x0d, 0x9f], &[Token::Uint(U256::one())]);
+    let pending_tx = pending_tx(Some(v2_pair()), swap);
+
+    let traced = Arc::new(mock());
+    let swaps = cache
+        .get_or_extract(&traced, &new_block(), &pending_tx, &pools_map)
+        .await
+        .unwrap();
+    assert_eq!(swaps.len(), 1);
+
+    // a pair without code emits no swap, so a second trace would find nothing
+    let silent = Arc::new(MockMiddleware::new().with_balance(victim(), U256::exp10(20)));
+    let cached = cache
+        .get_or_extract(&silent, &new_block(), &pending_tx, &pools_map)
+        .await
+        .unwrap();
+    assert_eq!(cached.len(), 1);
+    assert_eq!(cache.len(), 1);
+
+    let mut next = new_block();
+    next.block_number += U64::one();
+    let retraced = cache
+        .get_or_extract(&silent, &next, &pending_tx, &pools_map)
+        .await
+        .unwrap();
+    assert!(retraced.is_empty());
+}
+
+#[test]
+fn router_paths_decode_past_the_leading_arguments() {
+    let uint = |n: u64| Token::Uint(U256::from(n));
+    let (weth, token_a, token_b) = (weth(), token_a(), token_b());
+    let to = Token::Address(victim());
+    let deadline = Token::Uint(U256::MAX);
+    let path = v2_path(&[weth, token_a, token_b]);
+    let single = Token::Tuple(vec![
+        Token::Address(token_b),
+        Token::Address(weth),
+        uint(3000),
+        to.clone(),
+        uint(1000),
+        uint(0),
+        uint(0),
+    ]);
+
+    let cases = [
+        // the path follows amountOutMin, it is not the first argument
+        (
+            "7ff36ab5",
+            calldata(
+                [0x7f, 0xf3, 0x6a, 0xb5],
+                &[uint(5), path.clone(), to.clone(), deadline.clone()],
+            ),
+            vec![weth, token_a, token_b],
+        ),
+        (
+            "38ed1739",
+            calldata(
+                [0x38, 0xed, 0x17, 0x39],
+                &[uint(1000), uint(5), path.clone(), to.clone(), deadline.clone()],
+            ),
+            vec![weth, token_a, token_b],
+        ),
+        // SwapRouter02 drops the deadline
+        (
+            "472b43f3",
+            calldata([0x47, 0x2b, 0x43, 0xf3], &[uint(1000), uint(5), path, to.clone()]),
+            vec![weth, token_a, token_b],
+        ),
+        // single-hop params are a tuple, not a packed path
+        (
+            "04e45aaf",
+            calldata([0x04, 0xe4, 0x5a, 0xaf], &[single]),
+            vec![token_b, weth],
+        ),
+        // exactOutput paths run from tokenOut back to tokenIn
+        (
+            "09b81346",
+            calldata(
+                [0x09, 0xb8, 0x13, 0x46],
+                &[Token::Tuple(vec![
+                    v3_path(&[token_b, token_a, weth]),
+                    to,
+                    uint(1000),
+                    uint(u64::MAX),
+                ])],
+            ),
+            vec![weth, token_a, token_b],
+        ),
+    ];
+    for (method_id, input, expected) in cases {
+        assert_eq!(get_token_paths(method_id, &input), vec![expected], "{}", method_id);
+    }
+
+    // truncated calldata and unsupported selectors decode to nothing
+    assert!(get_token_paths("38ed1739", &[0x38, 0xed, 0x17, 0x39, 0x00]).is_empty());
+    let unsupported = calldata([0xde, 0xad, 0xbe, 0xef], &[uint(1)]);
+    assert!(get_token_paths("deadbeef", &unsupported).is_empty());
+}
+
+#[tokio::test]
+async fn main_currency_is_the_quote_asset_when_it_is_token1() {
+    // sorts before WETH, so WETH is token1
+    let token = H160::repeat_byte(0x01);
+    let pair = H160::repeat_byte(0x04);
+    let pools_map = HashMap::from([(
+        pair,
+        Pool {
+            id: 0,
+            address: pair,
+            version: DexVariant::UniswapV2,
+            token0: token,
+            token1: weth(),
+            fee: 300,
+            block_number: 0,
+            timestamp: 0,
+        },
+    )]);
+    let provider = Arc::new(mock());
+    let uint = |n: u64| Token::Uint(U256::from(n));
+    let to = Token::Address(victim());
+    let deadline = Token::Uint(U256::MAX);
+
+    let buy = calldata(
+        [0x7f, 0xf3, 0x6a, 0xb5],
+        &[uint(0), v2_path(&[weth(), token]), to.clone(), deadline.clone()],
+    );
+    let sell = calldata(
+        [0x18, 0xcb, 0xaf, 0xe5],
+        &[uint(1000), uint(0), v2_path(&[token, weth()]), to, deadline],
+    );
+    for (input, is_buy) in [(buy, true), (sell, false)] {
+        let pending_tx = pending_tx(Some(address(UNISWAP_V2_ROUTER)), input);
+        let swaps = extract_swap_info(&provider, &new_block(), &pending_tx, &pools_map)
+            .await
+            .unwrap();
+        assert_eq!(swaps.len(), 1);
+        assert_eq!(swaps[0].target_pair, pair);
+        assert_eq!(swaps[0].main_currency, weth());
+        assert_eq!(swaps[0].target_token, token);
+        assert!(!swaps[0].token0_is_main);
+        assert_eq!(matches!(swaps[0].direction, SwapDirection::Buy), is_buy);
+    }
+}
+
+#[tokio::test]
+async fn scripted_state_is_served() {
+    let contract = H160::repeat_byte(0x42);
+    let receipt = TransactionReceipt {
+        transaction_hash: H256::repeat_byte(0x99),
+        status: Some(U64::from(1)),
+        ..Default::default()
+    };
+    let trace = GethTrace::Unknown(json!({ "calls": [] }));
+    let provider = MockMiddleware::new()
+        .with_code(contract, Bytes::from(vec![0x00]))
+        .with_balance(contract, U256::from(7))
+        .with_storage(contract, U256::from(3), U256::from(42))
+        .with_receipt(receipt.clone())
+        .with_trace(contract, trace.clone());
+
+    assert_eq!(provider.get_code(contract, None).await.unwrap(), Bytes::from(vec![0x00]));
+    assert_eq!(provider.get_balance(contract, None).await.unwrap(), U256::from(7));
+    let slot = H256::from_low_u64_be(3);
+    let value = provider.get_storage_at(contract, slot, None).await.unwrap();
+    assert_eq!(value, H256::from_low_u64_be(42));
+    assert_eq!(
+        provider.get_transaction_receipt(receipt.transaction_hash).await.unwrap(),
+        Some(receipt)
+    );
+
+    // unscripted state reads as a fresh account
+    let fresh = H160::repeat_byte(0x43);
+    assert!(provider.get_code(fresh, None).await.unwrap().is_empty());
+    assert!(provider.get_transaction_count(fresh, None).await.unwrap().is_zero());
+    let unknown_receipt = provider.get_transaction_receipt(H256::zero()).await.unwrap();
+    assert!(unknown_receipt.is_none());
+
+    let options = GethDebugTracingCallOptions::default();
+    let call = TransactionRequest::new().to(contract);
+    let traced = provider.debug_trace_call(call, None, options.clone()).await.unwrap();
+    assert_eq!(serde_json::to_value(traced).unwrap(), serde_json::to_value(trace).unwrap());
+    let call = TransactionRequest::new().to(fresh);
+    assert!(provider.debug_trace_call(call, None, options).await.is_err());
+}
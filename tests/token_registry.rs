@@ -0,0 +1,104 @@
+//! Balance slot bookkeeping tests for `TokenRegistry`.
+//!
+//! Each test runs on its own cache file in the temp directory.
+use ethers::types::{H160, H256};
+use std::str::FromStr;
+
+use sandooo::common::balance_slot::{BalanceSlot, MappingLayout};
+use sandooo::common::constants::WETH;
+use sandooo::common::quote_assets::get_quote_assets;
+use sandooo::common::token_registry::TokenRegistry;
+use sandooo::common::token_safety::TokenSafetyReport;
+
+fn cache_path() -> String {
+    std::env::temp_dir()
+        .join(format!("sandooo-tokens-{:?}.json", H256::random()))
+        .to_string_lossy()
+        .to_string()
+}
+
+#[test]
+fn configured_slots_start_unverified() {
+    let path = cache_path();
+    let registry = TokenRegistry::with_cache_path(&path);
+    for asset in get_quote_assets().all() {
+        let token = registry.get_token(asset.address).unwrap();
+        assert_eq!(token.balance_slot, asset.balance_slot);
+        assert!(!token.balance_slot_verified);
+        assert!(token.verified_balance_slot(asset.address).is_none());
+    }
+
+    std::fs::remove_file(&path).ok();
+}
+
+#[test]
+fn discovered_slots_are_verified_and_cached() {
+    let path = cache_path();
+    let weth = H160::from_str(WETH).unwrap();
+    let proxied = BalanceSlot {
+        slot: 51,
+        layout: MappingLayout::Vyper,
+        storage_address: H160::repeat_byte(0x51),
+    };
+
+    let registry = TokenRegistry::with_cache_path(&path);
+    registry.update_balance_slot(weth, &proxied).unwrap();
+    let token = registry.get_token(weth).unwrap();
+    assert_eq!(token.balance_storage, Some(proxied.storage_address));
+    assert_eq!(token.verified_balance_slot(weth), Some(proxied));
+    registry.save_to_cache();
+
+    // a new registry on the same cache trusts it without discovering again
+    let reloaded = TokenRegistry::with_cache_path(&path);
+    let token = reloaded.get_token(weth).unwrap();
+    assert_eq!(token.verified_balance_slot(weth), Some(proxied));
+
+    std::fs::remove_file(&path).ok();
+}
+
+#[test]
+fn slots_in_the_token_itself_store_no_storage_address() {
+    let path = cache_path();
+    let registry = TokenRegistry::with_cache_path(&path);
+    let weth = H160::from_str(WETH).unwrap();
+    let own = BalanceSlot::solidity(weth, 3);
+
+    registry.update_balance_slot(weth, &own).unwrap();
+    let token = registry.get_token(weth).unwrap();
+    assert_eq!(token.balance_storage, None);
+    assert_eq!(token.verified_balance_slot(weth), Some(own));
+
+    // unknown tokens are refused
+    assert!(registry
+        .update_balance_slot(H160::repeat_byte(0x77), &own)
+        .is_err());
+
+    std::fs::remove_file(&path).ok();
+}
+
+#[test]
+fn flush_writes_skipped_debounced_saves() {
+    let path = cache_path();
+    let weth = H160::from_str(WETH).unwrap();
+    let registry = TokenRegistry::with_cache_path(&path);
+
+    // nothing skipped yet
+    assert!(!registry.flush());
+
+    let report = TokenSafetyReport {
+        block_number: 1,
+        ..Default::default()
+    };
+    // the registry wrote its cache file when it was created, so this save is skipped
+    registry.update_token_safety(weth, report.clone()).unwrap();
+    registry.save_to_cache_debounced();
+    let on_disk = TokenRegistry::with_cache_path(&path);
+    assert_eq!(on_disk.get_token_safety(weth), None);
+
+    assert!(registry.flush());
+    assert!(!registry.flush());
+    let on_disk = TokenRegistry::with_cache_path(&path);
+    assert_eq!(on_disk.get_token_safety(weth), Some(report));
+
+    std::fs::remove_file(&path).ok();
+}
@@ -0,0 +1,158 @@
+//! Tax arithmetic and verdict tests for `TokenSafetyReport`, and the appetizer's taxed probe.
+use ethers::types::{H160, I256, U256};
+
+use sandooo::common::constants::{MAX_TOKEN_TAX_BPS, TOKEN_SAFETY_RECHECK_BLOCKS};
+use sandooo::common::token_safety::{apply_tax, tax_bps, TokenSafetyReport};
+use sandooo::common::valuation::AssetDelta;
+use sandooo::sandwich::appetizer::probe_is_promising;
+use sandooo::sandwich::simulation::SimulatedSandwich;
+
+#[test]
+fn tax_is_the_shortfall_in_basis_points() {
+    assert_eq!(tax_bps(U256::from(1000), U256::from(1000)), 0);
+    assert_eq!(tax_bps(U256::from(1000), U256::from(950)), 500);
+    assert_eq!(tax_bps(U256::from(1000), U256::zero()), 10000);
+
+    // more than expected (rebasing tokens) and nothing expected are untaxed
+    assert_eq!(tax_bps(U256::from(1000), U256::from(1200)), 0);
+    assert_eq!(tax_bps(U256::zero(), U256::zero()), 0);
+}
+
+#[test]
+fn apply_tax_reduces_and_clamps() {
+    assert_eq!(apply_tax(U256::from(1000), 0), U256::from(1000));
+    assert_eq!(apply_tax(U256::from(1000), 250), U256::from(975));
+    assert_eq!(apply_tax(U256::from(1000), 10000), U256::zero());
+    assert_eq!(apply_tax(U256::from(1000), 20000), U256::zero());
+
+    let report = TokenSafetyReport {
+        sell_tax_bps: 500,
+        ..Default::default()
+    };
+    assert_eq!(report.apply_sell_tax(U256::from(1000)), U256::from(950));
+}
+
+#[test]
+fn untaxed_token_is_tradeable() {
+    let report = TokenSafetyReport::default();
+    assert!(!report.is_honeypot());
+    assert!(!report.is_taxed());
+    assert!(report.is_tradeable(MAX_TOKEN_TAX_BPS));
+}
+
+#[test]
+fn reverts_and_full_sell_tax_are_honeypots() {
+    for report in [
+        TokenSafetyReport {
+            buy_reverted: true,
+            ..Default::default()
+        },
+        TokenSafetyReport {
+            sell_reverted: true,
+            ..Default::default()
+        },
+        TokenSafetyReport {
+            sell_tax_bps: 10000,
+            ..Default::default()
+        },
+    ] {
+        assert!(report.is_honeypot(), "{:?}", report);
+        assert!(!report.is_tradeable(MAX_TOKEN_TAX_BPS));
+    }
+
+    // a reverting transfer isn't a honeypot, but our legs can't move the tokens
+    let report = TokenSafetyReport {
+        transfer_reverted: true,
+        ..Default::default()
+    };
+    assert!(!report.is_honeypot());
+    assert!(!report.is_tradeable(MAX_TOKEN_TAX_BPS));
+}
+
+#[test]
+fn every_leg_tax_is_capped() {
+    let max = MAX_TOKEN_TAX_BPS;
+    let taxed = |buy, transfer, sell| TokenSafetyReport {
+        buy_tax_bps: buy,
+        transfer_tax_bps: transfer,
+        sell_tax_bps: sell,
+        ..Default::default()
+    };
+
+    assert!(taxed(max, max, max).is_taxed());
+    assert!(taxed(max, max, max).is_tradeable(max));
+    assert!(!taxed(max + 1, 0, 0).is_tradeable(max));
+    assert!(!taxed(0, max + 1, 0).is_tradeable(max));
+    assert!(!taxed(0, 0, max + 1).is_tradeable(max));
+}
+
+#[test]
+fn rejected_reports_expire() {
+    let block = 1_000;
+    let rejected = TokenSafetyReport {
+        block_number: block,
+        buy_reverted: true,
+        ..Default::default()
+    };
+    let age = TOKEN_SAFETY_RECHECK_BLOCKS;
+    assert!(!rejected.is_expired(block + age - 1, MAX_TOKEN_TAX_BPS, age));
+    assert!(rejected.is_expired(block + age, MAX_TOKEN_TAX_BPS, age));
+
+    // tradeable reports are kept for good
+    let tradeable = TokenSafetyReport {
+        block_number: block,
+        sell_tax_bps: 100,
+        ..Default::default()
+    };
+    assert!(!tradeable.is_expired(block + 10 * age, MAX_TOKEN_TAX_BPS, age));
+}
+
+#[test]
+fn round_trip_tax_compounds_buy_and_sell() {
+    let report = TokenSafetyReport {
+        buy_tax_bps: 500,
+        transfer_tax_bps: 300,
+        sell_tax_bps: 1000,
+        ..Default::default()
+    };
+    // 10000 * 0.95 * 0.9 = 8550 comes back, the transfer tax isn't paid on our path
+    assert_eq!(report.round_trip_tax(U256::from(10000)), U256::from(1450));
+    assert_eq!(TokenSafetyReport::default().round_trip_tax(U256::from(10000)), U256::zero());
+}
+
+#[test]
+fn taxed_probes_are_priced_with_their_tax() {
+    let weth = H160::repeat_byte(0xee);
+    let amount_in = U256::from(10000);
+    // the probe lost 1000 of the 1450 the round trip paid in taxes
+    let probe = SimulatedSandwich {
+        profit: -1000,
+        asset_deltas: vec![AssetDelta {
+            asset: weth,
+            delta: I256::from(-1000),
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+    let taxed = TokenSafetyReport {
+        buy_tax_bps: 500,
+        sell_tax_bps: 1000,
+        ..Default::default()
+    };
+
+    assert!(probe_is_promising(&probe, weth, amount_in, Some(&taxed)));
+    assert!(!probe_is_promising(&probe, weth, amount_in, Some(&TokenSafetyReport::default())));
+    assert!(!probe_is_promising(&probe, weth, amount_in, None));
+
+    // losing more than the taxes explain is a loss at any size
+    let losing = SimulatedSandwich {
+        profit: -2000,
+        asset_deltas: vec![AssetDelta {
+            asset: weth,
+            delta: I256::from(-2000),
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+    assert!(!probe_is_promising(&losing, weth, amount_in, Some(&taxed)));
+}
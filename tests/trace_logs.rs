@@ -0,0 +1,83 @@
+//! Fork tests for `EvmSimulator::trace_logs`.
+//!
+//! Each test forks mainnet through `WSS_URL` and traces a Uniswap V2 router swap in revm, without
+//! any `debug_*` RPC. Tests are skipped when `WSS_URL` is not set.
+use ethers::abi::parse_abi;
+use ethers::prelude::BaseContract;
+use ethers::providers::{Provider, Ws};
+use ethers::types::{H160, U256};
+use std::str::FromStr;
+
+use sandooo::common::constants::{USDT, WETH};
+use sandooo::common::evm::{EvmSimulator, Tx};
+use sandooo::common::trace::{TracedLogKind, TxTrace};
+
+mod common;
+use common::{fork, UNISWAP_V2_ROUTER, V2_WETH_USDT};
+
+/// Traces `swapExactETHForTokens(amount_out_min, [WETH, USDT], owner, deadline)` for 1 ETH.
+fn trace_buy(simulator: &mut EvmSimulator<Provider<Ws>>, amount_out_min: U256) -> TxTrace {
+    let router = BaseContract::from(
+        parse_abi(&[
+            "function swapExactETHForTokens(uint256,address[],address,uint256) external payable returns (uint256[])",
+        ])
+        .unwrap(),
+    );
+    let path = vec![H160::from_str(WETH).unwrap(), H160::from_str(USDT).unwrap()];
+    let calldata = router
+        .encode(
+            "swapExactETHForTokens",
+            (amount_out_min, path, simulator.owner, U256::MAX),
+        )
+        .unwrap();
+
+    simulator
+        .trace_logs(Tx {
+            caller: simulator.owner,
+            transact_to: H160::from_str(UNISWAP_V2_ROUTER).unwrap(),
+            data: calldata.0,
+            value: U256::exp10(18),
+            gas_price: U256::zero(),
+            gas_limit: 500000,
+        })
+        .unwrap()
+}
+
+#[tokio::test]
+async fn traces_router_swap_logs() {
+    let mut simulator = match fork().await {
+        Some(simulator) => simulator,
+        None => return,
+    };
+    let pair = H160::from_str(V2_WETH_USDT).unwrap();
+
+    let trace = trace_buy(&mut simulator, U256::zero());
+
+    assert!(trace.success);
+    assert_eq!(trace.touched_pools, vec![pair]);
+    let swaps: Vec<_> = trace.swaps().collect();
+    assert_eq!(swaps.len(), 1);
+    assert_eq!(swaps[0].address, pair);
+    // WETH (token0) is paid into the pair
+    assert_eq!(swaps[0].token0_in(), Some(true));
+    assert!(trace.logs.iter().any(|log| log.kind == TracedLogKind::Sync));
+    assert!(trace.logs.iter().any(|log| log.kind == TracedLogKind::Transfer));
+    // router -> WETH deposit / transfer, pair swap -> USDT transfer
+    assert!(trace.calls.iter().any(|call| call.to == pair && call.depth == 1));
+}
+
+#[tokio::test]
+async fn drops_logs_of_reverted_transactions() {
+    let mut simulator = match fork().await {
+        Some(simulator) => simulator,
+        None => return,
+    };
+
+    // INSUFFICIENT_OUTPUT_AMOUNT
+    let trace = trace_buy(&mut simulator, U256::MAX);
+
+    assert!(!trace.success);
+    assert!(trace.logs.is_empty());
+    assert!(trace.touched_pools.is_empty());
+    assert!(!trace.calls[0].success);
+}
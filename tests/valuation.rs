@@ -0,0 +1,163 @@
+//! Tests for the `Valuator`'s pool and Chainlink prices.
+//!
+//! Pools and feeds are stand-in contracts returning fixed words in an offline snapshot.
+use ethers::providers::{MockProvider, Provider};
+use ethers::types::{Bytes, H160, H256, I256, U256, U64};
+use std::str::FromStr;
+
+use sandooo::common::constants::{USDT, WETH};
+use sandooo::common::evm::{EvmSimulator, NextBlockEnv};
+use sandooo::common::quote_assets::get_quote_assets;
+use sandooo::common::snapshot::{SnapshotAccount, StateSnapshot};
+use sandooo::common::valuation::{PriceSource, Valuator};
+
+/// Code returning `words` whatever it is called with.
+fn returning(words: &[U256]) -> Bytes {
+    let mut code = Vec::new();
+    for (i, word) in words.iter().enumerate() {
+        let mut bytes = [0u8; 32];
+        word.to_big_endian(&mut bytes);
+        code.push(0x7f); // PUSH32
+        code.extend(bytes);
+        code.extend([0x60, (i * 32) as u8, 0x52]); // PUSH1 offset, MSTORE
+    }
+    code.extend([0x60, (words.len() * 32) as u8, 0x60, 0x00, 0xf3]); // RETURN
+    Bytes::from(code)
+}
+
+/// A USDT/WETH market: the pool's reserves and the Chainlink USD answers (8 decimals).
+struct Market {
+    pool_weth: U256,
+    pool_usdt: U256,
+    eth_usd: i64,
+    usdt_usd: i64,
+}
+
+fn simulator(market: &Market) -> EvmSimulator<Provider<MockProvider>> {
+    let assets = get_quote_assets();
+    let weth = assets.get(H160::from_str(WETH).unwrap()).unwrap();
+    let usdt = assets.get(H160::from_str(USDT).unwrap()).unwrap();
+
+    let block = NextBlockEnv {
+        number: U64::from(19000001),
+        timestamp: U256::from(1_705_000_000u64),
+        base_fee: U256::from(20_000_000_000u64),
+        gas_limit: U256::from(30_000_000u64),
+        prevrandao: H256::zero(),
+        coinbase: H160::from_low_u64_be(0xc0ffee),
+        chain_id: 1,
+    };
+    let owner = H160::from_low_u64_be(0x0a);
+    let mut snapshot = StateSnapshot::new(block.clone(), owner);
+    snapshot.accounts.insert(owner, SnapshotAccount::default());
+    snapshot
+        .accounts
+        .insert(block.coinbase, SnapshotAccount::default());
+
+    // token0 = WETH: getReserves() -> (reserve0, reserve1, timestamp)
+    let reserves = returning(&[market.pool_weth, market.pool_usdt, U256::zero()]);
+    // latestRoundData() -> (roundId, answer, startedAt, updatedAt, answeredInRound)
+    let feed = |answer: i64| {
+        returning(&[
+            U256::one(),
+            I256::from(answer).into_raw(),
+            U256::zero(),
+            U256::zero(),
+            U256::one(),
+        ])
+    };
+    for (address, code) in [
+        (usdt.weth_pools[0], reserves),
+        (weth.price_feed.unwrap(), feed(market.eth_usd)),
+        (usdt.price_feed.unwrap(), feed(market.usdt_usd)),
+    ] {
+        snapshot.accounts.insert(
+            address,
+            SnapshotAccount {
+                code,
+                ..Default::default()
+            },
+        );
+    }
+    EvmSimulator::from_snapshot(&snapshot).unwrap()
+}
+
+fn usdt() -> H160 {
+    H160::from_str(USDT).unwrap()
+}
+
+/// ETH at $2000.
+fn market(pool_usdt: u64) -> Market {
+    Market {
+        pool_weth: U256::from(1000) * U256::exp10(18),
+        pool_usdt: U256::from(pool_usdt) * U256::exp10(6),
+        eth_usd: 200_000_000_000,
+        usdt_usd: 100_000_000,
+    }
+}
+
+#[test]
+fn weth_is_priced_at_par() {
+    let mut simulator = simulator(&market(2_000_000));
+    let mut valuator = Valuator::new();
+    let weth = H160::from_str(WETH).unwrap();
+
+    let price = valuator.price(&mut simulator, weth).unwrap();
+    assert_eq!(price.source, PriceSource::Weth);
+    let delta = valuator
+        .value_delta(weth, U256::exp10(18), U256::zero())
+        .unwrap();
+    assert_eq!(delta.eth_value, -I256::from_raw(U256::exp10(18)));
+}
+
+#[test]
+fn pool_price_agreeing_with_chainlink_is_used() {
+    let mut simulator = simulator(&market(2_000_000));
+    let mut valuator = Valuator::new();
+
+    let price = valuator.price(&mut simulator, usdt()).unwrap();
+    let pool = get_quote_assets().get(usdt()).unwrap().weth_pools[0];
+    assert_eq!(price.source, PriceSource::Pool(pool));
+
+    // 1000 USDT is half an ETH
+    let delta = valuator
+        .value_delta(usdt(), U256::zero(), U256::from(1000) * U256::exp10(6))
+        .unwrap();
+    assert_eq!(delta.eth_value, I256::from_raw(U256::exp10(17) * 5));
+}
+
+#[test]
+fn diverging_pool_falls_back_to_chainlink() {
+    // the pool prices USDT twice as high as Chainlink
+    let mut simulator = simulator(&market(1_000_000));
+    let mut valuator = Valuator::new();
+
+    let price = valuator.price(&mut simulator, usdt()).unwrap();
+    assert_eq!(price.source, PriceSource::Chainlink);
+    assert_eq!(
+        price.to_eth(U256::from(1000) * U256::exp10(6)),
+        U256::exp10(17) * 5
+    );
+}
+
+#[test]
+fn empty_pool_falls_back_to_chainlink() {
+    let mut simulator = simulator(&Market {
+        pool_weth: U256::zero(),
+        pool_usdt: U256::zero(),
+        ..market(0)
+    });
+    let price = Valuator::new().price(&mut simulator, usdt()).unwrap();
+    assert_eq!(price.source, PriceSource::Chainlink);
+}
+
+#[test]
+fn no_pool_and_no_feed_is_an_error() {
+    let mut simulator = simulator(&Market {
+        pool_weth: U256::zero(),
+        pool_usdt: U256::zero(),
+        eth_usd: 200_000_000_000,
+        usdt_usd: 0,
+    });
+    assert!(Valuator::new().price(&mut simulator, usdt()).is_err());
+}
@@ -0,0 +1,143 @@
+//! Tests for the bounded worker pool and its metrics.
+//!
+//! Everything here runs on plain jobs, without a node.
+use anyhow::anyhow;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{Barrier, Semaphore};
+
+use sandooo::common::metrics::{Metrics, MetricsSnapshot};
+use sandooo::common::worker_pool::WorkerPool;
+
+/// Waits until the counters satisfy `done`, failing after a few seconds.
+async fn wait_for(metrics: &Metrics, done: impl Fn(&MetricsSnapshot) -> bool) {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !done(&metrics.snapshot()) {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("timed out at {}", metrics.snapshot()));
+}
+
+#[tokio::test]
+async fn processes_every_job() {
+    let metrics = Arc::new(Metrics::new());
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let recorded = seen.clone();
+    let pool = WorkerPool::spawn("test", 4, 16, metrics.clone(), move |job: u64| {
+        let recorded = recorded.clone();
+        async move {
+            recorded.lock().unwrap().push(job);
+            Ok(())
+        }
+    });
+
+    for job in 0..10 {
+        assert!(pool.submit(job));
+    }
+    wait_for(&metrics, |m| m.jobs_processed == 10).await;
+
+    let mut seen = seen.lock().unwrap().clone();
+    seen.sort();
+    assert_eq!(seen, (0..10).collect::<Vec<u64>>());
+
+    let snapshot = metrics.snapshot();
+    assert_eq!(snapshot.jobs_queued, 10);
+    assert_eq!(snapshot.jobs_dropped, 0);
+    assert_eq!(snapshot.job_errors, 0);
+    assert_eq!(snapshot.in_flight, 0);
+    assert_eq!(pool.queued(), 0);
+}
+
+#[tokio::test]
+async fn jobs_run_concurrently() {
+    let metrics = Arc::new(Metrics::new());
+    // every job waits for the others, so they only finish if all four run at once
+    let barrier = Arc::new(Barrier::new(4));
+    let pool = WorkerPool::spawn("test", 4, 4, metrics.clone(), move |_job: u64| {
+        let barrier = barrier.clone();
+        async move {
+            barrier.wait().await;
+            Ok(())
+        }
+    });
+
+    for job in 0..4 {
+        assert!(pool.submit(job));
+    }
+    wait_for(&metrics, |m| m.jobs_processed == 4).await;
+}
+
+#[tokio::test]
+async fn drops_jobs_when_full() {
+    let metrics = Arc::new(Metrics::new());
+    let gate = Arc::new(Semaphore::new(0));
+    let worker_gate = gate.clone();
+    let pool = WorkerPool::spawn("test", 1, 2, metrics.clone(), move |_job: u64| {
+        let gate = worker_gate.clone();
+        async move {
+            gate.acquire().await.unwrap().forget();
+            Ok(())
+        }
+    });
+
+    // the only worker holds the first job, two more fill the queue
+    assert!(pool.submit(0));
+    wait_for(&metrics, |m| m.in_flight == 1).await;
+    assert!(pool.submit(1));
+    assert!(pool.submit(2));
+    assert_eq!(pool.queued(), 2);
+
+    assert!(!pool.submit(3));
+    assert_eq!(metrics.snapshot().jobs_dropped, 1);
+
+    gate.add_permits(3);
+    wait_for(&metrics, |m| m.jobs_processed == 3).await;
+    let snapshot = metrics.snapshot();
+    assert_eq!(snapshot.jobs_queued, 3);
+    assert_eq!(snapshot.in_flight, 0);
+}
+
+#[tokio::test]
+async fn counts_failed_jobs() {
+    let metrics = Arc::new(Metrics::new());
+    let pool = WorkerPool::spawn("test", 2, 8, metrics.clone(), |job: u64| async move {
+        match job % 2 {
+            0 => Ok(()),
+            _ => Err(anyhow!("odd job {}", job)),
+        }
+    });
+
+    for job in 0..6 {
+        assert!(pool.submit(job));
+    }
+    wait_for(&metrics, |m| m.jobs_processed == 6).await;
+    assert_eq!(metrics.snapshot().job_errors, 3);
+}
+
+#[test]
+fn metrics_snapshot() {
+    let metrics = Metrics::new();
+    assert_eq!(metrics.snapshot(), MetricsSnapshot::default());
+    assert_eq!(metrics.snapshot().mean_job_time(), Duration::ZERO);
+
+    metrics.event_received();
+    metrics.events_lagged(7);
+    metrics.job_started();
+    metrics.job_finished(Duration::from_millis(2), false);
+    metrics.job_started();
+    metrics.job_finished(Duration::from_millis(4), true);
+    metrics.opportunity();
+    metrics.bundle_submitted();
+
+    let snapshot = metrics.snapshot();
+    assert_eq!(snapshot.events_received, 1);
+    assert_eq!(snapshot.events_lagged, 7);
+    assert_eq!(snapshot.jobs_processed, 2);
+    assert_eq!(snapshot.job_errors, 1);
+    assert_eq!(snapshot.in_flight, 0);
+    assert_eq!(snapshot.mean_job_time(), Duration::from_millis(3));
+    assert_eq!(snapshot.opportunities, 1);
+    assert_eq!(snapshot.bundles_submitted, 1);
+}